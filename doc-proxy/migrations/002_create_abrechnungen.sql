@@ -0,0 +1,37 @@
+-- Create tables for HKP invoices (Abrechnungsnachrichten)
+-- Run this migration manually or via a migration tool
+
+-- Running invoice number per Rechnungssteller and year
+CREATE TABLE IF NOT EXISTS rechnungsnummern (
+    rechnungssteller_ik VARCHAR(9) NOT NULL,
+    jahr INTEGER NOT NULL,
+    letzte_nummer BIGINT NOT NULL,
+    PRIMARY KEY (rechnungssteller_ik, jahr)
+);
+
+CREATE TABLE IF NOT EXISTS abrechnungen (
+    id UUID PRIMARY KEY,
+    rechnungsnummer VARCHAR(20) NOT NULL,
+    rechnungssteller_ik VARCHAR(9) NOT NULL,
+    kostentraeger_ik VARCHAR(9) NOT NULL,
+    rechnungsempfaenger_ik VARCHAR(9) NOT NULL,
+    abrechnungsmonat VARCHAR(6) NOT NULL,
+    rechnungsdatum DATE NOT NULL,
+    gesamtbetrag NUMERIC(12, 2) NOT NULL,
+    anzahl_faelle INTEGER NOT NULL,
+    xml TEXT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    UNIQUE (rechnungssteller_ik, rechnungsnummer)
+);
+
+-- Index for listing invoices per Kasse and month
+CREATE INDEX IF NOT EXISTS idx_abrechnungen_kostentraeger
+    ON abrechnungen(kostentraeger_ik, abrechnungsmonat);
+
+-- Comment on tables
+COMMENT ON TABLE rechnungsnummern IS 'Last issued invoice number per Rechnungssteller and year';
+COMMENT ON TABLE abrechnungen IS 'Generated HKP_ARN invoices';
+COMMENT ON COLUMN abrechnungen.id IS 'Abrechnung_ID of the generated message';
+COMMENT ON COLUMN abrechnungen.rechnungsnummer IS 'Invoice number in format JJJJ-NNNNNN, unique per Rechnungssteller';
+COMMENT ON COLUMN abrechnungen.abrechnungsmonat IS 'Billing month in format JJJJMM';
+COMMENT ON COLUMN abrechnungen.xml IS 'Schema-valid Abrechnungsnachricht XML';
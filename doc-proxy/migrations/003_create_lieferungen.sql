@@ -0,0 +1,24 @@
+-- Create table for outgoing HKP deliveries (Nutzdaten files)
+-- Run this migration manually or via a migration tool
+
+CREATE TABLE IF NOT EXISTS lieferungen (
+    datei_id UUID PRIMARY KEY,
+    abrechnung_id UUID REFERENCES abrechnungen(id),
+    nachrichtentyp VARCHAR(8) NOT NULL,
+    verfahrenskennung VARCHAR(5) NOT NULL,
+    absender_ik VARCHAR(9) NOT NULL,
+    empfaenger_ik VARCHAR(9) NOT NULL,
+    erstellzeitpunkt TIMESTAMP WITH TIME ZONE NOT NULL,
+    xml TEXT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+-- Index for listing the deliveries of an invoice
+CREATE INDEX IF NOT EXISTS idx_lieferungen_abrechnung ON lieferungen(abrechnung_id);
+
+-- Comment on table
+COMMENT ON TABLE lieferungen IS 'Packaged Nutzdaten files, kept for resubmission';
+COMMENT ON COLUMN lieferungen.datei_id IS 'Datei_ID from the Nutzdaten header, unique across all deliveries';
+COMMENT ON COLUMN lieferungen.nachrichtentyp IS 'Nachrichtentyp: ABR_0000, FEH_TECH or FEH_FACH';
+COMMENT ON COLUMN lieferungen.verfahrenskennung IS 'EHKP0 for production data, THKP0 for test data';
+COMMENT ON COLUMN lieferungen.xml IS 'Schema-valid Nutzdaten file exactly as delivered';
@@ -0,0 +1,44 @@
+-- Create tables for incoming technical error messages (HKP_FEH_TECH)
+-- Run this migration manually or via a migration tool
+
+CREATE TABLE IF NOT EXISTS fehlernachrichten (
+    id UUID PRIMARY KEY,
+    datei_id VARCHAR(36) NOT NULL UNIQUE,
+    bezug_datei_id UUID REFERENCES lieferungen(datei_id),
+    message_id VARCHAR(998) NOT NULL,
+    absender_ik VARCHAR(9) NOT NULL,
+    empfaenger_ik VARCHAR(9) NOT NULL,
+    xml TEXT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS technische_fehler (
+    id UUID PRIMARY KEY,
+    fehlernachricht_id UUID NOT NULL REFERENCES fehlernachrichten(id) ON DELETE CASCADE,
+    leistungsnachweis_id VARCHAR(255),
+    fehlercode VARCHAR(5) NOT NULL,
+    fehlertext TEXT,
+    xpath TEXT,
+    feld TEXT,
+    resolved_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+-- Index for listing the errors of a delivery
+CREATE INDEX IF NOT EXISTS idx_fehlernachrichten_bezug ON fehlernachrichten(bezug_datei_id);
+
+-- Index for looking up open errors of a Leistungsnachweis
+CREATE INDEX IF NOT EXISTS idx_technische_fehler_leistungsnachweis ON technische_fehler(leistungsnachweis_id)
+    WHERE resolved_at IS NULL;
+
+CREATE INDEX IF NOT EXISTS idx_technische_fehler_fehlernachricht ON technische_fehler(fehlernachricht_id);
+
+-- Comment on tables
+COMMENT ON TABLE fehlernachrichten IS 'Received Fehlernachricht_technisch files';
+COMMENT ON COLUMN fehlernachrichten.datei_id IS 'Datei_ID from the Nutzdaten header of the received file';
+COMMENT ON COLUMN fehlernachrichten.bezug_datei_id IS 'Own delivery the errors refer to, NULL if it could not be correlated';
+COMMENT ON COLUMN fehlernachrichten.xml IS 'Received Nutzdaten file exactly as ingested';
+COMMENT ON TABLE technische_fehler IS 'Fehlerinformationen of a Fehlernachricht, one row per affected Leistungsnachweis';
+COMMENT ON COLUMN technische_fehler.leistungsnachweis_id IS 'Rejected Leistungsnachweis, NULL if the delivery could not be correlated';
+COMMENT ON COLUMN technische_fehler.feld IS 'Offending element below the Abrechnungsfall, NULL for errors concerning the whole delivery';
+COMMENT ON COLUMN technische_fehler.resolved_at IS 'Set once the Leistungsnachweis has been delivered again';
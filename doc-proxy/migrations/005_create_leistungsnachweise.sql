@@ -0,0 +1,49 @@
+-- Create tables for locally signed Leistungsnachweise
+-- Run this migration manually or via a migration tool
+
+CREATE TABLE IF NOT EXISTS leistungsnachweise (
+    id VARCHAR(255) PRIMARY KEY,
+    versichertennummer VARCHAR(20) NOT NULL,
+    name VARCHAR(255) NOT NULL,
+    vorname VARCHAR(255) NOT NULL,
+    ik_pflegedienst VARCHAR(9) NOT NULL,
+    abrechnungsmonat VARCHAR(6) NOT NULL,
+    schema VARCHAR(16) NOT NULL,
+    status VARCHAR(32) NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS signatur_ereignisse (
+    id UUID PRIMARY KEY,
+    leistungsnachweis_id VARCHAR(255) NOT NULL REFERENCES leistungsnachweise(id) ON DELETE CASCADE,
+    art VARCHAR(32) NOT NULL,
+    bildformat VARCHAR(32),
+    fehlgrund VARCHAR(32),
+    erlaeuterung TEXT,
+    signiert_von VARCHAR(255) NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS xml_artefakte (
+    id UUID PRIMARY KEY,
+    leistungsnachweis_id VARCHAR(255) NOT NULL REFERENCES leistungsnachweise(id) ON DELETE CASCADE,
+    signatur_ereignis_id UUID NOT NULL REFERENCES signatur_ereignisse(id) ON DELETE CASCADE,
+    schema VARCHAR(16) NOT NULL,
+    xml TEXT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+-- Indexes for looking up the history and latest artifact of a document
+CREATE INDEX IF NOT EXISTS idx_signatur_ereignisse_leistungsnachweis ON signatur_ereignisse(leistungsnachweis_id, created_at);
+CREATE INDEX IF NOT EXISTS idx_xml_artefakte_leistungsnachweis ON xml_artefakte(leistungsnachweis_id, created_at);
+
+-- Comment on tables
+COMMENT ON TABLE leistungsnachweise IS 'Leistungsnachweise signed through this proxy, keyed by the core document ID';
+COMMENT ON COLUMN leistungsnachweise.schema IS 'pfl_lnw (SGB XI) or hkp_lnw (SGB V)';
+COMMENT ON COLUMN leistungsnachweise.status IS 'Document status as reported by the API (draft, pending_signature, signed, finalized, ...)';
+COMMENT ON TABLE signatur_ereignisse IS 'One row per signature captured for a Leistungsnachweis';
+COMMENT ON COLUMN signatur_ereignisse.art IS 'Signature type (handwritten_digital, handwritten_paper, photo_confirmation, alternative_confirmation, missing)';
+COMMENT ON COLUMN signatur_ereignisse.bildformat IS 'MIME type of the signature image, NULL if missing';
+COMMENT ON COLUMN signatur_ereignisse.signiert_von IS 'Subject of the token that submitted the signature';
+COMMENT ON TABLE xml_artefakte IS 'Generated, schema-valid XML per signature';
@@ -0,0 +1,20 @@
+-- Create table for the status history of Leistungsnachweise
+-- Run this migration manually or via a migration tool
+
+CREATE TABLE IF NOT EXISTS status_uebergaenge (
+    id UUID PRIMARY KEY,
+    leistungsnachweis_id VARCHAR(255) NOT NULL REFERENCES leistungsnachweise(id) ON DELETE CASCADE,
+    von VARCHAR(32) NOT NULL,
+    nach VARCHAR(32) NOT NULL,
+    grund TEXT,
+    geaendert_von VARCHAR(255) NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+-- Index for listing the history of a document
+CREATE INDEX IF NOT EXISTS idx_status_uebergaenge_leistungsnachweis ON status_uebergaenge(leistungsnachweis_id, created_at);
+
+-- Comment on table
+COMMENT ON TABLE status_uebergaenge IS 'Every status change of a Leistungsnachweis, oldest first';
+COMMENT ON COLUMN status_uebergaenge.grund IS 'Reason given for rejecting or cancelling';
+COMMENT ON COLUMN status_uebergaenge.geaendert_von IS 'Subject of the token that changed the status, or the system process';
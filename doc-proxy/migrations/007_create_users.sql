@@ -0,0 +1,28 @@
+-- Create users table for password login
+-- Run this migration manually or via a migration tool
+
+CREATE EXTENSION IF NOT EXISTS "pgcrypto";
+
+CREATE TABLE IF NOT EXISTS users (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    username VARCHAR(64) UNIQUE NOT NULL,
+    name VARCHAR(255) NOT NULL,
+    email VARCHAR(255),
+    password_hash VARCHAR(255) NOT NULL,
+    role VARCHAR(50) NOT NULL DEFAULT 'staff',
+    is_active BOOLEAN NOT NULL DEFAULT true,
+    failed_login_attempts INTEGER NOT NULL DEFAULT 0,
+    locked_until TIMESTAMP WITH TIME ZONE,
+    last_login TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+-- Comment on table
+COMMENT ON TABLE users IS 'Staff and client accounts for password login';
+COMMENT ON COLUMN users.username IS 'Login name, stored in lower case';
+COMMENT ON COLUMN users.password_hash IS 'Argon2 hash of the password';
+COMMENT ON COLUMN users.role IS 'User role: admin, staff, or client';
+COMMENT ON COLUMN users.is_active IS 'Whether the user can log in';
+COMMENT ON COLUMN users.failed_login_attempts IS 'Failed logins since the last success or lockout';
+COMMENT ON COLUMN users.locked_until IS 'Login is refused until this time after too many failed attempts';
@@ -0,0 +1,12 @@
+-- Soft delete and unique e-mail addresses for users
+-- Run this migration manually or via a migration tool
+
+ALTER TABLE users ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMP WITH TIME ZONE;
+
+-- E-mail addresses are unique among users that have not been deleted
+CREATE UNIQUE INDEX IF NOT EXISTS idx_users_email ON users(LOWER(email)) WHERE deleted_at IS NULL;
+
+-- Index for listing devices bound to a user
+CREATE INDEX IF NOT EXISTS idx_devices_user_id ON devices(user_id);
+
+COMMENT ON COLUMN users.deleted_at IS 'Deleted users keep their row for history but can no longer log in';
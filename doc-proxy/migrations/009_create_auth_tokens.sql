@@ -0,0 +1,30 @@
+-- Create tables for refresh tokens and revoked access tokens
+-- Run this migration manually or via a migration tool
+
+CREATE TABLE IF NOT EXISTS refresh_tokens (
+    id UUID PRIMARY KEY,
+    family_id UUID NOT NULL,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    token_hash VARCHAR(64) UNIQUE NOT NULL,
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    used_at TIMESTAMP WITH TIME ZONE,
+    revoked_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+-- Index for revoking a whole token family
+CREATE INDEX IF NOT EXISTS idx_refresh_tokens_family ON refresh_tokens(family_id);
+
+CREATE TABLE IF NOT EXISTS revoked_tokens (
+    jti VARCHAR(64) PRIMARY KEY,
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    revoked_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+-- Comment on tables
+COMMENT ON TABLE refresh_tokens IS 'Refresh tokens; each use replaces the token with a new one of the same family';
+COMMENT ON COLUMN refresh_tokens.family_id IS 'All tokens descending from one login; revoked together on logout or reuse';
+COMMENT ON COLUMN refresh_tokens.token_hash IS 'SHA-256 of the token (hex); the token itself is never stored';
+COMMENT ON COLUMN refresh_tokens.used_at IS 'Set when the token was exchanged; presenting it again revokes the family';
+COMMENT ON TABLE revoked_tokens IS 'Deny list of access token IDs (jti) revoked before they expire';
+COMMENT ON COLUMN revoked_tokens.expires_at IS 'Expiry of the revoked token; the entry can be purged afterwards';
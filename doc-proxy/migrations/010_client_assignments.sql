@@ -0,0 +1,15 @@
+-- Tie user accounts to the clients (Versicherte) they may access
+-- Run this migration manually or via a migration tool
+
+ALTER TABLE users ADD COLUMN IF NOT EXISTS versichertennummer VARCHAR(20);
+
+CREATE TABLE IF NOT EXISTS staff_clients (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    versichertennummer VARCHAR(20) NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, versichertennummer)
+);
+
+-- Comment on columns and tables
+COMMENT ON COLUMN users.versichertennummer IS 'For client accounts: the Versicherter whose documents the account may access';
+COMMENT ON TABLE staff_clients IS 'Clients assigned to a staff account; staff only see documents of assigned clients';
@@ -0,0 +1,8 @@
+-- Accounts of users who log in via OpenID Connect
+-- Run this migration manually or via a migration tool
+
+ALTER TABLE users ADD COLUMN IF NOT EXISTS oidc_subject VARCHAR(255);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_users_oidc_subject ON users(oidc_subject);
+
+COMMENT ON COLUMN users.oidc_subject IS 'Subject (sub) at the OpenID Connect provider; such accounts have no password';
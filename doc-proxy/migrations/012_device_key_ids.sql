@@ -0,0 +1,16 @@
+-- Index device API keys by their non-secret key ID
+-- Run this migration manually or via a migration tool
+
+ALTER TABLE devices ADD COLUMN IF NOT EXISTS key_id VARCHAR(32);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_devices_key_id ON devices(key_id);
+
+-- Keys issued before this migration only exist as hashes, so their key ID
+-- cannot be filled in here. Such a key is checked against the devices without
+-- a key ID, within DEVICE_LEGACY_KEY_CHECKS_PER_MINUTE, and its first login
+-- stores the key ID. Devices still listed by
+--   SELECT id, name, mac_address FROM devices WHERE key_id IS NULL;
+-- after all tablets have logged in once can get a new key via
+-- POST /devices/{id}/regenerate-key.
+
+COMMENT ON COLUMN devices.key_id IS 'Key ID from the API key (dk_{key_id}_{secret}); selects the device at login';
@@ -0,0 +1,23 @@
+-- One-time pairing codes for enrolling devices
+-- Run this migration manually or via a migration tool
+
+CREATE TABLE IF NOT EXISTS device_pairing_codes (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    code_hash VARCHAR(64) UNIQUE NOT NULL,
+    name VARCHAR(255) NOT NULL,
+    role VARCHAR(50) NOT NULL DEFAULT 'client',
+    user_id UUID REFERENCES users(id) ON DELETE CASCADE,
+    created_by VARCHAR(255) NOT NULL,
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    failed_attempts INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+-- Comment on table
+COMMENT ON TABLE device_pairing_codes IS 'Codes a device redeems once for its API key; deleted on redemption';
+COMMENT ON COLUMN device_pairing_codes.code_hash IS 'SHA-256 of the numeric code';
+COMMENT ON COLUMN device_pairing_codes.name IS 'Name of the device created on redemption';
+COMMENT ON COLUMN device_pairing_codes.role IS 'Role of the device created on redemption';
+COMMENT ON COLUMN device_pairing_codes.user_id IS 'User the device is bound to';
+COMMENT ON COLUMN device_pairing_codes.created_by IS 'Subject of the admin who created the code';
+COMMENT ON COLUMN device_pairing_codes.failed_attempts IS 'Wrong codes tried while this code was open; the code is deleted once DEVICE_PAIRING_CODE_MAX_FAILURES is reached';
@@ -0,0 +1,46 @@
+-- Append-only audit log of signatures and security-relevant actions
+-- Run this migration manually or via a migration tool
+
+CREATE TABLE IF NOT EXISTS audit_log (
+    seq BIGINT PRIMARY KEY,
+    occurred_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    actor VARCHAR(255),
+    device_id UUID,
+    client_ip VARCHAR(45),
+    user_agent VARCHAR(512),
+    action VARCHAR(64) NOT NULL,
+    target_id VARCHAR(255),
+    payload_digest VARCHAR(64),
+    prev_hash VARCHAR(64) NOT NULL,
+    hash VARCHAR(64) UNIQUE NOT NULL
+);
+
+-- Indexes for the admin query filters
+CREATE INDEX IF NOT EXISTS idx_audit_log_target ON audit_log(target_id, seq);
+CREATE INDEX IF NOT EXISTS idx_audit_log_actor ON audit_log(actor, seq);
+CREATE INDEX IF NOT EXISTS idx_audit_log_occurred_at ON audit_log(occurred_at);
+
+-- Entries can only be added
+CREATE OR REPLACE FUNCTION audit_log_append_only() RETURNS trigger AS $$
+BEGIN
+    RAISE EXCEPTION 'audit_log is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS audit_log_no_change ON audit_log;
+CREATE TRIGGER audit_log_no_change BEFORE UPDATE OR DELETE ON audit_log
+    FOR EACH ROW EXECUTE FUNCTION audit_log_append_only();
+
+DROP TRIGGER IF EXISTS audit_log_no_truncate ON audit_log;
+CREATE TRIGGER audit_log_no_truncate BEFORE TRUNCATE ON audit_log
+    FOR EACH STATEMENT EXECUTE FUNCTION audit_log_append_only();
+
+-- Comment on table
+COMMENT ON TABLE audit_log IS 'Append-only audit trail; each entry hashes its predecessor, so changes break the chain';
+COMMENT ON COLUMN audit_log.seq IS 'Position in the hash chain, without gaps';
+COMMENT ON COLUMN audit_log.actor IS 'Token subject (user ID or device-<id>), or the login name for logins';
+COMMENT ON COLUMN audit_log.action IS 'Audited action (login, document_signed, device_deleted, ...)';
+COMMENT ON COLUMN audit_log.target_id IS 'ID of the affected document, device or user';
+COMMENT ON COLUMN audit_log.payload_digest IS 'SHA-256 of the request payload, e.g. the signature';
+COMMENT ON COLUMN audit_log.prev_hash IS 'Hash of the previous entry (zeros for the first)';
+COMMENT ON COLUMN audit_log.hash IS 'SHA-256 over prev_hash and all other columns';
@@ -0,0 +1,37 @@
+-- State for offline sync of tablets
+-- Run this migration manually or via a migration tool
+
+CREATE SEQUENCE IF NOT EXISTS sync_version_seq;
+
+CREATE TABLE IF NOT EXISTS sync_documents (
+    leistungsnachweis_id VARCHAR(255) PRIMARY KEY,
+    versichertennummer VARCHAR(20) NOT NULL,
+    fingerprint VARCHAR(64) NOT NULL,
+    version BIGINT NOT NULL,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS sync_uploads (
+    uploaded_by VARCHAR(255) NOT NULL,
+    idempotency_key VARCHAR(255) NOT NULL,
+    leistungsnachweis_id VARCHAR(255) NOT NULL,
+    captured_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    outcome VARCHAR(16) NOT NULL,
+    result JSONB NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (uploaded_by, idempotency_key)
+);
+
+-- Index for pulling the changes of a client since a cursor
+CREATE INDEX IF NOT EXISTS idx_sync_documents_client ON sync_documents(versichertennummer, version);
+CREATE INDEX IF NOT EXISTS idx_sync_uploads_leistungsnachweis ON sync_uploads(leistungsnachweis_id, created_at);
+
+-- Comment on tables
+COMMENT ON TABLE sync_documents IS 'Last document content seen by a sync pull; the version changes with the content';
+COMMENT ON COLUMN sync_documents.fingerprint IS 'SHA-256 of the document as delivered to tablets';
+COMMENT ON COLUMN sync_documents.version IS 'From sync_version_seq; tablets pull documents with a version above their cursor';
+COMMENT ON TABLE sync_uploads IS 'Signatures uploaded by tablets, so a retried upload returns the first result';
+COMMENT ON COLUMN sync_uploads.uploaded_by IS 'Token subject; keys of different users never collide';
+COMMENT ON COLUMN sync_uploads.captured_at IS 'When the signature was captured on the device';
+COMMENT ON COLUMN sync_uploads.outcome IS 'signed, conflict or rejected';
+COMMENT ON COLUMN sync_uploads.result IS 'Result reported for the upload';
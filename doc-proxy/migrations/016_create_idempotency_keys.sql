@@ -0,0 +1,27 @@
+-- Responses of requests sent with an Idempotency-Key header
+-- Run this migration manually or via a migration tool
+
+CREATE TABLE IF NOT EXISTS idempotency_keys (
+    actor VARCHAR(255) NOT NULL,
+    idempotency_key VARCHAR(255) NOT NULL,
+    request_fingerprint VARCHAR(64) NOT NULL,
+    method VARCHAR(10) NOT NULL,
+    path TEXT NOT NULL,
+    status_code SMALLINT,
+    content_type VARCHAR(255),
+    response_body BYTEA,
+    locked_until TIMESTAMP WITH TIME ZONE NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    PRIMARY KEY (actor, idempotency_key)
+);
+
+-- Index for purging expired keys
+CREATE INDEX IF NOT EXISTS idx_idempotency_keys_expires_at ON idempotency_keys(expires_at);
+
+-- Comment on table
+COMMENT ON TABLE idempotency_keys IS 'Stored responses, so a retried request returns the first response instead of running again';
+COMMENT ON COLUMN idempotency_keys.actor IS 'Token subject; keys of different users never collide';
+COMMENT ON COLUMN idempotency_keys.request_fingerprint IS 'SHA-256 of method, path and body; a key reused for another request is refused';
+COMMENT ON COLUMN idempotency_keys.status_code IS 'NULL while the first request is still running';
+COMMENT ON COLUMN idempotency_keys.locked_until IS 'A retry may take over a key still without a response after this time, e.g. after a crash';
@@ -0,0 +1,15 @@
+-- Leistungsnachweise billed by an invoice
+-- Run this migration manually or via a migration tool
+
+CREATE TABLE IF NOT EXISTS abrechnung_leistungsnachweise (
+    leistungsnachweis_id VARCHAR(255) PRIMARY KEY REFERENCES leistungsnachweise(id),
+    abrechnung_id UUID NOT NULL REFERENCES abrechnungen(id) ON DELETE CASCADE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+-- Index for listing the documents of an invoice
+CREATE INDEX IF NOT EXISTS idx_abrechnung_leistungsnachweise_abrechnung
+    ON abrechnung_leistungsnachweise(abrechnung_id);
+
+-- Comment on table
+COMMENT ON TABLE abrechnung_leistungsnachweise IS 'Signed Leistungsnachweise per invoice; the primary key keeps a document from being billed twice';
@@ -0,0 +1,20 @@
+-- Absolute lifetime of refresh token families
+-- Run this migration manually or via a migration tool
+
+ALTER TABLE refresh_tokens ADD COLUMN IF NOT EXISTS family_created_at TIMESTAMP WITH TIME ZONE;
+
+-- Families from before this migration start with their oldest stored token
+UPDATE refresh_tokens
+SET family_created_at = families.created_at
+FROM (
+    SELECT family_id, MIN(created_at) AS created_at
+    FROM refresh_tokens
+    GROUP BY family_id
+) AS families
+WHERE refresh_tokens.family_id = families.family_id
+    AND refresh_tokens.family_created_at IS NULL;
+
+ALTER TABLE refresh_tokens ALTER COLUMN family_created_at SET NOT NULL;
+
+-- Comment on column
+COMMENT ON COLUMN refresh_tokens.family_created_at IS 'Login that started the family; no token of the family is valid past REFRESH_TOKEN_MAX_AGE_DAYS after it';
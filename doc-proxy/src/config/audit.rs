@@ -0,0 +1,161 @@
+//! Request context for the audit log.
+
+use std::{
+    convert::Infallible,
+    net::{IpAddr, SocketAddr},
+    sync::LazyLock,
+};
+
+use axum::{
+    extract::{ConnectInfo, FromRequestParts},
+    http::{header::USER_AGENT, request::Parts},
+};
+use sea_orm::DatabaseConnection;
+use tracing::error;
+use uuid::Uuid;
+
+use super::auth::Claims;
+use crate::models::audit::AuditAction;
+use crate::repositories::audit_repository;
+
+/// Whether the client IP is taken from `X-Forwarded-For`. Only enable
+/// behind a reverse proxy that sets the header, clients can forge it.
+static TRUST_FORWARDED_FOR: LazyLock<bool> = LazyLock::new(|| {
+    std::env::var("TRUST_FORWARDED_FOR")
+        .map(|v| v.to_lowercase() == "true" || v == "1")
+        .unwrap_or(false)
+});
+
+/// Proxies in front of the server that append to `X-Forwarded-For`
+static TRUSTED_PROXY_HOPS: LazyLock<usize> = LazyLock::new(|| {
+    std::env::var("TRUSTED_PROXY_HOPS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|&n| n > 0)
+        .unwrap_or(1)
+});
+
+/// Longest user agent kept
+const MAX_USER_AGENT_LEN: usize = 512;
+
+/// Who made a request, and from where
+#[derive(Debug, Clone, Default)]
+pub struct AuditContext {
+    /// Token subject (user ID or `device-<id>`), or the login name for logins
+    pub actor: Option<String>,
+    pub device_id: Option<Uuid>,
+    pub client_ip: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+impl AuditContext {
+    /// The same request, attributed to `actor`
+    pub fn with_actor(mut self, actor: impl Into<String>) -> Self {
+        let actor = actor.into();
+        if let Some(device_id) = actor
+            .strip_prefix("device-")
+            .and_then(|id| Uuid::parse_str(id).ok())
+        {
+            self.device_id = Some(device_id);
+        }
+        self.actor = Some(actor);
+        self
+    }
+
+    /// Append an entry to the audit log. Failures are logged but do not undo
+    /// the audited action.
+    pub async fn record(
+        &self,
+        db: &DatabaseConnection,
+        action: AuditAction,
+        target_id: Option<&str>,
+        payload_digest: Option<String>,
+    ) {
+        if let Err(e) = audit_repository::append(db, self, action, target_id, payload_digest).await
+        {
+            error!(error = %e, action = action.as_str(), target_id = ?target_id, "Failed to write audit log");
+        }
+    }
+}
+
+impl<S> FromRequestParts<S> for AuditContext
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let user_agent = parts
+            .headers
+            .get(USER_AGENT)
+            .and_then(|v| v.to_str().ok())
+            .map(|ua| ua.chars().take(MAX_USER_AGENT_LEN).collect());
+        let context = Self {
+            client_ip: client_ip(parts),
+            user_agent,
+            ..Default::default()
+        };
+
+        // Set by the auth middleware on protected routes
+        Ok(match parts.extensions.get::<Claims>() {
+            Some(claims) => context.with_actor(claims.sub.clone()),
+            None => context,
+        })
+    }
+}
+
+fn client_ip(parts: &Parts) -> Option<String> {
+    if *TRUST_FORWARDED_FOR {
+        let forwarded = parts
+            .headers
+            .get_all("X-Forwarded-For")
+            .iter()
+            .map(|v| v.to_str().ok())
+            .collect::<Option<Vec<_>>>()
+            .and_then(|values| forwarded_client(&values.join(","), *TRUSTED_PROXY_HOPS));
+        if let Some(ip) = forwarded {
+            return Some(ip.to_string());
+        }
+    }
+
+    parts
+        .extensions
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip().to_string())
+}
+
+/// The address the outermost of `hops` trusted proxies saw the request come
+/// from. Each proxy appends its peer, so entries left of that one are
+/// whatever the client sent and are ignored.
+fn forwarded_client(header: &str, hops: usize) -> Option<IpAddr> {
+    header
+        .rsplit(',')
+        .nth(hops - 1)
+        .and_then(|ip| ip.trim().parse().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_forwarded_client_ignores_entries_sent_by_the_client() {
+        // The client sent a fake header, the proxy appended the real peer
+        let header = "203.0.113.7, 198.51.100.23";
+        assert_eq!(
+            forwarded_client(header, 1),
+            Some("198.51.100.23".parse().unwrap())
+        );
+
+        // Behind a CDN and a load balancer
+        let header = "203.0.113.7, 198.51.100.23, 10.0.0.5";
+        assert_eq!(
+            forwarded_client(header, 2),
+            Some("198.51.100.23".parse().unwrap())
+        );
+
+        // Fewer entries than proxies, or garbage where the proxy's entry should be
+        assert_eq!(forwarded_client("198.51.100.23", 2), None);
+        assert_eq!(forwarded_client("198.51.100.23, unknown", 1), None);
+    }
+}
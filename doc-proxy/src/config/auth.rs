@@ -6,23 +6,25 @@ use axum::{
     response::{IntoResponse, Response},
     Json,
 };
-use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
 use serde::{Deserialize, Serialize};
 use std::sync::LazyLock;
 
-/// JWT secret key - in production, use a proper secret management solution
-static JWT_SECRET: LazyLock<String> = LazyLock::new(|| {
-    std::env::var("JWT_SECRET").unwrap_or_else(|_| "dev-jwt-secret-change-in-production".to_string())
-});
+use super::jwt_keys;
 
-/// JWT token expiry in hours
-static JWT_EXPIRY_HOURS: LazyLock<i64> = LazyLock::new(|| {
-    std::env::var("JWT_EXPIRY_HOURS")
+/// Access token lifetime in minutes; sessions are extended with refresh tokens
+static JWT_ACCESS_TTL_MINUTES: LazyLock<i64> = LazyLock::new(|| {
+    std::env::var("JWT_ACCESS_TTL_MINUTES")
         .ok()
         .and_then(|v| v.parse().ok())
-        .unwrap_or(24)
+        .filter(|&m| m > 0)
+        .unwrap_or(15)
 });
 
+/// Access token lifetime in seconds, as reported to clients in `expires_in`
+pub fn access_token_ttl_seconds() -> i64 {
+    *JWT_ACCESS_TTL_MINUTES * 60
+}
+
 /// Claims stored in the JWT token
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Claims {
@@ -36,6 +38,8 @@ pub struct Claims {
     pub exp: i64,
     /// Issued at (Unix timestamp)
     pub iat: i64,
+    /// Token ID, used to revoke the token before it expires
+    pub jti: String,
 }
 
 /// User roles for authorization
@@ -47,11 +51,31 @@ pub enum UserRole {
     Client,
 }
 
+impl UserRole {
+    /// Role as stored in the database
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UserRole::Admin => "admin",
+            UserRole::Staff => "staff",
+            UserRole::Client => "client",
+        }
+    }
+
+    /// Parse a stored role; unknown roles get the least privileges
+    pub fn from_db(role: &str) -> Self {
+        match role {
+            "admin" => UserRole::Admin,
+            "staff" => UserRole::Staff,
+            _ => UserRole::Client,
+        }
+    }
+}
+
 impl Claims {
     /// Create new claims for a user
     pub fn new(user_id: String, name: String, role: UserRole) -> Self {
         let now = chrono::Utc::now().timestamp();
-        let exp = now + (*JWT_EXPIRY_HOURS * 3600);
+        let exp = now + access_token_ttl_seconds();
 
         Self {
             sub: user_id,
@@ -59,31 +83,30 @@ impl Claims {
             role,
             exp,
             iat: now,
+            jti: uuid::Uuid::new_v4().to_string(),
         }
     }
 
-    /// Generate a JWT token from these claims
+    /// Expiry as timestamp
+    pub fn expires_at(&self) -> chrono::DateTime<chrono::Utc> {
+        chrono::DateTime::from_timestamp(self.exp, 0).unwrap_or_default()
+    }
+
+    /// Generate a JWT token from these claims, signed with the current key
     pub fn to_token(&self) -> Result<String, AuthError> {
-        encode(
-            &Header::default(),
-            self,
-            &EncodingKey::from_secret(JWT_SECRET.as_bytes()),
-        )
-        .map_err(|_| AuthError::TokenCreation)
+        jwt_keys::current()
+            .sign(self)
+            .map_err(|_| AuthError::TokenCreation)
     }
 
     /// Decode and validate a JWT token
     pub fn from_token(token: &str) -> Result<Self, AuthError> {
-        decode::<Claims>(
-            token,
-            &DecodingKey::from_secret(JWT_SECRET.as_bytes()),
-            &Validation::default(),
-        )
-        .map(|data| data.claims)
-        .map_err(|e| match e.kind() {
-            jsonwebtoken::errors::ErrorKind::ExpiredSignature => AuthError::TokenExpired,
-            _ => AuthError::InvalidToken,
-        })
+        jwt_keys::current()
+            .verify::<Claims>(token)
+            .map_err(|e| match e.kind() {
+                jsonwebtoken::errors::ErrorKind::ExpiredSignature => AuthError::TokenExpired,
+                _ => AuthError::InvalidToken,
+            })
     }
 }
 
@@ -95,6 +118,11 @@ pub enum AuthError {
     TokenExpired,
     TokenCreation,
     InvalidCredentials,
+    AccountLocked,
+    Forbidden,
+    OidcDisabled,
+    ProviderUnavailable,
+    Internal,
 }
 
 impl IntoResponse for AuthError {
@@ -105,6 +133,13 @@ impl IntoResponse for AuthError {
             AuthError::TokenExpired => (StatusCode::UNAUTHORIZED, "Token expired"),
             AuthError::TokenCreation => (StatusCode::INTERNAL_SERVER_ERROR, "Failed to create token"),
             AuthError::InvalidCredentials => (StatusCode::UNAUTHORIZED, "Invalid credentials"),
+            AuthError::AccountLocked => (StatusCode::LOCKED, "Account temporarily locked"),
+            AuthError::Forbidden => (StatusCode::FORBIDDEN, "Insufficient permissions"),
+            AuthError::OidcDisabled => (StatusCode::NOT_FOUND, "Single sign-on is not configured"),
+            AuthError::ProviderUnavailable => {
+                (StatusCode::BAD_GATEWAY, "Identity provider unavailable")
+            }
+            AuthError::Internal => (StatusCode::INTERNAL_SERVER_ERROR, "Internal server error"),
         };
 
         (status, Json(serde_json::json!({ "error": message }))).into_response()
@@ -122,6 +157,11 @@ where
     type Rejection = AuthError;
 
     async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        // Already validated by the auth middleware
+        if let Some(claims) = parts.extensions.get::<Claims>() {
+            return Ok(AuthUser(claims.clone()));
+        }
+
         // Get Authorization header
         let auth_header = parts
             .headers
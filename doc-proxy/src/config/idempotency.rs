@@ -0,0 +1,363 @@
+//! `Idempotency-Key` support for state-changing routes.
+//!
+//! A tablet retrying a request after a timeout sends the same key again and
+//! gets the response of the first request instead of signing twice. The key
+//! is bound to a fingerprint of the request; reusing it for a different
+//! request is refused. Responses are kept for `IDEMPOTENCY_RETENTION_HOURS`.
+//!
+//! A key is released when its request is dropped, e.g. because the tablet
+//! hung up. Keys left behind by a crash can be claimed again after
+//! `IDEMPOTENCY_LOCK_SECS`.
+
+use std::sync::LazyLock;
+
+use axum::{
+    Json,
+    body::{Body, to_bytes},
+    extract::{Request, State},
+    http::{HeaderName, HeaderValue, Method, StatusCode, header::CONTENT_TYPE},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::{Duration, Utc};
+use sha2::{Digest, Sha256};
+use tracing::{error, info, warn};
+
+use super::auth::Claims;
+use crate::{
+    AppState,
+    repositories::{
+        entity::idempotency_key,
+        idempotency_repository::{self, Claim, NewIdempotencyKey},
+    },
+};
+
+/// Request header carrying the key
+pub const IDEMPOTENCY_KEY: HeaderName = HeaderName::from_static("idempotency-key");
+
+/// Response header set on replayed responses
+pub const IDEMPOTENCY_REPLAYED: HeaderName = HeaderName::from_static("idempotency-replayed");
+
+/// Longest accepted key
+const MAX_KEY_LEN: usize = 255;
+
+/// Largest request body buffered for the fingerprint, as axum's default
+/// body limit for extractors
+const MAX_BODY_BYTES: usize = 2 * 1024 * 1024;
+
+/// How long responses are kept for replay
+static RETENTION: LazyLock<Duration> = LazyLock::new(|| {
+    let hours = std::env::var("IDEMPOTENCY_RETENTION_HOURS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|&h| h > 0)
+        .unwrap_or(24);
+    Duration::hours(hours)
+});
+
+/// How long a request holds its key before a retry may take it over
+static LOCK_DURATION: LazyLock<Duration> = LazyLock::new(|| {
+    let secs = std::env::var("IDEMPOTENCY_LOCK_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|&s| s > 0)
+        .unwrap_or(120);
+    Duration::seconds(secs)
+});
+
+#[derive(Debug)]
+pub enum IdempotencyError {
+    InvalidKey,
+    BodyTooLarge,
+    KeyReused,
+    InProgress,
+    Internal,
+}
+
+impl IntoResponse for IdempotencyError {
+    fn into_response(self) -> Response {
+        let (status, message) = match self {
+            IdempotencyError::InvalidKey => (
+                StatusCode::BAD_REQUEST,
+                "Idempotency key must have 1 to 255 visible characters",
+            ),
+            IdempotencyError::BodyTooLarge => {
+                (StatusCode::PAYLOAD_TOO_LARGE, "Request body too large")
+            }
+            IdempotencyError::KeyReused => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "Idempotency key was already used for a different request",
+            ),
+            IdempotencyError::InProgress => (
+                StatusCode::CONFLICT,
+                "A request with this idempotency key is still in progress",
+            ),
+            IdempotencyError::Internal => {
+                (StatusCode::INTERNAL_SERVER_ERROR, "Internal server error")
+            }
+        };
+
+        (status, Json(serde_json::json!({ "error": message }))).into_response()
+    }
+}
+
+/// SHA-256 over method, path with query and body, length-prefixed so
+/// moving bytes between the parts changes the fingerprint.
+fn request_fingerprint(method: &Method, path: &str, body: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    for part in [method.as_str().as_bytes(), path.as_bytes(), body] {
+        hasher.update((part.len() as u64).to_be_bytes());
+        hasher.update(part);
+    }
+    hex::encode(hasher.finalize())
+}
+
+fn parse_key(value: &HeaderValue) -> Result<String, IdempotencyError> {
+    let key = value
+        .to_str()
+        .map_err(|_| IdempotencyError::InvalidKey)?
+        .trim();
+    if key.is_empty() || key.len() > MAX_KEY_LEN {
+        return Err(IdempotencyError::InvalidKey);
+    }
+    Ok(key.to_string())
+}
+
+/// The stored response of an earlier request
+fn replay(entry: idempotency_key::Model) -> Result<Response, IdempotencyError> {
+    let status = entry
+        .status_code
+        .and_then(|code| StatusCode::from_u16(u16::try_from(code).ok()?).ok())
+        .ok_or(IdempotencyError::InProgress)?;
+
+    let mut response = Response::new(Body::from(entry.response_body.unwrap_or_default()));
+    *response.status_mut() = status;
+    let headers = response.headers_mut();
+    if let Some(content_type) = entry
+        .content_type
+        .and_then(|c| HeaderValue::from_str(&c).ok())
+    {
+        headers.insert(CONTENT_TYPE, content_type);
+    }
+    headers.insert(IDEMPOTENCY_REPLAYED, HeaderValue::from_static("true"));
+    Ok(response)
+}
+
+/// Replays responses of requests sent again with the same `Idempotency-Key`.
+///
+/// Layered inside `auth_middleware`, since keys are kept per token subject.
+/// Requests without the header and safe methods pass through. Responses
+/// with a server error are not kept, so the request can be retried.
+pub async fn idempotency_middleware(
+    State(state): State<AppState>,
+    request: Request<Body>,
+    next: Next,
+) -> Result<Response, IdempotencyError> {
+    if matches!(
+        *request.method(),
+        Method::GET | Method::HEAD | Method::OPTIONS
+    ) {
+        return Ok(next.run(request).await);
+    }
+    let Some(key) = request.headers().get(&IDEMPOTENCY_KEY) else {
+        return Ok(next.run(request).await);
+    };
+    let key = parse_key(key)?;
+    let actor = request
+        .extensions()
+        .get::<Claims>()
+        .map_or_else(|| "anonymous".to_string(), |claims| claims.sub.clone());
+
+    let (parts, body) = request.into_parts();
+    let body = to_bytes(body, MAX_BODY_BYTES)
+        .await
+        .map_err(|_| IdempotencyError::BodyTooLarge)?;
+    let path = parts
+        .uri
+        .path_and_query()
+        .map_or_else(|| parts.uri.path(), |p| p.as_str())
+        .to_string();
+    let fingerprint = request_fingerprint(&parts.method, &path, &body);
+
+    let claim = idempotency_repository::claim(
+        &state.db,
+        NewIdempotencyKey {
+            actor: actor.clone(),
+            idempotency_key: key.clone(),
+            request_fingerprint: fingerprint.clone(),
+            method: parts.method.to_string(),
+            path: path.clone(),
+            locked_until: Utc::now() + *LOCK_DURATION,
+            expires_at: Utc::now() + *RETENTION,
+        },
+    )
+    .await
+    .map_err(|e| {
+        error!(error = %e, "Failed to claim idempotency key");
+        IdempotencyError::Internal
+    })?;
+    match claim {
+        Claim::Existing(entry) if entry.request_fingerprint != fingerprint => {
+            warn!(key = %key, actor = %actor, path = %path, "Idempotency key reused");
+            return Err(IdempotencyError::KeyReused);
+        }
+        Claim::Existing(entry) => {
+            info!(key = %key, actor = %actor, path = %path, "Replaying response");
+            return replay(*entry);
+        }
+        Claim::Claimed => {}
+    }
+
+    let guard = ClaimGuard {
+        state: state.clone(),
+        actor: actor.clone(),
+        key: key.clone(),
+        armed: true,
+    };
+    let response = next.run(Request::from_parts(parts, Body::from(body))).await;
+    if response.status().is_server_error() {
+        guard.release().await;
+        return Ok(response);
+    }
+
+    let (parts, body) = response.into_parts();
+    let body = match to_bytes(body, usize::MAX).await {
+        Ok(body) => body,
+        Err(e) => {
+            error!(error = %e, "Failed to read response for idempotency key");
+            guard.release().await;
+            return Err(IdempotencyError::Internal);
+        }
+    };
+    let content_type = parts
+        .headers
+        .get(CONTENT_TYPE)
+        .and_then(|c| c.to_str().ok())
+        .map(str::to_string);
+    if let Err(e) = idempotency_repository::complete(
+        &state.db,
+        &actor,
+        &key,
+        parts.status.as_u16() as i16,
+        content_type,
+        body.to_vec(),
+    )
+    .await
+    {
+        // The request ran; a retry finds the claim and gets a 409 until the
+        // lock runs out
+        error!(error = %e, key = %key, "Failed to store response for idempotency key");
+    }
+    guard.disarm();
+
+    Ok(Response::from_parts(parts, Body::from(body)))
+}
+
+/// A claimed key, released if the request is dropped before its response is
+/// stored
+struct ClaimGuard {
+    state: AppState,
+    actor: String,
+    key: String,
+    armed: bool,
+}
+
+impl ClaimGuard {
+    async fn release(mut self) {
+        self.armed = false;
+        release(&self.state, &self.actor, &self.key).await;
+    }
+
+    fn disarm(mut self) {
+        self.armed = false;
+    }
+}
+
+impl Drop for ClaimGuard {
+    fn drop(&mut self) {
+        if !self.armed {
+            return;
+        }
+        warn!(key = %self.key, actor = %self.actor, "Request dropped, releasing idempotency key");
+        let state = self.state.clone();
+        let (actor, key) = (
+            std::mem::take(&mut self.actor),
+            std::mem::take(&mut self.key),
+        );
+        tokio::spawn(async move { release(&state, &actor, &key).await });
+    }
+}
+
+async fn release(state: &AppState, actor: &str, key: &str) {
+    if let Err(e) = idempotency_repository::release(&state.db, actor, key).await {
+        error!(error = %e, key = %key, "Failed to release idempotency key");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(status_code: Option<i16>) -> idempotency_key::Model {
+        idempotency_key::Model {
+            actor: "user-1".to_string(),
+            idempotency_key: "key-1".to_string(),
+            request_fingerprint: String::new(),
+            method: "POST".to_string(),
+            path: "/leistungsnachweise/LN-1/sign".to_string(),
+            status_code,
+            content_type: Some("application/json".to_string()),
+            response_body: Some(b"{\"id\":\"LN-1\"}".to_vec()),
+            locked_until: Utc::now(),
+            created_at: Utc::now(),
+            expires_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_fingerprint_covers_method_path_and_body() {
+        let sign = request_fingerprint(&Method::POST, "/leistungsnachweise/1/sign", b"{}");
+        assert_eq!(
+            sign,
+            request_fingerprint(&Method::POST, "/leistungsnachweise/1/sign", b"{}")
+        );
+        assert_ne!(
+            sign,
+            request_fingerprint(&Method::POST, "/leistungsnachweise/2/sign", b"{}")
+        );
+        assert_ne!(
+            sign,
+            request_fingerprint(&Method::POST, "/leistungsnachweise/1/sign", b"{ }")
+        );
+        assert_ne!(
+            request_fingerprint(&Method::POST, "/a", b"b"),
+            request_fingerprint(&Method::POST, "/ab", b"")
+        );
+    }
+
+    #[test]
+    fn test_key_must_be_visible_and_bounded() {
+        assert_eq!(
+            parse_key(&HeaderValue::from_static(" abc ")).unwrap(),
+            "abc"
+        );
+        assert!(parse_key(&HeaderValue::from_static("  ")).is_err());
+        let long = "k".repeat(MAX_KEY_LEN + 1);
+        assert!(parse_key(&HeaderValue::from_str(&long).unwrap()).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_replay_returns_stored_response() {
+        let response = replay(entry(Some(200))).unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[CONTENT_TYPE], "application/json");
+        assert_eq!(response.headers()[IDEMPOTENCY_REPLAYED], "true");
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&body[..], b"{\"id\":\"LN-1\"}");
+
+        assert!(matches!(
+            replay(entry(None)),
+            Err(IdempotencyError::InProgress)
+        ));
+    }
+}
@@ -0,0 +1,447 @@
+//! Keys for signing and verifying access tokens.
+//!
+//! `JWT_KEYS` lists asymmetric keys as `kid:algorithm:path` entries separated
+//! by commas, e.g. `2026-10:EdDSA:/run/keys/2026-10.pem`. The first key signs
+//! new tokens; all keys verify tokens and are published as JWKS, so other
+//! services can verify our tokens without a shared secret. To rotate, put the
+//! new key first, keep the old one until the last tokens signed with it have
+//! expired, then remove it. The keys are reloaded on SIGHUP.
+//!
+//! Without `JWT_KEYS`, tokens are signed with the HMAC secret `JWT_SECRET`.
+
+use std::sync::{Arc, LazyLock, RwLock};
+
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use jsonwebtoken::{
+    Algorithm, DecodingKey, EncodingKey, Header, Validation, decode, decode_header, encode,
+    errors::{Error as JwtError, ErrorKind},
+    jwk::{
+        AlgorithmParameters, CommonParameters, EllipticCurve, EllipticCurveKeyParameters, Jwk,
+        JwkSet, KeyAlgorithm, OctetKeyPairParameters, PublicKeyUse, RSAKeyParameters,
+    },
+};
+use ring::{
+    rand::SystemRandom,
+    rsa,
+    signature::{
+        ECDSA_P256_SHA256_FIXED_SIGNING, ECDSA_P384_SHA384_FIXED_SIGNING, EcdsaKeyPair,
+        Ed25519KeyPair, KeyPair,
+    },
+};
+use serde::{Serialize, de::DeserializeOwned};
+use tracing::{error, info};
+
+/// Secret of earlier versions; refused unless `ALLOW_DEV_JWT_SECRET` is set
+pub const DEV_JWT_SECRET: &str = "dev-jwt-secret-change-in-production";
+
+/// Placeholder `JWT_SECRET` of earlier `.env.example` files, refused like the
+/// default since copied examples end up in deployments
+const EXAMPLE_JWT_SECRET: &str = "your-super-secret-jwt-key-change-in-production";
+
+#[derive(Debug, thiserror::Error)]
+pub enum KeyError {
+    #[error(
+        "JWT_SECRET is missing or the development default; configure JWT_KEYS or JWT_SECRET, \
+         or set ALLOW_DEV_JWT_SECRET=true for development"
+    )]
+    InsecureSecret,
+    #[error("Invalid JWT_KEYS entry '{0}', expected kid:algorithm:path")]
+    InvalidEntry(String),
+    #[error("Unsupported algorithm {algorithm} for key {kid}")]
+    UnsupportedAlgorithm { kid: String, algorithm: String },
+    #[error("Key ID {0} is configured more than once")]
+    DuplicateKid(String),
+    #[error("Failed to read key {kid}: {source}")]
+    Io { kid: String, source: std::io::Error },
+    #[error("Invalid key {kid}: {reason}")]
+    InvalidKey { kid: String, reason: String },
+}
+
+/// A key able to sign and verify tokens
+struct TokenKey {
+    /// `None` for the HMAC secret, which is not published
+    kid: Option<String>,
+    algorithm: Algorithm,
+    encoding: EncodingKey,
+    decoding: DecodingKey,
+    jwk: Option<Jwk>,
+}
+
+/// The configured keys; the first one signs
+pub struct KeyRing {
+    keys: Vec<TokenKey>,
+}
+
+impl KeyRing {
+    /// Load the keys from `JWT_KEYS`, falling back to `JWT_SECRET`.
+    pub fn from_env() -> Result<Self, KeyError> {
+        let allow_dev_secret = std::env::var("ALLOW_DEV_JWT_SECRET")
+            .map(|v| v.to_lowercase() == "true" || v == "1")
+            .unwrap_or(false);
+
+        Self::load(
+            std::env::var("JWT_KEYS").ok().as_deref(),
+            std::env::var("JWT_SECRET").ok().as_deref(),
+            allow_dev_secret,
+        )
+    }
+
+    pub fn load(
+        keys: Option<&str>,
+        secret: Option<&str>,
+        allow_dev_secret: bool,
+    ) -> Result<Self, KeyError> {
+        let entries: Vec<&str> = keys
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|e| !e.is_empty())
+            .collect();
+
+        if entries.is_empty() {
+            let secret = secret.filter(|s| !s.is_empty());
+            let secret = match secret {
+                Some(secret) if ![DEV_JWT_SECRET, EXAMPLE_JWT_SECRET].contains(&secret) => secret,
+                _ if allow_dev_secret => secret.unwrap_or(DEV_JWT_SECRET),
+                _ => return Err(KeyError::InsecureSecret),
+            };
+            return Ok(Self {
+                keys: vec![TokenKey::hmac(secret)],
+            });
+        }
+
+        let mut ring = Self { keys: Vec::new() };
+        for entry in entries {
+            let mut parts = entry.splitn(3, ':');
+            let (Some(kid), Some(algorithm), Some(path)) =
+                (parts.next(), parts.next(), parts.next())
+            else {
+                return Err(KeyError::InvalidEntry(entry.to_string()));
+            };
+            if kid.is_empty() || path.is_empty() {
+                return Err(KeyError::InvalidEntry(entry.to_string()));
+            }
+            if ring.find(kid).is_some() {
+                return Err(KeyError::DuplicateKid(kid.to_string()));
+            }
+
+            let pem = std::fs::read(path).map_err(|source| KeyError::Io {
+                kid: kid.to_string(),
+                source,
+            })?;
+            ring.keys.push(TokenKey::from_pem(kid, algorithm, &pem)?);
+        }
+        Ok(ring)
+    }
+
+    /// Key ID of the signing key, `None` for the HMAC secret
+    pub fn signing_kid(&self) -> Option<&str> {
+        self.keys[0].kid.as_deref()
+    }
+
+    /// Sign claims with the first key.
+    pub fn sign<T: Serialize>(&self, claims: &T) -> Result<String, JwtError> {
+        let key = &self.keys[0];
+        let mut header = Header::new(key.algorithm);
+        header.kid = key.kid.clone();
+        encode(&header, claims, &key.encoding)
+    }
+
+    /// Verify a token with the key named by its `kid`.
+    pub fn verify<T: DeserializeOwned>(&self, token: &str) -> Result<T, JwtError> {
+        let header = decode_header(token)?;
+        let key = match &header.kid {
+            Some(kid) => self.find(kid),
+            None => self.keys.iter().find(|k| k.kid.is_none()),
+        }
+        .ok_or_else(|| JwtError::from(ErrorKind::InvalidToken))?;
+
+        decode::<T>(token, &key.decoding, &Validation::new(key.algorithm)).map(|data| data.claims)
+    }
+
+    /// Public keys for `/.well-known/jwks.json`
+    pub fn jwks(&self) -> JwkSet {
+        JwkSet {
+            keys: self.keys.iter().filter_map(|k| k.jwk.clone()).collect(),
+        }
+    }
+
+    fn find(&self, kid: &str) -> Option<&TokenKey> {
+        self.keys.iter().find(|k| k.kid.as_deref() == Some(kid))
+    }
+}
+
+impl TokenKey {
+    fn hmac(secret: &str) -> Self {
+        Self {
+            kid: None,
+            algorithm: Algorithm::HS256,
+            encoding: EncodingKey::from_secret(secret.as_bytes()),
+            decoding: DecodingKey::from_secret(secret.as_bytes()),
+            jwk: None,
+        }
+    }
+
+    /// Load a private key (PKCS#8, or PKCS#1 for RSA) and derive its public JWK.
+    fn from_pem(kid: &str, algorithm_name: &str, pem: &[u8]) -> Result<Self, KeyError> {
+        let invalid = |reason: String| KeyError::InvalidKey {
+            kid: kid.to_string(),
+            reason,
+        };
+        let unsupported = || KeyError::UnsupportedAlgorithm {
+            kid: kid.to_string(),
+            algorithm: algorithm_name.to_string(),
+        };
+
+        let algorithm: Algorithm = algorithm_name.parse().map_err(|_| unsupported())?;
+        let parsed = pem::parse(pem).map_err(|e| invalid(e.to_string()))?;
+        let der = parsed.contents();
+        let rejected = |e: ring::error::KeyRejected| invalid(e.to_string());
+
+        let (encoding, params) = match algorithm {
+            Algorithm::RS256 | Algorithm::RS384 | Algorithm::RS512 => {
+                let key_pair = match parsed.tag() {
+                    "RSA PRIVATE KEY" => rsa::KeyPair::from_der(der),
+                    _ => rsa::KeyPair::from_pkcs8(der),
+                }
+                .map_err(rejected)?;
+                let public = rsa::PublicKeyComponents::<Vec<u8>>::from(key_pair.public());
+                let encoding =
+                    EncodingKey::from_rsa_pem(pem).map_err(|e| invalid(e.to_string()))?;
+                let params = AlgorithmParameters::RSA(RSAKeyParameters {
+                    key_type: Default::default(),
+                    n: URL_SAFE_NO_PAD.encode(&public.n),
+                    e: URL_SAFE_NO_PAD.encode(&public.e),
+                });
+                (encoding, params)
+            }
+            Algorithm::ES256 | Algorithm::ES384 => {
+                let (signing, curve) = match algorithm {
+                    Algorithm::ES256 => (&ECDSA_P256_SHA256_FIXED_SIGNING, EllipticCurve::P256),
+                    _ => (&ECDSA_P384_SHA384_FIXED_SIGNING, EllipticCurve::P384),
+                };
+                let key_pair = EcdsaKeyPair::from_pkcs8(signing, der, &SystemRandom::new())
+                    .map_err(rejected)?;
+                // Uncompressed point: 0x04 || x || y
+                let point = &key_pair.public_key().as_ref()[1..];
+                let (x, y) = point.split_at(point.len() / 2);
+                let encoding = EncodingKey::from_ec_pem(pem).map_err(|e| invalid(e.to_string()))?;
+                let params = AlgorithmParameters::EllipticCurve(EllipticCurveKeyParameters {
+                    key_type: Default::default(),
+                    curve,
+                    x: URL_SAFE_NO_PAD.encode(x),
+                    y: URL_SAFE_NO_PAD.encode(y),
+                });
+                (encoding, params)
+            }
+            Algorithm::EdDSA => {
+                let key_pair = Ed25519KeyPair::from_pkcs8_maybe_unchecked(der).map_err(rejected)?;
+                let encoding = EncodingKey::from_ed_pem(pem).map_err(|e| invalid(e.to_string()))?;
+                let params = AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                    key_type: Default::default(),
+                    curve: EllipticCurve::Ed25519,
+                    x: URL_SAFE_NO_PAD.encode(key_pair.public_key().as_ref()),
+                });
+                (encoding, params)
+            }
+            _ => return Err(unsupported()),
+        };
+
+        let jwk = Jwk {
+            common: CommonParameters {
+                public_key_use: Some(PublicKeyUse::Signature),
+                key_algorithm: algorithm_name.parse::<KeyAlgorithm>().ok(),
+                key_id: Some(kid.to_string()),
+                ..Default::default()
+            },
+            algorithm: params,
+        };
+        let decoding = DecodingKey::from_jwk(&jwk).map_err(|e| invalid(e.to_string()))?;
+
+        Ok(Self {
+            kid: Some(kid.to_string()),
+            algorithm,
+            encoding,
+            decoding,
+            jwk: Some(jwk),
+        })
+    }
+}
+
+static KEYS: LazyLock<RwLock<Option<Arc<KeyRing>>>> = LazyLock::new(|| RwLock::new(None));
+
+/// Load the keys at startup; the server must not start without them.
+pub fn init() -> Result<(), KeyError> {
+    let ring = KeyRing::from_env()?;
+    match ring.signing_kid() {
+        Some(kid) => info!(kid = %kid, keys = ring.keys.len(), "Loaded JWT signing keys"),
+        None => info!("Signing JWTs with JWT_SECRET"),
+    }
+    *KEYS.write().unwrap_or_else(|e| e.into_inner()) = Some(Arc::new(ring));
+    Ok(())
+}
+
+/// The current keys
+pub fn current() -> Arc<KeyRing> {
+    KEYS.read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+        .expect("JWT keys are loaded at startup")
+}
+
+/// Sign with a fixed HMAC secret unless keys are loaded already, for tests
+#[cfg(test)]
+pub fn init_for_tests() {
+    let mut keys = KEYS.write().unwrap_or_else(|e| e.into_inner());
+    if keys.is_none() {
+        let ring = KeyRing::load(None, Some("test-secret"), false).expect("test secret");
+        *keys = Some(Arc::new(ring));
+    }
+}
+
+/// Reload the keys on SIGHUP; a broken configuration keeps the old keys.
+pub fn spawn_reload_on_sighup() {
+    #[cfg(unix)]
+    tokio::spawn(async {
+        use tokio::signal::unix::{SignalKind, signal};
+
+        let Ok(mut hangup) = signal(SignalKind::hangup()) else {
+            error!("Failed to listen for SIGHUP, JWT keys will not be reloaded");
+            return;
+        };
+        while hangup.recv().await.is_some() {
+            if let Err(e) = init() {
+                error!(error = %e, "Failed to reload JWT keys, keeping the current ones");
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use ring::signature::ECDSA_P256_SHA256_FIXED_SIGNING;
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct TestClaims {
+        sub: String,
+        exp: i64,
+    }
+
+    fn claims() -> TestClaims {
+        TestClaims {
+            sub: "user-1".to_string(),
+            exp: chrono::Utc::now().timestamp() + 60,
+        }
+    }
+
+    /// Write a freshly generated PKCS#8 key as PEM and return its path
+    fn write_key(name: &str, pkcs8: &[u8]) -> String {
+        let path = std::env::temp_dir().join(format!("{}-{}.pem", name, uuid::Uuid::new_v4()));
+        std::fs::write(&path, pem::encode(&pem::Pem::new("PRIVATE KEY", pkcs8))).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    fn ed25519_key(name: &str) -> String {
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+        write_key(name, pkcs8.as_ref())
+    }
+
+    #[test]
+    fn test_default_secret_is_refused_without_dev_flag() {
+        assert!(matches!(
+            KeyRing::load(None, None, false),
+            Err(KeyError::InsecureSecret)
+        ));
+        for secret in [DEV_JWT_SECRET, EXAMPLE_JWT_SECRET, ""] {
+            assert!(matches!(
+                KeyRing::load(None, Some(secret), false),
+                Err(KeyError::InsecureSecret)
+            ));
+        }
+        assert!(KeyRing::load(None, None, true).is_ok());
+        assert!(KeyRing::load(None, Some(EXAMPLE_JWT_SECRET), true).is_ok());
+
+        let ring = KeyRing::load(None, Some("a-real-secret"), false).unwrap();
+        let token = ring.sign(&claims()).unwrap();
+        assert_eq!(ring.verify::<TestClaims>(&token).unwrap().sub, "user-1");
+        assert!(ring.jwks().keys.is_empty());
+    }
+
+    #[test]
+    fn test_asymmetric_keys_sign_with_first_and_verify_with_all() {
+        let old = ed25519_key("old");
+        let pkcs8 =
+            EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &SystemRandom::new())
+                .unwrap();
+        let new = write_key("new", pkcs8.as_ref());
+
+        let before = KeyRing::load(Some(&format!("old:EdDSA:{}", old)), None, false).unwrap();
+        let old_token = before.sign(&claims()).unwrap();
+
+        let spec = format!("new:ES256:{}, old:EdDSA:{}", new, old);
+        let rotated = KeyRing::load(Some(&spec), None, false).unwrap();
+        let new_token = rotated.sign(&claims()).unwrap();
+
+        assert_eq!(rotated.signing_kid(), Some("new"));
+        assert_eq!(
+            decode_header(&new_token).unwrap().kid.as_deref(),
+            Some("new")
+        );
+        assert!(rotated.verify::<TestClaims>(&new_token).is_ok());
+        assert!(rotated.verify::<TestClaims>(&old_token).is_ok());
+        // A token of a removed key is rejected
+        assert!(before.verify::<TestClaims>(&new_token).is_err());
+
+        let jwks = rotated.jwks();
+        assert_eq!(jwks.keys.len(), 2);
+        assert!(jwks.find("new").is_some());
+        let Some(Jwk {
+            algorithm: AlgorithmParameters::OctetKeyPair(params),
+            ..
+        }) = jwks.find("old")
+        else {
+            panic!("old key is not an OKP key");
+        };
+        assert_eq!(params.curve, EllipticCurve::Ed25519);
+
+        // Other services verify with the published key alone
+        let decoding = DecodingKey::from_jwk(jwks.find("new").unwrap()).unwrap();
+        assert!(
+            decode::<TestClaims>(&new_token, &decoding, &Validation::new(Algorithm::ES256)).is_ok()
+        );
+
+        std::fs::remove_file(old).unwrap();
+        std::fs::remove_file(new).unwrap();
+    }
+
+    #[test]
+    fn test_invalid_key_configuration_is_rejected() {
+        let key = ed25519_key("invalid");
+
+        assert!(matches!(
+            KeyRing::load(Some("no-path"), None, false),
+            Err(KeyError::InvalidEntry(_))
+        ));
+        assert!(matches!(
+            KeyRing::load(Some(&format!("a:HS256:{}", key)), None, false),
+            Err(KeyError::UnsupportedAlgorithm { .. })
+        ));
+        assert!(matches!(
+            KeyRing::load(Some(&format!("a:RS256:{}", key)), None, false),
+            Err(KeyError::InvalidKey { .. })
+        ));
+        assert!(matches!(
+            KeyRing::load(Some(&format!("a:EdDSA:{0},a:EdDSA:{0}", key)), None, false),
+            Err(KeyError::DuplicateKid(_))
+        ));
+        assert!(matches!(
+            KeyRing::load(Some("a:EdDSA:/nonexistent/key.pem"), None, false),
+            Err(KeyError::Io { .. })
+        ));
+
+        std::fs::remove_file(key).unwrap();
+    }
+}
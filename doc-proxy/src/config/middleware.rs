@@ -1,43 +1,73 @@
 use axum::{
     body::Body,
-    extract::Request,
-    http::{header::AUTHORIZATION, StatusCode},
+    extract::{Request, State},
+    http::header::AUTHORIZATION,
     middleware::Next,
     response::Response,
 };
+use tracing::error;
 
-use super::auth::Claims;
+use super::auth::{AuthError, Claims, UserRole};
+use crate::{repositories::token_repository, AppState};
 
-/// JWT authentication middleware.
-/// Validates the Bearer token in the Authorization header.
-/// In development mode (AUTH_DISABLED=true), allows all requests.
-pub async fn auth_middleware(request: Request<Body>, next: Next) -> Result<Response, StatusCode> {
-    // Check if auth is disabled (for development)
-    let auth_disabled = std::env::var("AUTH_DISABLED")
+/// Whether authentication is disabled (AUTH_DISABLED=true, development only)
+pub fn auth_disabled() -> bool {
+    std::env::var("AUTH_DISABLED")
         .map(|v| v.to_lowercase() == "true" || v == "1")
-        .unwrap_or(false);
+        .unwrap_or(false)
+}
 
-    if auth_disabled {
+/// JWT authentication middleware.
+/// Validates the Bearer token in the Authorization header and rejects
+/// tokens revoked by logout. The claims are handed on to later layers and
+/// handlers as a request extension.
+/// In development mode (AUTH_DISABLED=true), allows all requests.
+pub async fn auth_middleware(
+    State(state): State<AppState>,
+    mut request: Request<Body>,
+    next: Next,
+) -> Result<Response, AuthError> {
+    if auth_disabled() {
         return Ok(next.run(request).await);
     }
 
-    let auth_header = request
+    let token = request
         .headers()
         .get(AUTHORIZATION)
-        .and_then(|h| h.to_str().ok());
+        .and_then(|h| h.to_str().ok())
+        .ok_or(AuthError::MissingToken)?
+        .strip_prefix("Bearer ")
+        .ok_or(AuthError::InvalidToken)?;
+    let claims = Claims::from_token(token)?;
 
-    match auth_header {
-        Some(header) if validate_jwt(header) => Ok(next.run(request).await),
-        _ => Err(StatusCode::UNAUTHORIZED),
+    let revoked = token_repository::is_access_token_revoked(&state.db, &claims.jti)
+        .await
+        .map_err(|e| {
+            error!(error = %e, "Failed to check token revocation");
+            AuthError::Internal
+        })?;
+    if revoked {
+        return Err(AuthError::InvalidToken);
     }
-}
 
-/// Validate JWT token from Authorization header
-fn validate_jwt(header: &str) -> bool {
-    let token = match header.strip_prefix("Bearer ") {
-        Some(t) => t,
-        None => return false,
-    };
+    request.extensions_mut().insert(claims);
+    Ok(next.run(request).await)
+}
 
-    Claims::from_token(token).is_ok()
+/// Role check for a group of routes, layered inside `auth_middleware`:
+///
+/// ```ignore
+/// .route_layer(middleware::from_fn_with_state(ADMIN, require_role))
+/// ```
+pub async fn require_role(
+    State(allowed): State<&'static [UserRole]>,
+    request: Request<Body>,
+    next: Next,
+) -> Result<Response, AuthError> {
+    match request.extensions().get::<Claims>() {
+        Some(claims) if allowed.contains(&claims.role) => Ok(next.run(request).await),
+        Some(_) => Err(AuthError::Forbidden),
+        None if auth_disabled() => Ok(next.run(request).await),
+        None => Err(AuthError::MissingToken),
+    }
 }
@@ -1,5 +1,9 @@
+pub mod audit;
 pub mod auth;
 pub mod database;
+pub mod idempotency;
+pub mod jwt_keys;
 pub mod logger;
 pub mod middleware;
+pub mod policy;
 pub mod router;
@@ -0,0 +1,65 @@
+//! Authorization policy: which roles may use a route group and which
+//! clients (Versicherte) a token may access.
+
+use std::collections::HashSet;
+
+use sea_orm::DatabaseConnection;
+use uuid::Uuid;
+
+use super::auth::{Claims, UserRole};
+use crate::repositories::{device_repository, user_repository};
+
+/// Routes for administrators only
+pub const ADMIN: &[UserRole] = &[UserRole::Admin];
+
+/// Routes for staff and administrators
+pub const STAFF: &[UserRole] = &[UserRole::Admin, UserRole::Staff];
+
+/// Clients whose documents a token may access
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClientScope {
+    /// Every client (admins, or authentication disabled)
+    All,
+    /// Only the listed Versichertennummern
+    Clients(HashSet<String>),
+}
+
+impl ClientScope {
+    pub fn permits(&self, versichertennummer: &str) -> bool {
+        match self {
+            ClientScope::All => true,
+            ClientScope::Clients(clients) => clients.contains(versichertennummer.trim()),
+        }
+    }
+
+    /// Resolve the scope of a token.
+    ///
+    /// Admins see every client. Client tokens are tied to the account's own
+    /// Versichertennummer and staff tokens to the clients assigned to the
+    /// account. Device tokens act for the user the device is bound to;
+    /// unbound devices see no clients.
+    pub async fn resolve(db: &DatabaseConnection, claims: &Claims) -> Result<Self, sea_orm::DbErr> {
+        if claims.role == UserRole::Admin {
+            return Ok(ClientScope::All);
+        }
+
+        let user_id = match claims.sub.strip_prefix("device-") {
+            Some(device_id) => match Uuid::parse_str(device_id) {
+                Ok(device_id) => device_repository::find_by_id(db, device_id)
+                    .await?
+                    .filter(|d| d.is_active)
+                    .and_then(|d| d.user_id),
+                Err(_) => None,
+            },
+            None => Uuid::parse_str(&claims.sub).ok(),
+        };
+        let clients = match user_id {
+            Some(user_id) => {
+                user_repository::find_accessible_clients(db, user_id, &claims.role).await?
+            }
+            None => Vec::new(),
+        };
+
+        Ok(ClientScope::Clients(clients.into_iter().collect()))
+    }
+}
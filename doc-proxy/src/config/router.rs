@@ -1,8 +1,15 @@
-use axum::{middleware, routing::delete, routing::get, routing::post, Router};
+use axum::{middleware, routing::delete, routing::get, routing::patch, routing::post, Router};
 
 use crate::{
-    config::middleware::auth_middleware,
-    handlers::{auth, device, health, leistungsnachweis},
+    config::{
+        idempotency::idempotency_middleware,
+        middleware::{auth_middleware, require_role},
+        policy::{ADMIN, STAFF},
+    },
+    handlers::{
+        abrechnung, audit, auth, device, fehlernachricht, health, leistungsnachweis, lieferung,
+        metrics, users_api,
+    },
     AppState,
 };
 
@@ -12,19 +19,32 @@ pub fn init_routes(state: AppState) -> Router {
         .route("/auth/login", post(auth::login))
         .route("/auth/refresh", post(auth::refresh))
         .route("/auth/logout", post(auth::logout))
-        .route("/auth/device", post(auth::device_auth));
+        .route("/auth/device", post(auth::device_auth))
+        .route("/auth/device/pair", post(device::pair_device))
+        .route("/auth/oidc/login", get(auth::oidc_login))
+        .route("/auth/oidc/callback", get(auth::oidc_callback));
 
     // Protected auth routes
     let protected_auth_routes = Router::new()
         .route("/auth/me", get(auth::me))
-        .layer(middleware::from_fn(auth_middleware));
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            auth_middleware,
+        ));
 
-    // Protected routes - require authorization
-    let protected_routes = Router::new()
+    // Documents of a single client - the token must cover the client
+    let client_routes = Router::new()
         .route(
             "/leistungsnachweise",
             get(leistungsnachweis::list_leistungsnachweise),
         )
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            leistungsnachweis::authorize_client,
+        ));
+
+    // Single documents - the token must cover the document's client
+    let document_routes = Router::new()
         .route(
             "/leistungsnachweise/{id}",
             get(leistungsnachweis::get_leistungsnachweis),
@@ -33,28 +53,613 @@ pub fn init_routes(state: AppState) -> Router {
             "/leistungsnachweise/{id}/sign",
             post(leistungsnachweis::sign_leistungsnachweis),
         )
-        .layer(middleware::from_fn(auth_middleware));
+        .route(
+            "/leistungsnachweise/{id}/xml",
+            get(leistungsnachweis::get_leistungsnachweis_xml),
+        )
+        .route(
+            "/leistungsnachweise/{id}/pdf",
+            get(leistungsnachweis::get_leistungsnachweis_pdf),
+        )
+        .route(
+            "/leistungsnachweise/{id}/submit",
+            post(leistungsnachweis::submit_leistungsnachweis),
+        )
+        .route(
+            "/leistungsnachweise/{id}/history",
+            get(leistungsnachweis::get_status_history),
+        )
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            leistungsnachweis::authorize_document,
+        ));
+
+    // Status changes of single documents - staff or admin, and the token
+    // must cover the document's client
+    let review_routes = Router::new()
+        .route(
+            "/leistungsnachweise/{id}/reject",
+            post(leistungsnachweis::reject_leistungsnachweis),
+        )
+        .route(
+            "/leistungsnachweise/{id}/cancel",
+            post(leistungsnachweis::cancel_leistungsnachweis),
+        )
+        .route(
+            "/leistungsnachweise/{id}/reopen",
+            post(leistungsnachweis::reopen_leistungsnachweis),
+        )
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            leistungsnachweis::authorize_document,
+        ))
+        .route_layer(middleware::from_fn_with_state(STAFF, require_role));
+
+    // Protected routes - require authorization; batch and sync handlers
+    // check the client of every document themselves
+    let protected_routes = Router::new()
+        .route(
+            "/leistungsnachweise/batch-sign",
+            post(leistungsnachweis::batch_sign_leistungsnachweise),
+        )
+        .route("/export/batch", post(leistungsnachweis::export_batch))
+        .route(
+            "/sync/leistungsnachweise",
+            get(leistungsnachweis::sync_pull),
+        )
+        .route("/sync/signatures", post(leistungsnachweis::sync_upload))
+        .merge(client_routes)
+        .merge(document_routes)
+        .merge(review_routes)
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            idempotency_middleware,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            auth_middleware,
+        ));
+
+    // Invoice routes - require staff or admin authorization
+    let abrechnung_routes = Router::new()
+        .route("/abrechnungen", get(abrechnung::list_abrechnungen))
+        .route("/abrechnungen", post(abrechnung::create_abrechnungen))
+        .route("/abrechnungen/{id}", get(abrechnung::get_abrechnung))
+        .route(
+            "/abrechnungen/{id}/xml",
+            get(abrechnung::get_abrechnung_xml),
+        )
+        .route(
+            "/abrechnungen/{id}/lieferungen",
+            get(lieferung::list_lieferungen),
+        )
+        .route(
+            "/abrechnungen/{id}/lieferungen",
+            post(lieferung::create_lieferung),
+        )
+        .route("/lieferungen/{datei_id}", get(lieferung::get_lieferung))
+        .route(
+            "/lieferungen/{datei_id}/xml",
+            get(lieferung::get_lieferung_xml),
+        )
+        .route(
+            "/fehlernachrichten",
+            post(fehlernachricht::ingest_fehlernachricht),
+        )
+        .route(
+            "/fehlernachrichten/{id}",
+            get(fehlernachricht::get_fehlernachricht),
+        )
+        .route_layer(middleware::from_fn_with_state(STAFF, require_role))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            idempotency_middleware,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            auth_middleware,
+        ));
+
+    // Device routes issuing API keys or pairing codes - their responses must
+    // not be kept for idempotent replay
+    let device_credential_routes = Router::new()
+        .route("/devices", post(device::register_device))
+        .route("/devices/pairing-codes", post(device::create_pairing_code))
+        .route(
+            "/devices/{id}/regenerate-key",
+            post(device::regenerate_device_key),
+        );
 
     // Device management routes - require admin authorization
     let device_routes = Router::new()
         .route("/devices", get(device::list_devices))
-        .route("/devices", post(device::register_device))
         .route("/devices/{id}", get(device::get_device))
         .route("/devices/{id}", delete(device::delete_device))
         .route("/devices/{id}/deactivate", post(device::deactivate_device))
         .route("/devices/{id}/activate", post(device::activate_device))
-        .route(
-            "/devices/{id}/regenerate-key",
-            post(device::regenerate_device_key),
-        )
-        .layer(middleware::from_fn(auth_middleware));
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            idempotency_middleware,
+        ))
+        .merge(device_credential_routes)
+        .route_layer(middleware::from_fn_with_state(ADMIN, require_role))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            auth_middleware,
+        ));
+
+    // User management routes - require admin authorization
+    let user_routes = Router::new()
+        .route("/users", get(users_api::get_users))
+        .route("/users", post(users_api::create_user))
+        .route("/users/{id}", get(users_api::get_user))
+        .route("/users/{id}", patch(users_api::update_user))
+        .route("/users/{id}", delete(users_api::delete_user))
+        .route_layer(middleware::from_fn_with_state(ADMIN, require_role))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            idempotency_middleware,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            auth_middleware,
+        ));
+
+    // Audit log routes - require admin authorization
+    let audit_routes = Router::new()
+        .route("/audit", get(audit::get_audit_log))
+        .route("/audit/verify", get(audit::verify_audit_log))
+        .route("/audit/export", get(audit::export_audit_log))
+        .route_layer(middleware::from_fn_with_state(ADMIN, require_role))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            auth_middleware,
+        ));
 
     // Public routes - no authorization required
     Router::new()
         .route("/health", get(health::get_health))
+        .route("/metrics", get(metrics::get_metrics))
+        .route("/.well-known/jwks.json", get(auth::jwks))
+        .route(
+            "/export/downloads/{export_id}",
+            get(leistungsnachweis::download_export),
+        )
         .merge(auth_routes)
         .merge(protected_auth_routes)
         .merge(protected_routes)
+        .merge(abrechnung_routes)
         .merge(device_routes)
+        .merge(user_routes)
+        .merge(audit_routes)
         .with_state(state)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use chrono::Duration;
+    use reqwest::{Client, Method, Response, StatusCode};
+    use sea_orm::{DatabaseConnection, EntityTrait, PaginatorTrait};
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        config::{
+            auth::{Claims, UserRole},
+            jwt_keys,
+        },
+        repositories::{entity::idempotency_key, test_db},
+        services::{CoreClient, ExportStore, core_client::CoreClientConfig},
+    };
+
+    /// Serves the routes on a local port and returns the base URL
+    async fn serve(db: DatabaseConnection) -> String {
+        jwt_keys::init_for_tests();
+        let state = AppState {
+            db,
+            core_client: CoreClient::new(
+                String::new(),
+                String::new(),
+                true,
+                CoreClientConfig::from_env(),
+            ),
+            exports: ExportStore::new(
+                std::env::temp_dir().join("doc-proxy-router-tests"),
+                b"test-export-key".to_vec(),
+                Duration::minutes(15),
+                String::new(),
+            ),
+            oidc: None,
+        };
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let app = init_routes(state).into_make_service_with_connect_info::<SocketAddr>();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        base_url
+    }
+
+    fn token(role: UserRole) -> String {
+        Claims::new(uuid::Uuid::new_v4().to_string(), "Test".into(), role)
+            .to_token()
+            .unwrap()
+    }
+
+    async fn post(
+        base_url: &str,
+        path: &str,
+        token: &str,
+        key: &str,
+        body: &serde_json::Value,
+    ) -> Response {
+        Client::new()
+            .post(format!("{}{}", base_url, path))
+            .bearer_auth(token)
+            .header("idempotency-key", key)
+            .json(body)
+            .send()
+            .await
+            .unwrap()
+    }
+
+    async fn send(base_url: &str, method: Method, path: &str, token: &str) -> Response {
+        Client::new()
+            .request(method, format!("{}{}", base_url, path))
+            .bearer_auth(token)
+            .send()
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_issued_credentials_are_not_kept_for_replay() {
+        let Some(db) = test_db::connect().await else {
+            return;
+        };
+        let base_url = serve(db.clone()).await;
+        let admin = token(UserRole::Admin);
+
+        let device = json!({ "mac_address": "AA:BB:CC:DD:EE:01", "name": "Tablet 1" });
+        let created = post(&base_url, "/devices", &admin, "key-1", &device).await;
+        assert_eq!(created.status(), StatusCode::CREATED);
+        let created: serde_json::Value = created.json().await.unwrap();
+        let id = created["id"].as_str().unwrap();
+
+        // Runs again instead of handing out the stored API key
+        let repeated = post(&base_url, "/devices", &admin, "key-1", &device).await;
+        assert_eq!(repeated.status(), StatusCode::CONFLICT);
+        assert!(repeated.headers().get("idempotency-replayed").is_none());
+
+        let path = format!("/devices/{}/regenerate-key", id);
+        let regenerated = post(&base_url, &path, &admin, "key-2", &json!({})).await;
+        assert!(regenerated.status().is_success());
+        let pairing = json!({ "name": "Tablet 2" });
+        let path = "/devices/pairing-codes";
+        let code = post(&base_url, path, &admin, "key-3", &pairing).await;
+        assert!(code.status().is_success());
+        let stored = idempotency_key::Entity::find().count(&db).await.unwrap();
+        assert_eq!(stored, 0);
+
+        let path = format!("/devices/{}/deactivate", id);
+        post(&base_url, &path, &admin, "key-4", &json!({})).await;
+        let replayed = post(&base_url, &path, &admin, "key-4", &json!({})).await;
+        assert_eq!(replayed.headers()["idempotency-replayed"], "true");
+    }
+
+    #[tokio::test]
+    async fn test_device_roles_must_be_known() {
+        let Some(db) = test_db::connect().await else {
+            return;
+        };
+        let base_url = serve(db).await;
+        let admin = token(UserRole::Admin);
+
+        let device = json!({ "mac_address": "AA:BB:CC:DD:EE:02", "name": "T", "role": "root" });
+        let refused = post(&base_url, "/devices", &admin, "key-1", &device).await;
+        assert_eq!(refused.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let pairing = json!({ "name": "Tablet 3", "role": "superuser" });
+        let path = "/devices/pairing-codes";
+        let refused = post(&base_url, path, &admin, "key-2", &pairing).await;
+        assert_eq!(refused.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let pairing = json!({ "name": "Tablet 3", "role": "staff" });
+        let code = post(&base_url, path, &admin, "key-3", &pairing).await;
+        let code: serde_json::Value = code.json().await.unwrap();
+        let redeem = json!({ "code": code["code"], "mac_address": "AA:BB:CC:DD:EE:03" });
+        let paired = post(&base_url, "/auth/device/pair", "", "key-4", &redeem).await;
+        assert_eq!(paired.status(), StatusCode::CREATED);
+        let paired: serde_json::Value = paired.json().await.unwrap();
+        assert_eq!(paired["role"], "staff");
+    }
+
+    /// Creates a user through the API and returns its ID
+    async fn create_user(base_url: &str, admin: &str, username: &str, name: &str) -> String {
+        let user = json!({ "username": username, "name": name, "password": "password1" });
+        let created = post(base_url, "/users", admin, username, &user).await;
+        assert_eq!(created.status(), StatusCode::CREATED);
+        let created: serde_json::Value = created.json().await.unwrap();
+        created["id"].as_str().unwrap().to_string()
+    }
+
+    async fn list_users(base_url: &str, admin: &str, query: &str) -> serde_json::Value {
+        let path = format!("/users?{}", query);
+        let page = send(base_url, Method::GET, &path, admin).await;
+        assert_eq!(page.status(), StatusCode::OK);
+        page.json().await.unwrap()
+    }
+
+    fn usernames(page: &serde_json::Value) -> Vec<&str> {
+        page["content"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|u| u["username"].as_str().unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_users_are_paged_and_searched_literally() {
+        let Some(db) = test_db::connect().await else {
+            return;
+        };
+        let base_url = serve(db).await;
+        let admin = token(UserRole::Admin);
+        create_user(&base_url, &admin, "anna", "Anna 100%").await;
+        create_user(&base_url, &admin, "bernd", "Bernd_B").await;
+        create_user(&base_url, &admin, "carla", "Carla").await;
+
+        let first = list_users(&base_url, &admin, "page=0&size=2").await;
+        assert_eq!(usernames(&first), ["anna", "bernd"]);
+        assert_eq!(first["total_elements"], 3);
+        assert_eq!(first["total_pages"], 2);
+        let second = list_users(&base_url, &admin, "page=1&size=2").await;
+        assert_eq!(usernames(&second), ["carla"]);
+        assert_eq!(second["last"], true);
+        let oversized = send(&base_url, Method::GET, "/users?size=1000", &admin).await;
+        assert_eq!(oversized.status(), StatusCode::BAD_REQUEST);
+
+        // Wildcards in the search are matched as text
+        let percent = list_users(&base_url, &admin, "search=%25").await;
+        assert_eq!(usernames(&percent), ["anna"]);
+        let underscore = list_users(&base_url, &admin, "search=_").await;
+        assert_eq!(usernames(&underscore), ["bernd"]);
+        let case = list_users(&base_url, &admin, "search=CARL").await;
+        assert_eq!(usernames(&case), ["carla"]);
+    }
+
+    #[tokio::test]
+    async fn test_usernames_are_unique() {
+        let Some(db) = test_db::connect().await else {
+            return;
+        };
+        let base_url = serve(db).await;
+        let admin = token(UserRole::Admin);
+        create_user(&base_url, &admin, "anna", "Anna").await;
+
+        let again = json!({ "username": "anna", "name": "Other", "password": "password1" });
+        let duplicate = post(&base_url, "/users", &admin, "key-dup", &again).await;
+        assert_eq!(duplicate.status(), StatusCode::CONFLICT);
+    }
+
+    #[tokio::test]
+    async fn test_deleting_users() {
+        let Some(db) = test_db::connect().await else {
+            return;
+        };
+        let base_url = serve(db).await;
+        let admin_id = create_user(&base_url, &token(UserRole::Admin), "admin", "Admin").await;
+        let admin = Claims::new(admin_id.clone(), "Admin".into(), UserRole::Admin)
+            .to_token()
+            .unwrap();
+
+        let path = format!("/users/{}", admin_id);
+        let own = send(&base_url, Method::DELETE, &path, &admin).await;
+        assert_eq!(own.status(), StatusCode::BAD_REQUEST);
+
+        let carla = create_user(&base_url, &admin, "carla", "Carla").await;
+        let device = json!({ "mac_address": "AA:BB:CC:DD:EE:04", "name": "T", "user_id": carla });
+        let device = post(&base_url, "/devices", &admin, "key-dev", &device).await;
+        let device: serde_json::Value = device.json().await.unwrap();
+
+        let path = format!("/users/{}", carla);
+        let deleted = send(&base_url, Method::DELETE, &path, &admin).await;
+        assert_eq!(deleted.status(), StatusCode::NO_CONTENT);
+        let gone = send(&base_url, Method::GET, &path, &admin).await;
+        assert_eq!(gone.status(), StatusCode::NOT_FOUND);
+        let again = send(&base_url, Method::DELETE, &path, &admin).await;
+        assert_eq!(again.status(), StatusCode::NOT_FOUND);
+
+        // The user's devices stop authenticating with the account
+        let path = format!("/devices/{}", device["id"].as_str().unwrap());
+        let device = send(&base_url, Method::GET, &path, &admin).await;
+        let device: serde_json::Value = device.json().await.unwrap();
+        assert_eq!(device["is_active"], false);
+    }
+
+    /// Creates a user through the API and returns a token for it
+    async fn user_token(base_url: &str, admin: &str, user: serde_json::Value) -> String {
+        let username = user["username"].as_str().unwrap().to_string();
+        let role: UserRole = serde_json::from_value(user["role"].clone()).unwrap();
+        let mut user = user;
+        user["name"] = json!(username);
+        user["password"] = json!("password1");
+        let created = post(base_url, "/users", admin, &username, &user).await;
+        assert_eq!(created.status(), StatusCode::CREATED);
+        let created: serde_json::Value = created.json().await.unwrap();
+        let id = created["id"].as_str().unwrap().to_string();
+        Claims::new(id, username, role).to_token().unwrap()
+    }
+
+    /// Versichertennummer of the mock documents
+    const CLIENT: &str = "A123456789";
+
+    #[tokio::test]
+    async fn test_routes_check_role_and_client() {
+        let Some(db) = test_db::connect().await else {
+            return;
+        };
+        let base_url = serve(db).await;
+        let admin = token(UserRole::Admin);
+        let client = json!({ "username": "anna", "role": "client", "versichertennummer": CLIENT });
+        let client = user_token(&base_url, &admin, client).await;
+        let staff = json!({ "username": "staff", "role": "staff", "assigned_clients": [CLIENT] });
+        let staff = user_token(&base_url, &admin, staff).await;
+        let other_staff = json!({ "username": "other", "role": "staff" });
+        let other_staff = user_token(&base_url, &admin, other_staff).await;
+
+        let document = "/leistungsnachweise/7c1e4b2a-9d3f-4e8a-b6c5-1a2b3c4d5e01";
+        let (ok, forbidden) = (StatusCode::OK, StatusCode::FORBIDDEN);
+        let expected = [
+            (&admin, ok, ok, ok),
+            (&staff, forbidden, ok, ok),
+            (&other_staff, forbidden, ok, forbidden),
+            (&client, forbidden, forbidden, ok),
+        ];
+        for (token, users, abrechnungen, detail) in expected {
+            let response = send(&base_url, Method::GET, "/users", token).await;
+            assert_eq!(response.status(), users);
+            let response = send(&base_url, Method::GET, "/abrechnungen", token).await;
+            assert_eq!(response.status(), abrechnungen);
+            let response = send(&base_url, Method::GET, document, token).await;
+            assert_eq!(response.status(), detail);
+        }
+
+        // Clients see their documents but may not change their status
+        let body = json!({ "reason": "Falsche Leistung" });
+        for action in ["reject", "cancel", "reopen"] {
+            let path = format!("{}/{}", document, action);
+            for token in [&client, &other_staff] {
+                let refused = post(&base_url, &path, token, action, &body).await;
+                assert_eq!(refused.status(), forbidden);
+            }
+        }
+        let path = format!("{}/reject", document);
+        let rejected = post(&base_url, &path, &staff, "reject", &body).await;
+        assert_eq!(rejected.status(), ok);
+        let path = format!("{}/reopen", document);
+        let reopened = post(&base_url, &path, &admin, "reopen", &json!({})).await;
+        assert_eq!(reopened.status(), ok);
+    }
+
+    /// Audited actions on a target, newest first
+    async fn audit_actions(base_url: &str, admin: &str, target: &str) -> Vec<String> {
+        let path = format!("/audit?target_id={}", target);
+        let page = send(base_url, Method::GET, &path, admin).await;
+        let page: serde_json::Value = page.json().await.unwrap();
+        page["content"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|e| e["action"].as_str().unwrap().to_string())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_account_changes_are_audited() {
+        let Some(db) = test_db::connect().await else {
+            return;
+        };
+        let base_url = serve(db).await;
+        let admin = token(UserRole::Admin);
+
+        let anna = create_user(&base_url, &admin, "anna", "Anna").await;
+        let path = format!("/users/{}", anna);
+        let update = Client::new()
+            .patch(format!("{}{}", base_url, path))
+            .bearer_auth(&admin)
+            .json(&json!({ "name": "Anna B" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(update.status(), StatusCode::OK);
+        send(&base_url, Method::DELETE, &path, &admin).await;
+        let actions = audit_actions(&base_url, &admin, &anna).await;
+        assert_eq!(actions, ["user_deleted", "user_updated", "user_created"]);
+
+        let device = json!({ "mac_address": "AA:BB:CC:DD:EE:05", "name": "Tablet" });
+        let device = post(&base_url, "/devices", &admin, "key-dev", &device).await;
+        let device: serde_json::Value = device.json().await.unwrap();
+        let id = device["id"].as_str().unwrap();
+        for (action, key) in [("deactivate", "key-1"), ("activate", "key-2")] {
+            let path = format!("/devices/{}/{}", id, action);
+            let changed = post(&base_url, &path, &admin, key, &json!({})).await;
+            assert_eq!(changed.status(), StatusCode::OK);
+        }
+        let actions = audit_actions(&base_url, &admin, id).await;
+        let expected = [
+            "device_activated",
+            "device_deactivated",
+            "device_registered",
+        ];
+        assert_eq!(actions, expected);
+    }
+
+    #[tokio::test]
+    async fn test_status_changes_and_pairing_codes_are_audited() {
+        let Some(db) = test_db::connect().await else {
+            return;
+        };
+        let base_url = serve(db).await;
+        let admin = token(UserRole::Admin);
+
+        let id = "7c1e4b2a-9d3f-4e8a-b6c5-1a2b3c4d5e01";
+        let body = json!({ "reason": "Falsche Leistung" });
+        for action in ["reject", "cancel", "reopen"] {
+            let path = format!("/leistungsnachweise/{}/{}", id, action);
+            let changed = post(&base_url, &path, &admin, action, &body).await;
+            assert_eq!(changed.status(), StatusCode::OK);
+        }
+        let actions = audit_actions(&base_url, &admin, id).await;
+        let expected = [
+            "document_reopened",
+            "document_cancelled",
+            "document_rejected",
+        ];
+        assert_eq!(actions, expected);
+
+        let pairing = json!({ "name": "Tablet 2" });
+        let code = post(&base_url, "/devices/pairing-codes", &admin, "key", &pairing).await;
+        let code: serde_json::Value = code.json().await.unwrap();
+        let actions = audit_actions(&base_url, &admin, code["id"].as_str().unwrap()).await;
+        assert_eq!(actions, ["pairing_code_created"]);
+    }
+
+    #[tokio::test]
+    async fn test_sessions_end_on_logout_and_token_reuse() {
+        let Some(db) = test_db::connect().await else {
+            return;
+        };
+        let base_url = serve(db).await;
+        create_user(&base_url, &token(UserRole::Admin), "anna", "Anna").await;
+
+        let login = json!({ "username": "anna", "password": "password1" });
+        let session = post(&base_url, "/auth/login", "", "key-login", &login).await;
+        let session: serde_json::Value = session.json().await.unwrap();
+        let access = session["token"].as_str().unwrap();
+        let me = send(&base_url, Method::GET, "/auth/me", access).await;
+        assert_eq!(me.status(), StatusCode::OK);
+
+        let path = "/auth/refresh";
+        let first = json!({ "refresh_token": session["refresh_token"] });
+        let rotated = post(&base_url, path, "", "key-r1", &first).await;
+        assert_eq!(rotated.status(), StatusCode::OK);
+        let rotated: serde_json::Value = rotated.json().await.unwrap();
+
+        // The old refresh token again: the rotated one is revoked with it
+        let reused = post(&base_url, path, "", "key-r2", &first).await;
+        assert_eq!(reused.status(), StatusCode::UNAUTHORIZED);
+        let second = json!({ "refresh_token": rotated["refresh_token"] });
+        let revoked = post(&base_url, path, "", "key-r3", &second).await;
+        assert_eq!(revoked.status(), StatusCode::UNAUTHORIZED);
+
+        // The access token is denied by its jti after logout
+        let logout = post(&base_url, "/auth/logout", access, "key-out", &json!({})).await;
+        assert_eq!(logout.status(), StatusCode::NO_CONTENT);
+        let me = send(&base_url, Method::GET, "/auth/me", access).await;
+        assert_eq!(me.status(), StatusCode::UNAUTHORIZED);
+        let other = rotated["token"].as_str().unwrap();
+        let me = send(&base_url, Method::GET, "/auth/me", other).await;
+        assert_eq!(me.status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,81 @@
+//! HTTP handlers for Abrechnung API.
+
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::{AppState, repositories::abrechnung_repository};
+
+use super::{
+    error::{AbrechnungError, Result},
+    request::CreateAbrechnungenRequest,
+    response::AbrechnungResponse,
+    service,
+};
+
+/// POST /abrechnungen
+pub async fn create_abrechnungen(
+    State(state): State<AppState>,
+    Json(payload): Json<CreateAbrechnungenRequest>,
+) -> Result<(StatusCode, Json<Vec<AbrechnungResponse>>)> {
+    info!(faelle = payload.faelle.len(), "Creating abrechnungen");
+
+    let created = service::create_abrechnungen(&state.db, payload)
+        .await
+        .inspect_err(|e| error!(error = %e, "Failed to create abrechnungen"))?;
+
+    info!(count = created.len(), "Abrechnungen created");
+    Ok((StatusCode::CREATED, Json(created)))
+}
+
+/// GET /abrechnungen
+pub async fn list_abrechnungen(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<AbrechnungResponse>>> {
+    let abrechnungen = abrechnung_repository::find_all(&state.db).await?;
+    Ok(Json(
+        abrechnungen
+            .into_iter()
+            .map(AbrechnungResponse::from)
+            .collect(),
+    ))
+}
+
+/// GET /abrechnungen/{id}
+pub async fn get_abrechnung(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<AbrechnungResponse>> {
+    abrechnung_repository::find_by_id(&state.db, id)
+        .await?
+        .map(|a| Json(AbrechnungResponse::from(a)))
+        .ok_or_else(|| AbrechnungError::NotFound(format!("Abrechnung {}", id)))
+}
+
+/// GET /abrechnungen/{id}/xml
+pub async fn get_abrechnung_xml(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Response> {
+    let abrechnung = abrechnung_repository::find_by_id(&state.db, id)
+        .await?
+        .ok_or_else(|| AbrechnungError::NotFound(format!("Abrechnung {}", id)))?;
+
+    let disposition = format!(
+        "attachment; filename=\"{}\"",
+        service::xml_file_name(&abrechnung.rechnungsnummer)
+    );
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/xml".to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        abrechnung.xml,
+    )
+        .into_response())
+}
@@ -0,0 +1,81 @@
+//! Error types for Abrechnung API.
+
+use axum::{
+    Json,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use thiserror::Error;
+
+use crate::models::{abrechnung::BuildError, leistungsnachweis::ParseError, xsd::Violation};
+
+use super::response::AbrechnungErrorResponse;
+
+/// Domain errors for Abrechnung operations.
+#[derive(Error, Debug)]
+pub enum AbrechnungError {
+    #[error("Resource not found: {0}")]
+    NotFound(String),
+
+    #[error("Invalid request: {0}")]
+    BadRequest(String),
+
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
+    #[error("Database error: {0}")]
+    Database(#[from] sea_orm::DbErr),
+
+    #[error("Internal error: {0}")]
+    Internal(String),
+
+    #[error("Invoice data violates the schema ({} violations)", .0.len())]
+    SchemaViolation(Vec<Violation>),
+}
+
+impl From<BuildError> for AbrechnungError {
+    fn from(err: BuildError) -> Self {
+        match err {
+            BuildError::Validation(violations) | BuildError::EinsatzMismatch(violations) => {
+                Self::SchemaViolation(violations)
+            }
+            BuildError::Leistungsnachweis(ParseError::Validation(violations)) => {
+                Self::SchemaViolation(violations)
+            }
+            BuildError::XmlSerialize(e) => Self::Internal(e.to_string()),
+            BuildError::XmlDeserialize(e) => Self::Internal(e.to_string()),
+            e @ (BuildError::InvalidAmount { .. }
+            | BuildError::InvalidDatei(_)
+            | BuildError::Leistungsnachweis(_)
+            | BuildError::ConflictingRechnungsempfaenger { .. }) => Self::BadRequest(e.to_string()),
+        }
+    }
+}
+
+impl From<&AbrechnungError> for StatusCode {
+    fn from(err: &AbrechnungError) -> Self {
+        match err {
+            AbrechnungError::NotFound(_) => StatusCode::NOT_FOUND,
+            AbrechnungError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            AbrechnungError::Conflict(_) => StatusCode::CONFLICT,
+            AbrechnungError::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            AbrechnungError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            AbrechnungError::SchemaViolation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+        }
+    }
+}
+
+impl IntoResponse for AbrechnungError {
+    fn into_response(self) -> Response {
+        let status = StatusCode::from(&self);
+        let error = self.to_string();
+        let violations = match self {
+            AbrechnungError::SchemaViolation(violations) => violations,
+            _ => Vec::new(),
+        };
+        (status, Json(AbrechnungErrorResponse { error, violations })).into_response()
+    }
+}
+
+/// Result type alias for Abrechnung operations.
+pub type Result<T> = std::result::Result<T, AbrechnungError>;
@@ -0,0 +1,16 @@
+//! Abrechnung (HKP_ARN invoice) domain module.
+//!
+//! Structure:
+//! - `api` - HTTP handlers (thin, orchestration only)
+//! - `service` - Business logic
+//! - `request` - Request DTOs
+//! - `response` - Response DTOs
+//! - `error` - Domain error types
+
+mod api;
+mod error;
+pub mod request;
+pub mod response;
+mod service;
+
+pub use api::{create_abrechnungen, get_abrechnung, get_abrechnung_xml, list_abrechnungen};
@@ -0,0 +1,111 @@
+//! Request DTOs for Abrechnung API.
+
+use chrono::NaiveDate;
+use serde::Deserialize;
+
+/// Request body for generating invoices
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateAbrechnungenRequest {
+    /// IK des Pflegedienstes
+    pub leistungserbringer_ik: String,
+
+    /// IK des Rechnungsstellers, defaults to the Leistungserbringer
+    pub rechnungssteller_ik: Option<String>,
+
+    /// IK des Zahlungsempfängers, defaults to the Leistungserbringer
+    pub zahlungsempfaenger_ik: Option<String>,
+
+    /// Invoice date, defaults to today
+    pub rechnungsdatum: Option<NaiveDate>,
+
+    /// Signed documents to bill, each at most once; grouped into one invoice
+    /// per Kostenträger and Abrechnungsmonat
+    pub faelle: Vec<AbrechnungsfallInput>,
+}
+
+/// One signed Leistungsnachweis with prescription and price data
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AbrechnungsfallInput {
+    /// IK der Krankenkasse
+    pub kostentraeger_ik: String,
+
+    /// IK des Rechnungsempfängers, defaults to the Kostenträger
+    pub rechnungsempfaenger_ik: Option<String>,
+
+    /// ID of a signed or finalized HKP Leistungsnachweis. The XML stored when
+    /// it was signed is embedded; Krankenversichertennummer is read from it.
+    pub leistungsnachweis_id: String,
+
+    /// Abrechnungsmonat (JJJJMM)
+    pub abrechnungsmonat: String,
+
+    pub genehmigungskennzeichen: Option<String>,
+
+    #[serde(default)]
+    pub evo_ids: Vec<String>,
+
+    pub betriebsstaettennummer: String,
+
+    pub lebenslange_arztnummer: String,
+
+    pub verordnungsdatum: NaiveDate,
+
+    pub unfallkennzeichen: Option<String>,
+
+    /// Kennzeichen BVG/Sonstiges/SER, defaults to "0"
+    pub kennzeichen_bvg: Option<String>,
+
+    pub diagnoseschluessel: Vec<String>,
+
+    pub kennz_bes_versorgungsform: Option<String>,
+
+    pub einsaetze: Vec<EinsatzInput>,
+}
+
+/// One visit with its priced positions, in the order of the Leistungsnachweis
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EinsatzInput {
+    /// Beginn (xs:dateTime)
+    pub beginn: String,
+
+    /// Ende (xs:dateTime), taken from the Leistungsnachweis if omitted
+    pub ende: Option<String>,
+
+    pub positionen: Vec<PositionInput>,
+}
+
+/// Priced service position
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PositionInput {
+    pub positions_nr: String,
+
+    pub einzelleistung: Option<EinzelleistungInput>,
+
+    /// Menge as decimal string, e.g. "1" or "1.5"
+    pub menge: String,
+
+    pub le_abr_code: String,
+
+    pub tarif_kennz_bereich: String,
+
+    pub tarif_kennz_sonder: String,
+
+    /// Agreed unit price as decimal string, e.g. "12.34"
+    pub einzelpreis: String,
+
+    /// Taken from the Leistungsnachweis if omitted
+    #[serde(default)]
+    pub beschaeftigtennummern: Vec<String>,
+}
+
+/// Performed single service within a complex position
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EinzelleistungInput {
+    pub positions_nr: String,
+    pub menge: String,
+}
@@ -0,0 +1,55 @@
+//! Response DTOs for Abrechnung API.
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::{
+    models::{abrechnung::builder::format_betrag, xsd::Violation},
+    repositories::entity::abrechnung,
+};
+
+/// Stored invoice without its XML
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AbrechnungResponse {
+    /// Abrechnung_ID
+    pub id: Uuid,
+    pub rechnungsnummer: String,
+    pub rechnungssteller_ik: String,
+    pub kostentraeger_ik: String,
+    pub rechnungsempfaenger_ik: String,
+    pub abrechnungsmonat: String,
+    pub rechnungsdatum: NaiveDate,
+    /// Gesamtbetrag with two fraction digits
+    pub gesamtbetrag: String,
+    pub anzahl_faelle: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<abrechnung::Model> for AbrechnungResponse {
+    fn from(a: abrechnung::Model) -> Self {
+        Self {
+            id: a.id,
+            rechnungsnummer: a.rechnungsnummer,
+            rechnungssteller_ik: a.rechnungssteller_ik,
+            kostentraeger_ik: a.kostentraeger_ik,
+            rechnungsempfaenger_ik: a.rechnungsempfaenger_ik,
+            abrechnungsmonat: a.abrechnungsmonat,
+            rechnungsdatum: a.rechnungsdatum,
+            gesamtbetrag: format_betrag(a.gesamtbetrag),
+            anzahl_faelle: a.anzahl_faelle,
+            created_at: a.created_at,
+        }
+    }
+}
+
+/// Error body for Abrechnung operations
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AbrechnungErrorResponse {
+    pub error: String,
+    /// One entry per violated schema rule, located by XPath
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub violations: Vec<Violation>,
+}
@@ -0,0 +1,438 @@
+//! Business logic for Abrechnung operations.
+
+use std::collections::HashSet;
+
+use base64::Engine;
+use chrono::{Datelike, Utc};
+use sea_orm::{ConnectionTrait, DatabaseConnection, DbErr, SqlErr, TransactionTrait};
+use uuid::Uuid;
+
+use crate::{
+    handlers::leistungsnachweis::{request::SignatureType, response::DocumentSchema},
+    models::{
+        abrechnung::{
+            AbrechnungsfallPosition, Einsatz, Einzelleistung, Position,
+            builder::{self, Fall, Rechnungssteller},
+            fall_builder::{Verordnung, build_abrechnungsfall},
+        },
+        document_status::DocumentStatus,
+    },
+    repositories::{
+        abrechnung_repository::{self, NewAbrechnung},
+        leistungsnachweis_repository,
+    },
+};
+
+use super::{
+    error::{AbrechnungError, Result},
+    request::{AbrechnungsfallInput, CreateAbrechnungenRequest, EinsatzInput, PositionInput},
+    response::AbrechnungResponse,
+};
+
+/// Builds one Abrechnungsfall per signed document, groups them per Kostenträger and month and stores one
+/// numbered invoice per group.
+///
+/// The documents are loaded from storage: only signed or finalized HKP
+/// Leistungsnachweise with a captured signature are billed, with the XML
+/// stored when they were signed, and each of them only once.
+///
+/// All invoices are created in one transaction: if any of them fails schema
+/// validation, nothing is stored and no invoice number is consumed.
+pub async fn create_abrechnungen(
+    db: &DatabaseConnection,
+    req: CreateAbrechnungenRequest,
+) -> Result<Vec<AbrechnungResponse>> {
+    if req.faelle.is_empty() {
+        return Err(AbrechnungError::BadRequest(
+            "At least one Abrechnungsfall is required".into(),
+        ));
+    }
+
+    let steller = Rechnungssteller {
+        rechnungssteller_ik: req
+            .rechnungssteller_ik
+            .unwrap_or_else(|| req.leistungserbringer_ik.clone()),
+        zahlungsempfaenger_ik: req
+            .zahlungsempfaenger_ik
+            .unwrap_or_else(|| req.leistungserbringer_ik.clone()),
+        leistungserbringer_ik: req.leistungserbringer_ik,
+    };
+    let rechnungsdatum = req
+        .rechnungsdatum
+        .unwrap_or_else(|| Utc::now().date_naive());
+
+    let mut ids = HashSet::new();
+    if let Some(fall) = req
+        .faelle
+        .iter()
+        .find(|f| !ids.insert(f.leistungsnachweis_id.as_str()))
+    {
+        return Err(AbrechnungError::BadRequest(format!(
+            "Leistungsnachweis {} is listed more than once",
+            fall.leistungsnachweis_id
+        )));
+    }
+
+    let txn = db.begin().await?;
+    let ids: Vec<String> = ids.into_iter().map(str::to_string).collect();
+    if let Some(billed) = abrechnung_repository::find_billed(&txn, &ids)
+        .await?
+        .first()
+    {
+        return Err(already_billed(&billed.leistungsnachweis_id));
+    }
+
+    let mut faelle = Vec::with_capacity(req.faelle.len());
+    for (index, input) in req.faelle.into_iter().enumerate() {
+        let xml = signed_leistungsnachweis(&txn, &input.leistungsnachweis_id).await?;
+        faelle.push(convert_fall(index, input, &xml)?);
+    }
+    let entwuerfe = builder::group_faelle(faelle)?;
+
+    let mut created = Vec::with_capacity(entwuerfe.len());
+
+    for entwurf in entwuerfe {
+        let leistungsnachweis_ids: Vec<String> = entwurf
+            .faelle
+            .iter()
+            .map(|f| f.leistungsnachweis_id.clone())
+            .collect();
+        let laufende_nummer = abrechnung_repository::next_rechnungsnummer(
+            &txn,
+            &steller.rechnungssteller_ik,
+            rechnungsdatum.year(),
+        )
+        .await?;
+
+        let gesamtbetrag = entwurf.gesamtbetrag;
+        let abrechnungsmonat = entwurf.abrechnungsmonat.clone();
+        let anzahl_faelle = entwurf.faelle.len() as i32;
+        let nachricht = entwurf.into_nachricht(
+            &steller,
+            builder::rechnungsnummer(rechnungsdatum.year(), laufende_nummer),
+            rechnungsdatum,
+        );
+        let xml = nachricht.to_xml()?;
+
+        let id = Uuid::parse_str(&nachricht.abrechnung_id)
+            .map_err(|e| AbrechnungError::Internal(e.to_string()))?;
+        let model = abrechnung_repository::create(
+            &txn,
+            NewAbrechnung {
+                id,
+                rechnungsnummer: nachricht.rechnungsnummer,
+                rechnungssteller_ik: nachricht.rechnungssteller_ik,
+                kostentraeger_ik: nachricht.kostentraeger_ik,
+                rechnungsempfaenger_ik: nachricht.rechnungsempfaenger_ik,
+                abrechnungsmonat,
+                rechnungsdatum,
+                gesamtbetrag,
+                anzahl_faelle,
+                xml,
+            },
+        )
+        .await?;
+        abrechnung_repository::link_leistungsnachweise(&txn, id, &leistungsnachweis_ids)
+            .await
+            .map_err(|e| match e.sql_err() {
+                // Billed by a concurrent request
+                Some(SqlErr::UniqueConstraintViolation(_)) => AbrechnungError::Conflict(
+                    "A Leistungsnachweis of this request was billed concurrently".into(),
+                ),
+                _ => e.into(),
+            })?;
+        created.push(AbrechnungResponse::from(model));
+    }
+
+    txn.commit().await?;
+    Ok(created)
+}
+
+fn already_billed(leistungsnachweis_id: &str) -> AbrechnungError {
+    AbrechnungError::Conflict(format!(
+        "Leistungsnachweis {} is already billed",
+        leistungsnachweis_id
+    ))
+}
+
+/// Loads the XML of a Leistungsnachweis as stored when it was signed.
+///
+/// Fails unless the document is a signed or finalized HKP Leistungsnachweis
+/// whose latest XML carries a captured signature.
+async fn signed_leistungsnachweis<C: ConnectionTrait>(db: &C, id: &str) -> Result<String> {
+    let document = leistungsnachweis_repository::find_by_id(db, id)
+        .await?
+        .ok_or_else(|| AbrechnungError::NotFound(format!("Leistungsnachweis {}", id)))?;
+    if document.schema != DocumentSchema::HkpLnw.as_str() {
+        return Err(AbrechnungError::BadRequest(format!(
+            "Leistungsnachweis {} is not an HKP Leistungsnachweis",
+            id
+        )));
+    }
+    let status = document.status.parse::<DocumentStatus>();
+    if !matches!(
+        status,
+        Ok(DocumentStatus::Signed | DocumentStatus::Finalized)
+    ) {
+        return Err(AbrechnungError::BadRequest(format!(
+            "Leistungsnachweis {} is {}, only signed or finalized documents are billed",
+            id, document.status
+        )));
+    }
+
+    let unsigned =
+        || AbrechnungError::BadRequest(format!("Leistungsnachweis {} is not signed", id));
+    let artefakt = leistungsnachweis_repository::find_latest_xml(db, id)
+        .await?
+        .ok_or_else(unsigned)?;
+    let signatur =
+        leistungsnachweis_repository::find_signatur_ereignis(db, artefakt.signatur_ereignis_id)
+            .await?
+            .ok_or_else(|| DbErr::RecordNotFound(artefakt.signatur_ereignis_id.to_string()))?;
+    if signatur.art == SignatureType::Missing.as_str() {
+        return Err(unsigned());
+    }
+    Ok(artefakt.xml)
+}
+
+fn convert_fall(index: usize, input: AbrechnungsfallInput, xml: &str) -> Result<Fall> {
+    let verordnung = Verordnung {
+        genehmigungskennzeichen: input.genehmigungskennzeichen,
+        evo_ids: input.evo_ids,
+        betriebsstaettennummer: input.betriebsstaettennummer,
+        lebenslange_arztnummer: input.lebenslange_arztnummer,
+        verordnungsdatum: input.verordnungsdatum.format("%Y-%m-%d").to_string(),
+        unfallkennzeichen: input.unfallkennzeichen,
+        kennzeichen_bvg: input.kennzeichen_bvg.unwrap_or_else(|| "0".into()),
+        diagnoseschluessel: input.diagnoseschluessel,
+        kennz_bes_versorgungsform: input.kennz_bes_versorgungsform,
+    };
+    let einsaetze = input.einsaetze.into_iter().map(convert_einsatz).collect();
+
+    Ok(Fall {
+        rechnungsempfaenger_ik: input
+            .rechnungsempfaenger_ik
+            .unwrap_or_else(|| input.kostentraeger_ik.clone()),
+        kostentraeger_ik: input.kostentraeger_ik,
+        abrechnungsfall: build_abrechnungsfall(
+            &base64::engine::general_purpose::STANDARD.encode(xml),
+            &input.abrechnungsmonat,
+            verordnung,
+            einsaetze,
+            index,
+        )?,
+    })
+}
+
+fn convert_einsatz(input: EinsatzInput) -> Einsatz {
+    Einsatz {
+        beginn: input.beginn,
+        ende: input.ende,
+        positionen: input.positionen.into_iter().map(convert_position).collect(),
+    }
+}
+
+fn convert_position(input: PositionInput) -> AbrechnungsfallPosition {
+    AbrechnungsfallPosition {
+        position: Position {
+            positions_nr: input.positions_nr,
+            einzelleistung: input.einzelleistung.map(|e| Einzelleistung {
+                positions_nr: e.positions_nr,
+                menge: e.menge,
+            }),
+        },
+        menge: input.menge,
+        le_abr_code: input.le_abr_code,
+        tarif_kennz_bereich: input.tarif_kennz_bereich,
+        tarif_kennz_sonder: input.tarif_kennz_sonder,
+        einzelpreis: input.einzelpreis,
+        positionssumme: String::new(),
+        beschaeftigtennummern: input.beschaeftigtennummern,
+    }
+}
+
+/// Suggested file name for the invoice XML download.
+pub fn xml_file_name(rechnungsnummer: &str) -> String {
+    format!("Abrechnung_{}.xml", rechnungsnummer)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        models::leistungsnachweis::{
+            hkp,
+            types::{ArtDerUnterschrift, Dateityp, Unterschrift},
+        },
+        repositories::{
+            leistungsnachweis_repository::{
+                NewLeistungsnachweis, NewSignaturEreignis, NewXmlArtefakt,
+            },
+            test_db,
+        },
+    };
+
+    const SIGNED: &str = "7d444840-9dc0-11d1-b245-5ffdce74fa01";
+    const FINALIZED: &str = "7d444840-9dc0-11d1-b245-5ffdce74fa02";
+    const PENDING: &str = "7d444840-9dc0-11d1-b245-5ffdce74fa03";
+    const NOT_SIGNED: &str = "7d444840-9dc0-11d1-b245-5ffdce74fa04";
+
+    /// Stores a signed HKP Leistungsnachweis with one Einsatz in November 2024
+    async fn store(db: &DatabaseConnection, id: &str, status: &str, art: SignatureType) {
+        let position = |nr: &str, menge: &str| hkp::Position {
+            positions_nr: nr.into(),
+            menge: menge.into(),
+            beschaeftigtennummern: vec!["987654321".into()],
+        };
+        let lnw = hkp::Leistungsnachweis {
+            leistungserbringer_ik: "123456789".into(),
+            krankenversichertennummer: "B987654321".into(),
+            verantwortliche_fachkraft: "111222333".into(),
+            einsaetze: vec![hkp::Einsatz {
+                beginn: "2024-11-15T08:00:00".into(),
+                ende: Some("08:30:00".into()),
+                positionen: vec![position("030101", "1.00")],
+            }],
+            unterschrift_versicherter: hkp::UnterschriftVersicherter {
+                art: ArtDerUnterschrift::HandschriftlichDigital,
+                datum_uhrzeit: Some("2024-11-15T08:30:00".into()),
+                unterschrift: Some(Unterschrift {
+                    datei: "iVBORw0KGgo=".into(),
+                    dateityp: Dateityp::Png,
+                }),
+                fehlende_unterschrift: None,
+            },
+            id: id.into(),
+        };
+
+        leistungsnachweis_repository::upsert(
+            db,
+            NewLeistungsnachweis {
+                id: id.into(),
+                versichertennummer: "B987654321".into(),
+                name: "Muster".into(),
+                vorname: "Max".into(),
+                ik_pflegedienst: "123456789".into(),
+                abrechnungsmonat: "202411".into(),
+                schema: DocumentSchema::HkpLnw.as_str().into(),
+                status: status.into(),
+            },
+        )
+        .await
+        .unwrap();
+        let ereignis = leistungsnachweis_repository::create_signatur_ereignis(
+            db,
+            NewSignaturEreignis {
+                leistungsnachweis_id: id.into(),
+                art: art.as_str().into(),
+                bildformat: None,
+                fehlgrund: None,
+                erlaeuterung: None,
+                signiert_von: "user-1".into(),
+            },
+        )
+        .await
+        .unwrap();
+        leistungsnachweis_repository::create_xml_artefakt(
+            db,
+            NewXmlArtefakt {
+                leistungsnachweis_id: id.into(),
+                signatur_ereignis_id: ereignis.id,
+                schema: DocumentSchema::HkpLnw.as_str().into(),
+                xml: lnw.to_xml().unwrap(),
+            },
+        )
+        .await
+        .unwrap();
+    }
+
+    fn request(ids: &[&str]) -> CreateAbrechnungenRequest {
+        let faelle: Vec<_> = ids
+            .iter()
+            .map(|id| {
+                json!({
+                    "kostentraegerIk": "101234567",
+                    "leistungsnachweisId": id,
+                    "abrechnungsmonat": "202411",
+                    "genehmigungskennzeichen": "G-2024-001",
+                    "betriebsstaettennummer": "123456700",
+                    "lebenslangeArztnummer": "987654300",
+                    "verordnungsdatum": "2024-10-28",
+                    "diagnoseschluessel": ["E11.9"],
+                    "einsaetze": [{
+                        "beginn": "2024-11-15T08:00:00",
+                        "positionen": [{
+                            "positionsNr": "030101",
+                            "menge": "1",
+                            "leAbrCode": "10",
+                            "tarifKennzBereich": "03",
+                            "tarifKennzSonder": "000",
+                            "einzelpreis": "12.50"
+                        }]
+                    }]
+                })
+            })
+            .collect();
+        serde_json::from_value(json!({ "leistungserbringerIk": "123456789", "faelle": faelle }))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_only_stored_signed_documents_are_billed() {
+        let Some(db) = test_db::connect().await else {
+            return;
+        };
+        store(&db, SIGNED, "signed", SignatureType::HandwrittenDigital).await;
+        store(&db, FINALIZED, "finalized", SignatureType::HandwrittenPaper).await;
+        store(
+            &db,
+            PENDING,
+            "pending_signature",
+            SignatureType::HandwrittenDigital,
+        )
+        .await;
+        store(&db, NOT_SIGNED, "signed", SignatureType::Missing).await;
+
+        let unknown = "7d444840-9dc0-11d1-b245-5ffdce74fa05";
+        let result = create_abrechnungen(&db, request(&[SIGNED, unknown])).await;
+        assert!(matches!(result, Err(AbrechnungError::NotFound(_))));
+        for id in [PENDING, NOT_SIGNED] {
+            let result = create_abrechnungen(&db, request(&[id])).await;
+            assert!(matches!(result, Err(AbrechnungError::BadRequest(_))));
+        }
+
+        let created = create_abrechnungen(&db, request(&[SIGNED, FINALIZED]))
+            .await
+            .unwrap();
+        assert_eq!(created.len(), 1);
+        assert_eq!(created[0].anzahl_faelle, 2);
+    }
+
+    #[tokio::test]
+    async fn test_documents_are_billed_once() {
+        let Some(db) = test_db::connect().await else {
+            return;
+        };
+        store(&db, SIGNED, "signed", SignatureType::HandwrittenDigital).await;
+        store(
+            &db,
+            FINALIZED,
+            "finalized",
+            SignatureType::HandwrittenDigital,
+        )
+        .await;
+
+        let result = create_abrechnungen(&db, request(&[SIGNED, SIGNED])).await;
+        assert!(matches!(result, Err(AbrechnungError::BadRequest(_))));
+
+        create_abrechnungen(&db, request(&[SIGNED])).await.unwrap();
+        let result = create_abrechnungen(&db, request(&[FINALIZED, SIGNED])).await;
+        assert!(matches!(result, Err(AbrechnungError::Conflict(_))));
+        // Nothing of the refused request was billed
+        create_abrechnungen(&db, request(&[FINALIZED]))
+            .await
+            .unwrap();
+    }
+}
@@ -0,0 +1,142 @@
+//! Audit log handlers (admin only).
+
+use axum::{
+    Json,
+    extract::{Query, State},
+    http::{StatusCode, header},
+    response::IntoResponse,
+};
+use chrono::Utc;
+use tracing::{error, info, warn};
+
+use crate::{
+    AppState,
+    config::auth::AuthUser,
+    models::{
+        audit::{AuditEntryResponse, AuditExport, AuditFilter, AuditPageQuery, ChainVerification},
+        pagination::{PageResult, Pageable},
+    },
+    repositories::audit_repository,
+};
+
+/// Largest page size for listing entries
+const MAX_PAGE_SIZE: u64 = 500;
+
+/// Most entries in one export; narrow the filter for more
+const MAX_EXPORT_ENTRIES: u64 = 10_000;
+
+/// Error response for audit log operations
+#[derive(Debug, serde::Serialize)]
+pub struct AuditErrorResponse {
+    pub error: String,
+}
+
+type ApiError = (StatusCode, Json<AuditErrorResponse>);
+
+fn api_error(status: StatusCode, message: impl Into<String>) -> ApiError {
+    (
+        status,
+        Json(AuditErrorResponse {
+            error: message.into(),
+        }),
+    )
+}
+
+fn db_error(e: sea_orm::DbErr) -> ApiError {
+    error!(error = %e, "Audit log query failed");
+    api_error(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "Failed to read audit log",
+    )
+}
+
+/// GET /audit?actor=...&action=...&target_id=...&from=...&to=...&page=0&size=50
+pub async fn get_audit_log(
+    State(state): State<AppState>,
+    Query(filter): Query<AuditFilter>,
+    Query(query): Query<AuditPageQuery>,
+) -> Result<Json<PageResult<AuditEntryResponse>>, ApiError> {
+    if query.size == 0 || query.size > MAX_PAGE_SIZE {
+        return Err(api_error(
+            StatusCode::BAD_REQUEST,
+            format!("Page size must be between 1 and {}", MAX_PAGE_SIZE),
+        ));
+    }
+
+    let pageable = Pageable::new(query.page, query.size);
+    let entries = audit_repository::find_page(&state.db, &filter, &pageable)
+        .await
+        .map_err(db_error)?;
+
+    Ok(Json(entries))
+}
+
+/// GET /audit/verify
+pub async fn verify_audit_log(
+    State(state): State<AppState>,
+) -> Result<Json<ChainVerification>, ApiError> {
+    let verification = audit_repository::verify_chain(&state.db)
+        .await
+        .map_err(db_error)?;
+    if !verification.valid {
+        warn!(
+            first_invalid_seq = ?verification.first_invalid_seq,
+            "Audit log hash chain is broken"
+        );
+    }
+
+    Ok(Json(verification))
+}
+
+/// GET /audit/export?actor=...&action=...&target_id=...&from=...&to=...
+///
+/// Matching entries as a JSON file for a Pflegekasse or the MDK, together
+/// with a check of the whole chain.
+pub async fn export_audit_log(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    Query(filter): Query<AuditFilter>,
+) -> Result<impl IntoResponse, ApiError> {
+    let total = audit_repository::count(&state.db, &filter)
+        .await
+        .map_err(db_error)?;
+    if total > MAX_EXPORT_ENTRIES {
+        return Err(api_error(
+            StatusCode::BAD_REQUEST,
+            format!(
+                "{} entries match; narrow the filter to at most {}",
+                total, MAX_EXPORT_ENTRIES
+            ),
+        ));
+    }
+
+    let entries = audit_repository::find_all(&state.db, &filter, MAX_EXPORT_ENTRIES)
+        .await
+        .map_err(db_error)?;
+    let chain = audit_repository::verify_chain(&state.db)
+        .await
+        .map_err(db_error)?;
+
+    let exported_at = Utc::now();
+    info!(
+        exported_by = %claims.sub,
+        entries = entries.len(),
+        chain_valid = chain.valid,
+        "Audit log exported"
+    );
+
+    let disposition = format!(
+        "attachment; filename=\"audit-log-{}.json\"",
+        exported_at.format("%Y%m%d-%H%M%S")
+    );
+    Ok((
+        [(header::CONTENT_DISPOSITION, disposition)],
+        Json(AuditExport {
+            exported_at,
+            exported_by: claims.sub,
+            filter,
+            chain,
+            entries,
+        }),
+    ))
+}
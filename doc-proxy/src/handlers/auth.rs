@@ -2,14 +2,29 @@
 
 use axum::{
     Json,
-    extract::State,
-    http::{HeaderMap, StatusCode},
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Redirect},
 };
 use serde::{Deserialize, Serialize};
+use tracing::{error, warn};
 
 use crate::AppState;
-use crate::config::auth::{AuthError, AuthUser, Claims, UserRole};
-use crate::repositories::device_repository;
+use crate::config::{
+    audit::AuditContext,
+    auth::{AuthError, AuthUser, Claims, OptionalAuthUser, UserRole, access_token_ttl_seconds},
+    jwt_keys,
+};
+use crate::models::audit::AuditAction;
+use crate::repositories::{
+    device_repository,
+    token_repository::{self, TokenError},
+    user_repository::{self, UserError},
+};
+use crate::services::{OidcError, oidc::LOGIN_TIMEOUT};
+
+/// Cookie carrying the signed OIDC login to the browser that started it
+const OIDC_STATE_COOKIE: &str = "oidc_state";
 
 /// Login request body
 #[derive(Debug, Deserialize)]
@@ -18,12 +33,37 @@ pub struct LoginRequest {
     pub password: String,
 }
 
-/// Login response with JWT token
+/// Refresh request body
+#[derive(Debug, Deserialize)]
+pub struct RefreshRequest {
+    pub refresh_token: String,
+}
+
+/// Logout request body
+#[derive(Debug, Deserialize)]
+pub struct LogoutRequest {
+    pub refresh_token: Option<String>,
+}
+
+/// Query of the OIDC callback, as passed on from the provider's redirect
+#[derive(Debug, Deserialize)]
+pub struct OidcCallbackQuery {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    pub error: Option<String>,
+    pub error_description: Option<String>,
+}
+
+/// Login response with JWT access token and refresh token
 #[derive(Debug, Serialize)]
 pub struct LoginResponse {
     pub token: String,
+    pub refresh_token: String,
     pub user: UserInfo,
+    /// Access token lifetime in seconds
     pub expires_in: i64,
+    /// Refresh token lifetime in seconds
+    pub refresh_expires_in: i64,
 }
 
 /// User info returned after login
@@ -42,33 +82,84 @@ pub struct MeResponse {
     pub role: UserRole,
 }
 
-/// Login endpoint - validates credentials and returns JWT
+/// Login endpoint - validates credentials against the users table and returns JWT
 pub async fn login(
-    State(_state): State<AppState>,
+    State(state): State<AppState>,
+    audit: AuditContext,
     Json(request): Json<LoginRequest>,
 ) -> Result<Json<LoginResponse>, AuthError> {
-    // In development/mock mode, accept test credentials
-    // In production, this would validate against a database
-    let (user_id, name, role) = validate_credentials(&request.username, &request.password)?;
+    let user = match user_repository::authenticate(&state.db, &request.username, &request.password)
+        .await
+    {
+        Ok(user) => user,
+        Err(e) => {
+            let err = match e {
+                UserError::InvalidCredentials => AuthError::InvalidCredentials,
+                UserError::Locked(until) => {
+                    warn!(
+                        username = %request.username,
+                        locked_until = %until,
+                        "Login refused, account locked"
+                    );
+                    AuthError::AccountLocked
+                }
+                e => {
+                    error!(error = %e, "Failed to check credentials");
+                    return Err(AuthError::Internal);
+                }
+            };
+            audit
+                .with_actor(request.username)
+                .record(&state.db, AuditAction::LoginFailed, None, None)
+                .await;
+            return Err(err);
+        }
+    };
+
+    let role = UserRole::from_db(&user.role);
+    let refresh_token = token_repository::issue(&state.db, user.id)
+        .await
+        .map_err(|e| {
+            error!(error = %e, "Failed to issue refresh token");
+            AuthError::Internal
+        })?;
+
+    let user_id = user.id.to_string();
+    audit
+        .with_actor(user_id.as_str())
+        .record(&state.db, AuditAction::Login, Some(&user_id), None)
+        .await;
 
+    Ok(Json(login_response(
+        user_id,
+        user.name,
+        role,
+        refresh_token,
+    )?))
+}
+
+/// Mint an access token and pair it with a refresh token
+fn login_response(
+    user_id: String,
+    name: String,
+    role: UserRole,
+    refresh_token: token_repository::IssuedRefreshToken,
+) -> Result<LoginResponse, AuthError> {
     let claims = Claims::new(user_id.clone(), name.clone(), role.clone());
     let token = claims.to_token()?;
+    let refresh_expires_in = (refresh_token.expires_at - chrono::Utc::now()).num_seconds();
 
-    let expires_in = std::env::var("JWT_EXPIRY_HOURS")
-        .ok()
-        .and_then(|v| v.parse().ok())
-        .unwrap_or(24i64)
-        * 3600;
-
-    Ok(Json(LoginResponse {
+    Ok(LoginResponse {
         token,
+        refresh_token: refresh_token.token,
         user: UserInfo {
             id: user_id,
             name,
             role,
         },
-        expires_in,
-    }))
+        expires_in: access_token_ttl_seconds(),
+        refresh_expires_in,
+    })
 }
 
 /// Get current authenticated user
@@ -80,77 +171,197 @@ pub async fn me(AuthUser(claims): AuthUser) -> Json<MeResponse> {
     })
 }
 
-/// Validate user credentials
-/// In mock mode, accepts predefined test users
-/// In production, would check against database with hashed passwords
-fn validate_credentials(
-    username: &str,
-    password: &str,
-) -> Result<(String, String, UserRole), AuthError> {
-    // Mock users for development
-    // In production, this would query the database and verify password hash
-    let mock_users = [
-        (
-            "admin",
-            "admin123",
-            "user-1",
-            "Administrator",
-            UserRole::Admin,
-        ),
-        (
-            "staff",
-            "staff123",
-            "user-2",
-            "Pflegekraft",
-            UserRole::Staff,
-        ),
-        (
-            "client",
-            "client123",
-            "user-3",
-            "Hans Müller",
-            UserRole::Client,
-        ),
-    ];
-
-    for (u, p, id, name, role) in mock_users {
-        if username == u && password == p {
-            return Ok((id.to_string(), name.to_string(), role));
-        }
+/// Refresh token endpoint - exchanges a refresh token for a new access and
+/// refresh token. Each refresh token can be used once; reusing one revokes
+/// the whole session.
+pub async fn refresh(
+    State(state): State<AppState>,
+    Json(request): Json<RefreshRequest>,
+) -> Result<Json<LoginResponse>, AuthError> {
+    let refresh_token = token_repository::rotate(&state.db, &request.refresh_token)
+        .await
+        .map_err(|e| match e {
+            TokenError::Invalid => AuthError::InvalidToken,
+            TokenError::Expired => AuthError::TokenExpired,
+            TokenError::Reused { family_id, user_id } => {
+                warn!(%family_id, %user_id, "Refresh token reused, session revoked");
+                AuthError::InvalidToken
+            }
+            TokenError::Database(e) => {
+                error!(error = %e, "Failed to rotate refresh token");
+                AuthError::Internal
+            }
+        })?;
+
+    // The account may have been deactivated or deleted since login
+    let user = user_repository::find_by_id(&state.db, refresh_token.user_id)
+        .await
+        .map_err(|e| {
+            error!(error = %e, "Failed to load user");
+            AuthError::Internal
+        })?
+        .filter(|u| u.is_active);
+    let Some(user) = user else {
+        let _ = token_repository::revoke_all_for_user(&state.db, refresh_token.user_id).await;
+        return Err(AuthError::InvalidToken);
+    };
+
+    Ok(Json(login_response(
+        user.id.to_string(),
+        user.name,
+        user.role,
+        refresh_token,
+    )?))
+}
+
+/// Logout endpoint - revokes the session's refresh tokens and puts the
+/// presented access token on the deny list until it expires
+pub async fn logout(
+    State(state): State<AppState>,
+    OptionalAuthUser(claims): OptionalAuthUser,
+    request: Option<Json<LogoutRequest>>,
+) -> Result<StatusCode, AuthError> {
+    if let Some(refresh_token) = request.and_then(|Json(r)| r.refresh_token) {
+        token_repository::revoke(&state.db, &refresh_token)
+            .await
+            .map_err(|e| {
+                error!(error = %e, "Failed to revoke refresh token");
+                AuthError::Internal
+            })?;
     }
 
-    Err(AuthError::InvalidCredentials)
+    if let Some(claims) = claims {
+        token_repository::revoke_access_token(&state.db, &claims.jti, claims.expires_at())
+            .await
+            .map_err(|e| {
+                error!(error = %e, "Failed to revoke access token");
+                AuthError::Internal
+            })?;
+    }
+
+    Ok(StatusCode::NO_CONTENT)
 }
 
-/// Refresh token endpoint
-pub async fn refresh(AuthUser(claims): AuthUser) -> Result<Json<LoginResponse>, AuthError> {
-    // Create new claims with refreshed expiry
-    let new_claims = Claims::new(claims.sub.clone(), claims.name.clone(), claims.role.clone());
-    let token = new_claims.to_token()?;
+/// OIDC login endpoint - redirects the browser to the identity provider.
+/// The provider sends it back to `OIDC_REDIRECT_URI`, whose page passes
+/// `code` and `state` on to `oidc_callback`.
+pub async fn oidc_login(State(state): State<AppState>) -> Result<impl IntoResponse, AuthError> {
+    let oidc = state.oidc.as_ref().ok_or(AuthError::OidcDisabled)?;
+    let (url, login) = oidc.authorization_url().await.map_err(oidc_error)?;
 
-    let expires_in = std::env::var("JWT_EXPIRY_HOURS")
-        .ok()
-        .and_then(|v| v.parse().ok())
-        .unwrap_or(24i64)
-        * 3600;
+    let cookie = format!(
+        "{}={}; Path=/auth/oidc; Max-Age={}; HttpOnly; SameSite=Lax",
+        OIDC_STATE_COOKIE,
+        login,
+        LOGIN_TIMEOUT.as_secs()
+    );
+    Ok(([(header::SET_COOKIE, cookie)], Redirect::to(&url)))
+}
 
-    Ok(Json(LoginResponse {
-        token,
-        user: UserInfo {
-            id: claims.sub,
-            name: claims.name,
-            role: claims.role,
-        },
-        expires_in,
-    }))
+/// OIDC callback endpoint - completes the login at the identity provider
+/// and issues our own access and refresh token. The provider's groups
+/// decide the role; users without a mapped group are refused.
+pub async fn oidc_callback(
+    State(state): State<AppState>,
+    audit: AuditContext,
+    headers: HeaderMap,
+    Query(query): Query<OidcCallbackQuery>,
+) -> Result<impl IntoResponse, AuthError> {
+    let oidc = state.oidc.as_ref().ok_or(AuthError::OidcDisabled)?;
+
+    if let Some(error) = query.error {
+        warn!(
+            error = %error,
+            description = ?query.error_description,
+            "Identity provider refused login"
+        );
+        return Err(AuthError::InvalidCredentials);
+    }
+    let (Some(code), Some(login_state)) = (query.code, query.state) else {
+        return Err(AuthError::InvalidCredentials);
+    };
+    // The login must finish in the browser that started it
+    let Some(login) = cookie(&headers, OIDC_STATE_COOKIE) else {
+        warn!("OIDC callback without state cookie");
+        return Err(AuthError::InvalidCredentials);
+    };
+
+    let identity = oidc
+        .complete(&code, &login_state, login)
+        .await
+        .map_err(oidc_error)?;
+    let Some(role) = oidc.role_for(&identity.groups) else {
+        warn!(
+            subject = %identity.subject,
+            groups = ?identity.groups,
+            "OIDC login refused, no group maps to a role"
+        );
+        return Err(AuthError::Forbidden);
+    };
+
+    let user = user_repository::upsert_oidc_user(&state.db, &identity, &role)
+        .await
+        .map_err(|e| match e {
+            UserError::InvalidCredentials => {
+                warn!(subject = %identity.subject, "OIDC login refused, account disabled");
+                AuthError::InvalidCredentials
+            }
+            e => {
+                error!(error = %e, "Failed to store OIDC user");
+                AuthError::Internal
+            }
+        })?;
+    let refresh_token = token_repository::issue(&state.db, user.id)
+        .await
+        .map_err(|e| {
+            error!(error = %e, "Failed to issue refresh token");
+            AuthError::Internal
+        })?;
+
+    let user_id = user.id.to_string();
+    audit
+        .with_actor(user_id.as_str())
+        .record(&state.db, AuditAction::Login, Some(&user_id), None)
+        .await;
+
+    let response = login_response(user_id, user.name, role, refresh_token)?;
+    let cookie = format!("{}=; Path=/auth/oidc; Max-Age=0", OIDC_STATE_COOKIE);
+    Ok(([(header::SET_COOKIE, cookie)], Json(response)))
+}
+
+fn oidc_error(err: OidcError) -> AuthError {
+    match err {
+        OidcError::UnknownState | OidcError::Rejected(_) | OidcError::InvalidIdToken(_) => {
+            warn!(error = %err, "OIDC login refused");
+            AuthError::InvalidCredentials
+        }
+        OidcError::Request(_) | OidcError::Provider(_) => {
+            error!(error = %err, "Identity provider unavailable");
+            AuthError::ProviderUnavailable
+        }
+    }
+}
+
+/// Value of a cookie in the request
+fn cookie<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .find_map(|pair| {
+            let (key, value) = pair.trim().split_once('=')?;
+            (key == name).then_some(value)
+        })
 }
 
-/// Logout endpoint - client should discard token
-/// Server-side we could add token to blacklist if needed
-pub async fn logout() -> StatusCode {
-    // In a more complete implementation, we'd add the token to a blacklist
-    // For now, the client just discards the token
-    StatusCode::NO_CONTENT
+/// Public keys for verifying access tokens (JWKS).
+/// Empty while tokens are signed with the HMAC secret.
+pub async fn jwks() -> impl IntoResponse {
+    (
+        [(header::CACHE_CONTROL, "public, max-age=300")],
+        Json(jwt_keys::current().jwks()),
+    )
 }
 
 /// Device authentication response
@@ -174,6 +385,7 @@ pub struct DeviceInfo {
 /// Reads X-Device-Key header
 pub async fn device_auth(
     State(state): State<AppState>,
+    audit: AuditContext,
     headers: HeaderMap,
 ) -> Result<Json<DeviceAuthResponse>, AuthError> {
     // Get API key from header
@@ -197,25 +409,24 @@ pub async fn device_auth(
     let _ = device_repository::update_last_seen(&state.db, device.id).await;
 
     // Convert role string to UserRole
-    let role = match device.role.as_str() {
-        "admin" => UserRole::Admin,
-        "staff" => UserRole::Staff,
-        _ => UserRole::Client,
-    };
+    let role = UserRole::from_db(&device.role);
+
+    let subject = format!("device-{}", device.id);
+    audit
+        .with_actor(subject.as_str())
+        .record(
+            &state.db,
+            AuditAction::DeviceLogin,
+            Some(&device.id.to_string()),
+            None,
+        )
+        .await;
 
     // Create JWT for device
-    let claims = Claims::new(
-        format!("device-{}", device.id),
-        device.name.clone(),
-        role.clone(),
-    );
+    let claims = Claims::new(subject, device.name.clone(), role.clone());
     let token = claims.to_token()?;
 
-    let expires_in = std::env::var("JWT_EXPIRY_HOURS")
-        .ok()
-        .and_then(|v| v.parse().ok())
-        .unwrap_or(24i64)
-        * 3600;
+    let expires_in = access_token_ttl_seconds();
 
     Ok(Json(DeviceAuthResponse {
         token,
@@ -228,3 +439,23 @@ pub async fn device_auth(
         expires_in,
     }))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_oidc_errors_map_to_status_codes() {
+        let cases = [
+            (OidcError::UnknownState, StatusCode::UNAUTHORIZED),
+            (OidcError::Provider("down".into()), StatusCode::BAD_GATEWAY),
+            (
+                OidcError::InvalidIdToken("nonce mismatch".into()),
+                StatusCode::UNAUTHORIZED,
+            ),
+        ];
+        for (err, status) in cases {
+            assert_eq!(oidc_error(err).into_response().status(), status);
+        }
+    }
+}
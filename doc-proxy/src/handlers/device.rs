@@ -1,19 +1,43 @@
-//! Device management handlers (admin only).
+//! Device management handlers (admin only, except redeeming pairing codes).
+
+use std::{sync::LazyLock, time::Duration};
 
 use axum::{
     extract::{Path, State},
-    http::StatusCode,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
     Json,
 };
 use serde::Deserialize;
+use tracing::{info, warn};
 use uuid::Uuid;
 
+use crate::config::audit::AuditContext;
 use crate::config::auth::{AuthUser, UserRole};
+use crate::models::audit::AuditAction;
 use crate::repositories::device_repository::{
     self, CreateDeviceRequest, DeviceCreatedResponse, DeviceResponse,
 };
+use crate::repositories::pairing_repository::{
+    self, CreatePairingCodeRequest, PairingCodeResponse, PairingError,
+};
+use crate::utils::rate_limit::RateLimiter;
 use crate::AppState;
 
+/// Failed pairing attempts allowed per client address and 10 minutes.
+/// Each redemption takes a slot before the code is checked and gives it back
+/// on success, so concurrent guesses cannot pass on the same free slot. One
+/// client guessing cannot lock out others; guesses from many addresses are
+/// bounded by the attempts each code allows (see `pairing_repository`).
+static PAIRING_FAILURES: LazyLock<RateLimiter> = LazyLock::new(|| {
+    let max = std::env::var("DEVICE_PAIRING_MAX_FAILURES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|&n| n > 0)
+        .unwrap_or(20);
+    RateLimiter::new(max, Duration::from_secs(600))
+});
+
 /// Error response for device operations
 #[derive(Debug, serde::Serialize)]
 pub struct DeviceErrorResponse {
@@ -23,18 +47,7 @@ pub struct DeviceErrorResponse {
 /// List all devices (admin only)
 pub async fn list_devices(
     State(state): State<AppState>,
-    AuthUser(claims): AuthUser,
 ) -> Result<Json<Vec<DeviceResponse>>, (StatusCode, Json<DeviceErrorResponse>)> {
-    // Check admin role
-    if claims.role != UserRole::Admin {
-        return Err((
-            StatusCode::FORBIDDEN,
-            Json(DeviceErrorResponse {
-                error: "Admin access required".to_string(),
-            }),
-        ));
-    }
-
     let devices = device_repository::find_all(&state.db)
         .await
         .map_err(|e| {
@@ -52,18 +65,8 @@ pub async fn list_devices(
 /// Get device by ID (admin only)
 pub async fn get_device(
     State(state): State<AppState>,
-    AuthUser(claims): AuthUser,
     Path(id): Path<Uuid>,
 ) -> Result<Json<DeviceResponse>, (StatusCode, Json<DeviceErrorResponse>)> {
-    if claims.role != UserRole::Admin {
-        return Err((
-            StatusCode::FORBIDDEN,
-            Json(DeviceErrorResponse {
-                error: "Admin access required".to_string(),
-            }),
-        ));
-    }
-
     let device = device_repository::find_by_id(&state.db, id)
         .await
         .map_err(|e| {
@@ -91,25 +94,16 @@ pub async fn get_device(
 pub struct RegisterDeviceRequest {
     pub mac_address: String,
     pub name: String,
-    pub role: Option<String>,
+    pub role: Option<UserRole>,
     pub user_id: Option<Uuid>,
 }
 
 /// Register a new device (admin only) - returns API key once!
 pub async fn register_device(
     State(state): State<AppState>,
-    AuthUser(claims): AuthUser,
+    audit: AuditContext,
     Json(request): Json<RegisterDeviceRequest>,
 ) -> Result<(StatusCode, Json<DeviceCreatedResponse>), (StatusCode, Json<DeviceErrorResponse>)> {
-    if claims.role != UserRole::Admin {
-        return Err((
-            StatusCode::FORBIDDEN,
-            Json(DeviceErrorResponse {
-                error: "Admin access required".to_string(),
-            }),
-        ));
-    }
-
     // Validate MAC address format (basic check)
     let mac = request.mac_address.to_uppercase();
     if !is_valid_mac(&mac) {
@@ -161,24 +155,136 @@ pub async fn register_device(
         )
     })?;
 
+    let device_id = device.id.to_string();
+    audit
+        .record(
+            &state.db,
+            AuditAction::DeviceRegistered,
+            Some(&device_id),
+            None,
+        )
+        .await;
     Ok((StatusCode::CREATED, Json(device)))
 }
 
-/// Deactivate a device (admin only)
-pub async fn deactivate_device(
+/// Create a one-time pairing code for a new device (admin only) - returns the code once!
+pub async fn create_pairing_code(
     State(state): State<AppState>,
     AuthUser(claims): AuthUser,
-    Path(id): Path<Uuid>,
-) -> Result<Json<DeviceResponse>, (StatusCode, Json<DeviceErrorResponse>)> {
-    if claims.role != UserRole::Admin {
+    audit: AuditContext,
+    Json(request): Json<CreatePairingCodeRequest>,
+) -> Result<(StatusCode, Json<PairingCodeResponse>), (StatusCode, Json<DeviceErrorResponse>)> {
+    let pairing = pairing_repository::create(&state.db, request, &claims.sub)
+        .await
+        .map_err(|e| {
+            let status = match e {
+                PairingError::UnknownUser => StatusCode::BAD_REQUEST,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            (
+                status,
+                Json(DeviceErrorResponse {
+                    error: e.to_string(),
+                }),
+            )
+        })?;
+
+    info!(
+        pairing_id = %pairing.id,
+        name = %pairing.name,
+        created_by = %claims.sub,
+        "Created device pairing code"
+    );
+    audit
+        .record(
+            &state.db,
+            AuditAction::PairingCodeCreated,
+            Some(&pairing.id.to_string()),
+            None,
+        )
+        .await;
+    Ok((StatusCode::CREATED, Json(pairing)))
+}
+
+/// Request to redeem a pairing code
+#[derive(Debug, Deserialize)]
+pub struct PairDeviceRequest {
+    pub code: String,
+    pub mac_address: String,
+}
+
+/// Redeem a pairing code (public) - registers the device and returns its API key once!
+pub async fn pair_device(
+    State(state): State<AppState>,
+    audit: AuditContext,
+    Json(request): Json<PairDeviceRequest>,
+) -> Result<(StatusCode, Json<DeviceCreatedResponse>), Response> {
+    let mac = request.mac_address.to_uppercase();
+    if !is_valid_mac(&mac) {
         return Err((
-            StatusCode::FORBIDDEN,
+            StatusCode::BAD_REQUEST,
             Json(DeviceErrorResponse {
-                error: "Admin access required".to_string(),
+                error: "Invalid MAC address format. Use AA:BB:CC:DD:EE:FF".to_string(),
             }),
-        ));
+        )
+            .into_response());
+    }
+
+    let client = audit.client_ip.clone().unwrap_or_default();
+    let attempt = match PAIRING_FAILURES.reserve(&client) {
+        Ok(attempt) => attempt,
+        Err(retry_after) => {
+            warn!(client_ip = %client, "Device pairing refused, too many failed attempts");
+            let retry_after = retry_after.as_secs().max(1).to_string();
+            return Err((
+                StatusCode::TOO_MANY_REQUESTS,
+                [(header::RETRY_AFTER, retry_after)],
+                Json(DeviceErrorResponse {
+                    error: "Too many failed pairing attempts, try again later".to_string(),
+                }),
+            )
+                .into_response());
+        }
+    };
+
+    let result = pairing_repository::redeem(&state.db, &request.code, &mac).await;
+    // Only wrong codes keep their slot
+    if !matches!(result, Err(PairingError::InvalidCode)) {
+        attempt.refund();
     }
+    let device = result.map_err(|e| {
+        let status = match e {
+            PairingError::InvalidCode => {
+                warn!(mac_address = %mac, client_ip = %client, "Invalid device pairing code");
+                StatusCode::UNAUTHORIZED
+            }
+            PairingError::MacTaken => StatusCode::CONFLICT,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        (
+            status,
+            Json(DeviceErrorResponse {
+                error: e.to_string(),
+            }),
+        )
+            .into_response()
+    })?;
+
+    info!(device_id = %device.id, mac_address = %device.mac_address, "Paired device");
+    let device_id = device.id.to_string();
+    audit
+        .with_actor(format!("device-{}", device_id))
+        .record(&state.db, AuditAction::DevicePaired, Some(&device_id), None)
+        .await;
+    Ok((StatusCode::CREATED, Json(device)))
+}
 
+/// Deactivate a device (admin only)
+pub async fn deactivate_device(
+    State(state): State<AppState>,
+    audit: AuditContext,
+    Path(id): Path<Uuid>,
+) -> Result<Json<DeviceResponse>, (StatusCode, Json<DeviceErrorResponse>)> {
     let device = device_repository::deactivate(&state.db, id)
         .await
         .map_err(|e| {
@@ -198,24 +304,23 @@ pub async fn deactivate_device(
             )
         })?;
 
+    audit
+        .record(
+            &state.db,
+            AuditAction::DeviceDeactivated,
+            Some(&id.to_string()),
+            None,
+        )
+        .await;
     Ok(Json(device))
 }
 
 /// Activate a device (admin only)
 pub async fn activate_device(
     State(state): State<AppState>,
-    AuthUser(claims): AuthUser,
+    audit: AuditContext,
     Path(id): Path<Uuid>,
 ) -> Result<Json<DeviceResponse>, (StatusCode, Json<DeviceErrorResponse>)> {
-    if claims.role != UserRole::Admin {
-        return Err((
-            StatusCode::FORBIDDEN,
-            Json(DeviceErrorResponse {
-                error: "Admin access required".to_string(),
-            }),
-        ));
-    }
-
     let device = device_repository::activate(&state.db, id)
         .await
         .map_err(|e| {
@@ -235,24 +340,23 @@ pub async fn activate_device(
             )
         })?;
 
+    audit
+        .record(
+            &state.db,
+            AuditAction::DeviceActivated,
+            Some(&id.to_string()),
+            None,
+        )
+        .await;
     Ok(Json(device))
 }
 
 /// Regenerate API key for a device (admin only)
 pub async fn regenerate_device_key(
     State(state): State<AppState>,
-    AuthUser(claims): AuthUser,
+    audit: AuditContext,
     Path(id): Path<Uuid>,
 ) -> Result<Json<DeviceCreatedResponse>, (StatusCode, Json<DeviceErrorResponse>)> {
-    if claims.role != UserRole::Admin {
-        return Err((
-            StatusCode::FORBIDDEN,
-            Json(DeviceErrorResponse {
-                error: "Admin access required".to_string(),
-            }),
-        ));
-    }
-
     let device = device_repository::regenerate_api_key(&state.db, id)
         .await
         .map_err(|e| {
@@ -272,24 +376,23 @@ pub async fn regenerate_device_key(
             )
         })?;
 
+    audit
+        .record(
+            &state.db,
+            AuditAction::DeviceKeyRegenerated,
+            Some(&id.to_string()),
+            None,
+        )
+        .await;
     Ok(Json(device))
 }
 
 /// Delete a device (admin only)
 pub async fn delete_device(
     State(state): State<AppState>,
-    AuthUser(claims): AuthUser,
+    audit: AuditContext,
     Path(id): Path<Uuid>,
 ) -> Result<StatusCode, (StatusCode, Json<DeviceErrorResponse>)> {
-    if claims.role != UserRole::Admin {
-        return Err((
-            StatusCode::FORBIDDEN,
-            Json(DeviceErrorResponse {
-                error: "Admin access required".to_string(),
-            }),
-        ));
-    }
-
     // Check if device exists
     if device_repository::find_by_id(&state.db, id)
         .await
@@ -320,6 +423,14 @@ pub async fn delete_device(
         )
     })?;
 
+    audit
+        .record(
+            &state.db,
+            AuditAction::DeviceDeleted,
+            Some(&id.to_string()),
+            None,
+        )
+        .await;
     Ok(StatusCode::NO_CONTENT)
 }
 
@@ -0,0 +1,44 @@
+//! HTTP handlers for Fehlernachricht API.
+
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::AppState;
+
+use super::{error::Result, response::FehlernachrichtResponse, service};
+
+/// POST /fehlernachrichten
+///
+/// Body: the received Nutzdaten file (XML) containing a
+/// Fehlernachricht_technisch.
+pub async fn ingest_fehlernachricht(
+    State(state): State<AppState>,
+    body: String,
+) -> Result<(StatusCode, Json<FehlernachrichtResponse>)> {
+    info!(bytes = body.len(), "Ingesting fehlernachricht");
+
+    let nachricht = service::ingest_fehlernachricht(&state.db, body)
+        .await
+        .inspect_err(|e| error!(error = %e, "Failed to ingest fehlernachricht"))?;
+
+    info!(
+        id = %nachricht.id,
+        bezug_datei_id = ?nachricht.bezug_datei_id,
+        fehler = nachricht.fehler.len(),
+        "Fehlernachricht ingested"
+    );
+    Ok((StatusCode::CREATED, Json(nachricht)))
+}
+
+/// GET /fehlernachrichten/{id}
+pub async fn get_fehlernachricht(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<FehlernachrichtResponse>> {
+    service::get_fehlernachricht(&state.db, id).await.map(Json)
+}
@@ -0,0 +1,78 @@
+//! Error types for Fehlernachricht API.
+
+use axum::{
+    Json,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use thiserror::Error;
+
+use crate::models::{nutzdaten::NutzdatenError, xsd::Violation};
+
+use super::response::FehlernachrichtErrorResponse;
+
+/// Domain errors for Fehlernachricht operations.
+#[derive(Error, Debug)]
+pub enum FehlernachrichtError {
+    #[error("Resource not found: {0}")]
+    NotFound(String),
+
+    #[error("Invalid request: {0}")]
+    BadRequest(String),
+
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
+    #[error("Database error: {0}")]
+    Database(#[from] sea_orm::DbErr),
+
+    #[error("Internal error: {0}")]
+    Internal(String),
+
+    #[error("Received file violates the schema ({} violations)", .0.len())]
+    SchemaViolation(Vec<Violation>),
+}
+
+/// Errors of the received file; errors of stored deliveries are mapped to
+/// [`FehlernachrichtError::Internal`] by the service.
+impl From<NutzdatenError> for FehlernachrichtError {
+    fn from(err: NutzdatenError) -> Self {
+        match err {
+            NutzdatenError::Validation(violations) => Self::SchemaViolation(violations),
+            NutzdatenError::XmlDeserialize(e) => Self::BadRequest(e.to_string()),
+            e => Self::Internal(e.to_string()),
+        }
+    }
+}
+
+impl From<&FehlernachrichtError> for StatusCode {
+    fn from(err: &FehlernachrichtError) -> Self {
+        match err {
+            FehlernachrichtError::NotFound(_) => StatusCode::NOT_FOUND,
+            FehlernachrichtError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            FehlernachrichtError::Conflict(_) => StatusCode::CONFLICT,
+            FehlernachrichtError::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            FehlernachrichtError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            FehlernachrichtError::SchemaViolation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+        }
+    }
+}
+
+impl IntoResponse for FehlernachrichtError {
+    fn into_response(self) -> Response {
+        let status = StatusCode::from(&self);
+        let error = self.to_string();
+        let violations = match self {
+            FehlernachrichtError::SchemaViolation(violations) => violations,
+            _ => Vec::new(),
+        };
+        (
+            status,
+            Json(FehlernachrichtErrorResponse { error, violations }),
+        )
+            .into_response()
+    }
+}
+
+/// Result type alias for Fehlernachricht operations.
+pub type Result<T> = std::result::Result<T, FehlernachrichtError>;
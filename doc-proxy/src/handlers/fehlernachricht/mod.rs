@@ -0,0 +1,17 @@
+//! Fehlernachricht (HKP_FEH_TECH) domain module.
+//!
+//! Structure:
+//! - `api` - HTTP handlers (thin, orchestration only)
+//! - `service` - Business logic
+//! - `response` - Response DTOs
+//! - `error` - Domain error types
+//!
+//! The request body is the received Nutzdaten file itself, so there are no
+//! request DTOs.
+
+mod api;
+mod error;
+pub mod response;
+mod service;
+
+pub use api::{get_fehlernachricht, ingest_fehlernachricht};
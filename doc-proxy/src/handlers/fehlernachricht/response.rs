@@ -0,0 +1,88 @@
+//! Response DTOs for Fehlernachricht API.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::{
+    models::xsd::Violation,
+    repositories::entity::{fehlernachricht, technischer_fehler},
+};
+
+/// Received technical error message with its assigned errors
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FehlernachrichtResponse {
+    pub id: Uuid,
+    /// Datei_ID of the received file
+    pub datei_id: String,
+    /// Own delivery the errors refer to, `None` if it could not be correlated
+    pub bezug_datei_id: Option<Uuid>,
+    /// Invoice contained in that delivery
+    pub abrechnung_id: Option<Uuid>,
+    pub message_id: String,
+    pub absender_ik: String,
+    pub created_at: DateTime<Utc>,
+    pub fehler: Vec<TechnischerFehlerResponse>,
+}
+
+impl FehlernachrichtResponse {
+    pub fn new(
+        nachricht: fehlernachricht::Model,
+        abrechnung_id: Option<Uuid>,
+        fehler: Vec<technischer_fehler::Model>,
+    ) -> Self {
+        Self {
+            id: nachricht.id,
+            datei_id: nachricht.datei_id,
+            bezug_datei_id: nachricht.bezug_datei_id,
+            abrechnung_id,
+            message_id: nachricht.message_id,
+            absender_ik: nachricht.absender_ik,
+            created_at: nachricht.created_at,
+            fehler: fehler
+                .into_iter()
+                .map(TechnischerFehlerResponse::from)
+                .collect(),
+        }
+    }
+}
+
+/// Single error, assigned to the Leistungsnachweis it concerns
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TechnischerFehlerResponse {
+    /// `None` if the delivery could not be correlated
+    pub leistungsnachweis_id: Option<String>,
+    pub fehlercode: String,
+    pub fehlertext: Option<String>,
+    /// XPath as reported by the recipient
+    pub xpath: Option<String>,
+    /// Offending element below the Abrechnungsfall, `None` if the error
+    /// concerns the whole delivery
+    pub feld: Option<String>,
+    pub resolved_at: Option<DateTime<Utc>>,
+}
+
+impl From<technischer_fehler::Model> for TechnischerFehlerResponse {
+    fn from(f: technischer_fehler::Model) -> Self {
+        Self {
+            leistungsnachweis_id: f.leistungsnachweis_id,
+            fehlercode: f.fehlercode,
+            fehlertext: f.fehlertext,
+            xpath: f.xpath,
+            feld: f.feld,
+            resolved_at: f.resolved_at,
+        }
+    }
+}
+
+/// Error body for Fehlernachricht operations
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FehlernachrichtErrorResponse {
+    pub error: String,
+    /// One entry per violated schema rule, located by XPath
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub violations: Vec<Violation>,
+}
@@ -0,0 +1,216 @@
+//! Business logic for Fehlernachricht operations.
+
+use std::collections::BTreeSet;
+
+use sea_orm::{ConnectionTrait, DatabaseConnection, TransactionTrait};
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::{
+    models::{
+        document_status::{DocumentStatus, StatusAction},
+        nutzdaten::{
+            Nutzdaten,
+            types::{fehler::Fehlerinformation, header::Nachricht},
+        },
+    },
+    repositories::{
+        entity::{lieferung, technischer_fehler},
+        fehlernachricht_repository::{self, NewFehlernachricht, NewTechnischerFehler},
+        leistungsnachweis_repository, lieferung_repository,
+    },
+};
+
+use super::{
+    error::{FehlernachrichtError, Result},
+    response::FehlernachrichtResponse,
+};
+
+/// Stores a received Fehlernachricht_technisch and assigns its errors to the
+/// Leistungsnachweise of the delivery it refers to.
+///
+/// Errors located in an Abrechnungsfall are assigned to that case's
+/// Leistungsnachweis; errors concerning the whole delivery are assigned to
+/// every Leistungsnachweis in it. Affected documents known locally are moved
+/// to rejected. If the referenced delivery is unknown, the file is still
+/// stored so the errors are not lost.
+pub async fn ingest_fehlernachricht(
+    db: &DatabaseConnection,
+    xml: String,
+) -> Result<FehlernachrichtResponse> {
+    let datei = Nutzdaten::from_xml(&xml)?;
+    let Nachricht::FehlernachrichtTechnisch(fehlernachricht) = datei.body.nachricht else {
+        return Err(FehlernachrichtError::BadRequest(
+            "Body does not contain a Fehlernachricht_technisch".into(),
+        ));
+    };
+
+    if fehlernachricht_repository::find_by_datei_id(db, &datei.header.datei_id)
+        .await?
+        .is_some()
+    {
+        return Err(FehlernachrichtError::Conflict(format!(
+            "File {} has already been ingested",
+            datei.header.datei_id
+        )));
+    }
+
+    let lieferung = find_lieferung(db, fehlernachricht.datei_id.as_deref()).await?;
+    let leistungsnachweise = match &lieferung {
+        Some(l) => delivered_leistungsnachweise(l)?,
+        None => {
+            warn!(
+                datei_id = ?fehlernachricht.datei_id,
+                "Fehlernachricht refers to an unknown delivery"
+            );
+            Vec::new()
+        }
+    };
+    let fehler = assign_fehler(
+        &fehlernachricht.fehler.fehlerinformationen,
+        &leistungsnachweise,
+    );
+
+    let txn = db.begin().await?;
+    let (nachricht, fehler) = fehlernachricht_repository::create(
+        &txn,
+        NewFehlernachricht {
+            datei_id: datei.header.datei_id,
+            bezug_datei_id: lieferung.as_ref().map(|l| l.datei_id),
+            message_id: fehlernachricht.message_id,
+            absender_ik: datei.header.absender_ik,
+            empfaenger_ik: datei.header.empfaenger_ik,
+            xml,
+        },
+        fehler,
+    )
+    .await?;
+    reject_leistungsnachweise(&txn, &fehler, &nachricht.datei_id).await?;
+    txn.commit().await?;
+
+    Ok(FehlernachrichtResponse::new(
+        nachricht,
+        lieferung.and_then(|l| l.abrechnung_id),
+        fehler,
+    ))
+}
+
+/// Loads a stored error message with its errors.
+pub async fn get_fehlernachricht(
+    db: &DatabaseConnection,
+    id: Uuid,
+) -> Result<FehlernachrichtResponse> {
+    let nachricht = fehlernachricht_repository::find_by_id(db, id)
+        .await?
+        .ok_or_else(|| FehlernachrichtError::NotFound(format!("Fehlernachricht {}", id)))?;
+    let fehler = fehlernachricht_repository::find_fehler(db, id).await?;
+
+    let abrechnung_id = match nachricht.bezug_datei_id {
+        Some(datei_id) => lieferung_repository::find_by_datei_id(db, datei_id)
+            .await?
+            .and_then(|l| l.abrechnung_id),
+        None => None,
+    };
+
+    Ok(FehlernachrichtResponse::new(
+        nachricht,
+        abrechnung_id,
+        fehler,
+    ))
+}
+
+/// Moves the affected, locally known documents to rejected. Documents whose
+/// status does not allow a rejection keep it; the open errors still mark them
+/// as rejected in the API.
+async fn reject_leistungsnachweise<C: ConnectionTrait>(
+    db: &C,
+    fehler: &[technischer_fehler::Model],
+    datei_id: &str,
+) -> Result<()> {
+    let ids: BTreeSet<&str> = fehler
+        .iter()
+        .filter_map(|f| f.leistungsnachweis_id.as_deref())
+        .collect();
+
+    for id in ids {
+        let Some(document) = leistungsnachweis_repository::find_by_id(db, id).await? else {
+            continue;
+        };
+        let Ok(from) = document.status.parse::<DocumentStatus>() else {
+            continue;
+        };
+        if let Ok(to) = from.apply(StatusAction::Reject) {
+            leistungsnachweis_repository::transition(
+                db,
+                id,
+                from.as_str(),
+                to.as_str(),
+                Some(format!("Fehlernachricht_technisch {}", datei_id)),
+                "fehlernachricht",
+            )
+            .await?;
+        }
+    }
+    Ok(())
+}
+
+async fn find_lieferung(
+    db: &DatabaseConnection,
+    datei_id: Option<&str>,
+) -> Result<Option<lieferung::Model>> {
+    match datei_id.and_then(|id| Uuid::parse_str(id.trim()).ok()) {
+        Some(datei_id) => Ok(lieferung_repository::find_by_datei_id(db, datei_id).await?),
+        None => Ok(None),
+    }
+}
+
+/// Leistungsnachweis-IDs of the Abrechnungsfälle in a stored delivery, in
+/// document order.
+fn delivered_leistungsnachweise(lieferung: &lieferung::Model) -> Result<Vec<String>> {
+    let datei = Nutzdaten::from_xml(&lieferung.xml).map_err(|e| {
+        FehlernachrichtError::Internal(format!(
+            "Stored delivery {} is unreadable: {}",
+            lieferung.datei_id, e
+        ))
+    })?;
+
+    Ok(match datei.body.nachricht {
+        Nachricht::Abrechnungsnachricht(nachricht) => nachricht
+            .faelle
+            .into_iter()
+            .map(|f| f.leistungsnachweis_id)
+            .collect(),
+        _ => Vec::new(),
+    })
+}
+
+fn assign_fehler(
+    fehlerinformationen: &[Fehlerinformation],
+    leistungsnachweise: &[String],
+) -> Vec<NewTechnischerFehler> {
+    let entry = |f: &Fehlerinformation, leistungsnachweis_id, feld| NewTechnischerFehler {
+        leistungsnachweis_id,
+        fehlercode: f.fehlercode.clone(),
+        fehlertext: f.fehlertext.clone(),
+        xpath: f.xpath.clone(),
+        feld,
+    };
+
+    fehlerinformationen
+        .iter()
+        .flat_map(|f| {
+            let fundstelle = f.fundstelle().and_then(|fs| {
+                let id = leistungsnachweise.get(fs.abrechnungsfall.checked_sub(1)?)?;
+                Some((id.clone(), fs.feld))
+            });
+            match fundstelle {
+                Some((id, feld)) => vec![entry(f, Some(id), feld)],
+                None if leistungsnachweise.is_empty() => vec![entry(f, None, None)],
+                None => leistungsnachweise
+                    .iter()
+                    .map(|id| entry(f, Some(id.clone()), None))
+                    .collect(),
+            }
+        })
+        .collect()
+}
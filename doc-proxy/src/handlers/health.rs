@@ -1,13 +1,28 @@
-use axum::Json;
+use axum::{Json, extract::State};
 use serde::Serialize;
 
+use crate::AppState;
+use crate::services::core_client::CoreStatus;
+use crate::utils::circuit_breaker::CircuitState;
+
 #[derive(Serialize)]
 pub struct HealthResponse {
     pub status: String,
+    /// Circuit breaker for the core server
+    pub core: CoreStatus,
 }
 
-pub async fn get_health() -> Json<HealthResponse> {
+/// Always 200 while the proxy runs; `degraded` while requests to the core
+/// server are refused by the circuit breaker.
+pub async fn get_health(State(state): State<AppState>) -> Json<HealthResponse> {
+    let core = state.core_client.status();
+    let status = match core.circuit {
+        CircuitState::Closed => "ok",
+        CircuitState::Open | CircuitState::HalfOpen => "degraded",
+    };
+
     Json(HealthResponse {
-        status: "ok".to_string(),
+        status: status.to_string(),
+        core,
     })
 }
@@ -5,19 +5,38 @@
 
 use axum::{
     extract::{Path, Query, State},
-    http::StatusCode,
+    http::{header, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
+use std::collections::HashMap;
+
+use chrono::Utc;
 use tracing::{error, info};
+use uuid::Uuid;
 
-use crate::{models::pagination::PageResult, AppState};
+use crate::{
+    config::{audit::AuditContext, auth::AuthUser},
+    models::{audit::AuditAction, document_status::StatusAction, pagination::PageResult},
+    repositories::{audit_repository, leistungsnachweis_repository},
+    services::ExportStoreError,
+    AppState,
+};
 
 use super::{
     error::LeistungsnachweisError,
-    request::{ListLeistungsnachweiseQuery, SignQueryParams, SignLeistungsnachweisRequest},
-    response::{LeistungsnachweisDetail, LeistungsnachweisListItem},
-    service,
+    policy,
+    request::{
+        BatchExportRequest, BatchSignRequest, CancelRequest, ExportDownloadQuery,
+        ListLeistungsnachweiseQuery, RejectRequest, SignQueryParams, SignLeistungsnachweisRequest,
+        SyncPullQuery, SyncUploadRequest,
+    },
+    response::{
+        BatchExportResponse, BatchSignResponse, LeistungsnachweisDetail, LeistungsnachweisListItem,
+        StatusChangeResponse, StatusTransitionResponse, SyncOutcome, SyncPullResponse,
+        SyncUploadResponse,
+    },
+    service, sync,
 };
 
 // ============================================================================
@@ -31,15 +50,23 @@ pub async fn list_leistungsnachweise(
 ) -> Result<Json<PageResult<LeistungsnachweisListItem>>, StatusCode> {
     info!(client_id = %query.client_id, page = query.page, "Listing leistungsnachweise");
 
-    state
+    let mut page = state
         .core_client
         .list_leistungsnachweise(&query.client_id, query.page, query.size)
         .await
-        .map(Json)
         .map_err(|e| {
             error!(error = %e, "Failed to list leistungsnachweise");
-            LeistungsnachweisError::from(e).into()
-        })
+            StatusCode::from(LeistungsnachweisError::from(e))
+        })?;
+
+    service::apply_local_states(&state.db, &mut page.content)
+        .await
+        .map_err(|e| {
+            error!(error = %e, "Failed to load technical errors");
+            StatusCode::from(e)
+        })?;
+
+    Ok(Json(page))
 }
 
 /// GET /leistungsnachweise/{id}
@@ -49,63 +76,495 @@ pub async fn get_leistungsnachweis(
 ) -> Result<Json<LeistungsnachweisDetail>, StatusCode> {
     info!(id = %id, "Getting leistungsnachweis");
 
-    state
+    let mut detail = state
         .core_client
         .get_leistungsnachweis(&id)
         .await
-        .map(Json)
         .map_err(|e| {
             error!(error = %e, id = %id, "Failed to get leistungsnachweis");
-            LeistungsnachweisError::from(e).into()
-        })
+            StatusCode::from(LeistungsnachweisError::from(e))
+        })?;
+
+    service::apply_local_state(&state.db, &mut detail)
+        .await
+        .map_err(|e| {
+            error!(error = %e, id = %id, "Failed to load technical errors");
+            StatusCode::from(e)
+        })?;
+
+    Ok(Json(detail))
 }
 
 /// POST /leistungsnachweise/{id}/sign?generateXml=true|false
 pub async fn sign_leistungsnachweis(
     State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    audit: AuditContext,
     Path(id): Path<String>,
     Query(params): Query<SignQueryParams>,
-    Json(payload): Json<SignLeistungsnachweisRequest>,
+    Json(mut payload): Json<SignLeistungsnachweisRequest>,
 ) -> Result<Response, StatusCode> {
     info!(id = %id, generate_xml = params.generate_xml, "Signing leistungsnachweis");
 
-    service::validate_signature_request(&payload).map_err(|e| {
+    if let Err(e) = service::prepare_signature(&mut payload).await {
         error!(error = %e, "Validation failed");
+        return Ok(e.into_response());
+    }
+
+    let response = if params.generate_xml {
+        generate_xml_locally(&state, &id, &claims.sub, &payload).await
+    } else {
+        forward_to_core(&state, &id, &claims.sub, &payload).await
+    }?;
+
+    if response.status().is_success() {
+        state.core_client.invalidate(&id);
+        let digest = audit_repository::payload_digest(&payload);
+        audit
+            .record(
+                &state.db,
+                AuditAction::DocumentSigned,
+                Some(&id),
+                Some(digest),
+            )
+            .await;
+    }
+    Ok(response)
+}
+
+/// POST /leistungsnachweise/batch-sign
+///
+/// Signs each document with locally generated XML and reports the outcome
+/// per document.
+pub async fn batch_sign_leistungsnachweise(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    audit: AuditContext,
+    Json(payload): Json<BatchSignRequest>,
+) -> Result<Json<BatchSignResponse>, LeistungsnachweisError> {
+    info!(
+        count = payload.documents.len(),
+        all_or_nothing = payload.all_or_nothing,
+        "Batch signing leistungsnachweise"
+    );
+
+    // Digests of the signature requests, recorded for the signed documents
+    let digests: HashMap<String, String> = payload
+        .documents
+        .iter()
+        .map(|item| {
+            let (id, request) = item.clone().into_parts();
+            (id, audit_repository::payload_digest(&request))
+        })
+        .collect();
+
+    let scope = policy::scope(&state, &claims).await?;
+    let response = service::batch_sign(&state.db, &state.core_client, &scope, payload, &claims.sub)
+        .await
+        .inspect_err(|e| error!(error = %e, "Batch signing failed"))?;
+
+    info!(
+        succeeded = response.success_count,
+        failed = response.failure_count,
+        "Batch signing finished"
+    );
+    for result in response.results.iter().filter(|r| r.success) {
+        state.core_client.invalidate(&result.id);
+        let digest = digests.get(&result.id).cloned();
+        audit
+            .record(
+                &state.db,
+                AuditAction::DocumentSigned,
+                Some(&result.id),
+                digest,
+            )
+            .await;
+    }
+    Ok(Json(response))
+}
+
+/// GET /sync/leistungsnachweise?cursor=...&clients=...&limit=100
+///
+/// Documents of the token's clients changed since the cursor, for tablets
+/// working offline.
+pub async fn sync_pull(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    Query(query): Query<SyncPullQuery>,
+) -> Result<Json<SyncPullResponse>, LeistungsnachweisError> {
+    info!(cursor = ?query.cursor, limit = query.limit, "Pulling changed leistungsnachweise");
+
+    let scope = policy::scope(&state, &claims).await?;
+    let response = sync::pull(&state.db, &state.core_client, &scope, query)
+        .await
+        .inspect_err(|e| error!(error = %e, "Sync pull failed"))?;
+
+    info!(
+        documents = response.documents.len(),
+        cursor = %response.cursor,
+        has_more = response.has_more,
+        "Sync pull finished"
+    );
+    Ok(Json(response))
+}
+
+/// POST /sync/signatures
+///
+/// Uploads signatures captured offline and reports the outcome per
+/// signature, including conflicts with documents changed in the meantime.
+pub async fn sync_upload(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    audit: AuditContext,
+    Json(payload): Json<SyncUploadRequest>,
+) -> Result<Json<SyncUploadResponse>, LeistungsnachweisError> {
+    info!(
+        count = payload.signatures.len(),
+        "Uploading queued signatures"
+    );
+
+    // Digests of the signature requests, recorded for the signed documents
+    let digests: HashMap<String, String> = payload
+        .signatures
+        .iter()
+        .map(|queued| {
+            let digest = audit_repository::payload_digest(&queued.signature);
+            (queued.idempotency_key.trim().to_string(), digest)
+        })
+        .collect();
+
+    let scope = policy::scope(&state, &claims).await?;
+    let response = sync::upload(&state.db, &state.core_client, &scope, payload, &claims.sub)
+        .await
+        .inspect_err(|e| error!(error = %e, "Sync upload failed"))?;
+
+    let count = |outcome| {
+        response
+            .results
+            .iter()
+            .filter(|r| r.outcome == outcome)
+            .count()
+    };
+    info!(
+        signed = count(SyncOutcome::Signed),
+        conflicts = count(SyncOutcome::Conflict),
+        rejected = count(SyncOutcome::Rejected),
+        retry = count(SyncOutcome::Retry),
+        "Sync upload finished"
+    );
+    for result in response
+        .results
+        .iter()
+        .filter(|r| r.outcome == SyncOutcome::Signed && !r.replayed)
+    {
+        let digest = digests.get(&result.idempotency_key).cloned();
+        audit
+            .record(
+                &state.db,
+                AuditAction::DocumentSigned,
+                Some(&result.document_id),
+                digest,
+            )
+            .await;
+    }
+    Ok(Json(response))
+}
+
+/// GET /leistungsnachweise/{id}/xml
+///
+/// Returns the most recently generated XML of a locally signed document.
+pub async fn get_leistungsnachweis_xml(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Response, StatusCode> {
+    let artefakt = leistungsnachweis_repository::find_latest_xml(&state.db, &id)
+        .await
+        .map_err(|e| {
+            error!(error = %e, id = %id, "Failed to load stored XML");
+            StatusCode::from(LeistungsnachweisError::from(e))
+        })?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let disposition = format!(
+        "attachment; filename=\"{}\"",
+        service::xml_file_name(&artefakt.leistungsnachweis_id)
+    );
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/xml".to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        artefakt.xml,
+    )
+        .into_response())
+}
+
+/// GET /leistungsnachweise/{id}/pdf
+///
+/// Printable A4 document for preview and archive.
+pub async fn get_leistungsnachweis_pdf(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Response, StatusCode> {
+    info!(id = %id, "Rendering leistungsnachweis as PDF");
+
+    let mut detail = state
+        .core_client
+        .get_leistungsnachweis(&id)
+        .await
+        .map_err(|e| {
+            error!(error = %e, id = %id, "Failed to get leistungsnachweis");
+            StatusCode::from(LeistungsnachweisError::from(e))
+        })?;
+
+    service::apply_local_state(&state.db, &mut detail)
+        .await
+        .map_err(|e| {
+            error!(error = %e, id = %id, "Failed to load technical errors");
+            StatusCode::from(e)
+        })?;
+
+    let pdf = service::render_pdf(&state.db, &detail).await.map_err(|e| {
+        error!(error = %e, id = %id, "Failed to render PDF");
         StatusCode::from(e)
     })?;
 
-    if params.generate_xml {
-        generate_xml_locally(&state, &id, &payload).await
-    } else {
-        forward_to_core(&state, &id, &payload).await
+    let disposition = format!("inline; filename=\"{}\"", service::pdf_file_name(&id));
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/pdf".to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        pdf,
+    )
+        .into_response())
+}
+
+/// POST /leistungsnachweise/{id}/submit
+pub async fn submit_leistungsnachweis(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    Path(id): Path<String>,
+) -> Result<Json<StatusChangeResponse>, LeistungsnachweisError> {
+    info!(id = %id, "Submitting leistungsnachweis for signature");
+    change_status(&state, &id, StatusAction::Submit, None, &claims.sub).await
+}
+
+/// POST /leistungsnachweise/{id}/reject
+pub async fn reject_leistungsnachweis(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    audit: AuditContext,
+    Path(id): Path<String>,
+    Json(payload): Json<RejectRequest>,
+) -> Result<Json<StatusChangeResponse>, LeistungsnachweisError> {
+    info!(id = %id, "Rejecting leistungsnachweis");
+
+    let reason = payload.reason.trim();
+    if reason.is_empty() {
+        return Err(LeistungsnachweisError::BadRequest(
+            "Reason required for rejection".into(),
+        ));
     }
+    let change = change_status(
+        &state,
+        &id,
+        StatusAction::Reject,
+        Some(reason.to_string()),
+        &claims.sub,
+    )
+    .await?;
+
+    let digest = audit_repository::payload_digest(&reason);
+    audit
+        .record(
+            &state.db,
+            AuditAction::DocumentRejected,
+            Some(&id),
+            Some(digest),
+        )
+        .await;
+    Ok(change)
+}
+
+/// POST /leistungsnachweise/{id}/cancel
+pub async fn cancel_leistungsnachweis(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    audit: AuditContext,
+    Path(id): Path<String>,
+    payload: Option<Json<CancelRequest>>,
+) -> Result<Json<StatusChangeResponse>, LeistungsnachweisError> {
+    info!(id = %id, "Cancelling leistungsnachweis");
+
+    let reason = payload
+        .and_then(|Json(p)| p.reason)
+        .map(|r| r.trim().to_string())
+        .filter(|r| !r.is_empty());
+    let digest = reason.as_ref().map(audit_repository::payload_digest);
+    let change = change_status(&state, &id, StatusAction::Cancel, reason, &claims.sub).await?;
+
+    audit
+        .record(&state.db, AuditAction::DocumentCancelled, Some(&id), digest)
+        .await;
+    Ok(change)
+}
+
+/// POST /leistungsnachweise/{id}/reopen
+pub async fn reopen_leistungsnachweis(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    audit: AuditContext,
+    Path(id): Path<String>,
+) -> Result<Json<StatusChangeResponse>, LeistungsnachweisError> {
+    info!(id = %id, "Reopening leistungsnachweis");
+    let change = change_status(&state, &id, StatusAction::Reopen, None, &claims.sub).await?;
+
+    audit
+        .record(&state.db, AuditAction::DocumentReopened, Some(&id), None)
+        .await;
+    Ok(change)
+}
+
+/// GET /leistungsnachweise/{id}/history
+pub async fn get_status_history(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<Vec<StatusTransitionResponse>>, LeistungsnachweisError> {
+    service::status_history(&state.db, &id).await.map(Json)
+}
+
+/// POST /export/batch
+pub async fn export_batch(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    Json(payload): Json<BatchExportRequest>,
+) -> Result<Json<BatchExportResponse>, LeistungsnachweisError> {
+    info!(count = payload.ids.len(), format = ?payload.format, "Exporting leistungsnachweise");
+
+    let scope = policy::scope(&state, &claims).await?;
+    let response = service::export_documents(
+        &state.db,
+        &state.core_client,
+        &state.exports,
+        &scope,
+        payload,
+        &claims.sub,
+    )
+    .await
+    .inspect_err(|e| error!(error = %e, "Export failed"))?;
+
+    info!(files = response.file_count, expires_at = %response.expires_at, "Export stored");
+    Ok(Json(response))
+}
+
+/// GET /export/downloads/{export_id}?expires=...&signature=...
+///
+/// Public route; the signed link is the authorization.
+pub async fn download_export(
+    State(state): State<AppState>,
+    Path(export_id): Path<Uuid>,
+    Query(query): Query<ExportDownloadQuery>,
+) -> Result<Response, StatusCode> {
+    let archive = state
+        .exports
+        .open(export_id, query.expires, &query.signature)
+        .await
+        .map_err(|e| match e {
+            ExportStoreError::InvalidSignature => StatusCode::FORBIDDEN,
+            ExportStoreError::Expired => StatusCode::GONE,
+            ExportStoreError::NotFound => StatusCode::NOT_FOUND,
+            e => {
+                error!(error = %e, export_id = %export_id, "Failed to read export");
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        })?;
+
+    let disposition = format!(
+        "attachment; filename=\"{}\"",
+        service::export_file_name(export_id)
+    );
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/zip".to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        archive,
+    )
+        .into_response())
 }
 
 // ============================================================================
 // Private handler helpers
 // ============================================================================
 
+async fn change_status(
+    state: &AppState,
+    id: &str,
+    action: StatusAction,
+    reason: Option<String>,
+    changed_by: &str,
+) -> Result<Json<StatusChangeResponse>, LeistungsnachweisError> {
+    let change = service::change_status(
+        &state.db,
+        &state.core_client,
+        id,
+        action,
+        reason,
+        changed_by,
+    )
+    .await
+    .inspect_err(|e| error!(error = %e, id = %id, "Status change failed"))?;
+
+    info!(
+        id = %id,
+        from = %change.previous_status,
+        to = %change.new_status,
+        "Status changed"
+    );
+    Ok(Json(change))
+}
+
 async fn forward_to_core(
     state: &AppState,
     id: &str,
+    signed_by: &str,
     payload: &SignLeistungsnachweisRequest,
 ) -> Result<Response, StatusCode> {
     info!(id = %id, "Forwarding to core");
 
-    state
+    let detail = state
+        .core_client
+        .get_leistungsnachweis(id)
+        .await
+        .map_err(|e| {
+            error!(error = %e, "Failed to fetch for signing");
+            StatusCode::from(LeistungsnachweisError::from(e))
+        })?;
+    if let Err(e) = ensure_transition(state, &detail, StatusAction::Sign).await {
+        return status_error(id, e);
+    }
+
+    let signed = state
         .core_client
         .sign_leistungsnachweis(id, payload)
         .await
-        .map(|r| Json(r).into_response())
         .map_err(|e| {
             error!(error = %e, "Core signing failed");
-            LeistungsnachweisError::from(e).into()
-        })
+            StatusCode::from(LeistungsnachweisError::from(e))
+        })?;
+
+    if let Err(e) = service::record_signed(&state.db, &detail, signed_by).await {
+        return status_error(id, e);
+    }
+
+    Ok(Json(signed).into_response())
 }
 
 async fn generate_xml_locally(
     state: &AppState,
     id: &str,
+    signed_by: &str,
     payload: &SignLeistungsnachweisRequest,
 ) -> Result<Response, StatusCode> {
     info!(id = %id, "Generating XML locally");
@@ -118,12 +577,51 @@ async fn generate_xml_locally(
             error!(error = %e, "Failed to fetch for signing");
             StatusCode::from(LeistungsnachweisError::from(e))
         })?;
+    if let Err(e) = ensure_transition(state, &detail, StatusAction::Finalize).await {
+        return status_error(id, e);
+    }
 
-    let response = service::sign_and_generate_xml(&detail, payload).map_err(|e| {
-        error!(error = %e, "XML generation failed");
-        StatusCode::from(e)
-    })?;
+    let response = match service::sign_and_generate_xml(&detail, payload, Utc::now()) {
+        Ok(response) => response,
+        Err(e @ LeistungsnachweisError::SchemaViolation(_)) => {
+            error!(error = %e, id = %id, "Generated XML violates schema");
+            return Ok(e.into_response());
+        }
+        Err(e) => {
+            error!(error = %e, "XML generation failed");
+            return Err(StatusCode::from(e));
+        }
+    };
+
+    if let Err(e) = service::persist_signed(&state.db, &detail, payload, signed_by, &response).await
+    {
+        return status_error(id, e);
+    }
 
-    info!(id = %id, "XML generated successfully");
+    info!(id = %id, "XML generated and stored");
     Ok(Json(response).into_response())
 }
+
+/// Checks that `action` is allowed in the document's current status before
+/// any work is done.
+async fn ensure_transition(
+    state: &AppState,
+    detail: &LeistungsnachweisDetail,
+    action: StatusAction,
+) -> Result<(), LeistungsnachweisError> {
+    service::current_status(&state.db, detail)
+        .await?
+        .apply(action)?;
+    Ok(())
+}
+
+/// Status conflicts carry the from/to states in a JSON body; all other
+/// errors are reported by status code only.
+fn status_error(id: &str, e: LeistungsnachweisError) -> Result<Response, StatusCode> {
+    error!(error = %e, id = %id, "Signing failed");
+    match e {
+        LeistungsnachweisError::InvalidStatusTransition { .. }
+        | LeistungsnachweisError::Conflict(_) => Ok(e.into_response()),
+        e => Err(StatusCode::from(e)),
+    }
+}
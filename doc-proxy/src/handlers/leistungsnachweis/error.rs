@@ -1,9 +1,19 @@
 //! Error types for Leistungsnachweis API.
 
-use axum::http::StatusCode;
+use axum::{
+    Json,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
 use thiserror::Error;
+use tracing::error;
 
-use crate::services::CoreClientError;
+use crate::{
+    models::{document_status::InvalidTransition, signature::SignatureImageError, xsd::Violation},
+    services::CoreClientError,
+};
+
+use super::response::LeistungsnachweisErrorResponse;
 
 /// Domain errors for Leistungsnachweis operations.
 #[derive(Error, Debug)]
@@ -17,12 +27,21 @@ pub enum LeistungsnachweisError {
     #[error("Upstream service error: {0}")]
     Upstream(String),
 
+    #[error("Upstream service unavailable: {0}")]
+    Unavailable(String),
+
     #[error("Internal error: {0}")]
     Internal(String),
 
+    #[error("Database error: {0}")]
+    Database(#[from] sea_orm::DbErr),
+
     #[error("Unauthorized")]
     Unauthorized,
 
+    #[error("Insufficient permissions")]
+    Forbidden,
+
     #[error("Invalid status transition from {from} to {to}")]
     InvalidStatusTransition { from: String, to: String },
 
@@ -34,6 +53,9 @@ pub enum LeistungsnachweisError {
 
     #[error("Conflict: {0}")]
     Conflict(String),
+
+    #[error("Generated document violates the schema ({} violations)", .0.len())]
+    SchemaViolation(Vec<Violation>),
 }
 
 impl From<CoreClientError> for LeistungsnachweisError {
@@ -42,6 +64,10 @@ impl From<CoreClientError> for LeistungsnachweisError {
             CoreClientError::NotFound => Self::NotFound("Resource not found in core".into()),
             CoreClientError::Unauthorized => Self::Unauthorized,
             CoreClientError::Request(e) => Self::Upstream(e.to_string()),
+            CoreClientError::Timeout | CoreClientError::Decode(_) => {
+                Self::Upstream(err.to_string())
+            }
+            CoreClientError::CircuitOpen { .. } => Self::Unavailable(err.to_string()),
             CoreClientError::ServerError { status } => {
                 Self::Upstream(format!("Core server returned {}", status))
             }
@@ -49,21 +75,92 @@ impl From<CoreClientError> for LeistungsnachweisError {
     }
 }
 
+impl From<InvalidTransition> for LeistungsnachweisError {
+    fn from(err: InvalidTransition) -> Self {
+        Self::InvalidStatusTransition {
+            from: err.from.to_string(),
+            to: err.to.to_string(),
+        }
+    }
+}
+
+impl From<SignatureImageError> for LeistungsnachweisError {
+    fn from(err: SignatureImageError) -> Self {
+        Self::BadRequest(err.to_string())
+    }
+}
+
 impl From<LeistungsnachweisError> for StatusCode {
     fn from(err: LeistungsnachweisError) -> Self {
         match err {
             LeistungsnachweisError::NotFound(_) => StatusCode::NOT_FOUND,
             LeistungsnachweisError::BadRequest(_) => StatusCode::BAD_REQUEST,
             LeistungsnachweisError::Upstream(_) => StatusCode::BAD_GATEWAY,
+            LeistungsnachweisError::Unavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
             LeistungsnachweisError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            LeistungsnachweisError::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,
             LeistungsnachweisError::Unauthorized => StatusCode::UNAUTHORIZED,
+            LeistungsnachweisError::Forbidden => StatusCode::FORBIDDEN,
             LeistungsnachweisError::InvalidStatusTransition { .. } => StatusCode::CONFLICT,
             LeistungsnachweisError::ExportFailed(_) => StatusCode::INTERNAL_SERVER_ERROR,
             LeistungsnachweisError::BatchLimitExceeded { .. } => StatusCode::BAD_REQUEST,
             LeistungsnachweisError::Conflict(_) => StatusCode::CONFLICT,
+            LeistungsnachweisError::SchemaViolation(_) => StatusCode::UNPROCESSABLE_ENTITY,
         }
     }
 }
 
+impl IntoResponse for LeistungsnachweisError {
+    fn into_response(self) -> Response {
+        // Database errors can name tables and values; they stay in the log
+        let error = match &self {
+            LeistungsnachweisError::Database(e) => {
+                error!(error = %e, "Database error");
+                "Internal error".to_string()
+            }
+            e => e.to_string(),
+        };
+        let mut body = LeistungsnachweisErrorResponse {
+            error,
+            from: None,
+            to: None,
+            violations: Vec::new(),
+        };
+        let status = match self {
+            LeistungsnachweisError::InvalidStatusTransition { from, to } => {
+                body.from = Some(from);
+                body.to = Some(to);
+                StatusCode::CONFLICT
+            }
+            LeistungsnachweisError::SchemaViolation(violations) => {
+                body.violations = violations;
+                StatusCode::UNPROCESSABLE_ENTITY
+            }
+            e => StatusCode::from(e),
+        };
+        (status, Json(body)).into_response()
+    }
+}
+
 /// Result type alias for Leistungsnachweis operations.
 pub type Result<T> = std::result::Result<T, LeistungsnachweisError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_database_errors_are_not_sent_to_the_client() {
+        let err = LeistungsnachweisError::Database(sea_orm::DbErr::Custom(
+            "relation \"leistungsnachweise\" does not exist".into(),
+        ));
+        let response = err.into_response();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"], "Internal error");
+    }
+}
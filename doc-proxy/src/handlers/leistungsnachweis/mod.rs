@@ -6,11 +6,25 @@
 //! - `request` - Request DTOs
 //! - `response` - Response DTOs
 //! - `error` - Domain error types
+//! - `pdf` - Printable document layout
+//! - `policy` - Client scope checks for the routes
+//! - `sync` - Delta pulls and queued signature uploads for offline tablets
 
 mod api;
 mod error;
+mod pdf;
+mod policy;
 pub mod request;
 pub mod response;
 mod service;
+mod sync;
 
-pub use api::{get_leistungsnachweis, list_leistungsnachweise, sign_leistungsnachweis};
+pub use policy::{authorize_client, authorize_document};
+
+pub use api::{
+    batch_sign_leistungsnachweise, cancel_leistungsnachweis, download_export, export_batch,
+    get_leistungsnachweis, get_leistungsnachweis_pdf, get_leistungsnachweis_xml,
+    get_status_history, list_leistungsnachweise, reject_leistungsnachweis,
+    reopen_leistungsnachweis, sign_leistungsnachweis, submit_leistungsnachweis, sync_pull,
+    sync_upload,
+};
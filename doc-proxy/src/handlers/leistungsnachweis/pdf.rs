@@ -0,0 +1,350 @@
+//! Printable A4 rendering of a Leistungsnachweis.
+//!
+//! Used for the preview, paper copies for clients and relatives, and as
+//! printed proof for audits by the Pflegekasse.
+
+use std::collections::BTreeMap;
+
+use base64::Engine;
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime};
+use rust_decimal::Decimal;
+
+use crate::models::{
+    leistungsnachweis::{ArtDerUnterschrift, UnterschriftVersicherter},
+    pdf::{CONTENT_WIDTH, Column, Font, Image, PdfDocument},
+};
+
+use super::response::{DocumentSchema, DocumentStatus, LeistungsnachweisDetail, ServiceResponse};
+
+const TITLE_SIZE: f32 = 16.0;
+const HEADING_SIZE: f32 = 11.0;
+const TEXT_SIZE: f32 = 9.5;
+const TABLE_SIZE: f32 = 8.5;
+
+const INFO_COLUMNS: [Column; 2] = [Column::left(150.0), Column::left(CONTENT_WIDTH - 150.0)];
+
+const SERVICE_COLUMNS: [Column; 8] = [
+    Column::left(55.0),
+    Column::right(22.0),
+    Column::left(38.0),
+    Column::left(50.0),
+    Column::left(175.0),
+    Column::right(40.0),
+    Column::right(35.0),
+    Column::left(CONTENT_WIDTH - 415.0),
+];
+const SERVICE_HEADER: [&str; 8] = [
+    "Datum",
+    "Nr.",
+    "Beginn",
+    "Ziffer",
+    "Leistung",
+    "Menge",
+    "Min.",
+    "Mitarbeiter",
+];
+
+const TOTAL_COLUMNS: [Column; 5] = [
+    Column::left(55.0),
+    Column::left(CONTENT_WIDTH - 235.0),
+    Column::right(60.0),
+    Column::right(60.0),
+    Column::right(60.0),
+];
+const TOTAL_HEADER: [&str; 5] = ["Ziffer", "Leistung", "Einsätze", "Menge", "Min."];
+
+/// Renders the document as A4 PDF.
+///
+/// `unterschrift` is the captured signature, if any; its image is embedded
+/// when it is a PNG or JPEG.
+pub fn render(
+    detail: &LeistungsnachweisDetail,
+    unterschrift: Option<&UnterschriftVersicherter>,
+) -> Vec<u8> {
+    let monat = format_monat(&detail.billing_month);
+    let mut doc = PdfDocument::new(&format!("Leistungsnachweis {}", monat));
+    doc.set_footer(&format!(
+        "Leistungsnachweis {} · gedruckt am {}",
+        detail.id,
+        Local::now().format("%d.%m.%Y %H:%M")
+    ));
+
+    kopf(&mut doc, detail, &monat);
+    leistungen(&mut doc, detail);
+    summen(&mut doc, detail);
+    unterschrift_block(&mut doc, unterschrift);
+
+    doc.finish()
+}
+
+fn kopf(doc: &mut PdfDocument, detail: &LeistungsnachweisDetail, monat: &str) {
+    doc.paragraph("Leistungsnachweis", Font::Bold, TITLE_SIZE);
+    let rechtsgrundlage = match detail.schema {
+        DocumentSchema::PflLnw => "Pflegeversicherung (SGB XI)",
+        DocumentSchema::HkpLnw => "Häusliche Krankenpflege (SGB V)",
+    };
+    doc.paragraph(
+        &format!("Abrechnungsmonat {} · {}", monat, rechtsgrundlage),
+        Font::Regular,
+        TEXT_SIZE,
+    );
+    doc.space(8.0);
+
+    let versicherte = format!("{}, {}", detail.client.name, detail.client.vorname);
+    for (label, value) in [
+        ("Versicherte/r", versicherte.as_str()),
+        ("Versichertennummer", &detail.client.versichertennummer),
+        ("Pflegedienst (IK)", &detail.provider.ik),
+        (
+            "Verantwortliche Fachkraft",
+            &detail.provider.responsible_staff_id,
+        ),
+        ("Status", status_bezeichnung(detail.status)),
+    ] {
+        doc.row(&INFO_COLUMNS, &[label, value], Font::Regular, TEXT_SIZE);
+    }
+    doc.space(10.0);
+}
+
+fn leistungen(doc: &mut PdfDocument, detail: &LeistungsnachweisDetail) {
+    doc.paragraph("Erbrachte Leistungen", Font::Bold, HEADING_SIZE);
+    if detail.service_days.is_empty() {
+        doc.paragraph("Keine Leistungen erfasst.", Font::Regular, TEXT_SIZE);
+        doc.space(10.0);
+        return;
+    }
+
+    table_header(doc, &SERVICE_COLUMNS, &SERVICE_HEADER);
+    for day in &detail.service_days {
+        let datum = format_datum(&day.date, &day.display_date);
+        for deployment in &day.deployments {
+            let nr = deployment.sequence_number.to_string();
+            for (i, service) in deployment.services.iter().enumerate() {
+                let menge = service.quantity.as_deref().unwrap_or("");
+                let dauer = service
+                    .duration_minutes
+                    .map(|m| m.to_string())
+                    .unwrap_or_default();
+                let mitarbeiter = service.staff_ids.join(", ");
+                let (datum, nr, beginn) = if i == 0 {
+                    (
+                        datum.as_str(),
+                        nr.as_str(),
+                        deployment.display_start_time.as_str(),
+                    )
+                } else {
+                    ("", "", "")
+                };
+                table_row(
+                    doc,
+                    &SERVICE_COLUMNS,
+                    &SERVICE_HEADER,
+                    &[
+                        datum,
+                        nr,
+                        beginn,
+                        &service.code,
+                        &service.description,
+                        menge,
+                        &dauer,
+                        &mitarbeiter,
+                    ],
+                );
+            }
+        }
+    }
+    doc.rule();
+    doc.space(10.0);
+}
+
+/// Totals per Leistungsziffer
+#[derive(Default)]
+struct Summe<'a> {
+    bezeichnung: &'a str,
+    einsaetze: usize,
+    /// `None` once a quantity is missing or not a number
+    menge: Option<Decimal>,
+    minuten: u32,
+}
+
+fn summen(doc: &mut PdfDocument, detail: &LeistungsnachweisDetail) {
+    let mut summen: BTreeMap<&str, Summe> = BTreeMap::new();
+    let services = detail
+        .service_days
+        .iter()
+        .flat_map(|d| &d.deployments)
+        .flat_map(|d| &d.services);
+    for service in services {
+        let summe = summen.entry(&service.code).or_insert_with(|| Summe {
+            bezeichnung: &service.description,
+            menge: Some(Decimal::ZERO),
+            ..Summe::default()
+        });
+        summe.einsaetze += 1;
+        summe.menge = summe.menge.zip(parse_menge(service)).map(|(a, b)| a + b);
+        summe.minuten += u32::from(service.duration_minutes.unwrap_or(0));
+    }
+    if summen.is_empty() {
+        return;
+    }
+
+    if !doc.fits(4.0 * PdfDocument::line_height(TABLE_SIZE)) {
+        doc.new_page();
+    }
+    doc.paragraph("Summen je Leistungsziffer", Font::Bold, HEADING_SIZE);
+    table_header(doc, &TOTAL_COLUMNS, &TOTAL_HEADER);
+    for (code, summe) in &summen {
+        let einsaetze = summe.einsaetze.to_string();
+        let menge = summe.menge.map(format_decimal).unwrap_or_default();
+        let minuten = summe.minuten.to_string();
+        table_row(
+            doc,
+            &TOTAL_COLUMNS,
+            &TOTAL_HEADER,
+            &[code, summe.bezeichnung, &einsaetze, &menge, &minuten],
+        );
+    }
+    doc.rule();
+    doc.space(14.0);
+}
+
+fn unterschrift_block(doc: &mut PdfDocument, unterschrift: Option<&UnterschriftVersicherter>) {
+    // Heading, details and image or signature line stay on one page
+    if !doc.fits(150.0) {
+        doc.new_page();
+    }
+    doc.paragraph("Unterschrift des Versicherten", Font::Bold, HEADING_SIZE);
+
+    let Some(u) = unterschrift else {
+        doc.paragraph("Noch nicht unterschrieben.", Font::Regular, TEXT_SIZE);
+        doc.space(45.0);
+        doc.rule();
+        doc.paragraph(
+            "Datum, Unterschrift Versicherte/r",
+            Font::Regular,
+            TABLE_SIZE,
+        );
+        return;
+    };
+
+    doc.row(
+        &INFO_COLUMNS,
+        &["Art", u.art.bezeichnung()],
+        Font::Regular,
+        TEXT_SIZE,
+    );
+    if let Some(zeitpunkt) = &u.datum_uhrzeit {
+        doc.row(
+            &INFO_COLUMNS,
+            &["Zeitpunkt", &format_zeitpunkt(zeitpunkt)],
+            Font::Regular,
+            TEXT_SIZE,
+        );
+    }
+    if let Some(fehlend) = &u.fehlende_unterschrift {
+        doc.row(
+            &INFO_COLUMNS,
+            &["Grund", fehlend.grund.bezeichnung()],
+            Font::Regular,
+            TEXT_SIZE,
+        );
+        if let Some(erlaeuterung) = &fehlend.erlaeuterung {
+            doc.row(
+                &INFO_COLUMNS,
+                &["Erläuterung", erlaeuterung],
+                Font::Regular,
+                TEXT_SIZE,
+            );
+        }
+    }
+    doc.space(6.0);
+
+    match &u.unterschrift {
+        Some(datei) => {
+            let image = base64::engine::general_purpose::STANDARD
+                .decode(datei.datei.trim())
+                .ok()
+                .and_then(|bytes| Image::from_bytes(&bytes).ok());
+            match image {
+                Some(image) => doc.image(image, 220.0, 90.0),
+                None => doc.paragraph(
+                    &format!(
+                        "Die Unterschrift liegt als {}-Datei vor und kann nicht gedruckt werden.",
+                        datei.dateityp.bezeichnung()
+                    ),
+                    Font::Regular,
+                    TEXT_SIZE,
+                ),
+            }
+        }
+        None if u.art != ArtDerUnterschrift::Fehlend => doc.paragraph(
+            "Die Unterschrift ist im Kernsystem hinterlegt.",
+            Font::Regular,
+            TEXT_SIZE,
+        ),
+        None => {}
+    }
+}
+
+fn table_header(doc: &mut PdfDocument, columns: &[Column], header: &[&str]) {
+    doc.row(columns, header, Font::Bold, TABLE_SIZE);
+    doc.rule();
+}
+
+/// Table row; the header is repeated when the row starts a new page.
+fn table_row(doc: &mut PdfDocument, columns: &[Column], header: &[&str], cells: &[&str]) {
+    if !doc.fits(PdfDocument::line_height(TABLE_SIZE)) {
+        doc.new_page();
+        table_header(doc, columns, header);
+    }
+    doc.row(columns, cells, Font::Regular, TABLE_SIZE);
+}
+
+fn status_bezeichnung(status: DocumentStatus) -> &'static str {
+    match status {
+        DocumentStatus::Draft => "Entwurf",
+        DocumentStatus::PendingSignature => "Wartet auf Unterschrift",
+        DocumentStatus::Signed => "Unterschrieben",
+        DocumentStatus::Finalized => "Abgeschlossen",
+        DocumentStatus::Rejected => "Abgelehnt",
+        DocumentStatus::Cancelled => "Storniert",
+    }
+}
+
+fn parse_menge(service: &ServiceResponse) -> Option<Decimal> {
+    service.quantity.as_deref()?.replace(',', ".").parse().ok()
+}
+
+fn format_decimal(value: Decimal) -> String {
+    value.normalize().to_string().replace('.', ",")
+}
+
+/// JJJJMM as MM/JJJJ
+fn format_monat(monat: &str) -> String {
+    match (monat.get(..4), monat.get(4..6)) {
+        (Some(jahr), Some(monat)) => format!("{}/{}", monat, jahr),
+        _ => monat.to_string(),
+    }
+}
+
+/// JJJJMMTT as TT.MM.JJJJ, falling back to the display date from core
+fn format_datum(datum: &str, anzeige: &str) -> String {
+    NaiveDate::parse_from_str(datum, "%Y%m%d")
+        .map(|d| d.format("%d.%m.%Y").to_string())
+        .unwrap_or_else(|_| anzeige.to_string())
+}
+
+/// Signature time as written to PFL_LNW (JJJJMMTThhmmss), HKP_LNW
+/// (xs:dateTime) or reported by core (RFC 3339)
+fn format_zeitpunkt(zeitpunkt: &str) -> String {
+    ["%Y%m%d%H%M%S", "%Y-%m-%dT%H:%M:%S"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(zeitpunkt, format).ok())
+        .or_else(|| {
+            DateTime::parse_from_rfc3339(zeitpunkt)
+                .ok()
+                .map(|t| t.with_timezone(&Local).naive_local())
+        })
+        .map(|t| t.format("%d.%m.%Y %H:%M").to_string())
+        .unwrap_or_else(|| zeitpunkt.to_string())
+}
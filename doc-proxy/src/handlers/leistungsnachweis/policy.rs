@@ -0,0 +1,88 @@
+//! Client scope checks for Leistungsnachweis routes.
+//!
+//! The route layers run after `auth_middleware` and reject requests for
+//! documents of clients outside the token's scope before the handler runs.
+
+use axum::{
+    body::Body,
+    extract::{Path, Query, Request, State},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use tracing::{error, warn};
+
+use crate::{
+    AppState,
+    config::{
+        auth::{AuthError, Claims},
+        middleware::auth_disabled,
+        policy::ClientScope,
+    },
+};
+
+use super::{
+    error::{LeistungsnachweisError, Result},
+    request::ListLeistungsnachweiseQuery,
+    service,
+};
+
+/// Route layer for `GET /leistungsnachweise?clientId=...`
+pub async fn authorize_client(
+    State(state): State<AppState>,
+    Query(query): Query<ListLeistungsnachweiseQuery>,
+    request: Request<Body>,
+    next: Next,
+) -> Response {
+    let scope = match request_scope(&state, request.extensions().get()).await {
+        Ok(scope) => scope,
+        Err(response) => return response,
+    };
+    if !scope.permits(&query.client_id) {
+        warn!(client_id = %query.client_id, "Client outside of token scope");
+        return LeistungsnachweisError::Forbidden.into_response();
+    }
+    next.run(request).await
+}
+
+/// Route layer for `/leistungsnachweise/{id}/...`
+pub async fn authorize_document(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    request: Request<Body>,
+    next: Next,
+) -> Response {
+    let scope = match request_scope(&state, request.extensions().get()).await {
+        Ok(scope) => scope,
+        Err(response) => return response,
+    };
+    let ids = std::slice::from_ref(&id);
+    if let Err(e) = service::ensure_in_scope(&state.db, &state.core_client, &scope, ids).await {
+        if matches!(e, LeistungsnachweisError::Forbidden) {
+            warn!(id = %id, "Document outside of token scope");
+        }
+        return e.into_response();
+    }
+    next.run(request).await
+}
+
+/// Scope of the token of a request that passed `auth_middleware`.
+async fn request_scope(
+    state: &AppState,
+    claims: Option<&Claims>,
+) -> std::result::Result<ClientScope, Response> {
+    match claims {
+        Some(claims) => scope(state, claims)
+            .await
+            .map_err(IntoResponse::into_response),
+        None if auth_disabled() => Ok(ClientScope::All),
+        None => Err(AuthError::MissingToken.into_response()),
+    }
+}
+
+/// Scope of a token.
+pub async fn scope(state: &AppState, claims: &Claims) -> Result<ClientScope> {
+    ClientScope::resolve(&state.db, claims)
+        .await
+        .inspect_err(|e| error!(error = %e, sub = %claims.sub, "Failed to resolve client scope"))
+        .map_err(LeistungsnachweisError::from)
+}
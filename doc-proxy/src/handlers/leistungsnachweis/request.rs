@@ -1,5 +1,6 @@
 //! Request DTOs for Leistungsnachweis API.
 
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
 /// Query parameters for listing Leistungsnachweise
@@ -80,6 +81,19 @@ pub enum SignatureType {
     Missing,
 }
 
+impl SignatureType {
+    /// Name as serialized in the API and stored in the database
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SignatureType::HandwrittenDigital => "handwritten_digital",
+            SignatureType::HandwrittenPaper => "handwritten_paper",
+            SignatureType::PhotoConfirmation => "photo_confirmation",
+            SignatureType::AlternativeConfirmation => "alternative_confirmation",
+            SignatureType::Missing => "missing",
+        }
+    }
+}
+
 /// Supported image formats for signature
 #[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
@@ -138,6 +152,18 @@ pub enum MissingSignatureReason {
     Other,
 }
 
+impl MissingSignatureReason {
+    /// Name as serialized in the API and stored in the database
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MissingSignatureReason::UnableToSign => "unable_to_sign",
+            MissingSignatureReason::Refused => "refused",
+            MissingSignatureReason::NotPresent => "not_present",
+            MissingSignatureReason::Other => "other",
+        }
+    }
+}
+
 // Legacy type alias for backwards compatibility
 pub type FileType = ImageFormat;
 
@@ -220,10 +246,13 @@ pub struct CancelRequest {
 pub struct BatchSignRequest {
     /// Documents to sign
     pub documents: Vec<BatchSignItem>,
+    /// If true, nothing is stored unless every document can be signed
+    #[serde(default)]
+    pub all_or_nothing: bool,
 }
 
 /// Single item in a batch sign request
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct BatchSignItem {
     /// Document ID
@@ -238,6 +267,69 @@ pub struct BatchSignItem {
     pub missing_explanation: Option<String>,
 }
 
+impl BatchSignItem {
+    /// Splits the item into the document ID and its signature request.
+    pub fn into_parts(self) -> (String, SignLeistungsnachweisRequest) {
+        (
+            self.id,
+            SignLeistungsnachweisRequest {
+                signature_type: self.signature_type,
+                signature: self.signature,
+                missing_reason: self.missing_reason,
+                missing_explanation: self.missing_explanation,
+            },
+        )
+    }
+}
+
+// ============================================================================
+// Offline Sync Request Types
+// ============================================================================
+
+/// Query parameters for pulling changed documents
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncPullQuery {
+    /// Cursor of the previous pull; omit for a full pull
+    pub cursor: Option<String>,
+    /// Comma-separated Versichertennummern; defaults to all clients of the
+    /// token and is required for admins
+    pub clients: Option<String>,
+    /// Maximum number of documents, defaults to 100
+    #[serde(default = "default_sync_limit")]
+    pub limit: usize,
+}
+
+fn default_sync_limit() -> usize {
+    100
+}
+
+/// Request body for uploading signatures captured offline
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncUploadRequest {
+    /// Queued signatures, processed in order
+    pub signatures: Vec<QueuedSignature>,
+}
+
+/// A signature captured on a tablet while offline
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueuedSignature {
+    /// Chosen by the tablet once per captured signature; an upload with a
+    /// known key returns the stored result instead of signing again
+    pub idempotency_key: String,
+    /// Document ID
+    pub document_id: String,
+    /// Fingerprint of the document as pulled and shown when signing
+    pub base_fingerprint: String,
+    /// When the signature was captured on the device
+    pub captured_at: DateTime<Utc>,
+    /// Signature as for a live signing
+    #[serde(flatten)]
+    pub signature: SignLeistungsnachweisRequest,
+}
+
 // ============================================================================
 // Export Request Types
 // ============================================================================
@@ -252,6 +344,15 @@ pub struct BatchExportRequest {
     pub format: ExportFormat,
 }
 
+/// Query parameters of a signed export download link
+#[derive(Debug, Deserialize)]
+pub struct ExportDownloadQuery {
+    /// Expiry as Unix timestamp
+    pub expires: i64,
+    /// HMAC over export ID and expiry, hex encoded
+    pub signature: String,
+}
+
 /// Supported export formats
 #[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
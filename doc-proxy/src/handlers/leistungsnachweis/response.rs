@@ -1,8 +1,15 @@
 //! Response DTOs for Leistungsnachweis API.
 
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
-use super::request::{ImageFormat, MissingSignatureReason, SignatureType};
+pub use crate::models::document_status::DocumentStatus;
+use crate::{
+    models::xsd::Violation,
+    repositories::entity::{status_uebergang, technischer_fehler},
+};
+
+use super::request::{ExportFormat, ImageFormat, MissingSignatureReason, SignatureType};
 
 /// Summary response for list view
 #[derive(Debug, Serialize, Deserialize)]
@@ -18,13 +25,25 @@ pub struct LeistungsnachweisListItem {
     pub status: DocumentStatus,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// XML schema a document is billed under
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
-pub enum DocumentStatus {
-    Draft,
-    PendingSignature,
-    Signed,
-    Finalized,
+pub enum DocumentSchema {
+    /// PFL_LNW_2.1.0 - Pflegeversicherung (SGB XI)
+    #[default]
+    PflLnw,
+    /// HKP_LNW_1.0.0 - häusliche Krankenpflege (SGB V)
+    HkpLnw,
+}
+
+impl DocumentSchema {
+    /// Name as serialized in the API and stored in the database
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DocumentSchema::PflLnw => "pfl_lnw",
+            DocumentSchema::HkpLnw => "hkp_lnw",
+        }
+    }
 }
 
 /// Detailed response for single document view
@@ -38,12 +57,44 @@ pub struct LeistungsnachweisDetail {
     pub provider: ProviderInfo,
     /// Billing month (YYYYMM format)
     pub billing_month: String,
+    /// Schema used for XML generation, defaults to PFL_LNW
+    #[serde(default)]
+    pub schema: DocumentSchema,
     /// All service days with their services
     pub service_days: Vec<ServiceDayResponse>,
     /// Signature information (if present)
     pub signature: Option<SignatureInfo>,
     /// Document status
     pub status: DocumentStatus,
+    /// Open technical errors reported by the recipient of a delivery
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub technical_errors: Vec<TechnicalError>,
+}
+
+/// Technical error reported for a delivered document (Fehlernachricht_technisch)
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TechnicalError {
+    pub code: String,
+    pub text: Option<String>,
+    /// XPath as reported by the recipient
+    pub xpath: Option<String>,
+    /// Offending element below the Abrechnungsfall, e.g.
+    /// `Abrechnungsposition[2]/Menge`; `None` if the whole delivery was refused
+    pub field: Option<String>,
+    pub reported_at: DateTime<Utc>,
+}
+
+impl From<technischer_fehler::Model> for TechnicalError {
+    fn from(f: technischer_fehler::Model) -> Self {
+        Self {
+            code: f.fehlercode,
+            text: f.fehlertext,
+            xpath: f.xpath,
+            field: f.feld,
+            reported_at: f.created_at,
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -111,12 +162,30 @@ pub struct SignatureInfo {
 pub struct SignedLeistungsnachweisResponse {
     pub id: String,
     pub status: DocumentStatus,
+    /// Schema the XML was generated and validated against
+    pub schema: DocumentSchema,
     /// The final XML document as a string
     pub xml_content: String,
     /// Base64-encoded XML for download
     pub xml_base64: String,
 }
 
+/// Error body for Leistungsnachweis operations
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LeistungsnachweisErrorResponse {
+    pub error: String,
+    /// Current status of the document, for rejected status transitions
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
+    /// Requested status, for rejected status transitions
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to: Option<String>,
+    /// One entry per violated schema rule, located by XPath
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub violations: Vec<Violation>,
+}
+
 /// Response from core server after signing
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -163,6 +232,31 @@ pub struct StatusChangeResponse {
     pub changed_at: String,
 }
 
+/// Entry of the status history
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusTransitionResponse {
+    pub from: String,
+    pub to: String,
+    /// Reason given for rejecting or cancelling
+    pub reason: Option<String>,
+    /// User (token subject) or process that changed the status
+    pub changed_by: String,
+    pub changed_at: DateTime<Utc>,
+}
+
+impl From<status_uebergang::Model> for StatusTransitionResponse {
+    fn from(u: status_uebergang::Model) -> Self {
+        Self {
+            from: u.von,
+            to: u.nach,
+            reason: u.grund,
+            changed_by: u.geaendert_von,
+            changed_at: u.created_at,
+        }
+    }
+}
+
 // ============================================================================
 // Batch Operations Response Types
 // ============================================================================
@@ -206,3 +300,149 @@ pub struct BatchExportResponse {
     /// Number of files included in the export
     pub file_count: usize,
 }
+
+/// Contents of `manifest.json` in an export archive
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportManifest {
+    /// Export ID, also part of the download URL
+    pub export_id: String,
+    /// When the archive was created
+    pub created_at: String,
+    /// User who requested the export
+    pub created_by: String,
+    /// Requested export format
+    pub format: ExportFormat,
+    /// Files in the archive
+    pub files: Vec<ExportManifestEntry>,
+    /// Requested documents that could not be exported
+    pub skipped: Vec<SkippedDocument>,
+}
+
+/// A file in an export archive
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportManifestEntry {
+    /// Document ID
+    pub id: String,
+    /// Path of the file in the archive
+    pub file_name: String,
+    /// Billing month (YYYYMM), if the document is known locally
+    pub billing_month: Option<String>,
+    /// Local document status, if known
+    pub status: Option<DocumentStatus>,
+    /// SHA-256 of the file content, hex encoded
+    pub sha256: String,
+    /// File size in bytes
+    pub size: usize,
+}
+
+/// A requested document missing from an export archive
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SkippedDocument {
+    /// Document ID
+    pub id: String,
+    /// Why the document was not exported
+    pub reason: String,
+}
+
+// ============================================================================
+// Offline Sync Response Types
+// ============================================================================
+
+/// Documents changed since the cursor of a sync pull
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncPullResponse {
+    /// Changed documents, oldest change first
+    pub documents: Vec<SyncedDocument>,
+    /// Cursor for the next pull
+    pub cursor: String,
+    /// Whether more changes are waiting; pull again with the new cursor
+    pub has_more: bool,
+}
+
+/// A document as delivered to a tablet
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncedDocument {
+    /// Version of the document's content
+    pub version: i64,
+    /// Fingerprint to send back as `baseFingerprint` with a signature
+    pub fingerprint: String,
+    pub document: LeistungsnachweisDetail,
+}
+
+/// Results of a signature upload, in the order of the request
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncUploadResponse {
+    pub results: Vec<SyncUploadResult>,
+}
+
+/// Result for a single queued signature
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncUploadResult {
+    pub idempotency_key: String,
+    pub document_id: String,
+    pub outcome: SyncOutcome,
+    /// True if the idempotency key was uploaded before; the result is the
+    /// one reported then
+    #[serde(default)]
+    pub replayed: bool,
+    /// Details if the outcome is `conflict`
+    pub conflict: Option<SyncConflict>,
+    /// Error message if the signature was not stored
+    pub error: Option<String>,
+}
+
+/// What happened to a queued signature
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncOutcome {
+    /// Signed and stored
+    Signed,
+    /// The document changed since it was pulled; nothing was stored
+    Conflict,
+    /// The signature is invalid or not allowed; uploading it again with the
+    /// same key gives the same result
+    Rejected,
+    /// Temporary failure, upload again with the same key
+    Retry,
+}
+
+impl SyncOutcome {
+    /// Name as serialized in the API and stored in the database
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SyncOutcome::Signed => "signed",
+            SyncOutcome::Conflict => "conflict",
+            SyncOutcome::Rejected => "rejected",
+            SyncOutcome::Retry => "retry",
+        }
+    }
+}
+
+/// Why a queued signature conflicts with the current document
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictReason {
+    /// The document's content changed since the tablet pulled it
+    DocumentChanged,
+    /// The document can no longer be signed, e.g. it was signed elsewhere
+    InvalidStatus,
+}
+
+/// Current state of a document a queued signature conflicts with
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncConflict {
+    pub reason: ConflictReason,
+    pub current_status: DocumentStatus,
+    /// Fingerprint to sign against after the nurse reviewed the changes
+    pub current_fingerprint: String,
+    /// The document as it is now
+    pub current: LeistungsnachweisDetail,
+}
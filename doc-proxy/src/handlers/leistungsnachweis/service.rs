@@ -1,17 +1,85 @@
 //! Business logic for Leistungsnachweis operations.
 
-use crate::models::leistungsnachweis::types::{
-    ArtDerUnterschrift, Dateityp, Einsatz, Einzelleistung, ErbrachteLeistungen,
-    FehlendeUnterschrift, GrundFehlendeUnterschrift, Leistungen, Leistungsnachweis, Tag,
-    Unterschrift, UnterschriftVersicherter,
+use std::{
+    collections::{HashMap, HashSet},
+    io::{Cursor, Write},
+    sync::{Arc, LazyLock},
+};
+
+use chrono::{DateTime, NaiveDate, NaiveTime, SubsecRound, Utc};
+use sea_orm::{ConnectionTrait, DatabaseConnection, TransactionTrait};
+use sha2::{Digest, Sha256};
+use tokio::sync::Semaphore;
+use uuid::Uuid;
+use zip::{CompressionMethod, ZipWriter, write::SimpleFileOptions};
+
+use crate::{
+    config::policy::ClientScope,
+    models::{
+        document_status::StatusAction,
+        leistungsnachweis::{
+            ParseError, hkp,
+            types::{
+                ArtDerUnterschrift, Dateityp, Einsatz, Einzelleistung, ErbrachteLeistungen,
+                FehlendeUnterschrift, GrundFehlendeUnterschrift, Leistungen, Leistungsnachweis,
+                Tag, Unterschrift, UnterschriftVersicherter,
+            },
+        },
+        signature::{self, FileKind},
+    },
+    repositories::{
+        entity::{leistungsnachweis, status_uebergang, xml_artefakt},
+        fehlernachricht_repository,
+        leistungsnachweis_repository::{
+            self, NewLeistungsnachweis, NewSignaturEreignis, NewXmlArtefakt,
+        },
+    },
+    services::{CoreClient, CoreClientError, ExportStore},
 };
 
 use super::{
     error::{LeistungsnachweisError, Result},
-    request::{ImageFormat, MissingSignatureReason, SignLeistungsnachweisRequest, SignatureType},
-    response::{LeistungsnachweisDetail, SignedLeistungsnachweisResponse, DocumentStatus},
+    pdf,
+    request::{
+        BatchExportRequest, BatchSignRequest, ExportFormat, ImageFormat, MissingSignatureReason,
+        SignLeistungsnachweisRequest, SignatureType,
+    },
+    response::{
+        BatchExportResponse, BatchSignResponse, BatchSignResult, DocumentSchema, DocumentStatus,
+        ExportManifest, ExportManifestEntry, LeistungsnachweisDetail, LeistungsnachweisListItem,
+        SignatureInfo, SignedLeistungsnachweisResponse, SkippedDocument, StatusChangeResponse,
+        StatusTransitionResponse, TechnicalError,
+    },
 };
 
+/// Maximum number of documents in one batch sign request
+pub const MAX_BATCH_SIZE: usize = 50;
+
+/// Maximum number of documents in one export
+pub const MAX_EXPORT_SIZE: usize = 200;
+
+/// Documents signed in parallel per batch request
+static BATCH_SIGN_CONCURRENCY: LazyLock<usize> = LazyLock::new(|| {
+    std::env::var("BATCH_SIGN_CONCURRENCY")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|&n| n > 0)
+        .unwrap_or(4)
+});
+
+/// Largest signature image embedded in the XML, in bytes after decoding
+static SIGNATURE_MAX_BYTES: LazyLock<usize> = LazyLock::new(|| {
+    std::env::var("SIGNATURE_MAX_BYTES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|&n| n > 0)
+        .unwrap_or(256 * 1024)
+});
+
+/// Signature images decoded at the same time, one per CPU
+static SIGNATURE_SLOTS: LazyLock<Semaphore> =
+    LazyLock::new(|| Semaphore::new(std::thread::available_parallelism().map_or(2, |n| n.get())));
+
 /// Validates a signature request.
 pub fn validate_signature_request(req: &SignLeistungsnachweisRequest) -> Result<()> {
     match req.signature_type {
@@ -43,17 +111,312 @@ pub fn validate_signature_request(req: &SignLeistungsnachweisRequest) -> Result<
     Ok(())
 }
 
+/// Validates a signature request and normalizes its image for embedding.
+///
+/// The image must match its declared format and must not be blank; SVG is
+/// rasterized to PNG and oversized images are shrunk to `SIGNATURE_MAX_BYTES`.
+/// Decoding runs on a blocking thread, a few images at a time.
+pub async fn prepare_signature(req: &mut SignLeistungsnachweisRequest) -> Result<()> {
+    validate_signature_request(req)?;
+    if req.signature_type == SignatureType::Missing {
+        return Ok(());
+    }
+    let Some(sig_data) = req.signature.as_mut() else {
+        return Ok(());
+    };
+
+    let _slot = SIGNATURE_SLOTS
+        .acquire()
+        .await
+        .map_err(|e| LeistungsnachweisError::Internal(e.to_string()))?;
+    let encoded = std::mem::take(&mut sig_data.data);
+    let declared = file_kind(sig_data.format);
+    let normalized = tokio::task::spawn_blocking(move || {
+        let compact: String = encoded.split_ascii_whitespace().collect();
+        let data = base64::Engine::decode(&base64::engine::general_purpose::STANDARD, compact)
+            .map_err(|_| signature::SignatureImageError::InvalidBase64)?;
+        let image = signature::normalize(&data, declared, *SIGNATURE_MAX_BYTES)?;
+        let changed = image.data != data;
+        Ok::<_, signature::SignatureImageError>((encoded, image, changed))
+    })
+    .await
+    .map_err(|e| LeistungsnachweisError::Internal(e.to_string()))?;
+    let (encoded, image, changed) = normalized?;
+
+    sig_data.data = if changed {
+        base64::Engine::encode(&base64::engine::general_purpose::STANDARD, &image.data)
+    } else {
+        encoded
+    };
+    sig_data.format = image_format(image.kind);
+    Ok(())
+}
+
+fn file_kind(format: ImageFormat) -> FileKind {
+    match format {
+        ImageFormat::Png => FileKind::Png,
+        ImageFormat::Jpeg => FileKind::Jpeg,
+        ImageFormat::Svg => FileKind::Svg,
+        ImageFormat::Gif => FileKind::Gif,
+        ImageFormat::Tiff => FileKind::Tiff,
+        ImageFormat::Pdf => FileKind::Pdf,
+    }
+}
+
+fn image_format(kind: FileKind) -> ImageFormat {
+    match kind {
+        FileKind::Png => ImageFormat::Png,
+        FileKind::Jpeg => ImageFormat::Jpeg,
+        FileKind::Svg => ImageFormat::Svg,
+        FileKind::Gif => ImageFormat::Gif,
+        FileKind::Tiff => ImageFormat::Tiff,
+        FileKind::Pdf => ImageFormat::Pdf,
+    }
+}
+
+/// Applies the local status and open technical errors to a document from core.
+///
+/// Once a document is known locally, its status changes only through this
+/// proxy, so the local status wins. Open technical errors mark it as rejected
+/// and are attached so they can be shown next to the offending fields.
+pub async fn apply_local_state(
+    db: &DatabaseConnection,
+    detail: &mut LeistungsnachweisDetail,
+) -> Result<()> {
+    if let Some(document) = leistungsnachweis_repository::find_by_id(db, &detail.id).await? {
+        detail.status = parse_status(&document)?;
+    }
+
+    let fehler = fehlernachricht_repository::find_open_by_leistungsnachweise(
+        db,
+        std::slice::from_ref(&detail.id),
+    )
+    .await?;
+    if !fehler.is_empty() {
+        detail.status = DocumentStatus::Rejected;
+        detail.technical_errors = fehler.into_iter().map(TechnicalError::from).collect();
+    }
+    Ok(())
+}
+
+/// Applies the local status and open technical errors to listed documents.
+pub async fn apply_local_states(
+    db: &DatabaseConnection,
+    items: &mut [LeistungsnachweisListItem],
+) -> Result<()> {
+    let ids: Vec<String> = items.iter().map(|i| i.id.clone()).collect();
+    let local: HashMap<String, DocumentStatus> =
+        leistungsnachweis_repository::find_by_ids(db, &ids)
+            .await?
+            .into_iter()
+            .map(|d| parse_status(&d).map(|status| (d.id, status)))
+            .collect::<Result<_>>()?;
+    let rejected: HashSet<String> =
+        fehlernachricht_repository::find_open_by_leistungsnachweise(db, &ids)
+            .await?
+            .into_iter()
+            .filter_map(|f| f.leistungsnachweis_id)
+            .collect();
+
+    for item in items.iter_mut() {
+        if let Some(status) = local.get(&item.id) {
+            item.status = *status;
+        }
+        if rejected.contains(&item.id) {
+            item.status = DocumentStatus::Rejected;
+        }
+    }
+    Ok(())
+}
+
+/// Current status of a document: local if known, otherwise as reported by core.
+pub async fn current_status(
+    db: &DatabaseConnection,
+    detail: &LeistungsnachweisDetail,
+) -> Result<DocumentStatus> {
+    match leistungsnachweis_repository::find_by_id(db, &detail.id).await? {
+        Some(document) => parse_status(&document),
+        None => Ok(detail.status),
+    }
+}
+
+/// Fails with `Forbidden` unless every document belongs to a client in
+/// `scope`. Clients are taken from local documents if known, otherwise as
+/// reported by core; documents core does not know are left to the caller.
+pub async fn ensure_in_scope(
+    db: &DatabaseConnection,
+    core_client: &CoreClient,
+    scope: &ClientScope,
+    ids: &[String],
+) -> Result<()> {
+    if *scope == ClientScope::All {
+        return Ok(());
+    }
+
+    let mut clients: HashMap<String, String> = leistungsnachweis_repository::find_by_ids(db, ids)
+        .await?
+        .into_iter()
+        .map(|d| (d.id, d.versichertennummer))
+        .collect();
+    for id in ids {
+        if !clients.contains_key(id) {
+            match core_client.get_leistungsnachweis(id).await {
+                Ok(detail) => {
+                    clients.insert(id.clone(), detail.client.versichertennummer);
+                }
+                Err(CoreClientError::NotFound) => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    if clients.values().any(|client| !scope.permits(client)) {
+        return Err(LeistungsnachweisError::Forbidden);
+    }
+    Ok(())
+}
+
+/// Applies a lifecycle action to a document and records it in the history.
+///
+/// Documents not yet known locally are taken over from core with their
+/// current status first. Reopening resolves open technical errors, since the
+/// document is corrected and delivered again from draft.
+pub async fn change_status(
+    db: &DatabaseConnection,
+    core_client: &CoreClient,
+    id: &str,
+    action: StatusAction,
+    grund: Option<String>,
+    geaendert_von: &str,
+) -> Result<StatusChangeResponse> {
+    let document = match leistungsnachweis_repository::find_by_id(db, id).await? {
+        Some(document) => document,
+        None => {
+            let detail = core_client.get_leistungsnachweis(id).await?;
+            leistungsnachweis_repository::upsert(db, snapshot(&detail, detail.status)).await?
+        }
+    };
+    let from = parse_status(&document)?;
+    let to = from.apply(action)?;
+
+    let txn = db.begin().await?;
+    let uebergang = record_transition(&txn, id, from, to, grund, geaendert_von).await?;
+    if action == StatusAction::Reopen {
+        fehlernachricht_repository::resolve_for_leistungsnachweise(&txn, &[id.to_string()]).await?;
+    }
+    txn.commit().await?;
+    core_client.invalidate(id);
+
+    Ok(StatusChangeResponse {
+        id: id.to_string(),
+        previous_status: from,
+        new_status: to,
+        changed_at: uebergang.created_at.to_rfc3339(),
+    })
+}
+
+/// Status history of a document, oldest first.
+pub async fn status_history(
+    db: &DatabaseConnection,
+    id: &str,
+) -> Result<Vec<StatusTransitionResponse>> {
+    Ok(leistungsnachweis_repository::find_uebergaenge(db, id)
+        .await?
+        .into_iter()
+        .map(StatusTransitionResponse::from)
+        .collect())
+}
+
+/// Records a document signed through core as signed.
+pub async fn record_signed(
+    db: &DatabaseConnection,
+    detail: &LeistungsnachweisDetail,
+    signiert_von: &str,
+) -> Result<()> {
+    let txn = db.begin().await?;
+    let document =
+        leistungsnachweis_repository::upsert(&txn, snapshot(detail, detail.status)).await?;
+    let from = parse_status(&document)?;
+    let to = from.apply(StatusAction::Sign)?;
+    record_transition(&txn, &detail.id, from, to, None, signiert_von).await?;
+    txn.commit().await?;
+    Ok(())
+}
+
+fn snapshot(detail: &LeistungsnachweisDetail, status: DocumentStatus) -> NewLeistungsnachweis {
+    NewLeistungsnachweis {
+        id: detail.id.clone(),
+        versichertennummer: detail.client.versichertennummer.clone(),
+        name: detail.client.name.clone(),
+        vorname: detail.client.vorname.clone(),
+        ik_pflegedienst: detail.provider.ik.clone(),
+        abrechnungsmonat: detail.billing_month.clone(),
+        schema: detail.schema.as_str().to_string(),
+        status: status.as_str().to_string(),
+    }
+}
+
+fn parse_status(document: &leistungsnachweis::Model) -> Result<DocumentStatus> {
+    document
+        .status
+        .parse()
+        .map_err(|e| LeistungsnachweisError::Internal(format!("{} for {}", e, document.id)))
+}
+
+async fn record_transition<C: ConnectionTrait>(
+    db: &C,
+    id: &str,
+    from: DocumentStatus,
+    to: DocumentStatus,
+    grund: Option<String>,
+    geaendert_von: &str,
+) -> Result<status_uebergang::Model> {
+    leistungsnachweis_repository::transition(
+        db,
+        id,
+        from.as_str(),
+        to.as_str(),
+        grund,
+        geaendert_von,
+    )
+    .await?
+    .ok_or_else(|| {
+        LeistungsnachweisError::Conflict(format!(
+            "Status of {} was changed concurrently, expected {}",
+            id, from
+        ))
+    })
+}
+
 /// Signs a Leistungsnachweis and generates XSD-compliant XML.
+///
+/// The schema is picked per document (PFL_LNW for SGB XI, HKP_LNW for SGB V).
+/// The document is validated before serialization; schema violations are
+/// returned as [`LeistungsnachweisError::SchemaViolation`] with their XPaths.
+/// `signed_at` is the time of the signature, which for signatures captured
+/// offline lies before the upload.
 pub fn sign_and_generate_xml(
     detail: &LeistungsnachweisDetail,
     request: &SignLeistungsnachweisRequest,
+    signed_at: DateTime<Utc>,
 ) -> Result<SignedLeistungsnachweisResponse> {
-    let mut leistungsnachweis = convert_to_xml_model(detail);
-    leistungsnachweis.unterschrift_versicherter = build_unterschrift(request)?;
-
-    let xml_content = leistungsnachweis
-        .to_xml()
-        .map_err(|e| LeistungsnachweisError::Internal(format!("XML generation failed: {}", e)))?;
+    let xml_content = match detail.schema {
+        DocumentSchema::PflLnw => {
+            let mut leistungsnachweis = convert_to_xml_model(detail);
+            leistungsnachweis.unterschrift_versicherter = build_unterschrift(request, signed_at)?;
+            leistungsnachweis.to_xml()
+        }
+        DocumentSchema::HkpLnw => {
+            let mut leistungsnachweis = convert_to_hkp_model(detail);
+            leistungsnachweis.unterschrift_versicherter =
+                build_hkp_unterschrift(request, signed_at)?;
+            leistungsnachweis.to_xml()
+        }
+    }
+    .map_err(|e| match e {
+        ParseError::Validation(violations) => LeistungsnachweisError::SchemaViolation(violations),
+        e => LeistungsnachweisError::Internal(format!("XML generation failed: {}", e)),
+    })?;
 
     let xml_base64 = base64::Engine::encode(
         &base64::engine::general_purpose::STANDARD,
@@ -63,11 +426,470 @@ pub fn sign_and_generate_xml(
     Ok(SignedLeistungsnachweisResponse {
         id: detail.id.clone(),
         status: DocumentStatus::Finalized,
+        schema: detail.schema,
         xml_content,
         xml_base64,
     })
 }
 
+/// Finalizes the document and stores its signature and the generated XML in
+/// one transaction, so the signed result survives a tablet losing its
+/// connection and can be fetched again.
+///
+/// The status is re-checked inside the transaction, so a concurrent signing
+/// of the same document fails with a conflict instead of finalizing twice.
+pub async fn persist_signed(
+    db: &DatabaseConnection,
+    detail: &LeistungsnachweisDetail,
+    request: &SignLeistungsnachweisRequest,
+    signiert_von: &str,
+    signed: &SignedLeistungsnachweisResponse,
+) -> Result<()> {
+    let txn = db.begin().await?;
+    store_signed(&txn, detail, request, signiert_von, signed).await?;
+    txn.commit().await?;
+    Ok(())
+}
+
+/// Signs several documents, `BATCH_SIGN_CONCURRENCY` at a time.
+///
+/// Each document is signed as with `generateXml=true` and reported on its
+/// own. With `all_or_nothing`, all documents are prepared first and stored in
+/// a single transaction only if none of them failed.
+pub async fn batch_sign(
+    db: &DatabaseConnection,
+    core_client: &CoreClient,
+    scope: &ClientScope,
+    req: BatchSignRequest,
+    signiert_von: &str,
+) -> Result<BatchSignResponse> {
+    let requested = req.documents.len();
+    if requested == 0 {
+        return Err(LeistungsnachweisError::BadRequest(
+            "No documents to sign".into(),
+        ));
+    }
+    if requested > MAX_BATCH_SIZE {
+        return Err(LeistungsnachweisError::BatchLimitExceeded {
+            max: MAX_BATCH_SIZE,
+            requested,
+        });
+    }
+    let mut seen = HashSet::new();
+    if let Some(item) = req.documents.iter().find(|i| !seen.insert(i.id.as_str())) {
+        return Err(LeistungsnachweisError::BadRequest(format!(
+            "Document {} is listed more than once",
+            item.id
+        )));
+    }
+    let ids: Vec<String> = req.documents.iter().map(|i| i.id.clone()).collect();
+    ensure_in_scope(db, core_client, scope, &ids).await?;
+
+    let all_or_nothing = req.all_or_nothing;
+    let semaphore = Arc::new(Semaphore::new(*BATCH_SIGN_CONCURRENCY));
+    let tasks: Vec<_> = req
+        .documents
+        .into_iter()
+        .map(|item| {
+            let (db, core_client) = (db.clone(), core_client.clone());
+            let semaphore = Arc::clone(&semaphore);
+            let signiert_von = signiert_von.to_string();
+            let (id, request) = item.into_parts();
+            tokio::spawn(async move {
+                let _permit = semaphore.acquire().await;
+                let signed = prepare_signed(&db, &core_client, &id, request).await;
+                let signed = match signed {
+                    Ok(item) if !all_or_nothing => persist_signed(
+                        &db,
+                        &item.detail,
+                        &item.request,
+                        &signiert_von,
+                        &item.signed,
+                    )
+                    .await
+                    .map(|()| item),
+                    other => other,
+                };
+                (id, signed)
+            })
+        })
+        .collect();
+
+    let mut outcomes = Vec::with_capacity(tasks.len());
+    for task in tasks {
+        outcomes.push(task.await.map_err(|e| {
+            LeistungsnachweisError::Internal(format!("Batch signing task failed: {}", e))
+        })?);
+    }
+
+    let results = if all_or_nothing {
+        commit_all(db, outcomes, signiert_von).await?
+    } else {
+        outcomes
+            .into_iter()
+            .map(|(id, signed)| batch_result(id, signed.map(|_| ())))
+            .collect()
+    };
+
+    let success_count = results.iter().filter(|r| r.success).count();
+    Ok(BatchSignResponse {
+        failure_count: results.len() - success_count,
+        success_count,
+        results,
+    })
+}
+
+/// A document that has been signed but not stored yet
+struct SignedItem {
+    detail: LeistungsnachweisDetail,
+    request: SignLeistungsnachweisRequest,
+    signed: SignedLeistungsnachweisResponse,
+}
+
+async fn prepare_signed(
+    db: &DatabaseConnection,
+    core_client: &CoreClient,
+    id: &str,
+    mut request: SignLeistungsnachweisRequest,
+) -> Result<SignedItem> {
+    prepare_signature(&mut request).await?;
+    let detail = core_client.get_leistungsnachweis(id).await?;
+    current_status(db, &detail)
+        .await?
+        .apply(StatusAction::Finalize)?;
+    let signed = sign_and_generate_xml(&detail, &request, Utc::now())?;
+    Ok(SignedItem {
+        detail,
+        request,
+        signed,
+    })
+}
+
+/// Stores all prepared documents in one transaction. If any document failed,
+/// before or while storing, nothing is committed and the others are reported
+/// as not signed.
+async fn commit_all(
+    db: &DatabaseConnection,
+    mut outcomes: Vec<(String, Result<SignedItem>)>,
+    signiert_von: &str,
+) -> Result<Vec<BatchSignResult>> {
+    if outcomes.iter().all(|(_, signed)| signed.is_ok()) {
+        let txn = db.begin().await?;
+        let mut failed = false;
+        for (_, outcome) in outcomes.iter_mut() {
+            let Ok(item) = outcome else { continue };
+            if let Err(e) = store_signed(
+                &txn,
+                &item.detail,
+                &item.request,
+                signiert_von,
+                &item.signed,
+            )
+            .await
+            {
+                *outcome = Err(e);
+                failed = true;
+                break;
+            }
+        }
+        if !failed {
+            txn.commit().await?;
+            return Ok(outcomes
+                .into_iter()
+                .map(|(id, _)| batch_result(id, Ok(())))
+                .collect());
+        }
+    }
+
+    Ok(outcomes
+        .into_iter()
+        .map(|(id, signed)| match signed {
+            Ok(_) => BatchSignResult {
+                id,
+                success: false,
+                error: Some("Not signed because another document in the batch failed".into()),
+            },
+            Err(e) => batch_result(id, Err(e)),
+        })
+        .collect())
+}
+
+fn batch_result(id: String, outcome: Result<()>) -> BatchSignResult {
+    match outcome {
+        Ok(()) => BatchSignResult {
+            id,
+            success: true,
+            error: None,
+        },
+        Err(e) => BatchSignResult {
+            id,
+            success: false,
+            error: Some(e.to_string()),
+        },
+    }
+}
+
+async fn store_signed<C: ConnectionTrait>(
+    db: &C,
+    detail: &LeistungsnachweisDetail,
+    request: &SignLeistungsnachweisRequest,
+    signiert_von: &str,
+    signed: &SignedLeistungsnachweisResponse,
+) -> Result<()> {
+    let document =
+        leistungsnachweis_repository::upsert(db, snapshot(detail, detail.status)).await?;
+    let from = parse_status(&document)?;
+    let to = from.apply(StatusAction::Finalize)?;
+    record_transition(db, &detail.id, from, to, None, signiert_von).await?;
+
+    let ereignis = leistungsnachweis_repository::create_signatur_ereignis(
+        db,
+        NewSignaturEreignis {
+            leistungsnachweis_id: detail.id.clone(),
+            art: request.signature_type.as_str().to_string(),
+            bildformat: request
+                .signature
+                .as_ref()
+                .map(|s| s.format.mime_type().to_string()),
+            fehlgrund: request.missing_reason.map(|r| r.as_str().to_string()),
+            erlaeuterung: request.missing_explanation.clone(),
+            signiert_von: signiert_von.to_string(),
+        },
+    )
+    .await?;
+
+    leistungsnachweis_repository::create_xml_artefakt(
+        db,
+        NewXmlArtefakt {
+            leistungsnachweis_id: detail.id.clone(),
+            signatur_ereignis_id: ereignis.id,
+            schema: signed.schema.as_str().to_string(),
+            xml: signed.xml_content.clone(),
+        },
+    )
+    .await?;
+    Ok(())
+}
+
+/// Packs the requested documents into a ZIP archive with a `manifest.json`
+/// and stores it behind a signed, expiring download link.
+///
+/// `xml` exports the most recently generated XML of each document, `pdf` the
+/// rendered PDF and `zip` both. Documents without signed XML or unknown to
+/// core are listed as skipped in the manifest.
+pub async fn export_documents(
+    db: &DatabaseConnection,
+    core_client: &CoreClient,
+    exports: &ExportStore,
+    scope: &ClientScope,
+    req: BatchExportRequest,
+    exportiert_von: &str,
+) -> Result<BatchExportResponse> {
+    let mut seen = HashSet::new();
+    let ids: Vec<String> = req
+        .ids
+        .into_iter()
+        .filter(|id| seen.insert(id.clone()))
+        .collect();
+    if ids.is_empty() {
+        return Err(LeistungsnachweisError::BadRequest(
+            "No documents to export".into(),
+        ));
+    }
+    if ids.len() > MAX_EXPORT_SIZE {
+        return Err(LeistungsnachweisError::BatchLimitExceeded {
+            max: MAX_EXPORT_SIZE,
+            requested: ids.len(),
+        });
+    }
+    ensure_in_scope(db, core_client, scope, &ids).await?;
+    let documents: HashMap<String, leistungsnachweis::Model> =
+        leistungsnachweis_repository::find_by_ids(db, &ids)
+            .await?
+            .into_iter()
+            .map(|d| (d.id.clone(), d))
+            .collect();
+
+    let mut files = Vec::new();
+    let mut skipped = Vec::new();
+    for id in ids {
+        if req.format != ExportFormat::Pdf {
+            match leistungsnachweis_repository::find_latest_xml(db, &id).await? {
+                Some(artefakt) => files.push(ExportFile {
+                    id: id.clone(),
+                    file_name: xml_file_name(&id),
+                    content: artefakt.xml.into_bytes(),
+                }),
+                None => skipped.push(SkippedDocument {
+                    id: id.clone(),
+                    reason: "No signed XML stored".into(),
+                }),
+            }
+        }
+        if req.format != ExportFormat::Xml {
+            match export_pdf(db, core_client, &id).await {
+                Ok(content) => files.push(ExportFile {
+                    file_name: pdf_file_name(&id),
+                    content,
+                    id,
+                }),
+                Err(LeistungsnachweisError::NotFound(_)) => skipped.push(SkippedDocument {
+                    id,
+                    reason: "Not found in core".into(),
+                }),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+    if files.is_empty() {
+        return Err(LeistungsnachweisError::NotFound(
+            "None of the requested documents can be exported".into(),
+        ));
+    }
+
+    let export_id = Uuid::new_v4();
+    let manifest = ExportManifest {
+        export_id: export_id.to_string(),
+        created_at: Utc::now().trunc_subsecs(0).to_rfc3339(),
+        created_by: exportiert_von.to_string(),
+        format: req.format,
+        files: files
+            .iter()
+            .map(|f| {
+                let document = documents.get(&f.id);
+                ExportManifestEntry {
+                    id: f.id.clone(),
+                    file_name: f.file_name.clone(),
+                    billing_month: document.map(|d| d.abrechnungsmonat.clone()),
+                    status: document.and_then(|d| d.status.parse().ok()),
+                    sha256: hex::encode(Sha256::digest(&f.content)),
+                    size: f.content.len(),
+                }
+            })
+            .collect(),
+        skipped,
+    };
+    let archive = build_archive(&manifest, &files)
+        .map_err(|e| LeistungsnachweisError::ExportFailed(e.to_string()))?;
+
+    let stored = exports
+        .store(export_id, archive)
+        .await
+        .map_err(|e| LeistungsnachweisError::ExportFailed(e.to_string()))?;
+
+    Ok(BatchExportResponse {
+        download_url: stored.download_url,
+        expires_at: stored.expires_at.to_rfc3339(),
+        file_count: files.len(),
+    })
+}
+
+async fn export_pdf(
+    db: &DatabaseConnection,
+    core_client: &CoreClient,
+    id: &str,
+) -> Result<Vec<u8>> {
+    let mut detail = core_client.get_leistungsnachweis(id).await?;
+    apply_local_state(db, &mut detail).await?;
+    render_pdf(db, &detail).await
+}
+
+/// Suggested file name for an export archive download.
+pub fn export_file_name(export_id: Uuid) -> String {
+    format!("Export_{}.zip", export_id)
+}
+
+/// A file to be placed in an export archive
+struct ExportFile {
+    id: String,
+    file_name: String,
+    content: Vec<u8>,
+}
+
+fn build_archive(
+    manifest: &ExportManifest,
+    files: &[ExportFile],
+) -> std::result::Result<Vec<u8>, zip::result::ZipError> {
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+
+    for file in files {
+        zip.start_file(file.file_name.as_str(), options)?;
+        zip.write_all(&file.content)?;
+    }
+    zip.start_file("manifest.json", options)?;
+    serde_json::to_writer_pretty(&mut zip, manifest).map_err(std::io::Error::from)?;
+
+    Ok(zip.finish()?.into_inner())
+}
+
+/// Suggested file name for the XML download.
+pub fn xml_file_name(id: &str) -> String {
+    format!("Leistungsnachweis_{}.xml", id)
+}
+
+/// Suggested file name for the PDF download.
+pub fn pdf_file_name(id: &str) -> String {
+    format!("Leistungsnachweis_{}.pdf", id)
+}
+
+/// Renders a document as printable PDF.
+///
+/// The signature, including its image, is taken from the most recently
+/// generated XML. Documents signed elsewhere only show what core reports.
+pub async fn render_pdf(
+    db: &DatabaseConnection,
+    detail: &LeistungsnachweisDetail,
+) -> Result<Vec<u8>> {
+    let unterschrift = match leistungsnachweis_repository::find_latest_xml(db, &detail.id).await? {
+        Some(artefakt) => Some(stored_unterschrift(&artefakt)?),
+        None => detail.signature.as_ref().map(unterschrift_from_info),
+    };
+    Ok(pdf::render(detail, unterschrift.as_ref()))
+}
+
+/// Signature from stored XML, with the HKP variant mapped to the PFL types.
+fn stored_unterschrift(artefakt: &xml_artefakt::Model) -> Result<UnterschriftVersicherter> {
+    let unreadable = |e: ParseError| {
+        LeistungsnachweisError::Internal(format!(
+            "Stored XML of {} is unreadable: {}",
+            artefakt.leistungsnachweis_id, e
+        ))
+    };
+
+    if artefakt.schema == DocumentSchema::HkpLnw.as_str() {
+        let u = hkp::Leistungsnachweis::from_xml(&artefakt.xml)
+            .map_err(unreadable)?
+            .unterschrift_versicherter;
+        Ok(UnterschriftVersicherter {
+            art: u.art,
+            datum_uhrzeit: u.datum_uhrzeit,
+            unterschrift: u.unterschrift,
+            fehlende_unterschrift: u.fehlende_unterschrift.map(|f| FehlendeUnterschrift {
+                grund: f.grund,
+                erlaeuterung: f.sonstiges,
+            }),
+        })
+    } else {
+        Ok(Leistungsnachweis::from_xml(&artefakt.xml)
+            .map_err(unreadable)?
+            .unterschrift_versicherter)
+    }
+}
+
+/// Signature as reported by core, without image data.
+fn unterschrift_from_info(info: &SignatureInfo) -> UnterschriftVersicherter {
+    UnterschriftVersicherter {
+        art: map_signature_type(info.signature_type),
+        datum_uhrzeit: info.timestamp.clone(),
+        unterschrift: None,
+        fehlende_unterschrift: info.missing_reason.map(|grund| FehlendeUnterschrift {
+            grund: map_missing_reason(grund),
+            erlaeuterung: info.missing_explanation.clone(),
+        }),
+    }
+}
+
 /// Converts API response to XML model structure.
 fn convert_to_xml_model(detail: &LeistungsnachweisDetail) -> Leistungsnachweis {
     Leistungsnachweis {
@@ -88,9 +910,7 @@ fn convert_to_xml_model(detail: &LeistungsnachweisDetail) -> Leistungsnachweis {
     }
 }
 
-fn convert_leistungen(
-    service_days: &[super::response::ServiceDayResponse],
-) -> Leistungen {
+fn convert_leistungen(service_days: &[super::response::ServiceDayResponse]) -> Leistungen {
     Leistungen {
         tage: service_days
             .iter()
@@ -132,10 +952,72 @@ fn placeholder_unterschrift() -> UnterschriftVersicherter {
     }
 }
 
+/// Converts API response to the HKP_LNW model.
+///
+/// Each deployment becomes an Einsatz; a service without quantity yields an
+/// empty Menge, which is reported by schema validation.
+fn convert_to_hkp_model(detail: &LeistungsnachweisDetail) -> hkp::Leistungsnachweis {
+    hkp::Leistungsnachweis {
+        leistungserbringer_ik: detail.provider.ik.clone(),
+        krankenversichertennummer: detail.client.versichertennummer.clone(),
+        verantwortliche_fachkraft: detail.provider.responsible_staff_id.clone(),
+        einsaetze: detail
+            .service_days
+            .iter()
+            .flat_map(|day| {
+                day.deployments.iter().map(|d| hkp::Einsatz {
+                    beginn: to_xs_date_time(&day.date, &d.start_time),
+                    ende: None,
+                    positionen: d
+                        .services
+                        .iter()
+                        .map(|s| hkp::Position {
+                            positions_nr: s.code.clone(),
+                            menge: s.quantity.clone().unwrap_or_default(),
+                            beschaeftigtennummern: s.staff_ids.clone(),
+                        })
+                        .collect(),
+                })
+            })
+            .collect(),
+        unterschrift_versicherter: hkp::UnterschriftVersicherter {
+            art: ArtDerUnterschrift::Fehlend,
+            datum_uhrzeit: None,
+            unterschrift: None,
+            fehlende_unterschrift: Some(hkp::FehlendeUnterschrift {
+                grund: GrundFehlendeUnterschrift::NichtAnwesend,
+                sonstiges: None,
+            }),
+        },
+        id: detail.id.clone(),
+    }
+}
+
+/// Combines a JJJJMMTT date and hhmm time into an xs:dateTime.
+///
+/// Unparseable input is passed through so schema validation can locate it.
+fn to_xs_date_time(date: &str, time: &str) -> String {
+    match (
+        NaiveDate::parse_from_str(date, "%Y%m%d"),
+        NaiveTime::parse_from_str(time, "%H%M"),
+    ) {
+        (Ok(d), Ok(t)) => d.and_time(t).format("%Y-%m-%dT%H:%M:%S").to_string(),
+        _ => format!("{}T{}", date, time),
+    }
+}
+
 /// Builds UnterschriftVersicherter from request.
-fn build_unterschrift(req: &SignLeistungsnachweisRequest) -> Result<UnterschriftVersicherter> {
+fn build_unterschrift(
+    req: &SignLeistungsnachweisRequest,
+    signed_at: DateTime<Utc>,
+) -> Result<UnterschriftVersicherter> {
     let art = map_signature_type(req.signature_type);
-    let datum_uhrzeit = Some(chrono::Local::now().format("%Y%m%d%H%M%S").to_string());
+    let datum_uhrzeit = Some(
+        signed_at
+            .with_timezone(&chrono::Local)
+            .format("%Y%m%d%H%M%S")
+            .to_string(),
+    );
 
     let unterschrift = build_unterschrift_data(req)?;
     let fehlende_unterschrift = build_fehlende_unterschrift(req)?;
@@ -148,6 +1030,30 @@ fn build_unterschrift(req: &SignLeistungsnachweisRequest) -> Result<Unterschrift
     })
 }
 
+/// Builds the HKP UnterschriftVersicherter from request.
+fn build_hkp_unterschrift(
+    req: &SignLeistungsnachweisRequest,
+    signed_at: DateTime<Utc>,
+) -> Result<hkp::UnterschriftVersicherter> {
+    let fehlende_unterschrift =
+        build_fehlende_unterschrift(req)?.map(|f| hkp::FehlendeUnterschrift {
+            grund: f.grund,
+            sonstiges: f.erlaeuterung,
+        });
+
+    Ok(hkp::UnterschriftVersicherter {
+        art: map_signature_type(req.signature_type),
+        datum_uhrzeit: Some(
+            signed_at
+                .with_timezone(&chrono::Local)
+                .format("%Y-%m-%dT%H:%M:%S")
+                .to_string(),
+        ),
+        unterschrift: build_unterschrift_data(req)?,
+        fehlende_unterschrift,
+    })
+}
+
 fn map_signature_type(sig_type: SignatureType) -> ArtDerUnterschrift {
     match sig_type {
         SignatureType::HandwrittenDigital => ArtDerUnterschrift::HandschriftlichDigital,
@@ -163,9 +1069,10 @@ fn build_unterschrift_data(req: &SignLeistungsnachweisRequest) -> Result<Option<
         return Ok(None);
     }
 
-    let sig_data = req.signature.as_ref().ok_or_else(|| {
-        LeistungsnachweisError::BadRequest("Signature data required".into())
-    })?;
+    let sig_data = req
+        .signature
+        .as_ref()
+        .ok_or_else(|| LeistungsnachweisError::BadRequest("Signature data required".into()))?;
 
     Ok(Some(Unterschrift {
         datei: sig_data.data.clone(),
@@ -180,9 +1087,9 @@ fn build_fehlende_unterschrift(
         return Ok(None);
     }
 
-    let grund = req.missing_reason.ok_or_else(|| {
-        LeistungsnachweisError::BadRequest("Missing reason required".into())
-    })?;
+    let grund = req
+        .missing_reason
+        .ok_or_else(|| LeistungsnachweisError::BadRequest("Missing reason required".into()))?;
 
     Ok(Some(FehlendeUnterschrift {
         grund: map_missing_reason(grund),
@@ -203,8 +1110,242 @@ fn map_format_to_dateityp(format: &ImageFormat) -> Dateityp {
     match format {
         ImageFormat::Pdf => Dateityp::Pdf,
         ImageFormat::Jpeg => Dateityp::Jpeg,
-        ImageFormat::Png | ImageFormat::Svg => Dateityp::Png, // SVG → PNG for XSD
+        ImageFormat::Png | ImageFormat::Svg => Dateityp::Png, // SVG rasterized in prepare_signature
         ImageFormat::Gif => Dateityp::Gif,
         ImageFormat::Tiff => Dateityp::Tiff,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use axum::http::StatusCode;
+    use sea_orm::{EntityTrait, PaginatorTrait};
+
+    use super::*;
+    use crate::{
+        repositories::{entity::signatur_ereignis, test_db},
+        services::mock_data,
+    };
+
+    const PFL_ID: &str = "7c1e4b2a-9d3f-4e8a-b6c5-1a2b3c4d5e01";
+    const HKP_ID: &str = "7c1e4b2a-9d3f-4e8a-b6c5-1a2b3c4d5e02";
+
+    fn core_client() -> CoreClient {
+        use crate::services::core_client::CoreClientConfig;
+        CoreClient::new(
+            String::new(),
+            String::new(),
+            true,
+            CoreClientConfig::from_env(),
+        )
+    }
+
+    /// Batch of `(id, valid)` items; invalid ones lack their signature image
+    fn batch(items: &[(&str, bool)], all_or_nothing: bool) -> BatchSignRequest {
+        let documents: Vec<_> = items
+            .iter()
+            .map(|&(id, valid)| {
+                if valid {
+                    serde_json::json!({
+                        "id": id,
+                        "signatureType": "missing",
+                        "missingReason": "not_present"
+                    })
+                } else {
+                    serde_json::json!({ "id": id, "signatureType": "handwritten_digital" })
+                }
+            })
+            .collect();
+        serde_json::from_value(serde_json::json!({
+            "documents": documents,
+            "allOrNothing": all_or_nothing
+        }))
+        .unwrap()
+    }
+
+    fn missing_signature() -> SignLeistungsnachweisRequest {
+        SignLeistungsnachweisRequest {
+            signature_type: SignatureType::Missing,
+            signature: None,
+            missing_reason: Some(MissingSignatureReason::NotPresent),
+            missing_explanation: None,
+        }
+    }
+
+    #[test]
+    fn test_xml_carries_time_of_signature() {
+        let signed_at = "2026-03-02T08:15:00Z".parse::<DateTime<Utc>>().unwrap();
+        let local = signed_at.with_timezone(&chrono::Local);
+
+        let pfl = mock_data::mock_detail("7c1e4b2a-9d3f-4e8a-b6c5-1a2b3c4d5e01").unwrap();
+        let signed = sign_and_generate_xml(&pfl, &missing_signature(), signed_at).unwrap();
+        assert!(
+            signed
+                .xml_content
+                .contains(&local.format("%Y%m%d%H%M%S").to_string())
+        );
+
+        let hkp = mock_data::mock_detail("7c1e4b2a-9d3f-4e8a-b6c5-1a2b3c4d5e02").unwrap();
+        let signed = sign_and_generate_xml(&hkp, &missing_signature(), signed_at).unwrap();
+        assert!(
+            signed
+                .xml_content
+                .contains(&local.format("%Y-%m-%dT%H:%M:%S").to_string())
+        );
+    }
+
+    async fn stored_status(db: &DatabaseConnection) -> Option<String> {
+        leistungsnachweis_repository::find_by_id(db, PFL_ID)
+            .await
+            .unwrap()
+            .map(|d| d.status)
+    }
+
+    #[tokio::test]
+    async fn test_signature_and_xml_are_stored_together() {
+        let Some(db) = test_db::connect().await else {
+            return;
+        };
+        let detail = mock_data::mock_detail(PFL_ID).unwrap();
+        let request = missing_signature();
+
+        // The XML cannot be stored (Postgres refuses NUL in text), so the
+        // signature event and the status change are rolled back with it
+        let mut broken = sign_and_generate_xml(&detail, &request, Utc::now()).unwrap();
+        broken.xml_content.push('\0');
+        assert!(
+            persist_signed(&db, &detail, &request, "nurse", &broken)
+                .await
+                .is_err()
+        );
+        let events = signatur_ereignis::Entity::find().count(&db).await.unwrap();
+        assert_eq!(events, 0);
+        assert_eq!(stored_status(&db).await, None);
+
+        let signed = sign_and_generate_xml(&detail, &request, Utc::now()).unwrap();
+        persist_signed(&db, &detail, &request, "nurse", &signed)
+            .await
+            .unwrap();
+        let xml = leistungsnachweis_repository::find_latest_xml(&db, PFL_ID)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(xml.xml, signed.xml_content);
+        let event = signatur_ereignis::Entity::find_by_id(xml.signatur_ereignis_id)
+            .one(&db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(event.art, "missing");
+        assert_eq!(event.signiert_von, "nurse");
+        assert_eq!(stored_status(&db).await.as_deref(), Some("finalized"));
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_signing_stores_one_signature() {
+        let Some(db) = test_db::connect().await else {
+            return;
+        };
+        let detail = mock_data::mock_detail(PFL_ID).unwrap();
+        let request = missing_signature();
+        let signed = sign_and_generate_xml(&detail, &request, Utc::now()).unwrap();
+
+        let (first, second) = tokio::join!(
+            persist_signed(&db, &detail, &request, "nurse-1", &signed),
+            persist_signed(&db, &detail, &request, "nurse-2", &signed),
+        );
+        let mut results = [first, second];
+        results.sort_by_key(|r| r.is_err());
+        let [won, lost] = results;
+        assert!(won.is_ok());
+        assert_eq!(StatusCode::from(lost.unwrap_err()), StatusCode::CONFLICT);
+
+        let events = signatur_ereignis::Entity::find().count(&db).await.unwrap();
+        assert_eq!(events, 1);
+    }
+
+    #[tokio::test]
+    async fn test_batch_is_checked_before_signing() {
+        // Refused before any database access
+        let db = DatabaseConnection::default();
+        let core = core_client();
+
+        let duplicate = batch(&[(PFL_ID, true), (HKP_ID, true), (PFL_ID, true)], false);
+        let err = batch_sign(&db, &core, &ClientScope::All, duplicate, "nurse")
+            .await
+            .unwrap_err();
+        assert!(matches!(err, LeistungsnachweisError::BadRequest(m) if m.contains(PFL_ID)));
+
+        let ids: Vec<String> = (0..=MAX_BATCH_SIZE).map(|i| format!("LN-{}", i)).collect();
+        let items: Vec<_> = ids.iter().map(|id| (id.as_str(), true)).collect();
+        let err = batch_sign(&db, &core, &ClientScope::All, batch(&items, false), "nurse")
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            LeistungsnachweisError::BatchLimitExceeded { max: MAX_BATCH_SIZE, requested }
+                if requested == MAX_BATCH_SIZE + 1
+        ));
+
+        let err = batch_sign(&db, &core, &ClientScope::All, batch(&[], false), "nurse")
+            .await
+            .unwrap_err();
+        assert!(matches!(err, LeistungsnachweisError::BadRequest(_)));
+    }
+
+    #[tokio::test]
+    async fn test_batch_reports_each_document() {
+        let Some(db) = test_db::connect().await else {
+            return;
+        };
+        let request = batch(&[(PFL_ID, true), (HKP_ID, false)], false);
+        let response = batch_sign(&db, &core_client(), &ClientScope::All, request, "nurse")
+            .await
+            .unwrap();
+
+        assert_eq!((response.success_count, response.failure_count), (1, 1));
+        let [signed, failed] = &response.results[..] else {
+            panic!("expected two results");
+        };
+        assert_eq!((signed.id.as_str(), signed.success), (PFL_ID, true));
+        assert_eq!((failed.id.as_str(), failed.success), (HKP_ID, false));
+        assert!(
+            failed
+                .error
+                .as_ref()
+                .unwrap()
+                .contains("Signature data required")
+        );
+
+        assert_eq!(stored_status(&db).await.as_deref(), Some("finalized"));
+        let hkp = leistungsnachweis_repository::find_by_id(&db, HKP_ID).await;
+        assert!(hkp.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_all_or_nothing_batch_stores_nothing_if_one_fails() {
+        let Some(db) = test_db::connect().await else {
+            return;
+        };
+        let request = batch(&[(PFL_ID, true), (HKP_ID, false)], true);
+        let response = batch_sign(&db, &core_client(), &ClientScope::All, request, "nurse")
+            .await
+            .unwrap();
+
+        assert_eq!((response.success_count, response.failure_count), (0, 2));
+        let skipped = &response.results[0];
+        assert_eq!(skipped.id, PFL_ID);
+        assert!(skipped.error.as_ref().unwrap().contains("another document"));
+        assert_eq!(stored_status(&db).await, None);
+        let events = signatur_ereignis::Entity::find().count(&db).await.unwrap();
+        assert_eq!(events, 0);
+
+        let request = batch(&[(PFL_ID, true), (HKP_ID, true)], true);
+        let response = batch_sign(&db, &core_client(), &ClientScope::All, request, "nurse")
+            .await
+            .unwrap();
+        assert_eq!((response.success_count, response.failure_count), (2, 0));
+        let events = signatur_ereignis::Entity::find().count(&db).await.unwrap();
+        assert_eq!(events, 2);
+    }
+}
@@ -0,0 +1,631 @@
+//! Offline sync for tablets.
+//!
+//! Tablets pull the documents of their clients that changed since a cursor,
+//! capture signatures without a connection and upload them later. Every
+//! pulled document carries a fingerprint of its content; a queued signature
+//! names the fingerprint it was captured against, so a document that changed
+//! in the meantime is reported as a conflict instead of being signed.
+
+use std::{
+    collections::BTreeSet,
+    sync::{Arc, LazyLock},
+};
+
+use chrono::{Duration, Utc};
+use sea_orm::DatabaseConnection;
+use sha2::{Digest, Sha256};
+use tokio::sync::Semaphore;
+use tracing::warn;
+
+use crate::{
+    config::policy::ClientScope,
+    models::document_status::StatusAction,
+    repositories::sync_repository::{self, DocumentFingerprint, NewSyncUpload},
+    services::{CoreClient, CoreClientError},
+};
+
+use super::{
+    error::{LeistungsnachweisError, Result},
+    request::{QueuedSignature, SyncPullQuery, SyncUploadRequest},
+    response::{
+        ConflictReason, LeistungsnachweisDetail, SyncConflict, SyncOutcome, SyncPullResponse,
+        SyncUploadResponse, SyncUploadResult, SyncedDocument,
+    },
+    service,
+};
+
+/// Maximum number of documents in one pull
+pub const MAX_PULL_LIMIT: usize = 500;
+
+/// Maximum number of signatures in one upload
+pub const MAX_UPLOAD_SIZE: usize = service::MAX_BATCH_SIZE;
+
+/// Page size when listing a client's documents at the core server
+const LIST_PAGE_SIZE: u64 = 100;
+
+/// Longest accepted idempotency key
+const MAX_IDEMPOTENCY_KEY_LEN: usize = 255;
+
+/// How far a capture timestamp may lie in the future, for device clocks
+/// running ahead
+const MAX_CLOCK_SKEW_MINUTES: i64 = 5;
+
+/// Documents fetched from the core server in parallel per pull
+static SYNC_FETCH_CONCURRENCY: LazyLock<usize> = LazyLock::new(|| {
+    std::env::var("SYNC_FETCH_CONCURRENCY")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|&n| n > 0)
+        .unwrap_or(4)
+});
+
+/// SHA-256 of a document as delivered to tablets, hex encoded.
+///
+/// Taken after the local state is applied, so a status change or a new
+/// technical error changes the fingerprint just like a change at core.
+pub fn fingerprint(detail: &LeistungsnachweisDetail) -> String {
+    let bytes = serde_json::to_vec(detail).unwrap_or_default();
+    hex::encode(Sha256::digest(bytes))
+}
+
+fn parse_cursor(cursor: Option<&str>) -> Result<i64> {
+    match cursor.map(str::trim).filter(|c| !c.is_empty()) {
+        None => Ok(0),
+        Some(cursor) => cursor
+            .parse()
+            .ok()
+            .filter(|&c: &i64| c >= 0)
+            .ok_or_else(|| LeistungsnachweisError::BadRequest(format!("Invalid cursor: {cursor}"))),
+    }
+}
+
+/// Clients to pull: the requested ones, which must be in scope, or all
+/// clients of the scope.
+fn sync_clients(scope: &ClientScope, requested: Option<&str>) -> Result<BTreeSet<String>> {
+    let requested: BTreeSet<String> = requested
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|c| !c.is_empty())
+        .map(str::to_string)
+        .collect();
+
+    if requested.is_empty() {
+        return match scope {
+            ClientScope::Clients(clients) => Ok(clients.iter().cloned().collect()),
+            ClientScope::All => Err(LeistungsnachweisError::BadRequest(
+                "Clients required for a token covering all clients".into(),
+            )),
+        };
+    }
+    if requested.iter().any(|client| !scope.permits(client)) {
+        return Err(LeistungsnachweisError::Forbidden);
+    }
+    Ok(requested)
+}
+
+/// Changes after `since`, oldest first and at most `limit` of them, with the
+/// cursor for the next pull and whether more changes are waiting.
+fn select_changes<T>(
+    mut changes: Vec<(i64, T)>,
+    since: i64,
+    limit: usize,
+) -> (Vec<(i64, T)>, i64, bool) {
+    changes.retain(|(version, _)| *version > since);
+    changes.sort_by_key(|(version, _)| *version);
+    let has_more = changes.len() > limit;
+    changes.truncate(limit);
+    let cursor = changes.last().map_or(since, |(version, _)| *version);
+    (changes, cursor, has_more)
+}
+
+/// Documents of the requested clients that changed since the cursor.
+///
+/// Every pull compares the current documents with the fingerprints seen
+/// before and gives changed ones a new version. The cursor covers the
+/// clients of the pull; a tablet whose clients changed pulls without a
+/// cursor once.
+pub async fn pull(
+    db: &DatabaseConnection,
+    core_client: &CoreClient,
+    scope: &ClientScope,
+    query: SyncPullQuery,
+) -> Result<SyncPullResponse> {
+    let since = parse_cursor(query.cursor.as_deref())?;
+    if query.limit == 0 || query.limit > MAX_PULL_LIMIT {
+        return Err(LeistungsnachweisError::BadRequest(format!(
+            "Limit must be between 1 and {}",
+            MAX_PULL_LIMIT
+        )));
+    }
+    let clients = sync_clients(scope, query.clients.as_deref())?;
+
+    let mut ids = Vec::new();
+    for client in &clients {
+        let mut page = 0;
+        loop {
+            let listed = core_client
+                .list_leistungsnachweise(client, page, LIST_PAGE_SIZE)
+                .await?;
+            ids.extend(listed.content.into_iter().map(|item| item.id));
+            if listed.last || listed.empty {
+                break;
+            }
+            page += 1;
+        }
+    }
+
+    let semaphore = Arc::new(Semaphore::new(*SYNC_FETCH_CONCURRENCY));
+    let tasks: Vec<_> = ids
+        .into_iter()
+        .map(|id| {
+            let (db, core_client) = (db.clone(), core_client.clone());
+            let semaphore = Arc::clone(&semaphore);
+            tokio::spawn(async move {
+                let _permit = semaphore.acquire().await;
+                let mut detail = match core_client.get_leistungsnachweis(&id).await {
+                    Ok(detail) => detail,
+                    // Removed between listing and fetching
+                    Err(CoreClientError::NotFound) => return Ok(None),
+                    Err(e) => return Err(LeistungsnachweisError::from(e)),
+                };
+                service::apply_local_state(&db, &mut detail).await?;
+                Ok(Some(detail))
+            })
+        })
+        .collect();
+
+    let mut documents = Vec::with_capacity(tasks.len());
+    for task in tasks {
+        let fetched = task.await.map_err(|e| {
+            LeistungsnachweisError::Internal(format!("Sync fetch task failed: {}", e))
+        })?;
+        if let Some(detail) = fetched? {
+            documents.push((fingerprint(&detail), detail));
+        }
+    }
+
+    let versions = sync_repository::record_fingerprints(
+        db,
+        documents
+            .iter()
+            .map(|(fingerprint, detail)| DocumentFingerprint {
+                leistungsnachweis_id: detail.id.clone(),
+                versichertennummer: detail.client.versichertennummer.clone(),
+                fingerprint: fingerprint.clone(),
+            })
+            .collect(),
+    )
+    .await?;
+
+    let changes = documents
+        .into_iter()
+        .filter_map(|document| Some((*versions.get(&document.1.id)?, document)))
+        .collect();
+    let (changes, cursor, has_more) = select_changes(changes, since, query.limit);
+
+    Ok(SyncPullResponse {
+        documents: changes
+            .into_iter()
+            .map(|(version, (fingerprint, document))| SyncedDocument {
+                version,
+                fingerprint,
+                document,
+            })
+            .collect(),
+        cursor: cursor.to_string(),
+        has_more,
+    })
+}
+
+/// Signs queued signatures in order and reports the outcome of each.
+///
+/// Results other than `retry` are stored under their idempotency key, so an
+/// upload repeated after a lost response returns the same results.
+pub async fn upload(
+    db: &DatabaseConnection,
+    core_client: &CoreClient,
+    scope: &ClientScope,
+    req: SyncUploadRequest,
+    uploaded_by: &str,
+) -> Result<SyncUploadResponse> {
+    let requested = req.signatures.len();
+    if requested == 0 {
+        return Err(LeistungsnachweisError::BadRequest(
+            "No signatures to upload".into(),
+        ));
+    }
+    if requested > MAX_UPLOAD_SIZE {
+        return Err(LeistungsnachweisError::BatchLimitExceeded {
+            max: MAX_UPLOAD_SIZE,
+            requested,
+        });
+    }
+
+    let mut results = Vec::with_capacity(requested);
+    for queued in req.signatures {
+        results.push(upload_one(db, core_client, scope, queued, uploaded_by).await);
+    }
+    Ok(SyncUploadResponse { results })
+}
+
+async fn upload_one(
+    db: &DatabaseConnection,
+    core_client: &CoreClient,
+    scope: &ClientScope,
+    queued: QueuedSignature,
+    uploaded_by: &str,
+) -> SyncUploadResult {
+    let key = queued.idempotency_key.trim();
+    if key.is_empty() || key.len() > MAX_IDEMPOTENCY_KEY_LEN {
+        return failed(
+            &queued,
+            LeistungsnachweisError::BadRequest(format!(
+                "Idempotency key must have 1 to {} characters",
+                MAX_IDEMPOTENCY_KEY_LEN
+            )),
+        );
+    }
+
+    // Before any replay, since a stored result carries the whole document
+    let id = std::slice::from_ref(&queued.document_id);
+    if let Err(e) = service::ensure_in_scope(db, core_client, scope, id).await {
+        return failed(&queued, e);
+    }
+
+    match sync_repository::find_upload(db, uploaded_by, key).await {
+        Ok(Some(stored)) if stored.leistungsnachweis_id != queued.document_id => {
+            return failed(
+                &queued,
+                LeistungsnachweisError::BadRequest(
+                    "Idempotency key was used for another document".into(),
+                ),
+            );
+        }
+        Ok(Some(stored)) => return replayed(&queued, stored.result),
+        Ok(None) => {}
+        Err(e) => return failed(&queued, e.into()),
+    }
+
+    let result = match sign_queued(db, core_client, &queued, uploaded_by).await {
+        Ok(result) => result,
+        Err(e) => failed(&queued, e),
+    };
+    if result.outcome == SyncOutcome::Retry {
+        return result;
+    }
+
+    let stored = match serde_json::to_value(&result) {
+        Ok(value) => sync_repository::store_upload(
+            db,
+            NewSyncUpload {
+                idempotency_key: key.to_string(),
+                leistungsnachweis_id: queued.document_id.clone(),
+                uploaded_by: uploaded_by.to_string(),
+                captured_at: queued.captured_at,
+                outcome: result.outcome.as_str().to_string(),
+                result: value.clone(),
+            },
+        )
+        .await
+        .map(|stored| (stored.result != value).then_some(stored.result)),
+        Err(e) => {
+            warn!(error = %e, "Failed to serialize sync upload result");
+            Ok(None)
+        }
+    };
+    match stored {
+        Ok(None) => result,
+        // Another upload with the same key was stored first
+        Ok(Some(first)) => replayed(&queued, first),
+        Err(e) => {
+            // Signed, but a retry would report a conflict; keep the outcome
+            warn!(error = %e, key = %key, "Failed to store sync upload result");
+            result
+        }
+    }
+}
+
+/// Checks a queued signature against the current document and signs it.
+///
+/// The document must already be checked to be in scope.
+async fn sign_queued(
+    db: &DatabaseConnection,
+    core_client: &CoreClient,
+    queued: &QueuedSignature,
+    uploaded_by: &str,
+) -> Result<SyncUploadResult> {
+    if queued.captured_at > Utc::now() + Duration::minutes(MAX_CLOCK_SKEW_MINUTES) {
+        return Err(LeistungsnachweisError::BadRequest(
+            "Capture timestamp lies in the future".into(),
+        ));
+    }
+    let mut signature = queued.signature.clone();
+    service::prepare_signature(&mut signature).await?;
+    let id = &queued.document_id;
+
+    // Conflicts must be decided on the current document, not a cached one
+    core_client.invalidate(id);
+    let mut detail = core_client.get_leistungsnachweis(id).await?;
+    service::apply_local_state(db, &mut detail).await?;
+
+    let current_fingerprint = fingerprint(&detail);
+    let reason = if detail.status.apply(StatusAction::Finalize).is_err() {
+        Some(ConflictReason::InvalidStatus)
+    } else if current_fingerprint != queued.base_fingerprint {
+        Some(ConflictReason::DocumentChanged)
+    } else {
+        None
+    };
+    if let Some(reason) = reason {
+        return Ok(conflict(queued, reason, current_fingerprint, detail));
+    }
+
+    let signed = service::sign_and_generate_xml(&detail, &signature, queued.captured_at)?;
+    match service::persist_signed(db, &detail, &signature, uploaded_by, &signed).await {
+        Ok(()) => {}
+        // Signed concurrently since the check above
+        Err(
+            LeistungsnachweisError::InvalidStatusTransition { .. }
+            | LeistungsnachweisError::Conflict(_),
+        ) => {
+            return Ok(conflict(
+                queued,
+                ConflictReason::InvalidStatus,
+                current_fingerprint,
+                detail,
+            ));
+        }
+        Err(e) => return Err(e),
+    }
+    core_client.invalidate(id);
+
+    Ok(SyncUploadResult {
+        idempotency_key: queued.idempotency_key.trim().to_string(),
+        document_id: id.clone(),
+        outcome: SyncOutcome::Signed,
+        replayed: false,
+        conflict: None,
+        error: None,
+    })
+}
+
+fn conflict(
+    queued: &QueuedSignature,
+    reason: ConflictReason,
+    current_fingerprint: String,
+    current: LeistungsnachweisDetail,
+) -> SyncUploadResult {
+    SyncUploadResult {
+        idempotency_key: queued.idempotency_key.trim().to_string(),
+        document_id: queued.document_id.clone(),
+        outcome: SyncOutcome::Conflict,
+        replayed: false,
+        conflict: Some(SyncConflict {
+            reason,
+            current_status: current.status,
+            current_fingerprint,
+            current,
+        }),
+        error: Some(match reason {
+            ConflictReason::DocumentChanged => "Document changed since it was pulled".into(),
+            ConflictReason::InvalidStatus => "Document can no longer be signed".into(),
+        }),
+    }
+}
+
+/// Temporary failures are retried by the tablet; everything else stays.
+fn outcome_of(e: &LeistungsnachweisError) -> SyncOutcome {
+    match e {
+        LeistungsnachweisError::Upstream(_)
+        | LeistungsnachweisError::Unavailable(_)
+        | LeistungsnachweisError::Database(_)
+        | LeistungsnachweisError::Internal(_)
+        | LeistungsnachweisError::Unauthorized => SyncOutcome::Retry,
+        LeistungsnachweisError::InvalidStatusTransition { .. }
+        | LeistungsnachweisError::Conflict(_) => SyncOutcome::Conflict,
+        _ => SyncOutcome::Rejected,
+    }
+}
+
+fn failed(queued: &QueuedSignature, e: LeistungsnachweisError) -> SyncUploadResult {
+    let outcome = outcome_of(&e);
+    if outcome == SyncOutcome::Retry {
+        warn!(error = %e, id = %queued.document_id, "Queued signature not processed");
+    }
+    SyncUploadResult {
+        idempotency_key: queued.idempotency_key.trim().to_string(),
+        document_id: queued.document_id.clone(),
+        outcome,
+        replayed: false,
+        conflict: None,
+        error: Some(e.to_string()),
+    }
+}
+
+fn replayed(queued: &QueuedSignature, stored: serde_json::Value) -> SyncUploadResult {
+    match serde_json::from_value::<SyncUploadResult>(stored) {
+        Ok(result) => SyncUploadResult {
+            replayed: true,
+            ..result
+        },
+        Err(e) => failed(
+            queued,
+            LeistungsnachweisError::Internal(format!("Stored sync result unreadable: {}", e)),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::{repositories::test_db, services::core_client::CoreClientConfig};
+
+    /// Pending PFL_LNW document of client A123456789 in mock mode
+    const DOCUMENT_ID: &str = "7c1e4b2a-9d3f-4e8a-b6c5-1a2b3c4d5e01";
+
+    fn queued(key: &str) -> QueuedSignature {
+        serde_json::from_value(serde_json::json!({
+            "idempotencyKey": key,
+            "documentId": DOCUMENT_ID,
+            "baseFingerprint": "ab12",
+            "capturedAt": "2026-03-02T08:15:00Z",
+            "signatureType": "missing",
+            "missingReason": "not_present"
+        }))
+        .unwrap()
+    }
+
+    fn scope(client: &str) -> ClientScope {
+        ClientScope::Clients(HashSet::from([client.to_string()]))
+    }
+
+    #[test]
+    fn test_cursor_parsing() {
+        assert_eq!(parse_cursor(None).unwrap(), 0);
+        assert_eq!(parse_cursor(Some(" ")).unwrap(), 0);
+        assert_eq!(parse_cursor(Some("42")).unwrap(), 42);
+        assert!(parse_cursor(Some("-1")).is_err());
+        assert!(parse_cursor(Some("abc")).is_err());
+    }
+
+    #[test]
+    fn test_changes_after_cursor_oldest_first() {
+        let changes = vec![(7, "c"), (3, "a"), (5, "b"), (9, "d")];
+
+        let (selected, cursor, has_more) = select_changes(changes.clone(), 3, 2);
+        assert_eq!(selected, vec![(5, "b"), (7, "c")]);
+        assert_eq!((cursor, has_more), (7, true));
+
+        let (selected, cursor, has_more) = select_changes(changes.clone(), cursor, 2);
+        assert_eq!(selected, vec![(9, "d")]);
+        assert_eq!((cursor, has_more), (9, false));
+
+        // Nothing new keeps the cursor
+        let (selected, cursor, has_more) = select_changes(changes, cursor, 2);
+        assert!(selected.is_empty());
+        assert_eq!((cursor, has_more), (9, false));
+    }
+
+    #[test]
+    fn test_sync_clients_stay_in_scope() {
+        let scope = ClientScope::Clients(HashSet::from(["A1".to_string(), "B2".to_string()]));
+
+        let all = sync_clients(&scope, None).unwrap();
+        assert_eq!(all.into_iter().collect::<Vec<_>>(), vec!["A1", "B2"]);
+        let one = sync_clients(&scope, Some(" B2, ")).unwrap();
+        assert_eq!(one.into_iter().collect::<Vec<_>>(), vec!["B2"]);
+        assert!(matches!(
+            sync_clients(&scope, Some("A1,C3")),
+            Err(LeistungsnachweisError::Forbidden)
+        ));
+
+        assert!(matches!(
+            sync_clients(&ClientScope::All, None),
+            Err(LeistungsnachweisError::BadRequest(_))
+        ));
+        assert!(sync_clients(&ClientScope::All, Some("C3")).is_ok());
+    }
+
+    #[test]
+    fn test_queued_signature_carries_signature_request() {
+        let queued: QueuedSignature = serde_json::from_value(serde_json::json!({
+            "idempotencyKey": "tablet-7-0001",
+            "documentId": "LN-1",
+            "baseFingerprint": "ab12",
+            "capturedAt": "2026-03-02T08:15:00Z",
+            "signatureType": "missing",
+            "missingReason": "not_present"
+        }))
+        .unwrap();
+
+        assert_eq!(queued.document_id, "LN-1");
+        assert!(service::validate_signature_request(&queued.signature).is_ok());
+    }
+
+    #[test]
+    fn test_transient_failures_are_retried() {
+        assert_eq!(
+            outcome_of(&LeistungsnachweisError::Unavailable("core".into())),
+            SyncOutcome::Retry
+        );
+        assert_eq!(
+            outcome_of(&LeistungsnachweisError::BadRequest("signature".into())),
+            SyncOutcome::Rejected
+        );
+        assert_eq!(
+            outcome_of(&LeistungsnachweisError::Forbidden),
+            SyncOutcome::Rejected
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stored_upload_is_replayed_only_to_its_uploader_in_scope() {
+        let Some(db) = test_db::connect().await else {
+            return;
+        };
+        let core_client = CoreClient::new(
+            String::new(),
+            String::new(),
+            true,
+            CoreClientConfig::from_env(),
+        );
+        let first = SyncUploadResult {
+            idempotency_key: "tablet-7-0001".into(),
+            document_id: DOCUMENT_ID.into(),
+            outcome: SyncOutcome::Signed,
+            replayed: false,
+            conflict: None,
+            error: None,
+        };
+        sync_repository::store_upload(
+            &db,
+            NewSyncUpload {
+                idempotency_key: "tablet-7-0001".into(),
+                leistungsnachweis_id: DOCUMENT_ID.into(),
+                uploaded_by: "user-1".into(),
+                captured_at: Utc::now(),
+                outcome: SyncOutcome::Signed.as_str().into(),
+                result: serde_json::to_value(&first).unwrap(),
+            },
+        )
+        .await
+        .unwrap();
+
+        let result = upload_one(
+            &db,
+            &core_client,
+            &scope("B987654321"),
+            queued("tablet-7-0001"),
+            "user-1",
+        )
+        .await;
+        assert_eq!(result.outcome, SyncOutcome::Rejected);
+        assert!(!result.replayed);
+
+        let result = upload_one(
+            &db,
+            &core_client,
+            &scope("A123456789"),
+            queued("tablet-7-0001"),
+            "user-1",
+        )
+        .await;
+        assert_eq!(result.outcome, SyncOutcome::Signed);
+        assert!(result.replayed);
+
+        // The same key of another user is an upload of its own
+        let result = upload_one(
+            &db,
+            &core_client,
+            &scope("A123456789"),
+            queued("tablet-7-0001"),
+            "user-2",
+        )
+        .await;
+        assert_eq!(result.outcome, SyncOutcome::Conflict);
+        assert!(!result.replayed);
+    }
+}
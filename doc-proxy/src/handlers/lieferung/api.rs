@@ -0,0 +1,86 @@
+//! HTTP handlers for Lieferung API.
+
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::{AppState, repositories::lieferung_repository};
+
+use super::{
+    error::{LieferungError, Result},
+    request::CreateLieferungRequest,
+    response::LieferungResponse,
+    service,
+};
+
+/// POST /abrechnungen/{id}/lieferungen
+pub async fn create_lieferung(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    payload: Option<Json<CreateLieferungRequest>>,
+) -> Result<(StatusCode, Json<LieferungResponse>)> {
+    let Json(payload) = payload.unwrap_or_default();
+    info!(abrechnung_id = %id, verfahrenskennung = ?payload.verfahrenskennung, "Packaging abrechnung");
+
+    let lieferung = service::create_lieferung(&state.db, id, payload)
+        .await
+        .inspect_err(|e| error!(error = %e, abrechnung_id = %id, "Failed to package abrechnung"))?;
+
+    info!(datei_id = %lieferung.datei_id, "Lieferung created");
+    Ok((StatusCode::CREATED, Json(lieferung)))
+}
+
+/// GET /abrechnungen/{id}/lieferungen
+pub async fn list_lieferungen(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<LieferungResponse>>> {
+    let lieferungen = lieferung_repository::find_by_abrechnung(&state.db, id).await?;
+    Ok(Json(
+        lieferungen
+            .into_iter()
+            .map(LieferungResponse::from)
+            .collect(),
+    ))
+}
+
+/// GET /lieferungen/{datei_id}
+pub async fn get_lieferung(
+    State(state): State<AppState>,
+    Path(datei_id): Path<Uuid>,
+) -> Result<Json<LieferungResponse>> {
+    lieferung_repository::find_by_datei_id(&state.db, datei_id)
+        .await?
+        .map(|l| Json(LieferungResponse::from(l)))
+        .ok_or_else(|| LieferungError::NotFound(format!("Lieferung {}", datei_id)))
+}
+
+/// GET /lieferungen/{datei_id}/xml
+///
+/// Returns the file exactly as it was packaged, for (re)submission.
+pub async fn get_lieferung_xml(
+    State(state): State<AppState>,
+    Path(datei_id): Path<Uuid>,
+) -> Result<Response> {
+    let lieferung = lieferung_repository::find_by_datei_id(&state.db, datei_id)
+        .await?
+        .ok_or_else(|| LieferungError::NotFound(format!("Lieferung {}", datei_id)))?;
+
+    let disposition = format!(
+        "attachment; filename=\"{}\"",
+        service::xml_file_name(lieferung.datei_id)
+    );
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/xml".to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        lieferung.xml,
+    )
+        .into_response())
+}
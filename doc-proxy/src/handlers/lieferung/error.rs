@@ -0,0 +1,69 @@
+//! Error types for Lieferung API.
+
+use axum::{
+    Json,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use thiserror::Error;
+
+use crate::models::{abrechnung::BuildError, nutzdaten::NutzdatenError, xsd::Violation};
+
+use super::response::LieferungErrorResponse;
+
+/// Domain errors for Lieferung operations.
+#[derive(Error, Debug)]
+pub enum LieferungError {
+    #[error("Resource not found: {0}")]
+    NotFound(String),
+
+    #[error("Database error: {0}")]
+    Database(#[from] sea_orm::DbErr),
+
+    #[error("Internal error: {0}")]
+    Internal(String),
+
+    #[error("Delivery file violates the schema ({} violations)", .0.len())]
+    SchemaViolation(Vec<Violation>),
+}
+
+impl From<NutzdatenError> for LieferungError {
+    fn from(err: NutzdatenError) -> Self {
+        match err {
+            NutzdatenError::Validation(violations) => Self::SchemaViolation(violations),
+            e => Self::Internal(e.to_string()),
+        }
+    }
+}
+
+impl From<BuildError> for LieferungError {
+    fn from(err: BuildError) -> Self {
+        Self::Internal(format!("Stored invoice is unreadable: {}", err))
+    }
+}
+
+impl From<&LieferungError> for StatusCode {
+    fn from(err: &LieferungError) -> Self {
+        match err {
+            LieferungError::NotFound(_) => StatusCode::NOT_FOUND,
+            LieferungError::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            LieferungError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            LieferungError::SchemaViolation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+        }
+    }
+}
+
+impl IntoResponse for LieferungError {
+    fn into_response(self) -> Response {
+        let status = StatusCode::from(&self);
+        let error = self.to_string();
+        let violations = match self {
+            LieferungError::SchemaViolation(violations) => violations,
+            _ => Vec::new(),
+        };
+        (status, Json(LieferungErrorResponse { error, violations })).into_response()
+    }
+}
+
+/// Result type alias for Lieferung operations.
+pub type Result<T> = std::result::Result<T, LieferungError>;
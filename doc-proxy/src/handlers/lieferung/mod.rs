@@ -0,0 +1,16 @@
+//! Lieferung (HKP_DAT delivery file) domain module.
+//!
+//! Structure:
+//! - `api` - HTTP handlers (thin, orchestration only)
+//! - `service` - Business logic
+//! - `request` - Request DTOs
+//! - `response` - Response DTOs
+//! - `error` - Domain error types
+
+mod api;
+mod error;
+pub mod request;
+pub mod response;
+mod service;
+
+pub use api::{create_lieferung, get_lieferung, get_lieferung_xml, list_lieferungen};
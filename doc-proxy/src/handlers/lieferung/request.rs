@@ -0,0 +1,15 @@
+//! Request DTOs for Lieferung API.
+
+use serde::Deserialize;
+
+use crate::models::nutzdaten::Verfahrenskennung;
+
+/// Request body for packaging an invoice into a Nutzdaten file
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateLieferungRequest {
+    /// `EHKP0` for production data, `THKP0` for test data.
+    /// Defaults to test data so nothing is billed by accident.
+    #[serde(default)]
+    pub verfahrenskennung: Verfahrenskennung,
+}
@@ -0,0 +1,47 @@
+//! Response DTOs for Lieferung API.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::{models::xsd::Violation, repositories::entity::lieferung};
+
+/// Stored delivery file without its XML
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LieferungResponse {
+    /// Datei_ID from the Nutzdaten header
+    pub datei_id: Uuid,
+    pub abrechnung_id: Option<Uuid>,
+    pub nachrichtentyp: String,
+    pub verfahrenskennung: String,
+    pub absender_ik: String,
+    pub empfaenger_ik: String,
+    pub erstellzeitpunkt: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<lieferung::Model> for LieferungResponse {
+    fn from(l: lieferung::Model) -> Self {
+        Self {
+            datei_id: l.datei_id,
+            abrechnung_id: l.abrechnung_id,
+            nachrichtentyp: l.nachrichtentyp,
+            verfahrenskennung: l.verfahrenskennung,
+            absender_ik: l.absender_ik,
+            empfaenger_ik: l.empfaenger_ik,
+            erstellzeitpunkt: l.erstellzeitpunkt,
+            created_at: l.created_at,
+        }
+    }
+}
+
+/// Error body for Lieferung operations
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LieferungErrorResponse {
+    pub error: String,
+    /// One entry per violated schema rule, located by XPath
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub violations: Vec<Violation>,
+}
@@ -0,0 +1,74 @@
+//! Business logic for Lieferung operations.
+
+use chrono::{SubsecRound, Utc};
+use sea_orm::{DatabaseConnection, TransactionTrait};
+use uuid::Uuid;
+
+use crate::{
+    models::{
+        abrechnung::types::Abrechnungsnachricht,
+        nutzdaten::{Nachrichtentyp, Nutzdaten},
+    },
+    repositories::{
+        abrechnung_repository, fehlernachricht_repository,
+        lieferung_repository::{self, NewLieferung},
+    },
+};
+
+use super::{
+    error::{LieferungError, Result},
+    request::CreateLieferungRequest,
+    response::LieferungResponse,
+};
+
+/// Wraps a stored invoice into a Nutzdaten file with a fresh Datei_ID and
+/// stores the file as delivered.
+///
+/// Packaging the same invoice again yields a new file with a new Datei_ID,
+/// which is what a resubmission after a technical rejection requires. Open
+/// technical errors of the contained Leistungsnachweise are resolved with it.
+pub async fn create_lieferung(
+    db: &DatabaseConnection,
+    abrechnung_id: Uuid,
+    req: CreateLieferungRequest,
+) -> Result<LieferungResponse> {
+    let abrechnung = abrechnung_repository::find_by_id(db, abrechnung_id)
+        .await?
+        .ok_or_else(|| LieferungError::NotFound(format!("Abrechnung {}", abrechnung_id)))?;
+    let nachricht = Abrechnungsnachricht::from_xml(&abrechnung.xml)?;
+    let leistungsnachweise: Vec<String> = nachricht
+        .faelle
+        .iter()
+        .map(|f| f.leistungsnachweis_id.clone())
+        .collect();
+
+    let datei_id = Uuid::new_v4();
+    let erstellzeitpunkt = Utc::now().trunc_subsecs(0);
+    let datei = Nutzdaten::abrechnung(nachricht, datei_id, erstellzeitpunkt, req.verfahrenskennung);
+    let xml = datei.to_xml()?;
+
+    let txn = db.begin().await?;
+    let lieferung = lieferung_repository::create(
+        &txn,
+        NewLieferung {
+            datei_id,
+            abrechnung_id: Some(abrechnung_id),
+            nachrichtentyp: Nachrichtentyp::Abrechnung.code().to_string(),
+            verfahrenskennung: req.verfahrenskennung.code().to_string(),
+            absender_ik: datei.header.absender_ik,
+            empfaenger_ik: datei.header.empfaenger_ik,
+            erstellzeitpunkt,
+            xml,
+        },
+    )
+    .await?;
+    fehlernachricht_repository::resolve_for_leistungsnachweise(&txn, &leistungsnachweise).await?;
+    txn.commit().await?;
+
+    Ok(LieferungResponse::from(lieferung))
+}
+
+/// Suggested file name for the delivery download.
+pub fn xml_file_name(datei_id: Uuid) -> String {
+    format!("Nutzdaten_{}.xml", datei_id)
+}
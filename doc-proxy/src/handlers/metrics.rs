@@ -0,0 +1,53 @@
+//! Metrics in the Prometheus text format.
+
+use std::fmt::Write;
+
+use axum::{extract::State, http::header, response::IntoResponse};
+
+use crate::AppState;
+use crate::utils::circuit_breaker::CircuitState;
+
+/// GET /metrics
+pub async fn get_metrics(State(state): State<AppState>) -> impl IntoResponse {
+    let cache = state.core_client.cache_metrics();
+    let core = state.core_client.status();
+
+    let mut body = String::new();
+    let mut metric = |name: &str, kind: &str, help: &str, value: u64| {
+        let _ = writeln!(body, "# HELP {name} {help}");
+        let _ = writeln!(body, "# TYPE {name} {kind}");
+        let _ = writeln!(body, "{name} {value}");
+    };
+    metric(
+        "core_cache_hits_total",
+        "counter",
+        "Core server responses served from the cache",
+        cache.hits,
+    );
+    metric(
+        "core_cache_misses_total",
+        "counter",
+        "Core server responses requested, including revalidations",
+        cache.misses,
+    );
+    metric(
+        "core_cache_revalidations_total",
+        "counter",
+        "Cached core server responses confirmed unchanged (304)",
+        cache.revalidations,
+    );
+    metric(
+        "core_cache_entries",
+        "gauge",
+        "Core server responses currently cached",
+        cache.entries as u64,
+    );
+    metric(
+        "core_circuit_open",
+        "gauge",
+        "1 while the circuit breaker for the core server is not closed",
+        u64::from(core.circuit != CircuitState::Closed),
+    );
+
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], body)
+}
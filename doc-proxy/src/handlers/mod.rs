@@ -1,5 +1,10 @@
+pub mod abrechnung;
+pub mod audit;
 pub mod auth;
 pub mod device;
+pub mod fehlernachricht;
 pub mod health;
 pub mod leistungsnachweis;
+pub mod lieferung;
+pub mod metrics;
 pub mod users_api;
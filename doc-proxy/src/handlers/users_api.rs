@@ -1,67 +1,238 @@
+//! User management handlers (admin only).
+
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
     Json,
 };
+use tracing::{error, info};
 use uuid::Uuid;
 
 use crate::{
-    models::user::{CreateUserRequest, UpdateUserRequest, UserResponse},
-    repositories::user_repository,
+    config::{
+        audit::AuditContext,
+        auth::{AuthUser, UserRole},
+    },
+    models::{
+        audit::AuditAction,
+        pagination::{PageResult, Pageable},
+        user::{CreateUserRequest, ListUsersQuery, UpdateUserRequest, UserResponse},
+    },
+    repositories::{
+        token_repository,
+        user_repository::{self, UserError},
+    },
     AppState,
 };
 
-pub async fn get_users(State(state): State<AppState>) -> Result<Json<Vec<UserResponse>>, StatusCode> {
-    let users = user_repository::find_all(&state.db)
+/// Largest page size for listing users
+const MAX_PAGE_SIZE: u64 = 100;
+
+/// Minimum password length for new and changed passwords
+const MIN_PASSWORD_LENGTH: usize = 8;
+
+/// Error response for user operations
+#[derive(Debug, serde::Serialize)]
+pub struct UserErrorResponse {
+    pub error: String,
+}
+
+type ApiError = (StatusCode, Json<UserErrorResponse>);
+
+fn api_error(status: StatusCode, message: impl Into<String>) -> ApiError {
+    (
+        status,
+        Json(UserErrorResponse {
+            error: message.into(),
+        }),
+    )
+}
+
+fn validate_password(password: &str) -> Result<(), ApiError> {
+    if password.chars().count() < MIN_PASSWORD_LENGTH {
+        return Err(api_error(
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Password must be at least {} characters",
+                MIN_PASSWORD_LENGTH
+            ),
+        ));
+    }
+    Ok(())
+}
+
+fn user_error(e: UserError) -> ApiError {
+    match e {
+        UserError::Conflict(message) => api_error(StatusCode::CONFLICT, message),
+        e => {
+            error!(error = %e, "User operation failed");
+            api_error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+        }
+    }
+}
+
+fn db_error(e: sea_orm::DbErr) -> ApiError {
+    user_error(UserError::Database(e))
+}
+
+/// GET /users?page=0&size=20&search=...
+pub async fn get_users(
+    State(state): State<AppState>,
+    Query(query): Query<ListUsersQuery>,
+) -> Result<Json<PageResult<UserResponse>>, ApiError> {
+    if query.size == 0 || query.size > MAX_PAGE_SIZE {
+        return Err(api_error(
+            StatusCode::BAD_REQUEST,
+            format!("Page size must be between 1 and {}", MAX_PAGE_SIZE),
+        ));
+    }
+
+    let pageable = Pageable::new(query.page, query.size);
+    let users = user_repository::find_page(&state.db, &pageable, query.search.as_deref())
         .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        .map_err(db_error)?;
 
     Ok(Json(users))
 }
 
+/// GET /users/{id}
 pub async fn get_user(
     State(state): State<AppState>,
     Path(id): Path<Uuid>,
-) -> Result<Json<UserResponse>, StatusCode> {
+) -> Result<Json<UserResponse>, ApiError> {
     let user = user_repository::find_by_id(&state.db, id)
         .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-        .ok_or(StatusCode::NOT_FOUND)?;
+        .map_err(db_error)?
+        .ok_or_else(|| api_error(StatusCode::NOT_FOUND, "User not found"))?;
 
     Ok(Json(user))
 }
 
+/// POST /users
 pub async fn create_user(
     State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    audit: AuditContext,
     Json(payload): Json<CreateUserRequest>,
-) -> Result<(StatusCode, Json<UserResponse>), StatusCode> {
+) -> Result<(StatusCode, Json<UserResponse>), ApiError> {
+    if payload.username.trim().is_empty() || payload.name.trim().is_empty() {
+        return Err(api_error(
+            StatusCode::BAD_REQUEST,
+            "Username and name are required",
+        ));
+    }
+    validate_password(&payload.password)?;
+    if payload.role == Some(UserRole::Client)
+        && payload
+            .versichertennummer
+            .as_deref()
+            .is_none_or(|v| v.trim().is_empty())
+    {
+        return Err(api_error(
+            StatusCode::BAD_REQUEST,
+            "Client accounts require a Versichertennummer",
+        ));
+    }
+
     let user = user_repository::create(&state.db, payload)
         .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        .map_err(user_error)?;
+    audit
+        .record(
+            &state.db,
+            AuditAction::UserCreated,
+            Some(&user.id.to_string()),
+            None,
+        )
+        .await;
 
+    info!(
+        user_id = %user.id,
+        username = %user.username,
+        created_by = %claims.sub,
+        "Created user"
+    );
     Ok((StatusCode::CREATED, Json(user)))
 }
 
+/// PATCH /users/{id}
 pub async fn update_user(
     State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    audit: AuditContext,
     Path(id): Path<Uuid>,
     Json(payload): Json<UpdateUserRequest>,
-) -> Result<Json<UserResponse>, StatusCode> {
+) -> Result<Json<UserResponse>, ApiError> {
+    if let Some(password) = &payload.password {
+        validate_password(password)?;
+    }
+    if payload.is_active == Some(false) && claims.sub == id.to_string() {
+        return Err(api_error(
+            StatusCode::BAD_REQUEST,
+            "You cannot deactivate your own account",
+        ));
+    }
+
+    // Existing sessions end when the account is disabled or its password changes
+    let end_sessions = payload.is_active == Some(false) || payload.password.is_some();
+
     let user = user_repository::update(&state.db, id, payload)
         .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-        .ok_or(StatusCode::NOT_FOUND)?;
+        .map_err(user_error)?
+        .ok_or_else(|| api_error(StatusCode::NOT_FOUND, "User not found"))?;
+
+    if end_sessions {
+        token_repository::revoke_all_for_user(&state.db, id)
+            .await
+            .map_err(db_error)?;
+    }
+    audit
+        .record(
+            &state.db,
+            AuditAction::UserUpdated,
+            Some(&id.to_string()),
+            None,
+        )
+        .await;
 
+    info!(user_id = %id, updated_by = %claims.sub, "Updated user");
     Ok(Json(user))
 }
 
+/// DELETE /users/{id}
+///
+/// Soft delete; the user's history stays attributable.
 pub async fn delete_user(
     State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    audit: AuditContext,
     Path(id): Path<Uuid>,
-) -> Result<StatusCode, StatusCode> {
-    user_repository::delete(&state.db, id)
+) -> Result<StatusCode, ApiError> {
+    if claims.sub == id.to_string() {
+        return Err(api_error(
+            StatusCode::BAD_REQUEST,
+            "You cannot delete your own account",
+        ));
+    }
+
+    let deleted = user_repository::soft_delete(&state.db, id)
+        .await
+        .map_err(db_error)?;
+    if !deleted {
+        return Err(api_error(StatusCode::NOT_FOUND, "User not found"));
+    }
+    token_repository::revoke_all_for_user(&state.db, id)
         .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        .map_err(db_error)?;
+    audit
+        .record(
+            &state.db,
+            AuditAction::UserDeleted,
+            Some(&id.to_string()),
+            None,
+        )
+        .await;
 
+    info!(user_id = %id, deleted_by = %claims.sub, "Deleted user");
     Ok(StatusCode::NO_CONTENT)
 }
@@ -1,10 +1,13 @@
-use axum::http::{header, HeaderValue, Method};
+use axum::http::{HeaderValue, Method, header};
 use sea_orm::DatabaseConnection;
 use tower_http::cors::CorsLayer;
-use tracing::info;
+use tracing::{error, info};
 
-use crate::config::{database::init_db_pool, logger::init_logger, router::init_routes};
-use crate::services::CoreClient;
+use crate::config::{database::init_db_pool, jwt_keys, logger::init_logger, router::init_routes};
+use crate::repositories::{
+    idempotency_repository, token_repository, user_repository, user_repository::UserError,
+};
+use crate::services::{CoreClient, ExportStore, OidcClient};
 
 mod config;
 mod handlers;
@@ -17,6 +20,9 @@ mod utils;
 pub struct AppState {
     pub db: DatabaseConnection,
     pub core_client: CoreClient,
+    pub exports: ExportStore,
+    /// `None` while OIDC login is not configured
+    pub oidc: Option<OidcClient>,
 }
 
 /// Build CORS layer based on environment.
@@ -50,18 +56,63 @@ fn build_cors_layer() -> CorsLayer {
             header::ACCEPT,
             header::ORIGIN,
             header::HeaderName::from_static("x-device-key"),
+            header::HeaderName::from_static("idempotency-key"),
         ])
         .allow_credentials(true)
         .max_age(std::time::Duration::from_secs(3600))
 }
 
+/// Create the first admin account from `ADMIN_USERNAME` and `ADMIN_PASSWORD`
+/// while the users table is still empty. Refuses to start with an empty or
+/// example password.
+async fn bootstrap_admin(db: &DatabaseConnection) {
+    let (Ok(username), Ok(password)) = (
+        std::env::var("ADMIN_USERNAME"),
+        std::env::var("ADMIN_PASSWORD"),
+    ) else {
+        return;
+    };
+
+    match user_repository::bootstrap_admin(db, &username, &password).await {
+        Ok(true) => info!(username = %username, "Created initial admin account"),
+        Ok(false) => {}
+        Err(e @ UserError::InsecureAdminPassword) => {
+            error!(error = %e, "Refusing to start");
+            std::process::exit(1);
+        }
+        Err(e) => error!(error = %e, "Failed to create initial admin account"),
+    }
+}
+
 #[tokio::main]
 async fn main() {
     init_logger();
 
+    if let Err(e) = jwt_keys::init() {
+        error!(error = %e, "Refusing to start");
+        std::process::exit(1);
+    }
+    jwt_keys::spawn_reload_on_sighup();
+
     let pool = init_db_pool().await;
+    bootstrap_admin(&pool).await;
+    token_repository::spawn_purge_task(pool.clone(), std::time::Duration::from_secs(3600));
+    idempotency_repository::spawn_purge_task(pool.clone(), std::time::Duration::from_secs(3600));
     let core_client = CoreClient::from_env();
-    let state = AppState { db: pool, core_client };
+    let exports = match ExportStore::from_env() {
+        Ok(exports) => exports,
+        Err(e) => {
+            error!(error = %e, "Refusing to start");
+            std::process::exit(1);
+        }
+    };
+    exports.spawn_purge_task(std::time::Duration::from_secs(60));
+    let state = AppState {
+        db: pool,
+        core_client,
+        exports,
+        oidc: OidcClient::from_env(),
+    };
 
     let cors = build_cors_layer();
     let app = init_routes(state).layer(cors);
@@ -76,7 +127,11 @@ async fn main() {
 
     info!("Server running on http://{}", &address);
 
-    axum::serve(listener, app)
-        .await
-        .expect("Server failed to start");
+    // Client addresses are recorded in the audit log
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<std::net::SocketAddr>(),
+    )
+    .await
+    .expect("Server failed to start");
 }
@@ -0,0 +1,153 @@
+//! Invoice builder: prices Abrechnungsfälle and groups them into one
+//! Abrechnungsnachricht per Kostenträger and Abrechnungsmonat.
+
+use std::collections::BTreeMap;
+
+use chrono::NaiveDate;
+use rust_decimal::{Decimal, RoundingStrategy};
+use uuid::Uuid;
+
+use super::{
+    error::BuildError,
+    types::{Abrechnungsfall, Abrechnungsnachricht, Verarbeitungskennzeichen},
+};
+
+/// An Abrechnungsfall together with the Kasse it is billed to.
+#[derive(Debug, Clone)]
+pub struct Fall {
+    /// IK der Krankenkasse
+    pub kostentraeger_ik: String,
+    /// IK des Rechnungsempfängers (Kasse or its Datenannahmestelle)
+    pub rechnungsempfaenger_ik: String,
+    pub abrechnungsfall: Abrechnungsfall,
+}
+
+/// Institution codes of the invoicing party.
+#[derive(Debug, Clone)]
+pub struct Rechnungssteller {
+    pub leistungserbringer_ik: String,
+    pub rechnungssteller_ik: String,
+    pub zahlungsempfaenger_ik: String,
+}
+
+/// Invoice for one Kostenträger and month, waiting for its Rechnungsnummer.
+#[derive(Debug, Clone)]
+pub struct Rechnungsentwurf {
+    pub kostentraeger_ik: String,
+    pub rechnungsempfaenger_ik: String,
+    pub abrechnungsmonat: String,
+    pub gesamtbetrag: Decimal,
+    pub faelle: Vec<Abrechnungsfall>,
+}
+
+impl Rechnungsentwurf {
+    /// Completes the draft into an Abrechnungsnachricht with a fresh Abrechnung_ID.
+    pub fn into_nachricht(
+        self,
+        steller: &Rechnungssteller,
+        rechnungsnummer: String,
+        rechnungsdatum: NaiveDate,
+    ) -> Abrechnungsnachricht {
+        Abrechnungsnachricht {
+            leistungserbringer_ik: steller.leistungserbringer_ik.clone(),
+            rechnungssteller_ik: steller.rechnungssteller_ik.clone(),
+            zahlungsempfaenger_ik: steller.zahlungsempfaenger_ik.clone(),
+            rechnungsempfaenger_ik: self.rechnungsempfaenger_ik,
+            kostentraeger_ik: self.kostentraeger_ik,
+            verarbeitungskennzeichen: Verarbeitungskennzeichen::Abrechnung,
+            vorgaengerrechnung_datei_id: None,
+            rechnungsnummer,
+            abrechnung_id: Uuid::new_v4().to_string(),
+            rechnungsdatum: rechnungsdatum.format("%Y-%m-%d").to_string(),
+            gesamtbetrag: format_betrag(self.gesamtbetrag),
+            faelle: self.faelle,
+        }
+    }
+}
+
+/// Prices all cases and groups them per Kostenträger and Abrechnungsmonat.
+///
+/// Drafts are ordered by Kostentraeger_IK, then Abrechnungsmonat; cases keep
+/// their input order within a draft.
+pub fn group_faelle(faelle: Vec<Fall>) -> Result<Vec<Rechnungsentwurf>, BuildError> {
+    let mut drafts: BTreeMap<(String, String), Rechnungsentwurf> = BTreeMap::new();
+
+    for (i, mut fall) in faelle.into_iter().enumerate() {
+        let betrag = price_fall(&mut fall.abrechnungsfall, i)?;
+        let key = (
+            fall.kostentraeger_ik.clone(),
+            fall.abrechnungsfall.abrechnungsmonat.clone(),
+        );
+
+        let draft = drafts.entry(key).or_insert_with(|| Rechnungsentwurf {
+            kostentraeger_ik: fall.kostentraeger_ik.clone(),
+            rechnungsempfaenger_ik: fall.rechnungsempfaenger_ik.clone(),
+            abrechnungsmonat: fall.abrechnungsfall.abrechnungsmonat.clone(),
+            gesamtbetrag: Decimal::ZERO,
+            faelle: Vec::new(),
+        });
+
+        if draft.rechnungsempfaenger_ik != fall.rechnungsempfaenger_ik {
+            return Err(BuildError::ConflictingRechnungsempfaenger {
+                kostentraeger_ik: fall.kostentraeger_ik,
+            });
+        }
+
+        draft.gesamtbetrag += betrag;
+        draft.faelle.push(fall.abrechnungsfall);
+    }
+
+    Ok(drafts.into_values().collect())
+}
+
+/// Computes every Positionssumme and the Gesamtbetrag of a case in place.
+///
+/// `index` is the position of the case in the request, used to locate errors.
+pub fn price_fall(fall: &mut Abrechnungsfall, index: usize) -> Result<Decimal, BuildError> {
+    let mut gesamt = Decimal::ZERO;
+
+    for (e, einsatz) in fall.einsaetze.iter_mut().enumerate() {
+        for (p, position) in einsatz.positionen.iter_mut().enumerate() {
+            let path = format!(
+                "/Abrechnungsfall[{}]/Einsatz[{}]/Abrechnungsfall_Position[{}]",
+                index + 1,
+                e + 1,
+                p + 1
+            );
+            let menge = parse_amount(&position.menge, || format!("{path}/Menge"))?;
+            let preis = parse_amount(&position.einzelpreis, || format!("{path}/Einzelpreis"))?;
+
+            let summe = round_kaufmaennisch(menge * preis);
+            position.positionssumme = format_betrag(summe);
+            gesamt += summe;
+        }
+    }
+
+    fall.gesamtbetrag = format_betrag(gesamt);
+    Ok(gesamt)
+}
+
+/// Builds a Rechnungsnummer from the invoice year and its running number.
+pub fn rechnungsnummer(jahr: i32, laufende_nummer: i64) -> String {
+    format!("{}-{:06}", jahr, laufende_nummer)
+}
+
+/// Formats an amount with exactly two fraction digits.
+pub fn format_betrag(betrag: Decimal) -> String {
+    format!("{:.2}", round_kaufmaennisch(betrag))
+}
+
+/// Rounds half away from zero to cents ("kaufmännisch gerundet").
+fn round_kaufmaennisch(value: Decimal) -> Decimal {
+    value.round_dp_with_strategy(2, RoundingStrategy::MidpointAwayFromZero)
+}
+
+fn parse_amount(value: &str, xpath: impl FnOnce() -> String) -> Result<Decimal, BuildError> {
+    value
+        .trim()
+        .parse::<Decimal>()
+        .map_err(|_| BuildError::InvalidAmount {
+            xpath: xpath(),
+            value: value.to_string(),
+        })
+}
@@ -0,0 +1,41 @@
+//! Error types for building Abrechnungen.
+
+use thiserror::Error;
+
+use crate::models::{leistungsnachweis::ParseError, xsd::Violation};
+
+/// Errors that can occur when building or serializing an Abrechnung.
+#[derive(Error, Debug)]
+pub enum BuildError {
+    /// XML deserialization error
+    #[error("XML parsing error: {0}")]
+    XmlDeserialize(#[from] quick_xml::DeError),
+
+    /// XML serialization error
+    #[error("XML serialization error: {0}")]
+    XmlSerialize(#[from] quick_xml::SeError),
+
+    /// Schema validation error, one entry per violated rule
+    #[error("Validation error: {}", Violation::join(.0))]
+    Validation(Vec<Violation>),
+
+    /// Menge or Einzelpreis is not a decimal number
+    #[error("Invalid amount `{value}` at {xpath}")]
+    InvalidAmount { xpath: String, value: String },
+
+    /// Leistungsnachweis_Datei is not base64-encoded UTF-8
+    #[error("Invalid Leistungsnachweis_Datei: {0}")]
+    InvalidDatei(String),
+
+    /// The embedded Leistungsnachweis is not a valid HKP_LNW document
+    #[error("Invalid Leistungsnachweis: {0}")]
+    Leistungsnachweis(#[from] ParseError),
+
+    /// Priced Einsätze do not match the Einsätze of the Leistungsnachweis
+    #[error("Einsätze do not match the Leistungsnachweis: {}", Violation::join(.0))]
+    EinsatzMismatch(Vec<Violation>),
+
+    /// Cases for the same Kostenträger name different invoice recipients
+    #[error("Conflicting Rechnungsempfaenger_IK for Kostentraeger_IK {kostentraeger_ik}")]
+    ConflictingRechnungsempfaenger { kostentraeger_ik: String },
+}
@@ -0,0 +1,281 @@
+//! Abrechnungsfall builder: combines one signed HKP Leistungsnachweis with
+//! prescription and price data.
+//!
+//! The priced Einsätze are cross-checked against the Einsätze of the embedded
+//! Leistungsnachweis, so an invoice can never bill more than was signed.
+
+use base64::Engine;
+use chrono::{DateTime, Duration, NaiveDateTime, NaiveTime};
+use rust_decimal::Decimal;
+use uuid::Uuid;
+
+use crate::models::{leistungsnachweis::hkp, xsd::Violation};
+
+use super::{
+    builder::price_fall,
+    error::BuildError,
+    types::{Abrechnungsfall, AbrechnungsfallPosition, Einsatz},
+};
+
+/// Prescription data of an Abrechnungsfall.
+#[derive(Debug, Clone)]
+pub struct Verordnung {
+    /// Genehmigungskennzeichen der Krankenkasse (alternative to `evo_ids`)
+    pub genehmigungskennzeichen: Option<String>,
+    /// IDs elektronischer Verordnungen
+    pub evo_ids: Vec<String>,
+    pub betriebsstaettennummer: String,
+    pub lebenslange_arztnummer: String,
+    /// Verordnungsdatum (`JJJJ-MM-TT`)
+    pub verordnungsdatum: String,
+    pub unfallkennzeichen: Option<String>,
+    pub kennzeichen_bvg: String,
+    pub diagnoseschluessel: Vec<String>,
+    pub kennz_bes_versorgungsform: Option<String>,
+}
+
+/// Builds a priced, schema-valid Abrechnungsfall from a signed Leistungsnachweis.
+///
+/// `leistungsnachweis_datei` is the base64-encoded HKP_LNW document as returned
+/// by the sign endpoint. Leistungsnachweis_ID and Krankenversichertennummer are
+/// taken from it. `einsaetze` carry the price data; an empty `ende` or empty
+/// Beschäftigtennummern are filled from the Leistungsnachweis, every other
+/// field must match it. `index` is the position of the case in the request,
+/// used to locate errors.
+pub fn build_abrechnungsfall(
+    leistungsnachweis_datei: &str,
+    abrechnungsmonat: &str,
+    verordnung: Verordnung,
+    mut einsaetze: Vec<Einsatz>,
+    index: usize,
+) -> Result<Abrechnungsfall, BuildError> {
+    let lnw = decode_leistungsnachweis(leistungsnachweis_datei)?;
+    let root = format!("/Abrechnungsfall[{}]", index + 1);
+
+    let violations = cross_check(&root, &lnw, abrechnungsmonat, &mut einsaetze);
+    if !violations.is_empty() {
+        return Err(BuildError::EinsatzMismatch(violations));
+    }
+
+    let mut fall = Abrechnungsfall {
+        id: Uuid::new_v4().to_string(),
+        leistungsnachweis_datei: leistungsnachweis_datei.trim().to_string(),
+        leistungsnachweis_id: lnw.id,
+        abrechnungsmonat: abrechnungsmonat.to_string(),
+        genehmigungskennzeichen: verordnung.genehmigungskennzeichen,
+        evo_ids: verordnung.evo_ids,
+        betriebsstaettennummer: verordnung.betriebsstaettennummer,
+        lebenslange_arztnummer: verordnung.lebenslange_arztnummer,
+        verordnungsdatum: verordnung.verordnungsdatum,
+        unfallkennzeichen: verordnung.unfallkennzeichen,
+        kennzeichen_bvg: verordnung.kennzeichen_bvg,
+        diagnoseschluessel: verordnung.diagnoseschluessel,
+        krankenversichertennummer: lnw.krankenversichertennummer,
+        kennz_bes_versorgungsform: verordnung.kennz_bes_versorgungsform,
+        gesamtbetrag: String::new(),
+        einsaetze,
+    };
+    price_fall(&mut fall, index)?;
+
+    // Schema violations are reported relative to the standalone document
+    match fall.to_xml() {
+        Ok(_) => Ok(fall),
+        Err(BuildError::Validation(violations)) => Err(BuildError::Validation(
+            violations
+                .into_iter()
+                .map(|v| Violation {
+                    xpath: v.xpath.replacen("/Abrechnungsfall", &root, 1),
+                    message: v.message,
+                })
+                .collect(),
+        )),
+        Err(e) => Err(e),
+    }
+}
+
+/// Decodes and validates the embedded HKP Leistungsnachweis.
+fn decode_leistungsnachweis(datei: &str) -> Result<hkp::Leistungsnachweis, BuildError> {
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(datei.trim())
+        .map_err(|e| BuildError::InvalidDatei(e.to_string()))?;
+    let xml = String::from_utf8(bytes).map_err(|e| BuildError::InvalidDatei(e.to_string()))?;
+
+    Ok(hkp::Leistungsnachweis::from_xml(&xml)?)
+}
+
+/// Compares the priced Einsätze with the Einsätze of the Leistungsnachweis and
+/// fills in values the caller left empty.
+fn cross_check(
+    root: &str,
+    lnw: &hkp::Leistungsnachweis,
+    abrechnungsmonat: &str,
+    einsaetze: &mut [Einsatz],
+) -> Vec<Violation> {
+    let mut violations = Vec::new();
+
+    if einsaetze.len() != lnw.einsaetze.len() {
+        violations.push(Violation::new(
+            format!("{root}/Einsatz"),
+            format!(
+                "Leistungsnachweis has {} Einsätze, {} were priced",
+                lnw.einsaetze.len(),
+                einsaetze.len()
+            ),
+        ));
+    }
+
+    for (e, (einsatz, signed)) in einsaetze.iter_mut().zip(&lnw.einsaetze).enumerate() {
+        let path = format!("{root}/Einsatz[{}]", e + 1);
+
+        let beginn = parse_date_time(&einsatz.beginn);
+        let signed_beginn = parse_date_time(&signed.beginn);
+        if beginn.is_none() || beginn != signed_beginn {
+            violations.push(Violation::new(
+                format!("{path}/Beginn_Datum_Uhrzeit"),
+                format!(
+                    "`{}` does not match Beginn `{}` of the Leistungsnachweis",
+                    einsatz.beginn, signed.beginn
+                ),
+            ));
+        }
+        if let Some(beginn) = signed_beginn
+            && beginn.format("%Y%m").to_string() != abrechnungsmonat
+        {
+            violations.push(Violation::new(
+                format!("{path}/Beginn_Datum_Uhrzeit"),
+                format!(
+                    "Einsatz on {} is outside Abrechnungsmonat {}",
+                    beginn.date(),
+                    abrechnungsmonat
+                ),
+            ));
+        }
+
+        let signed_ende = signed_beginn
+            .zip(signed.ende.as_deref())
+            .and_then(|(b, e)| ende_date_time(b, e));
+        match (&einsatz.ende, signed_ende) {
+            (None, Some(ende)) => {
+                einsatz.ende = Some(ende.format("%Y-%m-%dT%H:%M:%S").to_string());
+            }
+            (Some(ende), signed_ende) if parse_date_time(ende) != signed_ende => {
+                violations.push(Violation::new(
+                    format!("{path}/Ende_Datum_Uhrzeit"),
+                    format!(
+                        "`{}` does not match Ende `{}` of the Leistungsnachweis",
+                        ende,
+                        signed.ende.as_deref().unwrap_or("-")
+                    ),
+                ));
+            }
+            _ => {}
+        }
+
+        if einsatz.positionen.len() != signed.positionen.len() {
+            violations.push(Violation::new(
+                format!("{path}/Abrechnungsfall_Position"),
+                format!(
+                    "Einsatz has {} positions in the Leistungsnachweis, {} were priced",
+                    signed.positionen.len(),
+                    einsatz.positionen.len()
+                ),
+            ));
+        }
+
+        for (p, (position, signed)) in einsatz
+            .positionen
+            .iter_mut()
+            .zip(&signed.positionen)
+            .enumerate()
+        {
+            let path = format!("{path}/Abrechnungsfall_Position[{}]", p + 1);
+            check_position(&path, position, signed, &mut violations);
+        }
+    }
+
+    violations
+}
+
+fn check_position(
+    path: &str,
+    position: &mut AbrechnungsfallPosition,
+    signed: &hkp::Position,
+    violations: &mut Vec<Violation>,
+) {
+    if position.position.positions_nr != signed.positions_nr {
+        violations.push(Violation::new(
+            format!("{path}/Position/Positions_Nr"),
+            format!(
+                "`{}` does not match Positions_Nr `{}` of the Leistungsnachweis",
+                position.position.positions_nr, signed.positions_nr
+            ),
+        ));
+    }
+
+    // Unparseable amounts are reported by `price_fall`
+    if let (Ok(menge), Ok(signed_menge)) = (
+        position.menge.trim().parse::<Decimal>(),
+        signed.menge.trim().parse::<Decimal>(),
+    ) && menge != signed_menge
+    {
+        violations.push(Violation::new(
+            format!("{path}/Menge"),
+            format!(
+                "`{}` does not match Menge `{}` of the Leistungsnachweis",
+                position.menge, signed.menge
+            ),
+        ));
+    }
+
+    if position.beschaeftigtennummern.is_empty() {
+        position.beschaeftigtennummern = signed.beschaeftigtennummern.clone();
+    } else if !same_members(
+        &position.beschaeftigtennummern,
+        &signed.beschaeftigtennummern,
+    ) {
+        violations.push(Violation::new(
+            format!("{path}/Beschaeftigtennummer"),
+            format!(
+                "{:?} does not match Beschäftigtennummern {:?} of the Leistungsnachweis",
+                position.beschaeftigtennummern, signed.beschaeftigtennummern
+            ),
+        ));
+    }
+}
+
+fn same_members(a: &[String], b: &[String]) -> bool {
+    let mut a: Vec<&str> = a.iter().map(String::as_str).collect();
+    let mut b: Vec<&str> = b.iter().map(String::as_str).collect();
+    a.sort_unstable();
+    b.sort_unstable();
+    a == b
+}
+
+/// Parses an xs:dateTime, ignoring any timezone designator.
+fn parse_date_time(value: &str) -> Option<NaiveDateTime> {
+    let value = value.trim();
+    NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%.f")
+        .ok()
+        .or_else(|| {
+            DateTime::parse_from_rfc3339(value)
+                .ok()
+                .map(|d| d.naive_local())
+        })
+}
+
+/// Combines the Beginn of an Einsatz with its Ende_Uhrzeit (xs:time).
+///
+/// An Ende before the Beginn means the Einsatz ran past midnight.
+fn ende_date_time(beginn: NaiveDateTime, ende: &str) -> Option<NaiveDateTime> {
+    let ende = ende.trim();
+    // Strip a timezone designator such as `Z` or `+01:00`
+    let time = NaiveTime::parse_from_str(ende, "%H:%M:%S%.f")
+        .ok()
+        .or_else(|| NaiveTime::parse_from_str(ende.get(..8)?, "%H:%M:%S").ok())?;
+
+    let mut date_time = beginn.date().and_time(time);
+    if time < beginn.time() {
+        date_time += Duration::days(1);
+    }
+    Some(date_time)
+}
@@ -0,0 +1,382 @@
+//! Abrechnung der häuslichen Krankenpflege (SGB V § 302)
+//!
+//! Schemas: HKP_ARN_1.0.0.xsd (Abrechnungsnachricht) and HKP_ARF_1.0.0.xsd
+//! (Abrechnungsfall)
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use crate::models::abrechnung::{builder, fall_builder};
+//!
+//! let fall = fall_builder::build_abrechnungsfall(&xml_base64, "202411", verordnung, einsaetze, 0)?;
+//! for entwurf in builder::group_faelle(faelle)? {
+//!     let nachricht = entwurf.into_nachricht(&steller, nummer, heute);
+//!     let xml = nachricht.to_xml()?;
+//! }
+//! ```
+
+pub mod builder;
+pub mod error;
+pub mod fall_builder;
+mod parser;
+pub mod types;
+
+pub use error::BuildError;
+pub use types::{AbrechnungsfallPosition, Einsatz, Einzelleistung, Position};
+
+#[cfg(test)]
+mod tests {
+    use base64::Engine;
+    use chrono::NaiveDate;
+    use rust_decimal::Decimal;
+
+    use super::{
+        builder::{Fall, Rechnungssteller, group_faelle},
+        fall_builder::{Verordnung, build_abrechnungsfall},
+        types::{Abrechnungsfall, Abrechnungsnachricht},
+        *,
+    };
+    use crate::models::leistungsnachweis::{
+        hkp,
+        types::{ArtDerUnterschrift, Dateityp, Unterschrift},
+    };
+
+    fn sample_fall(kostentraeger_ik: &str, monat: &str, einzelpreis: &str) -> Fall {
+        Fall {
+            kostentraeger_ik: kostentraeger_ik.into(),
+            rechnungsempfaenger_ik: kostentraeger_ik.into(),
+            abrechnungsfall: Abrechnungsfall {
+                id: "0b6f1c7e-2a3d-4e5f-8a9b-0c1d2e3f4a5b".into(),
+                leistungsnachweis_datei: "PExlaXN0dW5nc25hY2h3ZWlzLz4=".into(),
+                leistungsnachweis_id: "550e8400-e29b-41d4-a716-446655440000".into(),
+                abrechnungsmonat: monat.into(),
+                genehmigungskennzeichen: Some("G-2024-001".into()),
+                evo_ids: Vec::new(),
+                betriebsstaettennummer: "123456700".into(),
+                lebenslange_arztnummer: "987654300".into(),
+                verordnungsdatum: "2024-10-28".into(),
+                unfallkennzeichen: None,
+                kennzeichen_bvg: "0".into(),
+                diagnoseschluessel: vec!["E11.9".into()],
+                krankenversichertennummer: "A123456789".into(),
+                kennz_bes_versorgungsform: None,
+                gesamtbetrag: String::new(),
+                einsaetze: vec![Einsatz {
+                    beginn: "2024-11-15T08:00:00".into(),
+                    ende: None,
+                    positionen: vec![
+                        AbrechnungsfallPosition {
+                            position: Position {
+                                positions_nr: "030101".into(),
+                                einzelleistung: None,
+                            },
+                            menge: "1".into(),
+                            le_abr_code: "10".into(),
+                            tarif_kennz_bereich: "03".into(),
+                            tarif_kennz_sonder: "000".into(),
+                            einzelpreis: einzelpreis.into(),
+                            positionssumme: String::new(),
+                            beschaeftigtennummern: vec!["987654321".into()],
+                        },
+                        AbrechnungsfallPosition {
+                            position: Position {
+                                positions_nr: "030201".into(),
+                                einzelleistung: None,
+                            },
+                            menge: "1.5".into(),
+                            le_abr_code: "10".into(),
+                            tarif_kennz_bereich: "03".into(),
+                            tarif_kennz_sonder: "000".into(),
+                            einzelpreis: "3.33".into(),
+                            positionssumme: String::new(),
+                            beschaeftigtennummern: vec!["987654321".into()],
+                        },
+                    ],
+                }],
+            },
+        }
+    }
+
+    fn steller() -> Rechnungssteller {
+        Rechnungssteller {
+            leistungserbringer_ik: "123456789".into(),
+            rechnungssteller_ik: "123456789".into(),
+            zahlungsempfaenger_ik: "123456789".into(),
+        }
+    }
+
+    #[test]
+    fn test_positions_are_priced_and_rounded() {
+        let entwuerfe = group_faelle(vec![sample_fall("101234567", "202411", "12.50")]).unwrap();
+        let fall = &entwuerfe[0].faelle[0];
+
+        // 1.5 × 3.33 = 4.995 → 5.00 (kaufmännisch gerundet)
+        assert_eq!(fall.einsaetze[0].positionen[0].positionssumme, "12.50");
+        assert_eq!(fall.einsaetze[0].positionen[1].positionssumme, "5.00");
+        assert_eq!(fall.gesamtbetrag, "17.50");
+        assert_eq!(entwuerfe[0].gesamtbetrag, Decimal::new(1750, 2));
+    }
+
+    #[test]
+    fn test_grouping_per_kostentraeger_and_month() {
+        let entwuerfe = group_faelle(vec![
+            sample_fall("109999999", "202411", "10.00"),
+            sample_fall("101234567", "202411", "10.00"),
+            sample_fall("101234567", "202410", "10.00"),
+            sample_fall("101234567", "202411", "20.00"),
+        ])
+        .unwrap();
+
+        let keys: Vec<(&str, &str, usize, String)> = entwuerfe
+            .iter()
+            .map(|e| {
+                (
+                    e.kostentraeger_ik.as_str(),
+                    e.abrechnungsmonat.as_str(),
+                    e.faelle.len(),
+                    builder::format_betrag(e.gesamtbetrag),
+                )
+            })
+            .collect();
+        assert_eq!(
+            keys,
+            vec![
+                ("101234567", "202410", 1, "15.00".to_string()),
+                ("101234567", "202411", 2, "40.00".to_string()),
+                ("109999999", "202411", 1, "15.00".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_conflicting_rechnungsempfaenger() {
+        let mut other = sample_fall("101234567", "202411", "10.00");
+        other.rechnungsempfaenger_ik = "660000000".into();
+
+        let result = group_faelle(vec![sample_fall("101234567", "202411", "10.00"), other]);
+        assert!(matches!(
+            result,
+            Err(BuildError::ConflictingRechnungsempfaenger { .. })
+        ));
+    }
+
+    #[test]
+    fn test_invalid_amount_is_located() {
+        let Err(BuildError::InvalidAmount { xpath, .. }) =
+            group_faelle(vec![sample_fall("101234567", "202411", "zwölf")])
+        else {
+            panic!("expected invalid amount");
+        };
+        assert_eq!(
+            xpath,
+            "/Abrechnungsfall[1]/Einsatz[1]/Abrechnungsfall_Position[1]/Einzelpreis"
+        );
+    }
+
+    #[test]
+    fn test_nachricht_is_schema_valid() {
+        let entwurf = group_faelle(vec![sample_fall("101234567", "202411", "12.50")])
+            .unwrap()
+            .remove(0);
+        let nachricht = entwurf.into_nachricht(
+            &steller(),
+            builder::rechnungsnummer(2024, 42),
+            NaiveDate::from_ymd_opt(2024, 12, 2).unwrap(),
+        );
+
+        assert_eq!(nachricht.rechnungsnummer, "2024-000042");
+        let xml = nachricht.to_xml().unwrap();
+        assert!(xml.contains("<arn:Gesamtbetrag>17.50</arn:Gesamtbetrag>"));
+        assert!(xml.contains("<arf:Positionssumme>5.00</arf:Positionssumme>"));
+        assert_eq!(Abrechnungsnachricht::from_xml(&xml).unwrap(), nachricht);
+    }
+
+    #[test]
+    fn test_nachricht_violations_are_reported() {
+        let mut fall = sample_fall("101234567", "202411", "12.50");
+        fall.abrechnungsfall.lebenslange_arztnummer = "12345".into();
+        let nachricht = group_faelle(vec![fall]).unwrap().remove(0).into_nachricht(
+            &steller(),
+            builder::rechnungsnummer(2024, 1),
+            NaiveDate::from_ymd_opt(2024, 12, 2).unwrap(),
+        );
+
+        let Err(BuildError::Validation(violations)) = nachricht.to_xml() else {
+            panic!("expected validation error");
+        };
+        assert_eq!(violations.len(), 1);
+        assert_eq!(
+            violations[0].xpath,
+            "/Abrechnungsnachricht/Abrechnungsfall[1]/Lebenslange_Arztnummer"
+        );
+    }
+
+    /// Signed HKP Leistungsnachweis matching the Einsatz of `sample_fall`.
+    fn signed_leistungsnachweis() -> hkp::Leistungsnachweis {
+        let position = |nr: &str, menge: &str| hkp::Position {
+            positions_nr: nr.into(),
+            menge: menge.into(),
+            beschaeftigtennummern: vec!["987654321".into()],
+        };
+        hkp::Leistungsnachweis {
+            leistungserbringer_ik: "123456789".into(),
+            krankenversichertennummer: "B987654321".into(),
+            verantwortliche_fachkraft: "111222333".into(),
+            einsaetze: vec![hkp::Einsatz {
+                beginn: "2024-11-15T08:00:00".into(),
+                ende: Some("08:30:00".into()),
+                positionen: vec![position("030101", "1.00"), position("030201", "1.50")],
+            }],
+            unterschrift_versicherter: hkp::UnterschriftVersicherter {
+                art: ArtDerUnterschrift::HandschriftlichDigital,
+                datum_uhrzeit: Some("2024-11-15T08:30:00".into()),
+                unterschrift: Some(Unterschrift {
+                    datei: "iVBORw0KGgo=".into(),
+                    dateityp: Dateityp::Png,
+                }),
+                fehlende_unterschrift: None,
+            },
+            id: "7d444840-9dc0-11d1-b245-5ffdce74fad2".into(),
+        }
+    }
+
+    fn encode(lnw: &hkp::Leistungsnachweis) -> String {
+        base64::engine::general_purpose::STANDARD.encode(lnw.to_xml().unwrap())
+    }
+
+    fn verordnung() -> Verordnung {
+        Verordnung {
+            genehmigungskennzeichen: Some("G-2024-001".into()),
+            evo_ids: Vec::new(),
+            betriebsstaettennummer: "123456700".into(),
+            lebenslange_arztnummer: "987654300".into(),
+            verordnungsdatum: "2024-10-28".into(),
+            unfallkennzeichen: None,
+            kennzeichen_bvg: "0".into(),
+            diagnoseschluessel: vec!["E11.9".into()],
+            kennz_bes_versorgungsform: None,
+        }
+    }
+
+    fn priced_einsaetze() -> Vec<Einsatz> {
+        let mut einsaetze = sample_fall("101234567", "202411", "12.50")
+            .abrechnungsfall
+            .einsaetze;
+        einsaetze[0].positionen[1].beschaeftigtennummern.clear();
+        einsaetze
+    }
+
+    #[test]
+    fn test_fall_from_signed_leistungsnachweis() {
+        let datei = encode(&signed_leistungsnachweis());
+        let fall =
+            build_abrechnungsfall(&datei, "202411", verordnung(), priced_einsaetze(), 0).unwrap();
+
+        assert_eq!(fall.leistungsnachweis_datei, datei);
+        assert_eq!(
+            fall.leistungsnachweis_id,
+            "7d444840-9dc0-11d1-b245-5ffdce74fad2"
+        );
+        assert_eq!(fall.krankenversichertennummer, "B987654321");
+        assert_eq!(
+            fall.einsaetze[0].ende.as_deref(),
+            Some("2024-11-15T08:30:00")
+        );
+        assert_eq!(
+            fall.einsaetze[0].positionen[1].beschaeftigtennummern,
+            vec!["987654321".to_string()]
+        );
+        assert_eq!(fall.gesamtbetrag, "17.50");
+    }
+
+    #[test]
+    fn test_fall_ende_past_midnight() {
+        let mut lnw = signed_leistungsnachweis();
+        lnw.einsaetze[0].beginn = "2024-11-30T23:45:00".into();
+        lnw.einsaetze[0].ende = Some("00:15:00".into());
+        let mut einsaetze = priced_einsaetze();
+        einsaetze[0].beginn = "2024-11-30T23:45:00".into();
+
+        let fall =
+            build_abrechnungsfall(&encode(&lnw), "202411", verordnung(), einsaetze, 0).unwrap();
+        assert_eq!(
+            fall.einsaetze[0].ende.as_deref(),
+            Some("2024-12-01T00:15:00")
+        );
+    }
+
+    #[test]
+    fn test_fall_positions_must_match_einsaetze() {
+        let mut einsaetze = priced_einsaetze();
+        einsaetze[0].positionen[0].position.positions_nr = "030102".into();
+        einsaetze[0].positionen[1].menge = "2".into();
+
+        let Err(BuildError::EinsatzMismatch(violations)) = build_abrechnungsfall(
+            &encode(&signed_leistungsnachweis()),
+            "202412",
+            verordnung(),
+            einsaetze,
+            1,
+        ) else {
+            panic!("expected mismatch");
+        };
+
+        let xpaths: Vec<&str> = violations.iter().map(|v| v.xpath.as_str()).collect();
+        assert_eq!(
+            xpaths,
+            vec![
+                "/Abrechnungsfall[2]/Einsatz[1]/Beginn_Datum_Uhrzeit",
+                "/Abrechnungsfall[2]/Einsatz[1]/Abrechnungsfall_Position[1]/Position/Positions_Nr",
+                "/Abrechnungsfall[2]/Einsatz[1]/Abrechnungsfall_Position[2]/Menge",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fall_missing_einsatz() {
+        let mut lnw = signed_leistungsnachweis();
+        lnw.einsaetze.push(lnw.einsaetze[0].clone());
+
+        let result =
+            build_abrechnungsfall(&encode(&lnw), "202411", verordnung(), priced_einsaetze(), 0);
+        assert!(
+            matches!(result, Err(BuildError::EinsatzMismatch(v)) if v[0].xpath == "/Abrechnungsfall[1]/Einsatz")
+        );
+    }
+
+    #[test]
+    fn test_fall_rejects_invalid_datei() {
+        let result = build_abrechnungsfall(
+            "kein base64!",
+            "202411",
+            verordnung(),
+            priced_einsaetze(),
+            0,
+        );
+        assert!(matches!(result, Err(BuildError::InvalidDatei(_))));
+
+        let pfl = base64::engine::general_purpose::STANDARD.encode("<Leistungsnachweis/>");
+        let result = build_abrechnungsfall(&pfl, "202411", verordnung(), priced_einsaetze(), 0);
+        assert!(matches!(result, Err(BuildError::Leistungsnachweis(_))));
+    }
+
+    #[test]
+    fn test_fall_schema_violations_are_located() {
+        let mut verordnung = verordnung();
+        verordnung.betriebsstaettennummer = "12345".into();
+
+        let Err(BuildError::Validation(violations)) = build_abrechnungsfall(
+            &encode(&signed_leistungsnachweis()),
+            "202411",
+            verordnung,
+            priced_einsaetze(),
+            2,
+        ) else {
+            panic!("expected validation error");
+        };
+        assert_eq!(
+            violations[0].xpath,
+            "/Abrechnungsfall[3]/Betriebsstaettennummer"
+        );
+    }
+}
@@ -0,0 +1,85 @@
+//! XML serialization for the Abrechnungsnachricht and Abrechnungsfall.
+
+use serde::Serialize;
+
+use crate::models::xsd::{self, NS_HKP_ARF, NS_HKP_ARN, QName};
+
+use super::{
+    error::BuildError,
+    types::{Abrechnungsfall, Abrechnungsnachricht},
+};
+
+/// Root element with the `arn` and `arf` namespace declarations.
+#[derive(Serialize)]
+struct Namespaced<'a, T> {
+    #[serde(rename = "@xmlns:arn", skip_serializing_if = "Option::is_none")]
+    arn: Option<&'static str>,
+    #[serde(rename = "@xmlns:arf")]
+    arf: &'static str,
+    #[serde(flatten)]
+    inner: &'a T,
+}
+
+impl Abrechnungsnachricht {
+    /// Parse an Abrechnungsnachricht previously written by [`Self::to_xml`].
+    pub fn from_xml(xml: &str) -> Result<Self, BuildError> {
+        Ok(quick_xml::de::from_str(xml)?)
+    }
+
+    /// Serialize to a standalone XML document.
+    ///
+    /// HKP_ARN declares no global element, so the output is validated as an
+    /// `arn:Abrechnungsnachricht` root of type `Abrechnungsnachricht_Ctp`.
+    pub fn to_xml(&self) -> Result<String, BuildError> {
+        let ns = Namespaced {
+            arn: Some(NS_HKP_ARN),
+            arf: NS_HKP_ARF,
+            inner: self,
+        };
+        to_validated_xml(
+            &ns,
+            "arn:Abrechnungsnachricht",
+            QName::new(NS_HKP_ARN, "Abrechnungsnachricht"),
+            QName::new(NS_HKP_ARN, "Abrechnungsnachricht_Ctp"),
+        )
+    }
+}
+
+impl Abrechnungsfall {
+    /// Serialize to a standalone `arf:Abrechnungsfall` document of type
+    /// `Abrechnungsfall_Ctp` and validate it.
+    pub fn to_xml(&self) -> Result<String, BuildError> {
+        let ns = Namespaced {
+            arn: None,
+            arf: NS_HKP_ARF,
+            inner: self,
+        };
+        to_validated_xml(
+            &ns,
+            "arf:Abrechnungsfall",
+            QName::new(NS_HKP_ARF, "Abrechnungsfall"),
+            QName::new(NS_HKP_ARF, "Abrechnungsfall_Ctp"),
+        )
+    }
+}
+
+fn to_validated_xml<T: Serialize>(
+    value: &T,
+    root_tag: &str,
+    root: QName,
+    ty: QName,
+) -> Result<String, BuildError> {
+    let mut body = String::new();
+    let serializer = quick_xml::se::Serializer::with_root(&mut body, Some(root_tag))?;
+    value.serialize(serializer)?;
+
+    let mut xml = String::from(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    xml.push('\n');
+    xml.push_str(&body);
+
+    let violations = xsd::schemas().validate_as(&xml, &root, &ty);
+    if !violations.is_empty() {
+        return Err(BuildError::Validation(violations));
+    }
+    Ok(xml)
+}
@@ -0,0 +1,197 @@
+//! Abrechnungsfall structures (HKP_ARF_1.0.0.xsd, prefix `arf`).
+
+use serde::{Deserialize, Serialize};
+
+/// Abrechnungsfall: one signed Leistungsnachweis with prescription and prices
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Abrechnungsfall {
+    /// UUID, unique per Rechnungsnummer
+    #[serde(rename = "arf:Abrechnungsfall_ID", alias = "Abrechnungsfall_ID")]
+    pub id: String,
+
+    /// Signed Leistungsnachweis XML, base64-encoded
+    #[serde(
+        rename = "arf:Leistungsnachweis_Datei",
+        alias = "Leistungsnachweis_Datei"
+    )]
+    pub leistungsnachweis_datei: String,
+
+    /// Leistungsnachweis-ID of the embedded document
+    #[serde(rename = "arf:Leistungsnachweis_ID", alias = "Leistungsnachweis_ID")]
+    pub leistungsnachweis_id: String,
+
+    /// Abrechnungsmonat im Format JJJJMM
+    #[serde(rename = "arf:Abrechnungsmonat", alias = "Abrechnungsmonat")]
+    pub abrechnungsmonat: String,
+
+    /// Genehmigungskennzeichen der Krankenkasse, max 20 chars
+    /// (alternative to `evo_ids`)
+    #[serde(
+        rename = "arf:Genehmigungskennzeichen",
+        alias = "Genehmigungskennzeichen",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub genehmigungskennzeichen: Option<String>,
+
+    /// IDs elektronischer Verordnungen, 22-256 chars each
+    #[serde(
+        rename = "arf:eVO_ID",
+        alias = "eVO_ID",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub evo_ids: Vec<String>,
+
+    /// Betriebsstättennummer des verordnenden Arztes, 9 digits
+    #[serde(
+        rename = "arf:Betriebsstaettennummer",
+        alias = "Betriebsstaettennummer"
+    )]
+    pub betriebsstaettennummer: String,
+
+    /// Lebenslange Arztnummer, 9 digits
+    #[serde(
+        rename = "arf:Lebenslange_Arztnummer",
+        alias = "Lebenslange_Arztnummer"
+    )]
+    pub lebenslange_arztnummer: String,
+
+    /// Verordnungsdatum (xs:date, `JJJJ-MM-TT`)
+    #[serde(rename = "arf:Verordnungsdatum", alias = "Verordnungsdatum")]
+    pub verordnungsdatum: String,
+
+    /// Unfallkennzeichen, 1 char
+    #[serde(
+        rename = "arf:Unfallkennzeichen",
+        alias = "Unfallkennzeichen",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub unfallkennzeichen: Option<String>,
+
+    /// Kennzeichen BVG/Sonstiges/SER, 1 char
+    #[serde(
+        rename = "arf:Kennzeichen_BVG_Sonstiges_SER",
+        alias = "Kennzeichen_BVG_Sonstiges_SER"
+    )]
+    pub kennzeichen_bvg: String,
+
+    /// 1-n ICD-10-GM Diagnoseschlüssel
+    #[serde(rename = "arf:Diagnoseschluessel", alias = "Diagnoseschluessel")]
+    pub diagnoseschluessel: Vec<String>,
+
+    /// Krankenversichertennummer, capital letter followed by 9 digits
+    #[serde(
+        rename = "arf:Krankenversichertennummer",
+        alias = "Krankenversichertennummer"
+    )]
+    pub krankenversichertennummer: String,
+
+    /// Kennzeichen besondere Versorgungsform, max 25 chars
+    #[serde(
+        rename = "arf:Kennz_Bes_Versorgungsform",
+        alias = "Kennz_Bes_Versorgungsform",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub kennz_bes_versorgungsform: Option<String>,
+
+    /// Summe aller Positionssummen
+    #[serde(rename = "arf:Gesamtbetrag", alias = "Gesamtbetrag")]
+    pub gesamtbetrag: String,
+
+    /// 1-n Einsätze
+    #[serde(rename = "arf:Einsatz", alias = "Einsatz")]
+    pub einsaetze: Vec<Einsatz>,
+}
+
+/// Abgerechneter Einsatz
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Einsatz {
+    /// Beginn des Einsatzes (xs:dateTime)
+    #[serde(rename = "arf:Beginn_Datum_Uhrzeit", alias = "Beginn_Datum_Uhrzeit")]
+    pub beginn: String,
+
+    /// Ende des Einsatzes (xs:dateTime), bei Zeitvergütung
+    #[serde(
+        rename = "arf:Ende_Datum_Uhrzeit",
+        alias = "Ende_Datum_Uhrzeit",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub ende: Option<String>,
+
+    /// 1-n Abrechnungspositionen
+    #[serde(
+        rename = "arf:Abrechnungsfall_Position",
+        alias = "Abrechnungsfall_Position"
+    )]
+    pub positionen: Vec<AbrechnungsfallPosition>,
+}
+
+/// Bepreiste Abrechnungsposition
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AbrechnungsfallPosition {
+    /// Abgerechnete Positionsnummer
+    #[serde(rename = "arf:Position", alias = "Position")]
+    pub position: Position,
+
+    /// Menge im Format 99.99
+    #[serde(rename = "arf:Menge", alias = "Menge")]
+    pub menge: String,
+
+    /// Leistungserbringer-Abrechnungscode, 2 chars
+    #[serde(rename = "arf:LE_AbrCode", alias = "LE_AbrCode")]
+    pub le_abr_code: String,
+
+    /// Tarifkennzeichen Tarifbereich, 2 digits
+    #[serde(rename = "arf:TarifKennz_Bereich", alias = "TarifKennz_Bereich")]
+    pub tarif_kennz_bereich: String,
+
+    /// Tarifkennzeichen Sondertarif, 3 chars
+    #[serde(rename = "arf:TarifKennz_Sonder", alias = "TarifKennz_Sonder")]
+    pub tarif_kennz_sonder: String,
+
+    /// Vereinbarter Einzelpreis
+    #[serde(rename = "arf:Einzelpreis", alias = "Einzelpreis")]
+    pub einzelpreis: String,
+
+    /// Menge × Einzelpreis, kaufmännisch gerundet
+    #[serde(rename = "arf:Positionssumme", alias = "Positionssumme")]
+    pub positionssumme: String,
+
+    /// 1-2 Beschäftigtennummern
+    #[serde(rename = "arf:Beschaeftigtennummer", alias = "Beschaeftigtennummer")]
+    pub beschaeftigtennummern: Vec<String>,
+}
+
+/// Positionsnummer mit optionaler erbrachter Einzelleistung
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Position {
+    /// Positionsnummer, 6 chars
+    #[serde(rename = "arf:Positions_Nr", alias = "Positions_Nr")]
+    pub positions_nr: String,
+
+    /// Erbrachte Einzelleistung bei Komplexleistungen
+    #[serde(
+        rename = "arf:Einzelleistung",
+        alias = "Einzelleistung",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub einzelleistung: Option<Einzelleistung>,
+}
+
+/// Erbrachte Einzelleistung innerhalb einer Komplexposition
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Einzelleistung {
+    /// Positionsnummer der erbrachten Leistung, 6 chars
+    #[serde(
+        rename = "arf:Erbrachte_Leistung_Pos_Nr",
+        alias = "Erbrachte_Leistung_Pos_Nr"
+    )]
+    pub positions_nr: String,
+
+    /// Menge der erbrachten Leistung im Format 99.99
+    #[serde(
+        rename = "arf:Erbrachte_Leistung_Menge",
+        alias = "Erbrachte_Leistung_Menge"
+    )]
+    pub menge: String,
+}
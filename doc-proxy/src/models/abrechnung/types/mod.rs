@@ -0,0 +1,12 @@
+//! Data types for the HKP Abrechnung (SGB V § 302).
+//!
+//! Elements are written with the `arn` and `arf` prefixes so the structures
+//! can be embedded unchanged in a `Nutzdaten` envelope; the enclosing document
+//! declares both namespaces. The deserializer matches local names, so every
+//! field carries its unprefixed name as an alias.
+
+mod fall;
+mod nachricht;
+
+pub use fall::{Abrechnungsfall, AbrechnungsfallPosition, Einsatz, Einzelleistung, Position};
+pub use nachricht::{Abrechnungsnachricht, Verarbeitungskennzeichen};
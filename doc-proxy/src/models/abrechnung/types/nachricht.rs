@@ -0,0 +1,89 @@
+//! Abrechnungsnachricht structures (HKP_ARN_1.0.0.xsd, prefix `arn`).
+
+use serde::{Deserialize, Serialize};
+
+use super::Abrechnungsfall;
+
+/// Abrechnungsnachricht: one invoice to a Kostenträger
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename = "arn:Abrechnungsnachricht")]
+pub struct Abrechnungsnachricht {
+    /// IK des Pflegedienstes, der die Leistungen erbracht hat
+    #[serde(rename = "arn:Leistungserbringer_IK", alias = "Leistungserbringer_IK")]
+    pub leistungserbringer_ik: String,
+
+    /// IK des Rechnungsstellers (Pflegedienst oder Abrechnungszentrum)
+    #[serde(rename = "arn:Rechnungssteller_IK", alias = "Rechnungssteller_IK")]
+    pub rechnungssteller_ik: String,
+
+    /// IK, auf dessen Konto gezahlt wird
+    #[serde(rename = "arn:Zahlungsempfaenger_IK", alias = "Zahlungsempfaenger_IK")]
+    pub zahlungsempfaenger_ik: String,
+
+    /// IK des Rechnungsempfängers (Kasse oder Datenannahmestelle)
+    #[serde(
+        rename = "arn:Rechnungsempfaenger_IK",
+        alias = "Rechnungsempfaenger_IK"
+    )]
+    pub rechnungsempfaenger_ik: String,
+
+    /// IK der Krankenkasse
+    #[serde(rename = "arn:Kostentraeger_IK", alias = "Kostentraeger_IK")]
+    pub kostentraeger_ik: String,
+
+    /// Art der Rechnung
+    #[serde(
+        rename = "arn:Verarbeitungskennzeichen",
+        alias = "Verarbeitungskennzeichen"
+    )]
+    pub verarbeitungskennzeichen: Verarbeitungskennzeichen,
+
+    /// Datei_ID der korrigierten Rechnung, bei Korrektur/Wiederaufnahme
+    #[serde(
+        rename = "arn:Vorgaengerrechnung_Datei_ID",
+        alias = "Vorgaengerrechnung_Datei_ID",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub vorgaengerrechnung_datei_id: Option<String>,
+
+    /// Rechnungsnummer, max 25 chars, unique per year and Rechnungssteller
+    #[serde(rename = "arn:Rechnungsnummer", alias = "Rechnungsnummer")]
+    pub rechnungsnummer: String,
+
+    /// UUID of this invoice
+    #[serde(rename = "arn:Abrechnung_ID", alias = "Abrechnung_ID")]
+    pub abrechnung_id: String,
+
+    /// Rechnungsdatum (xs:date, `JJJJ-MM-TT`)
+    #[serde(rename = "arn:Rechnungsdatum", alias = "Rechnungsdatum")]
+    pub rechnungsdatum: String,
+
+    /// Summe aller Abrechnungsfälle
+    #[serde(rename = "arn:Gesamtbetrag", alias = "Gesamtbetrag")]
+    pub gesamtbetrag: String,
+
+    /// 1-n Abrechnungsfälle
+    #[serde(rename = "arn:Abrechnungsfall", alias = "Abrechnungsfall")]
+    pub faelle: Vec<Abrechnungsfall>,
+}
+
+/// Verarbeitungskennzeichen gemäß HKP_BAS
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum Verarbeitungskennzeichen {
+    /// 01 = Abrechnung ohne Besonderheiten
+    #[default]
+    #[serde(rename = "01")]
+    Abrechnung,
+    /// 02 = Nachforderung
+    #[serde(rename = "02")]
+    Nachforderung,
+    /// 03 = Zuzahlungsforderung
+    #[serde(rename = "03")]
+    Zuzahlungsforderung,
+    /// 04 = Korrekturrechnung
+    #[serde(rename = "04")]
+    Korrekturrechnung,
+    /// 10 = Wiederaufnahme
+    #[serde(rename = "10")]
+    Wiederaufnahme,
+}
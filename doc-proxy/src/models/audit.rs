@@ -0,0 +1,119 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Actions recorded in the audit log
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    Login,
+    LoginFailed,
+    DeviceLogin,
+    DocumentSigned,
+    DocumentRejected,
+    DocumentCancelled,
+    DocumentReopened,
+    DeviceRegistered,
+    DevicePaired,
+    PairingCodeCreated,
+    DeviceKeyRegenerated,
+    DeviceDeleted,
+    DeviceActivated,
+    DeviceDeactivated,
+    UserCreated,
+    UserUpdated,
+    UserDeleted,
+}
+
+impl AuditAction {
+    /// Action as stored in the database
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditAction::Login => "login",
+            AuditAction::LoginFailed => "login_failed",
+            AuditAction::DeviceLogin => "device_login",
+            AuditAction::DocumentSigned => "document_signed",
+            AuditAction::DocumentRejected => "document_rejected",
+            AuditAction::DocumentCancelled => "document_cancelled",
+            AuditAction::DocumentReopened => "document_reopened",
+            AuditAction::DeviceRegistered => "device_registered",
+            AuditAction::DevicePaired => "device_paired",
+            AuditAction::PairingCodeCreated => "pairing_code_created",
+            AuditAction::DeviceKeyRegenerated => "device_key_regenerated",
+            AuditAction::DeviceDeleted => "device_deleted",
+            AuditAction::DeviceActivated => "device_activated",
+            AuditAction::DeviceDeactivated => "device_deactivated",
+            AuditAction::UserCreated => "user_created",
+            AuditAction::UserUpdated => "user_updated",
+            AuditAction::UserDeleted => "user_deleted",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntryResponse {
+    pub seq: i64,
+    pub occurred_at: DateTime<Utc>,
+    pub actor: Option<String>,
+    pub device_id: Option<Uuid>,
+    pub client_ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub action: String,
+    pub target_id: Option<String>,
+    /// SHA-256 of the request payload, e.g. the signature
+    pub payload_digest: Option<String>,
+    pub prev_hash: String,
+    pub hash: String,
+}
+
+/// Filters for querying and exporting the audit log
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AuditFilter {
+    pub actor: Option<String>,
+    pub action: Option<AuditAction>,
+    /// Document, device or user ID
+    pub target_id: Option<String>,
+    /// Entries at or after this time
+    pub from: Option<DateTime<Utc>>,
+    /// Entries before this time
+    pub to: Option<DateTime<Utc>>,
+}
+
+/// Paging for querying the audit log, newest entries first
+#[derive(Debug, Deserialize)]
+pub struct AuditPageQuery {
+    /// Page number (0-indexed), defaults to 0
+    #[serde(default)]
+    pub page: u64,
+    /// Page size, defaults to 50
+    #[serde(default = "default_page_size")]
+    pub size: u64,
+}
+
+fn default_page_size() -> u64 {
+    50
+}
+
+/// Result of checking the hash chain
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChainVerification {
+    pub valid: bool,
+    /// Entries checked
+    pub entries: u64,
+    /// First entry that does not link to its predecessor or whose hash does
+    /// not match its content
+    pub first_invalid_seq: Option<i64>,
+}
+
+/// Audit log export handed to a Pflegekasse or the MDK
+#[derive(Debug, Serialize)]
+pub struct AuditExport {
+    pub exported_at: DateTime<Utc>,
+    pub exported_by: String,
+    pub filter: AuditFilter,
+    /// Check of the whole chain at export time
+    pub chain: ChainVerification,
+    /// Matching entries, oldest first. Each entry's hash covers all of its
+    /// fields and the hash of its predecessor.
+    pub entries: Vec<AuditEntryResponse>,
+}
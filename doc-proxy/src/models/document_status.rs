@@ -0,0 +1,199 @@
+//! Lifecycle of a Leistungsnachweis
+//!
+//! ```text
+//! draft ──submit──▶ pending_signature ──sign──▶ signed ──finalize──▶ finalized
+//!   │                     │                       │                      │
+//!   └──────cancel─────────┴──────cancel───────────┤        reject────────┘
+//!                         └──────reject───────────┴──reject──▶ rejected ──cancel──▶ cancelled
+//!                                                               └──reopen──▶ draft ◀──reopen──┘
+//! ```
+//!
+//! Signing locally generates the XML right away and goes straight to
+//! `finalized`. A finalized document can only leave that state by being
+//! rejected, so it can never be signed twice.
+
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DocumentStatus {
+    Draft,
+    PendingSignature,
+    Signed,
+    Finalized,
+    /// Refused, either by staff or by the recipient of a delivery
+    Rejected,
+    Cancelled,
+}
+
+/// Operation that moves a document to another status
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatusAction {
+    /// Hand the document to the client for signing
+    Submit,
+    /// Signature captured and forwarded to core
+    Sign,
+    /// Signature captured and XML generated
+    Finalize,
+    Reject,
+    Cancel,
+    /// Back to draft for correction
+    Reopen,
+}
+
+/// A status change that the lifecycle does not allow
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+#[error("Invalid status transition from {from} to {to}")]
+pub struct InvalidTransition {
+    pub from: DocumentStatus,
+    pub to: DocumentStatus,
+}
+
+impl DocumentStatus {
+    /// Name as serialized in the API and stored in the database
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DocumentStatus::Draft => "draft",
+            DocumentStatus::PendingSignature => "pending_signature",
+            DocumentStatus::Signed => "signed",
+            DocumentStatus::Finalized => "finalized",
+            DocumentStatus::Rejected => "rejected",
+            DocumentStatus::Cancelled => "cancelled",
+        }
+    }
+
+    /// Status after applying `action`, or the rejected transition.
+    pub fn apply(self, action: StatusAction) -> Result<Self, InvalidTransition> {
+        use DocumentStatus::*;
+
+        let allowed = match action {
+            StatusAction::Submit => matches!(self, Draft),
+            StatusAction::Sign => matches!(self, Draft | PendingSignature),
+            StatusAction::Finalize => matches!(self, Draft | PendingSignature | Signed),
+            StatusAction::Reject => matches!(self, PendingSignature | Signed | Finalized),
+            StatusAction::Cancel => matches!(self, Draft | PendingSignature | Signed | Rejected),
+            StatusAction::Reopen => matches!(self, Rejected | Cancelled),
+        };
+
+        if allowed {
+            Ok(action.target())
+        } else {
+            Err(InvalidTransition {
+                from: self,
+                to: action.target(),
+            })
+        }
+    }
+}
+
+impl StatusAction {
+    /// Status a document ends up in after this action
+    pub fn target(self) -> DocumentStatus {
+        match self {
+            StatusAction::Submit => DocumentStatus::PendingSignature,
+            StatusAction::Sign => DocumentStatus::Signed,
+            StatusAction::Finalize => DocumentStatus::Finalized,
+            StatusAction::Reject => DocumentStatus::Rejected,
+            StatusAction::Cancel => DocumentStatus::Cancelled,
+            StatusAction::Reopen => DocumentStatus::Draft,
+        }
+    }
+}
+
+impl fmt::Display for DocumentStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for DocumentStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "draft" => Ok(DocumentStatus::Draft),
+            "pending_signature" => Ok(DocumentStatus::PendingSignature),
+            "signed" => Ok(DocumentStatus::Signed),
+            "finalized" => Ok(DocumentStatus::Finalized),
+            "rejected" => Ok(DocumentStatus::Rejected),
+            "cancelled" => Ok(DocumentStatus::Cancelled),
+            other => Err(format!("Unknown document status '{}'", other)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DocumentStatus::*, *};
+
+    const ALL: [DocumentStatus; 6] = [
+        Draft,
+        PendingSignature,
+        Signed,
+        Finalized,
+        Rejected,
+        Cancelled,
+    ];
+
+    #[test]
+    fn test_happy_path() {
+        let status = Draft
+            .apply(StatusAction::Submit)
+            .and_then(|s| s.apply(StatusAction::Sign))
+            .and_then(|s| s.apply(StatusAction::Finalize));
+        assert_eq!(status, Ok(Finalized));
+    }
+
+    #[test]
+    fn test_finalized_cannot_be_signed_again() {
+        assert_eq!(
+            Finalized.apply(StatusAction::Finalize),
+            Err(InvalidTransition {
+                from: Finalized,
+                to: Finalized
+            })
+        );
+        assert!(Finalized.apply(StatusAction::Sign).is_err());
+        assert!(Finalized.apply(StatusAction::Cancel).is_err());
+    }
+
+    #[test]
+    fn test_rejected_and_cancelled_can_be_reopened() {
+        assert_eq!(Finalized.apply(StatusAction::Reject), Ok(Rejected));
+        assert_eq!(Rejected.apply(StatusAction::Reopen), Ok(Draft));
+        assert_eq!(Rejected.apply(StatusAction::Cancel), Ok(Cancelled));
+        assert_eq!(Cancelled.apply(StatusAction::Reopen), Ok(Draft));
+
+        for status in [Draft, PendingSignature, Signed, Finalized] {
+            assert!(status.apply(StatusAction::Reopen).is_err());
+        }
+    }
+
+    #[test]
+    fn test_cancelled_is_terminal_until_reopened() {
+        for action in [
+            StatusAction::Submit,
+            StatusAction::Sign,
+            StatusAction::Finalize,
+            StatusAction::Reject,
+            StatusAction::Cancel,
+        ] {
+            assert!(Cancelled.apply(action).is_err(), "{:?}", action);
+        }
+    }
+
+    #[test]
+    fn test_str_roundtrip() {
+        for status in ALL {
+            assert_eq!(status.as_str().parse::<DocumentStatus>(), Ok(status));
+            assert_eq!(
+                serde_json::to_value(status).unwrap(),
+                serde_json::Value::from(status.as_str())
+            );
+        }
+        assert!("archived".parse::<DocumentStatus>().is_err());
+    }
+}
@@ -2,6 +2,8 @@
 
 use thiserror::Error;
 
+use crate::models::xsd::Violation;
+
 /// Errors that can occur when parsing or validating a Leistungsnachweis.
 #[derive(Error, Debug)]
 pub enum ParseError {
@@ -13,7 +15,7 @@ pub enum ParseError {
     #[error("XML serialization error: {0}")]
     XmlSerialize(#[from] quick_xml::SeError),
 
-    /// Schema validation error
-    #[error("Validation error: {0}")]
-    Validation(String),
+    /// Schema validation error, one entry per violated rule
+    #[error("Validation error: {}", Violation::join(.0))]
+    Validation(Vec<Violation>),
 }
@@ -0,0 +1,128 @@
+//! Leistungsnachweis der häuslichen Krankenpflege (SGB V § 302)
+//!
+//! Schema: HKP_LNW_1.0.0.xsd (namespace `SLE_LWN/1.0`)
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use crate::models::leistungsnachweis::hkp;
+//!
+//! let lnw = hkp::Leistungsnachweis::from_xml(xml)?;
+//! println!("ID: {}", lnw.id);
+//! ```
+
+mod parser;
+pub mod types;
+pub mod validator;
+
+pub use types::{
+    Einsatz, FehlendeUnterschrift, Leistungsnachweis, Position, UnterschriftVersicherter,
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::leistungsnachweis::{
+        ParseError,
+        types::{ArtDerUnterschrift, Dateityp, GrundFehlendeUnterschrift, Unterschrift},
+    };
+
+    fn sample_leistungsnachweis() -> Leistungsnachweis {
+        Leistungsnachweis {
+            leistungserbringer_ik: "123456789".into(),
+            krankenversichertennummer: "A123456789".into(),
+            verantwortliche_fachkraft: "111222333".into(),
+            einsaetze: vec![Einsatz {
+                beginn: "2024-11-15T08:00:00".into(),
+                ende: Some("08:30:00".into()),
+                positionen: vec![Position {
+                    positions_nr: "010101".into(),
+                    menge: "1.00".into(),
+                    beschaeftigtennummern: vec!["987654321".into(), "987654322".into()],
+                }],
+            }],
+            unterschrift_versicherter: UnterschriftVersicherter {
+                art: ArtDerUnterschrift::HandschriftlichDigital,
+                datum_uhrzeit: Some("2024-11-15T08:30:00".into()),
+                unterschrift: Some(Unterschrift {
+                    datei: "iVBORw0KGgo=".into(),
+                    dateityp: Dateityp::Png,
+                }),
+                fehlende_unterschrift: None,
+            },
+            id: "550e8400-e29b-41d4-a716-446655440000".into(),
+        }
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let original = sample_leistungsnachweis();
+        let xml = original.to_xml().unwrap();
+        assert!(xml.contains(
+            r#"<Leistungsnachweis xmlns="http://www.gkv-datenaustausch.de/XMLSchema/SLE_LWN/1.0">"#
+        ));
+
+        let parsed = Leistungsnachweis::from_xml(&xml).unwrap();
+        assert_eq!(original, parsed);
+    }
+
+    #[test]
+    fn test_fehlende_unterschrift() {
+        let mut lnw = sample_leistungsnachweis();
+        lnw.unterschrift_versicherter = UnterschriftVersicherter {
+            art: ArtDerUnterschrift::Fehlend,
+            datum_uhrzeit: Some("2024-11-15T08:30:00".into()),
+            unterschrift: None,
+            fehlende_unterschrift: Some(FehlendeUnterschrift {
+                grund: GrundFehlendeUnterschrift::Sonstiges,
+                sonstiges: Some("Versicherter im Krankenhaus".into()),
+            }),
+        };
+
+        let xml = lnw.to_xml().unwrap();
+        assert!(xml.contains("<fehlende_Unterschrift><Kennzeichen_Grund>4</Kennzeichen_Grund>"));
+        assert_eq!(Leistungsnachweis::from_xml(&xml).unwrap(), lnw);
+    }
+
+    #[test]
+    fn test_schema_violations_are_reported_with_xpath() {
+        let mut lnw = sample_leistungsnachweis();
+        lnw.krankenversichertennummer = "123456789012".into();
+        lnw.einsaetze[0].positionen[0].positions_nr = "01001".into();
+        lnw.einsaetze[0].positionen[0]
+            .beschaeftigtennummern
+            .push("987654323".into());
+        lnw.unterschrift_versicherter.unterschrift = Some(Unterschrift {
+            datei: "iVBORw0KGgo=".into(),
+            dateityp: Dateityp::Pdf,
+        });
+
+        let Err(ParseError::Validation(violations)) = lnw.to_xml() else {
+            panic!("expected validation error");
+        };
+
+        let xpaths: Vec<&str> = violations.iter().map(|v| v.xpath.as_str()).collect();
+        assert_eq!(
+            xpaths,
+            vec![
+                "/Leistungsnachweis/Krankenversichertennummer",
+                "/Leistungsnachweis/Einsatz[1]/Leistungsnachweis_Position[1]/Positions_Nr",
+                "/Leistungsnachweis/Einsatz[1]/Leistungsnachweis_Position[1]/Beschäftigtennummer[3]",
+                "/Leistungsnachweis/Unterschrift_Versicherter/Unterschrift/Dateityp",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_from_xml_rejects_pfl_document() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Leistungsnachweis>
+    <LeistungsnachweisID>550e8400-e29b-41d4-a716-446655440000</LeistungsnachweisID>
+</Leistungsnachweis>"#;
+
+        assert!(matches!(
+            Leistungsnachweis::from_xml(xml),
+            Err(ParseError::Validation(_))
+        ));
+    }
+}
@@ -0,0 +1,52 @@
+//! XML parsing and serialization for the HKP Leistungsnachweis.
+
+use serde::Serialize;
+
+use crate::models::{leistungsnachweis::error::ParseError, xsd::NS_SLE_LWN};
+
+use super::{types::Leistungsnachweis, validator};
+
+/// Root element with the `SLE_LWN` default namespace declaration.
+#[derive(Serialize)]
+struct Namespaced<'a> {
+    #[serde(rename = "@xmlns")]
+    xmlns: &'static str,
+    #[serde(flatten)]
+    inner: &'a Leistungsnachweis,
+}
+
+impl Leistungsnachweis {
+    /// Parse XML string into Leistungsnachweis.
+    ///
+    /// The document is checked against `HKP_LNW_1.0.0.xsd` before it is
+    /// deserialized, so structural errors are reported with their XPath.
+    pub fn from_xml(xml: &str) -> Result<Self, ParseError> {
+        validator::check_schema(xml)?;
+        let result: Self = quick_xml::de::from_str(xml)?;
+        validator::check_rules(&result)?;
+        Ok(result)
+    }
+
+    /// Serialize to XML string.
+    ///
+    /// The output is validated against the schema and the cross-field rules
+    /// before it is returned. Returns a valid XML document with the XML
+    /// declaration and the `SLE_LWN` namespace.
+    pub fn to_xml(&self) -> Result<String, ParseError> {
+        let mut body = String::new();
+        let serializer =
+            quick_xml::se::Serializer::with_root(&mut body, Some("Leistungsnachweis"))?;
+        Namespaced {
+            xmlns: NS_SLE_LWN,
+            inner: self,
+        }
+        .serialize(serializer)?;
+
+        let mut xml = String::from(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+        xml.push('\n');
+        xml.push_str(&body);
+
+        validator::validate_document(&xml, self)?;
+        Ok(xml)
+    }
+}
@@ -0,0 +1,110 @@
+//! Data types for the Leistungsnachweis der häuslichen Krankenpflege (SGB V § 302).
+//!
+//! Mirrors `HKP_LNW_1.0.0.xsd` (namespace `SLE_LWN/1.0`). The key tables for
+//! Art der Unterschrift, Dateityp and Grund are shared with PFL_LNW and reuse
+//! the enums from [`crate::models::leistungsnachweis::types`].
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::leistungsnachweis::types::{
+    ArtDerUnterschrift, GrundFehlendeUnterschrift, Unterschrift,
+};
+
+/// Root element: Leistungsnachweis
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename = "Leistungsnachweis")]
+pub struct Leistungsnachweis {
+    /// IK des Pflegedienstes, 9 digits
+    #[serde(rename = "Leistungserbringer_IK")]
+    pub leistungserbringer_ik: String,
+
+    /// Krankenversichertennummer, capital letter followed by 9 digits
+    #[serde(rename = "Krankenversichertennummer")]
+    pub krankenversichertennummer: String,
+
+    /// LBNR der verantwortlichen Fachkraft, 9 digits
+    #[serde(rename = "Verantwortliche_Fachkraft")]
+    pub verantwortliche_fachkraft: String,
+
+    /// 1-n Einsätze
+    #[serde(rename = "Einsatz")]
+    pub einsaetze: Vec<Einsatz>,
+
+    /// Unterschrift des Versicherten
+    #[serde(rename = "Unterschrift_Versicherter")]
+    pub unterschrift_versicherter: UnterschriftVersicherter,
+
+    /// UUID, 36 chars
+    #[serde(rename = "Leistungsnachweis-ID")]
+    pub id: String,
+}
+
+/// Einzelner Einsatz
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Einsatz {
+    /// Tag und Beginn des Einsatzes (xs:dateTime, `JJJJ-MM-TTThh:mm:ss`)
+    #[serde(rename = "Beginn_Datum_Uhrzeit")]
+    pub beginn: String,
+
+    /// Ende des Einsatzes (xs:time, `hh:mm:ss`), required for Zeitvergütung
+    #[serde(rename = "Ende_Uhrzeit", skip_serializing_if = "Option::is_none")]
+    pub ende: Option<String>,
+
+    /// 1-n Positionen
+    #[serde(rename = "Leistungsnachweis_Position")]
+    pub positionen: Vec<Position>,
+}
+
+/// Leistungsnachweis-Position
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Position {
+    /// Positionsnummer, 6 chars alphanumeric
+    #[serde(rename = "Positions_Nr")]
+    pub positions_nr: String,
+
+    /// Menge im Format 99.99
+    #[serde(rename = "Menge")]
+    pub menge: String,
+
+    /// 1-2 Beschäftigtennummern
+    #[serde(rename = "Beschäftigtennummer")]
+    pub beschaeftigtennummern: Vec<String>,
+}
+
+/// Unterschrift des Versicherten
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct UnterschriftVersicherter {
+    /// Art der Unterschrift 1-5
+    #[serde(rename = "Art_der_Unterschrift")]
+    pub art: ArtDerUnterschrift,
+
+    /// Datum und Uhrzeit (xs:dateTime), optional bei Art = 3
+    #[serde(
+        rename = "Datum_Uhrzeit_der_Unterschrift",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub datum_uhrzeit: Option<String>,
+
+    /// Unterschrift-Daten, bei Art 1-4
+    #[serde(rename = "Unterschrift", skip_serializing_if = "Option::is_none")]
+    pub unterschrift: Option<Unterschrift>,
+
+    /// Fehlende Unterschrift, bei Art = 5
+    #[serde(
+        rename = "fehlende_Unterschrift",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub fehlende_unterschrift: Option<FehlendeUnterschrift>,
+}
+
+/// Fehlende Unterschrift
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FehlendeUnterschrift {
+    /// Grund 1-4 gemäß Schlüsselverzeichnis 3.3
+    #[serde(rename = "Kennzeichen_Grund")]
+    pub grund: GrundFehlendeUnterschrift,
+
+    /// Freitext bei Grund = 4, max 150 chars
+    #[serde(rename = "Sonstiges", skip_serializing_if = "Option::is_none")]
+    pub sonstiges: Option<String>,
+}
@@ -0,0 +1,103 @@
+//! Validation logic for the HKP Leistungsnachweis according to HKP_LNW_1.0.0.xsd.
+//!
+//! Structure and field types are checked by the generic schema engine in
+//! [`crate::models::xsd`]; this module adds the cross-field rules that the
+//! schema only states in its annotations.
+
+use crate::models::{
+    leistungsnachweis::{
+        error::ParseError,
+        types::{ArtDerUnterschrift, Dateityp, GrundFehlendeUnterschrift},
+    },
+    xsd::{self, Violation},
+};
+
+use super::types::Leistungsnachweis;
+
+const UNTERSCHRIFT_PATH: &str = "/Leistungsnachweis/Unterschrift_Versicherter";
+
+/// Check an XML document against the shipped HKP_LNW schema.
+pub fn check_schema(xml: &str) -> Result<(), ParseError> {
+    into_result(xsd::schemas().validate(xml))
+}
+
+/// Check the rules from the schema annotations that XSD cannot express.
+pub fn check_rules(lnw: &Leistungsnachweis) -> Result<(), ParseError> {
+    let mut violations = Vec::new();
+    validate_unterschrift(lnw, &mut violations);
+    into_result(violations)
+}
+
+/// Check a serialized document against the schema and the cross-field rules,
+/// reporting all violations at once.
+pub fn validate_document(xml: &str, lnw: &Leistungsnachweis) -> Result<(), ParseError> {
+    let mut violations = xsd::schemas().validate(xml);
+    validate_unterschrift(lnw, &mut violations);
+    into_result(violations)
+}
+
+fn into_result(violations: Vec<Violation>) -> Result<(), ParseError> {
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(ParseError::Validation(violations))
+    }
+}
+
+/// Validate Unterschrift_Versicherter according to Art_der_Unterschrift.
+fn validate_unterschrift(lnw: &Leistungsnachweis, out: &mut Vec<Violation>) {
+    let u = &lnw.unterschrift_versicherter;
+
+    match u.art {
+        ArtDerUnterschrift::Fehlend => {
+            if u.unterschrift.is_some() {
+                out.push(Violation::new(
+                    format!("{UNTERSCHRIFT_PATH}/Unterschrift"),
+                    "Unterschrift must not be present when Art_der_Unterschrift=5",
+                ));
+            }
+        }
+        _ => {
+            if u.fehlende_unterschrift.is_some() {
+                out.push(Violation::new(
+                    format!("{UNTERSCHRIFT_PATH}/fehlende_Unterschrift"),
+                    "fehlende_Unterschrift must not be present when Art_der_Unterschrift!=5",
+                ));
+            }
+        }
+    }
+
+    // Datum_Uhrzeit_der_Unterschrift may only be omitted for Art = 3
+    if u.datum_uhrzeit.is_none() && u.art != ArtDerUnterschrift::BestaetigungFoto {
+        out.push(Violation::new(
+            UNTERSCHRIFT_PATH,
+            "Datum_Uhrzeit_der_Unterschrift required unless Art_der_Unterschrift=3",
+        ));
+    }
+
+    if let Some(ref unterschrift) = u.unterschrift {
+        let allowed: &[Dateityp] = match u.art {
+            ArtDerUnterschrift::HandschriftlichDigital
+            | ArtDerUnterschrift::HandschriftlichPapier => &[Dateityp::Png, Dateityp::Gif],
+            ArtDerUnterschrift::BestaetigungFoto => &[Dateityp::Jpeg],
+            _ => &[],
+        };
+        if !allowed.is_empty() && !allowed.contains(&unterschrift.dateityp) {
+            out.push(Violation::new(
+                format!("{UNTERSCHRIFT_PATH}/Unterschrift/Dateityp"),
+                "Dateityp must be 3 or 4 for Art_der_Unterschrift 1-2 and 2 for Art_der_Unterschrift=3",
+            ));
+        }
+    }
+
+    // Sonstiges required when Kennzeichen_Grund=4
+    if let Some(ref fe) = u.fehlende_unterschrift
+        && fe.grund == GrundFehlendeUnterschrift::Sonstiges
+        && fe.sonstiges.is_none()
+    {
+        out.push(Violation::new(
+            format!("{UNTERSCHRIFT_PATH}/fehlende_Unterschrift"),
+            "Sonstiges required when Kennzeichen_Grund=4",
+        ));
+    }
+}
@@ -2,6 +2,8 @@
 //!
 //! Schema: PFL_LNW_2.1.0.xsd
 //!
+//! The SGB V home nursing variant (HKP_LNW_1.0.0.xsd) lives in [`hkp`].
+//!
 //! # Example
 //!
 //! ```rust,ignore
@@ -17,6 +19,7 @@
 //! ```
 
 pub mod error;
+pub mod hkp;
 mod parser;
 pub mod types;
 pub mod validator;
@@ -152,4 +155,27 @@ mod tests {
                 .is_some()
         );
     }
+
+    #[test]
+    fn test_validation_reports_all_violations_with_xpath() {
+        let mut lnw = sample_leistungsnachweis();
+        lnw.erbrachte_leistungen.ik_pflegedienst = "12345".into();
+        lnw.erbrachte_leistungen.abrechnungsmonat = "202413".into();
+        lnw.erbrachte_leistungen.leistungen.tage[0].einsaetze[0].einzelleistungen[0]
+            .beschaeftigtennummern = vec!["987654321".into(), "LBNR-001".into()];
+
+        let Err(ParseError::Validation(violations)) = lnw.to_xml() else {
+            panic!("expected validation error");
+        };
+
+        let xpaths: Vec<&str> = violations.iter().map(|v| v.xpath.as_str()).collect();
+        assert_eq!(
+            xpaths,
+            vec![
+                "/Leistungsnachweis/ErbrachteLeistungen/IKPflegedienst",
+                "/Leistungsnachweis/ErbrachteLeistungen/Abrechnungsmonat",
+                "/Leistungsnachweis/ErbrachteLeistungen/Leistungen/Tag[1]/Einsatz[1]/Einzelleistung[1]/Beschaeftigtennummer[2]",
+            ]
+        );
+    }
 }
@@ -23,8 +23,10 @@ impl Leistungsnachweis {
 
     /// Serialize to XML string.
     ///
-    /// Returns a valid XML document with the XML declaration.
+    /// Validates the structure first, so only schema-conformant documents are
+    /// emitted. Returns a valid XML document with the XML declaration.
     pub fn to_xml(&self) -> Result<String, ParseError> {
+        validator::validate(self)?;
         let mut xml = String::from(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
         xml.push('\n');
         xml.push_str(&quick_xml::se::to_string(self)?);
@@ -49,6 +49,19 @@ pub enum ArtDerUnterschrift {
     Fehlend,
 }
 
+impl ArtDerUnterschrift {
+    /// Bezeichnung für Ausdrucke
+    pub fn bezeichnung(&self) -> &'static str {
+        match self {
+            ArtDerUnterschrift::HandschriftlichDigital => "Handschriftlich, digital erfasst",
+            ArtDerUnterschrift::HandschriftlichPapier => "Handschriftlich auf Papier",
+            ArtDerUnterschrift::BestaetigungFoto => "Bestätigung durch Foto",
+            ArtDerUnterschrift::AlternativeBestätigung => "Alternative Bestätigung",
+            ArtDerUnterschrift::Fehlend => "Unterschrift fehlt",
+        }
+    }
+}
+
 /// Unterschrift-Daten (Zeile 26)
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Unterschrift {
@@ -76,6 +89,19 @@ pub enum Dateityp {
     Tiff,
 }
 
+impl Dateityp {
+    /// Bezeichnung für Ausdrucke
+    pub fn bezeichnung(&self) -> &'static str {
+        match self {
+            Dateityp::Pdf => "PDF",
+            Dateityp::Jpeg => "JPEG",
+            Dateityp::Png => "PNG",
+            Dateityp::Gif => "GIF",
+            Dateityp::Tiff => "TIFF",
+        }
+    }
+}
+
 /// Fehlende Unterschrift (Zeile 29)
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct FehlendeUnterschrift {
@@ -107,3 +133,17 @@ pub enum GrundFehlendeUnterschrift {
     #[serde(rename = "4")]
     Sonstiges,
 }
+
+impl GrundFehlendeUnterschrift {
+    /// Bezeichnung für Ausdrucke
+    pub fn bezeichnung(&self) -> &'static str {
+        match self {
+            GrundFehlendeUnterschrift::NichtUnterschriftsfaehig => {
+                "Versicherte/r nicht unterschriftsfähig"
+            }
+            GrundFehlendeUnterschrift::Verweigert => "Versicherte/r verweigert die Unterschrift",
+            GrundFehlendeUnterschrift::NichtAnwesend => "Versicherte/r nicht anwesend",
+            GrundFehlendeUnterschrift::Sonstiges => "Sonstiger Grund",
+        }
+    }
+}
@@ -1,164 +1,293 @@
 //! Validation logic for Leistungsnachweis according to PFL_LNW_2.1.0.xsd.
+//!
+//! Field values are checked against the shared simple types of
+//! `SLP_BAS_1.0.0.xsd` where the PFL schema reuses them; all violations are
+//! collected and reported with their XPath location.
+
+use crate::models::xsd::{self, Violation};
 
 use super::{
     error::ParseError,
     types::{ArtDerUnterschrift, Einsatz, GrundFehlendeUnterschrift, Leistungsnachweis, Tag},
 };
 
+const ROOT: &str = "/Leistungsnachweis";
+
 /// Validate the Leistungsnachweis structure according to the spec.
+///
+/// Returns all violations at once rather than stopping at the first one.
 pub fn validate(lnw: &Leistungsnachweis) -> Result<(), ParseError> {
-    validate_id(lnw)?;
-    validate_erbrachte_leistungen(lnw)?;
-    validate_unterschrift(lnw)?;
-    Ok(())
+    let mut violations = Vec::new();
+
+    validate_id(lnw, &mut violations);
+    validate_erbrachte_leistungen(lnw, &mut violations);
+    validate_unterschrift(lnw, &mut violations);
+
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(ParseError::Validation(violations))
+    }
 }
 
-/// Validate LeistungsnachweisID (UUID, 36 chars).
-fn validate_id(lnw: &Leistungsnachweis) -> Result<(), ParseError> {
-    if lnw.id.len() != 36 {
-        return Err(ParseError::Validation(format!(
-            "LeistungsnachweisID must be 36 chars, got {}",
-            lnw.id.len()
-        )));
+/// Check a value against an SLP_BAS simple type, recording a violation on failure.
+fn check_type(out: &mut Vec<Violation>, xpath: String, type_name: &str, value: &str) {
+    if let Err(reason) = xsd::check_slp_bas(type_name, value) {
+        out.push(Violation::new(xpath, reason));
     }
-    Ok(())
+}
+
+/// Check that a value consists of exactly `len` ASCII digits.
+fn check_digits(out: &mut Vec<Violation>, xpath: String, value: &str, len: usize, format: &str) {
+    if value.len() != len || !value.chars().all(|c| c.is_ascii_digit()) {
+        out.push(Violation::new(
+            xpath,
+            format!("`{}` must be {} digits ({})", value, len, format),
+        ));
+    }
+}
+
+/// Validate LeistungsnachweisID (UUID, 36 chars).
+fn validate_id(lnw: &Leistungsnachweis, out: &mut Vec<Violation>) {
+    check_type(
+        out,
+        format!("{ROOT}/LeistungsnachweisID"),
+        "UUID_Stp",
+        &lnw.id,
+    );
 }
 
 /// Validate ErbrachteLeistungen and its children.
-fn validate_erbrachte_leistungen(lnw: &Leistungsnachweis) -> Result<(), ParseError> {
+fn validate_erbrachte_leistungen(lnw: &Leistungsnachweis, out: &mut Vec<Violation>) {
     let el = &lnw.erbrachte_leistungen;
+    let path = format!("{ROOT}/ErbrachteLeistungen");
 
-    // IK Pflegedienst must be 9 digits
-    if el.ik_pflegedienst.len() != 9 || !el.ik_pflegedienst.chars().all(|c| c.is_ascii_digit()) {
-        return Err(ParseError::Validation(
-            "IKPflegedienst must be exactly 9 digits".into(),
-        ));
-    }
+    check_type(
+        out,
+        format!("{path}/IKPflegedienst"),
+        "Institutionskennzeichen_Stp",
+        &el.ik_pflegedienst,
+    );
+    check_type(
+        out,
+        format!("{path}/Abrechnungsmonat"),
+        "Abrechnungsmonat_Stp",
+        &el.abrechnungsmonat,
+    );
 
-    // Abrechnungsmonat format JJJJMM
-    if el.abrechnungsmonat.len() != 6 || !el.abrechnungsmonat.chars().all(|c| c.is_ascii_digit()) {
-        return Err(ParseError::Validation(
-            "Abrechnungsmonat must be 6 digits (JJJJMM)".into(),
+    // Versichertennummer: 10-12 chars
+    let vnr_len = el.versichertennummer.chars().count();
+    if !(10..=12).contains(&vnr_len) {
+        out.push(Violation::new(
+            format!("{path}/Versichertennummer"),
+            format!("Versichertennummer must be 10-12 chars, got {}", vnr_len),
         ));
     }
 
-    validate_tage(lnw)?;
-    Ok(())
+    check_type(out, format!("{path}/Name"), "Name_Stp", &el.name);
+    check_type(out, format!("{path}/Vorname"), "Name_Stp", &el.vorname);
+
+    validate_tage(lnw, &format!("{path}/Leistungen"), out);
+
+    check_type(
+        out,
+        format!("{path}/BeschaeftigtennummerVerantwortlicheFachkraft"),
+        "LBNR_stp",
+        &el.beschaeftigtennummer_verantwortliche_fachkraft,
+    );
 }
 
 /// Validate Tage and nested structures.
-fn validate_tage(lnw: &Leistungsnachweis) -> Result<(), ParseError> {
+fn validate_tage(lnw: &Leistungsnachweis, path: &str, out: &mut Vec<Violation>) {
     let tage = &lnw.erbrachte_leistungen.leistungen.tage;
 
     // Tage: 1-31
     if tage.is_empty() || tage.len() > 31 {
-        return Err(ParseError::Validation("Tag must occur 1-31 times".into()));
+        out.push(Violation::new(
+            path,
+            format!("Tag must occur 1-31 times, found {}", tage.len()),
+        ));
     }
 
-    for tag in tage {
+    for (i, tag) in tage.iter().enumerate() {
+        let tag_path = format!("{path}/Tag[{}]", i + 1);
+
         // Datum format JJJJMMTT
-        if tag.datum.len() != 8 || !tag.datum.chars().all(|c| c.is_ascii_digit()) {
-            return Err(ParseError::Validation(
-                "Datum must be 8 digits (JJJJMMTT)".into(),
-            ));
-        }
+        check_digits(out, format!("{tag_path}/Datum"), &tag.datum, 8, "JJJJMMTT");
 
-        validate_einsaetze(tag)?;
+        validate_einsaetze(tag, &tag_path, out);
     }
-
-    Ok(())
 }
 
 /// Validate Einsätze for a Tag.
-fn validate_einsaetze(tag: &Tag) -> Result<(), ParseError> {
+fn validate_einsaetze(tag: &Tag, path: &str, out: &mut Vec<Violation>) {
     // Einsätze: 1-99
     if tag.einsaetze.is_empty() || tag.einsaetze.len() > 99 {
-        return Err(ParseError::Validation(
-            "Einsatz must occur 1-99 times per Tag".into(),
+        out.push(Violation::new(
+            path,
+            format!(
+                "Einsatz must occur 1-99 times per Tag, found {}",
+                tag.einsaetze.len()
+            ),
         ));
     }
 
-    for einsatz in &tag.einsaetze {
+    for (i, einsatz) in tag.einsaetze.iter().enumerate() {
+        let einsatz_path = format!("{path}/Einsatz[{}]", i + 1);
+
         // Laufende Nummer 1-99
-        if einsatz.laufende_nummer < 1 || einsatz.laufende_nummer > 99 {
-            return Err(ParseError::Validation("LaufendeNummer must be 1-99".into()));
-        }
+        check_type(
+            out,
+            format!("{einsatz_path}/LaufendeNummer"),
+            "Anzahl_Max99_Stp",
+            &einsatz.laufende_nummer.to_string(),
+        );
 
         // Uhrzeit format hhmm
-        if einsatz.uhrzeit_beginn.len() != 4
-            || !einsatz.uhrzeit_beginn.chars().all(|c| c.is_ascii_digit())
-        {
-            return Err(ParseError::Validation(
-                "UhrzeitBeginn must be 4 digits (hhmm)".into(),
-            ));
-        }
+        check_digits(
+            out,
+            format!("{einsatz_path}/UhrzeitBeginn"),
+            &einsatz.uhrzeit_beginn,
+            4,
+            "hhmm",
+        );
 
-        validate_einzelleistungen(einsatz)?;
+        validate_einzelleistungen(einsatz, &einsatz_path, out);
     }
-
-    Ok(())
 }
 
 /// Validate Einzelleistungen for an Einsatz.
-fn validate_einzelleistungen(einsatz: &Einsatz) -> Result<(), ParseError> {
+fn validate_einzelleistungen(einsatz: &Einsatz, path: &str, out: &mut Vec<Violation>) {
     // Einzelleistungen: 1-99
     if einsatz.einzelleistungen.is_empty() || einsatz.einzelleistungen.len() > 99 {
-        return Err(ParseError::Validation(
-            "Einzelleistung must occur 1-99 times per Einsatz".into(),
+        out.push(Violation::new(
+            path,
+            format!(
+                "Einzelleistung must occur 1-99 times per Einsatz, found {}",
+                einsatz.einzelleistungen.len()
+            ),
         ));
     }
 
-    for el in &einsatz.einzelleistungen {
+    for (i, el) in einsatz.einzelleistungen.iter().enumerate() {
+        let el_path = format!("{path}/Einzelleistung[{}]", i + 1);
+
+        check_type(
+            out,
+            format!("{el_path}/BezeichnungDerLeistung"),
+            "Freitext_kurz_Stp",
+            &el.bezeichnung,
+        );
+
+        if let Some(ref anzahl) = el.anzahl {
+            check_type(
+                out,
+                format!("{el_path}/Anzahl"),
+                "Anzahl_Dezimal_Stp",
+                anzahl,
+            );
+        }
+
+        if let Some(dauer) = el.tatsaechliche_dauer {
+            check_type(
+                out,
+                format!("{el_path}/TatsaechlicheDauer"),
+                "Anzahl_MinutenTag_Stp",
+                &dauer.to_string(),
+            );
+        }
+
         // Beschäftigtennummern: 1-3
         if el.beschaeftigtennummern.is_empty() || el.beschaeftigtennummern.len() > 3 {
-            return Err(ParseError::Validation(
-                "Beschaeftigtennummer must occur 1-3 times".into(),
+            out.push(Violation::new(
+                el_path.clone(),
+                format!(
+                    "Beschaeftigtennummer must occur 1-3 times, found {}",
+                    el.beschaeftigtennummern.len()
+                ),
             ));
         }
-    }
 
-    Ok(())
+        for (j, nummer) in el.beschaeftigtennummern.iter().enumerate() {
+            check_type(
+                out,
+                format!("{el_path}/Beschaeftigtennummer[{}]", j + 1),
+                "LBNR_stp",
+                nummer,
+            );
+        }
+    }
 }
 
 /// Validate UnterschriftVersicherter according to Art.
-fn validate_unterschrift(lnw: &Leistungsnachweis) -> Result<(), ParseError> {
+fn validate_unterschrift(lnw: &Leistungsnachweis, out: &mut Vec<Violation>) {
     let u = &lnw.unterschrift_versicherter;
+    let path = format!("{ROOT}/UnterschriftVersicherter");
 
     match u.art {
         ArtDerUnterschrift::Fehlend => {
             if u.unterschrift.is_some() {
-                return Err(ParseError::Validation(
-                    "Unterschrift must not be present when Art=5".into(),
+                out.push(Violation::new(
+                    format!("{path}/Unterschrift"),
+                    "Unterschrift must not be present when Art=5",
                 ));
             }
             if u.fehlende_unterschrift.is_none() {
-                return Err(ParseError::Validation(
-                    "FehlendeUnterschrift required when Art=5".into(),
+                out.push(Violation::new(
+                    path.clone(),
+                    "FehlendeUnterschrift required when Art=5",
                 ));
             }
         }
         _ => {
             if u.fehlende_unterschrift.is_some() {
-                return Err(ParseError::Validation(
-                    "FehlendeUnterschrift must not be present when Art!=5".into(),
+                out.push(Violation::new(
+                    format!("{path}/FehlendeUnterschrift"),
+                    "FehlendeUnterschrift must not be present when Art!=5",
                 ));
             }
             if u.unterschrift.is_none() {
-                return Err(ParseError::Validation(
-                    "Unterschrift required when Art=1-4".into(),
+                out.push(Violation::new(
+                    path.clone(),
+                    "Unterschrift required when Art=1-4",
                 ));
             }
         }
     }
 
-    // FehlendeUnterschrift: Erläuterung required when Grund=4
-    if let Some(ref fe) = u.fehlende_unterschrift {
-        if fe.grund == GrundFehlendeUnterschrift::Sonstiges && fe.erlaeuterung.is_none() {
-            return Err(ParseError::Validation(
-                "ErlaeuterungSonstiges required when Grund=4".into(),
-            ));
+    if let Some(ref datum) = u.datum_uhrzeit {
+        check_digits(
+            out,
+            format!("{path}/DatumUndUhrzeitDerUnterschrift"),
+            datum,
+            14,
+            "JJJJMMTThhmmss",
+        );
+    }
+
+    if let Some(ref unterschrift) = u.unterschrift {
+        let datei = xsd::QName::new(xsd::XS_NS, "base64Binary");
+        if let Err(reason) = xsd::schemas().check_simple_type(&datei, &unterschrift.datei) {
+            out.push(Violation::new(format!("{path}/Unterschrift/Datei"), reason));
         }
     }
 
-    Ok(())
+    if let Some(ref fe) = u.fehlende_unterschrift {
+        let fe_path = format!("{path}/FehlendeUnterschrift");
+
+        // Erläuterung required when Grund=4
+        match fe.erlaeuterung {
+            Some(ref erlaeuterung) => check_type(
+                out,
+                format!("{fe_path}/ErlaeuterungSonstiges"),
+                "Freitext_kurz_Stp",
+                erlaeuterung,
+            ),
+            None if fe.grund == GrundFehlendeUnterschrift::Sonstiges => out.push(Violation::new(
+                fe_path,
+                "ErlaeuterungSonstiges required when Grund=4",
+            )),
+            None => {}
+        }
+    }
 }
@@ -1,4 +1,11 @@
+pub mod abrechnung;
+pub mod audit;
 pub mod document;
+pub mod document_status;
 pub mod leistungsnachweis;
+pub mod nutzdaten;
 pub mod pagination;
+pub mod pdf;
+pub mod signature;
 pub mod user;
+pub mod xsd;
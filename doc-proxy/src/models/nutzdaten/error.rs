@@ -0,0 +1,21 @@
+//! Error types for Nutzdaten envelopes.
+
+use thiserror::Error;
+
+use crate::models::xsd::Violation;
+
+/// Errors that can occur when packaging or reading a Nutzdaten file.
+#[derive(Error, Debug)]
+pub enum NutzdatenError {
+    /// XML deserialization error
+    #[error("XML parsing error: {0}")]
+    XmlDeserialize(#[from] quick_xml::DeError),
+
+    /// XML serialization error
+    #[error("XML serialization error: {0}")]
+    XmlSerialize(#[from] quick_xml::SeError),
+
+    /// Schema validation error, one entry per violated rule
+    #[error("Validation error: {}", Violation::join(.0))]
+    Validation(Vec<Violation>),
+}
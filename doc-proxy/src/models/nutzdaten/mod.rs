@@ -0,0 +1,221 @@
+//! Nutzdaten: transport envelope of every HKP delivery
+//!
+//! Schema: HKP_DAT_1.0.0.xsd (namespace `SLP`)
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use crate::models::nutzdaten::{Nutzdaten, Verfahrenskennung};
+//!
+//! let datei = Nutzdaten::abrechnung(nachricht, datei_id, Utc::now(), Verfahrenskennung::Echtdaten);
+//! let xml = datei.to_xml()?;
+//! ```
+
+pub mod error;
+mod parser;
+pub mod types;
+
+pub use error::NutzdatenError;
+pub use types::{Nachrichtentyp, Nutzdaten, Verfahrenskennung};
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use uuid::Uuid;
+
+    use super::{
+        types::{fehler::*, header::*},
+        *,
+    };
+    use crate::models::abrechnung::{
+        Einsatz,
+        types::{
+            Abrechnungsfall, AbrechnungsfallPosition, Abrechnungsnachricht, Position,
+            Verarbeitungskennzeichen,
+        },
+    };
+
+    fn sample_nachricht() -> Abrechnungsnachricht {
+        Abrechnungsnachricht {
+            leistungserbringer_ik: "123456789".into(),
+            rechnungssteller_ik: "123456789".into(),
+            zahlungsempfaenger_ik: "123456789".into(),
+            rechnungsempfaenger_ik: "660000000".into(),
+            kostentraeger_ik: "101234567".into(),
+            verarbeitungskennzeichen: Verarbeitungskennzeichen::Abrechnung,
+            vorgaengerrechnung_datei_id: None,
+            rechnungsnummer: "2024-000001".into(),
+            abrechnung_id: "1b4e28ba-2fa1-11d2-883f-0016d3cca427".into(),
+            rechnungsdatum: "2024-12-02".into(),
+            gesamtbetrag: "12.50".into(),
+            faelle: vec![Abrechnungsfall {
+                id: "0b6f1c7e-2a3d-4e5f-8a9b-0c1d2e3f4a5b".into(),
+                leistungsnachweis_datei: "PExlaXN0dW5nc25hY2h3ZWlzLz4=".into(),
+                leistungsnachweis_id: "550e8400-e29b-41d4-a716-446655440000".into(),
+                abrechnungsmonat: "202411".into(),
+                genehmigungskennzeichen: Some("G-2024-001".into()),
+                evo_ids: Vec::new(),
+                betriebsstaettennummer: "123456700".into(),
+                lebenslange_arztnummer: "987654300".into(),
+                verordnungsdatum: "2024-10-28".into(),
+                unfallkennzeichen: None,
+                kennzeichen_bvg: "0".into(),
+                diagnoseschluessel: vec!["E11.9".into()],
+                krankenversichertennummer: "A123456789".into(),
+                kennz_bes_versorgungsform: None,
+                gesamtbetrag: "12.50".into(),
+                einsaetze: vec![Einsatz {
+                    beginn: "2024-11-15T08:00:00".into(),
+                    ende: None,
+                    positionen: vec![AbrechnungsfallPosition {
+                        position: Position {
+                            positions_nr: "030101".into(),
+                            einzelleistung: None,
+                        },
+                        menge: "1".into(),
+                        le_abr_code: "10".into(),
+                        tarif_kennz_bereich: "03".into(),
+                        tarif_kennz_sonder: "000".into(),
+                        einzelpreis: "12.50".into(),
+                        positionssumme: "12.50".into(),
+                        beschaeftigtennummern: vec!["987654321".into()],
+                    }],
+                }],
+            }],
+        }
+    }
+
+    fn sample_datei() -> Nutzdaten {
+        Nutzdaten::abrechnung(
+            sample_nachricht(),
+            Uuid::parse_str("6fa459ea-ee8a-3ca4-894e-db77e160355e").unwrap(),
+            Utc.with_ymd_and_hms(2024, 12, 2, 9, 30, 0).unwrap(),
+            Verfahrenskennung::Testdaten,
+        )
+    }
+
+    #[test]
+    fn test_abrechnung_roundtrip() {
+        let datei = sample_datei();
+        let xml = datei.to_xml().unwrap();
+
+        assert!(
+            xml.contains(r#"<Nutzdaten xmlns="http://www.gkv-datenaustausch.de/XMLSchema/SLP""#)
+        );
+        assert!(xml.contains("<Absender_IK>123456789</Absender_IK>"));
+        assert!(xml.contains("<Empfaenger_IK>660000000</Empfaenger_IK>"));
+        assert!(xml.contains("<Erstellzeitpunkt>2024-12-02T09:30:00Z</Erstellzeitpunkt>"));
+        assert!(xml.contains("<Nachrichtentyp>ABR_0000</Nachrichtentyp>"));
+        assert!(xml.contains("<Body><Abrechnungsnachricht><arn:Leistungserbringer_IK>"));
+
+        assert_eq!(Nutzdaten::from_xml(&xml).unwrap(), datei);
+    }
+
+    #[test]
+    fn test_fehlernachricht_roundtrip() {
+        let mut datei = sample_datei();
+        datei.header.nachrichtentyp = Nachrichtentyp::FehlerTechnisch;
+        datei.body.nachricht = Nachricht::FehlernachrichtTechnisch(FehlernachrichtTechnisch {
+            datei_id: Some("6fa459ea-ee8a-3ca4-894e-db77e160355e".into()),
+            message_id: "<1234@kim.example>".into(),
+            fehler: Fehler {
+                fehlerinformationen: vec![Fehlerinformation {
+                    fehlercode: "00000".into(),
+                    fehlertext: Some("Ungültiger Wert".into()),
+                    xpath: Some("/Nutzdaten/Header/Absender_IK".into()),
+                }],
+            },
+        });
+
+        let xml = datei.to_xml().unwrap();
+        assert!(xml.contains("<feh_tech:Fehlercode>00000</feh_tech:Fehlercode>"));
+        assert_eq!(Nutzdaten::from_xml(&xml).unwrap(), datei);
+    }
+
+    #[test]
+    fn test_header_violations_are_located() {
+        let mut datei = sample_datei();
+        datei.header.empfaenger_ik = "66".into();
+        datei.header.logische_version = "1.0".into();
+
+        let Err(NutzdatenError::Validation(violations)) = datei.to_xml() else {
+            panic!("expected validation error");
+        };
+        let xpaths: Vec<&str> = violations.iter().map(|v| v.xpath.as_str()).collect();
+        assert_eq!(
+            xpaths,
+            vec![
+                "/Nutzdaten/Header/Empfaenger_IK",
+                "/Nutzdaten/Header/Logische_Version"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_embedded_invoice_is_validated() {
+        let mut nachricht = sample_nachricht();
+        nachricht.faelle[0].lebenslange_arztnummer = "12345".into();
+        let datei = Nutzdaten::abrechnung(
+            nachricht,
+            Uuid::new_v4(),
+            Utc::now(),
+            Verfahrenskennung::Echtdaten,
+        );
+
+        let Err(NutzdatenError::Validation(violations)) = datei.to_xml() else {
+            panic!("expected validation error");
+        };
+        assert_eq!(
+            violations[0].xpath,
+            "/Nutzdaten/Body/Abrechnungsnachricht/Abrechnungsfall[1]/Lebenslange_Arztnummer"
+        );
+    }
+
+    fn fehler_at(xpath: &str) -> Fehlerinformation {
+        Fehlerinformation {
+            fehlercode: "00000".into(),
+            fehlertext: None,
+            xpath: Some(xpath.into()),
+        }
+    }
+
+    #[test]
+    fn test_fundstelle_in_abrechnungsfall() {
+        let fehler = fehler_at(
+            "/Nutzdaten/Body/arn:Abrechnungsnachricht/arn:Abrechnungsfall[3]/arf:Abrechnungsposition[2]/arf:Menge",
+        );
+        assert_eq!(
+            fehler.fundstelle(),
+            Some(Fundstelle {
+                abrechnungsfall: 3,
+                feld: Some("Abrechnungsposition[2]/Menge".into()),
+            })
+        );
+
+        let fehler = fehler_at("/Nutzdaten/Body/Abrechnungsnachricht/Abrechnungsfall");
+        assert_eq!(
+            fehler.fundstelle(),
+            Some(Fundstelle {
+                abrechnungsfall: 1,
+                feld: None,
+            })
+        );
+    }
+
+    #[test]
+    fn test_fundstelle_outside_abrechnungsfall() {
+        assert_eq!(
+            fehler_at("/Nutzdaten/Header/Absender_IK").fundstelle(),
+            None
+        );
+        assert_eq!(
+            fehler_at("/Nutzdaten/Body/Abrechnungsnachricht/Rechnungsnummer").fundstelle(),
+            None
+        );
+        assert_eq!(fehler_at("/Abrechnungsfall_ID").fundstelle(), None);
+
+        let mut fehler = fehler_at("");
+        fehler.xpath = None;
+        assert_eq!(fehler.fundstelle(), None);
+    }
+}
@@ -0,0 +1,66 @@
+//! XML parsing and serialization for Nutzdaten files.
+
+use serde::Serialize;
+
+use crate::models::xsd::{self, NS_HKP_ARF, NS_HKP_ARN, NS_HKP_FEH_TECH, NS_SLP};
+
+use super::{error::NutzdatenError, types::Nutzdaten};
+
+/// Root element with the `SLP` default namespace and the prefixes used by
+/// the embedded messages.
+#[derive(Serialize)]
+struct Namespaced<'a> {
+    #[serde(rename = "@xmlns")]
+    xmlns: &'static str,
+    #[serde(rename = "@xmlns:arn")]
+    arn: &'static str,
+    #[serde(rename = "@xmlns:arf")]
+    arf: &'static str,
+    #[serde(rename = "@xmlns:feh_tech")]
+    feh_tech: &'static str,
+    #[serde(flatten)]
+    inner: &'a Nutzdaten,
+}
+
+impl Nutzdaten {
+    /// Parse a Nutzdaten file.
+    ///
+    /// The document is checked against `HKP_DAT_1.0.0.xsd` before it is
+    /// deserialized, so structural errors are reported with their XPath.
+    pub fn from_xml(xml: &str) -> Result<Self, NutzdatenError> {
+        validate(xml)?;
+        Ok(quick_xml::de::from_str(xml)?)
+    }
+
+    /// Serialize to a ready-to-send file.
+    ///
+    /// The output is validated against `HKP_DAT_1.0.0.xsd`, including the
+    /// embedded message, before it is returned.
+    pub fn to_xml(&self) -> Result<String, NutzdatenError> {
+        let mut body = String::new();
+        let serializer = quick_xml::se::Serializer::with_root(&mut body, Some("Nutzdaten"))?;
+        Namespaced {
+            xmlns: NS_SLP,
+            arn: NS_HKP_ARN,
+            arf: NS_HKP_ARF,
+            feh_tech: NS_HKP_FEH_TECH,
+            inner: self,
+        }
+        .serialize(serializer)?;
+
+        let mut xml = String::from(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+        xml.push('\n');
+        xml.push_str(&body);
+
+        validate(&xml)?;
+        Ok(xml)
+    }
+}
+
+fn validate(xml: &str) -> Result<(), NutzdatenError> {
+    let violations = xsd::schemas().validate(xml);
+    if !violations.is_empty() {
+        return Err(NutzdatenError::Validation(violations));
+    }
+    Ok(())
+}
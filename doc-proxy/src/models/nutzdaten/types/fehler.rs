@@ -0,0 +1,92 @@
+//! Technical error message structures (HKP_FEH_TECH_1.0.0.xsd, prefix `feh_tech`).
+
+use serde::{Deserialize, Serialize};
+
+/// Fehlernachricht_technisch: a delivery was rejected by the recipient
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FehlernachrichtTechnisch {
+    /// Datei-ID of the rejected delivery; missing if decryption or
+    /// signature verification failed
+    #[serde(
+        rename = "feh_tech:Datei_ID",
+        alias = "Datei_ID",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub datei_id: Option<String>,
+
+    /// Message-ID of the KIM message the error refers to
+    #[serde(rename = "feh_tech:Message_ID", alias = "Message_ID")]
+    pub message_id: String,
+
+    #[serde(rename = "feh_tech:Fehler", alias = "Fehler")]
+    pub fehler: Fehler,
+}
+
+/// 1-99 error entries
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Fehler {
+    #[serde(rename = "feh_tech:Fehlerinformation", alias = "Fehlerinformation")]
+    pub fehlerinformationen: Vec<Fehlerinformation>,
+}
+
+/// Single error entry
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Fehlerinformation {
+    /// Fehlercode gemäß Schlüsselverzeichnis 3.1
+    #[serde(rename = "feh_tech:Fehlercode", alias = "Fehlercode")]
+    pub fehlercode: String,
+
+    /// Fehlertext, e.g. the message of the recipient's XML parser
+    #[serde(
+        rename = "feh_tech:Fehlertext",
+        alias = "Fehlertext",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub fehlertext: Option<String>,
+
+    /// XPath of the offending element
+    #[serde(
+        rename = "feh_tech:XPath",
+        alias = "XPath",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub xpath: Option<String>,
+}
+
+/// Location of an error inside the Abrechnungsnachricht of the rejected file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fundstelle {
+    /// 1-based position of the Abrechnungsfall
+    pub abrechnungsfall: usize,
+    /// Path of the offending element below the Abrechnungsfall without
+    /// namespace prefixes, e.g. `Abrechnungsposition[2]/Menge`
+    pub feld: Option<String>,
+}
+
+impl Fehlerinformation {
+    /// Locates the error in an Abrechnungsfall of the rejected file.
+    ///
+    /// Returns `None` if the XPath is missing or points outside of any
+    /// Abrechnungsfall (header, invoice level), i.e. the error concerns the
+    /// whole delivery.
+    pub fn fundstelle(&self) -> Option<Fundstelle> {
+        let mut steps = self
+            .xpath
+            .as_deref()?
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .map(|s| s.rsplit_once(':').map_or(s, |(_, local)| local));
+
+        let abrechnungsfall = steps.find_map(|step| match step.split_once('[') {
+            None if step == "Abrechnungsfall" => Some(1),
+            Some(("Abrechnungsfall", index)) => index.strip_suffix(']')?.trim().parse().ok(),
+            _ => None,
+        })?;
+        let feld = steps.collect::<Vec<_>>().join("/");
+
+        Some(Fundstelle {
+            abrechnungsfall,
+            feld: (!feld.is_empty()).then_some(feld),
+        })
+    }
+}
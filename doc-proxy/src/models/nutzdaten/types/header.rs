@@ -0,0 +1,160 @@
+//! Envelope structures (HKP_DAT_1.0.0.xsd, default namespace `SLP`).
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::models::abrechnung::types::Abrechnungsnachricht;
+
+use super::FehlernachrichtTechnisch;
+
+/// Logische Version of the HKP procedure implemented by this crate
+pub const LOGISCHE_VERSION: &str = "1.0.0";
+
+/// Nutzdaten: root of every delivered file
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename = "Nutzdaten")]
+pub struct Nutzdaten {
+    #[serde(rename = "Header")]
+    pub header: Header,
+
+    #[serde(rename = "Body")]
+    pub body: Body,
+}
+
+impl Nutzdaten {
+    /// Wraps an invoice for delivery from its Rechnungssteller to its
+    /// Rechnungsempfänger.
+    pub fn abrechnung(
+        nachricht: Abrechnungsnachricht,
+        datei_id: Uuid,
+        erstellzeitpunkt: DateTime<Utc>,
+        verfahrenskennung: Verfahrenskennung,
+    ) -> Self {
+        Self {
+            header: Header {
+                absender_ik: nachricht.rechnungssteller_ik.clone(),
+                empfaenger_ik: nachricht.rechnungsempfaenger_ik.clone(),
+                erstellzeitpunkt: erstellzeitpunkt.format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+                datei_id: datei_id.to_string(),
+                verfahrenskennung,
+                unterverfahren: Unterverfahren::Hkp,
+                nachrichtentyp: Nachrichtentyp::Abrechnung,
+                logische_version: LOGISCHE_VERSION.to_string(),
+                dokurechnung: None,
+            },
+            body: Body {
+                nachricht: Nachricht::Abrechnungsnachricht(nachricht),
+            },
+        }
+    }
+}
+
+/// Header: sender, recipient and identification of the delivery
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Header {
+    /// IK des Absenders
+    #[serde(rename = "Absender_IK")]
+    pub absender_ik: String,
+
+    /// IK des Empfängers
+    #[serde(rename = "Empfaenger_IK")]
+    pub empfaenger_ik: String,
+
+    /// Erstellzeitpunkt der Nutzdatendatei (xs:dateTime)
+    #[serde(rename = "Erstellzeitpunkt")]
+    pub erstellzeitpunkt: String,
+
+    /// Eindeutige Datei-ID (UUID)
+    #[serde(rename = "Datei_ID")]
+    pub datei_id: String,
+
+    #[serde(rename = "Verfahrenskennung")]
+    pub verfahrenskennung: Verfahrenskennung,
+
+    #[serde(rename = "Unterverfahren")]
+    pub unterverfahren: Unterverfahren,
+
+    #[serde(rename = "Nachrichtentyp")]
+    pub nachrichtentyp: Nachrichtentyp,
+
+    /// Logische Version des Verfahrens, e.g. `1.0.0`
+    #[serde(rename = "Logische_Version")]
+    pub logische_version: String,
+
+    /// Only used by Abrechnungsdienstleister of the Kassen
+    #[serde(rename = "Dokurechnung", skip_serializing_if = "Option::is_none")]
+    pub dokurechnung: Option<bool>,
+}
+
+/// Body: exactly one message
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Body {
+    #[serde(rename = "$value")]
+    pub nachricht: Nachricht,
+}
+
+/// Message carried in the Body
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum Nachricht {
+    #[serde(rename = "Abrechnungsnachricht")]
+    Abrechnungsnachricht(Abrechnungsnachricht),
+
+    #[serde(rename = "Fehlernachricht_technisch")]
+    FehlernachrichtTechnisch(FehlernachrichtTechnisch),
+}
+
+/// Verfahrenskennung (Transportweg)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum Verfahrenskennung {
+    /// Echtdaten
+    #[serde(rename = "EHKP0")]
+    Echtdaten,
+    /// Testdaten
+    #[default]
+    #[serde(rename = "THKP0")]
+    Testdaten,
+}
+
+/// Kennung des Unterverfahrens
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum Unterverfahren {
+    #[default]
+    #[serde(rename = "HKP")]
+    Hkp,
+}
+
+/// Kennung des Nachrichtentyps
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum Nachrichtentyp {
+    /// Abrechnungsnachricht
+    #[serde(rename = "ABR_0000")]
+    Abrechnung,
+    /// Technische Fehlernachricht
+    #[serde(rename = "FEH_TECH")]
+    FehlerTechnisch,
+    /// Fachliche Fehlernachricht
+    #[serde(rename = "FEH_FACH")]
+    FehlerFachlich,
+}
+
+impl Nachrichtentyp {
+    /// Code as written in the Header
+    pub fn code(self) -> &'static str {
+        match self {
+            Self::Abrechnung => "ABR_0000",
+            Self::FehlerTechnisch => "FEH_TECH",
+            Self::FehlerFachlich => "FEH_FACH",
+        }
+    }
+}
+
+impl Verfahrenskennung {
+    /// Code as written in the Header
+    pub fn code(self) -> &'static str {
+        match self {
+            Self::Echtdaten => "EHKP0",
+            Self::Testdaten => "THKP0",
+        }
+    }
+}
@@ -0,0 +1,11 @@
+//! Nutzdaten types.
+//!
+//! Header and Body live in the `SLP` default namespace. Embedded messages keep
+//! their own prefixes (`arn`/`arf` for invoices, `feh_tech` for technical
+//! errors), which the root element declares.
+
+pub mod fehler;
+pub mod header;
+
+pub use fehler::FehlernachrichtTechnisch;
+pub use header::{Nachrichtentyp, Nutzdaten, Verfahrenskennung};
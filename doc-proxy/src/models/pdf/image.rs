@@ -0,0 +1,168 @@
+//! Raster images for embedding in a PDF.
+//!
+//! JPEG data is embedded as is. PNG data is decoded and stored deflated, with
+//! the alpha channel as a separate soft mask so transparent canvas signatures
+//! print on white.
+
+use std::io::Cursor;
+
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ImageError {
+    #[error("Unsupported image format")]
+    Unsupported,
+
+    #[error("Invalid JPEG: {0}")]
+    Jpeg(String),
+
+    #[error("Invalid PNG: {0}")]
+    Png(#[from] png::DecodingError),
+}
+
+/// Color space of the image samples
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorSpace {
+    Gray,
+    Rgb,
+    Cmyk,
+}
+
+/// Compression of the image samples
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    /// JPEG as is
+    Dct,
+    /// zlib-compressed raw samples
+    Flate,
+}
+
+/// An image ready to be written as PDF image XObject
+#[derive(Debug, Clone)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub color_space: ColorSpace,
+    pub encoding: Encoding,
+    pub data: Vec<u8>,
+    /// zlib-compressed 8-bit alpha samples, if the image is transparent
+    pub alpha: Option<Vec<u8>>,
+}
+
+impl Image {
+    /// Reads a PNG or JPEG image, detected by its signature.
+    pub fn from_bytes(data: &[u8]) -> Result<Self, ImageError> {
+        if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Self::from_png(data)
+        } else if data.starts_with(&[0xff, 0xd8, 0xff]) {
+            Self::from_jpeg(data)
+        } else {
+            Err(ImageError::Unsupported)
+        }
+    }
+
+    fn from_jpeg(data: &[u8]) -> Result<Self, ImageError> {
+        let (width, height, components) = jpeg_frame(data)?;
+        let color_space = match components {
+            1 => ColorSpace::Gray,
+            3 => ColorSpace::Rgb,
+            4 => ColorSpace::Cmyk,
+            n => return Err(ImageError::Jpeg(format!("{} color components", n))),
+        };
+
+        Ok(Self {
+            width: width.into(),
+            height: height.into(),
+            color_space,
+            encoding: Encoding::Dct,
+            data: data.to_vec(),
+            alpha: None,
+        })
+    }
+
+    fn from_png(data: &[u8]) -> Result<Self, ImageError> {
+        let mut decoder = png::Decoder::new(Cursor::new(data));
+        decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+        let mut reader = decoder.read_info()?;
+        let mut buf = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buf)?;
+        let samples = &buf[..info.buffer_size()];
+
+        let (color_space, channels, has_alpha) = match info.color_type {
+            png::ColorType::Grayscale => (ColorSpace::Gray, 1, false),
+            png::ColorType::GrayscaleAlpha => (ColorSpace::Gray, 2, true),
+            png::ColorType::Rgb => (ColorSpace::Rgb, 3, false),
+            png::ColorType::Rgba => (ColorSpace::Rgb, 4, true),
+            // Expanded by the decoder
+            png::ColorType::Indexed => return Err(ImageError::Unsupported),
+        };
+
+        let (color, alpha) = if has_alpha {
+            let mut color = Vec::with_capacity(samples.len());
+            let mut alpha = Vec::with_capacity(samples.len() / channels);
+            for pixel in samples.chunks_exact(channels) {
+                let (a, c) = pixel.split_last().expect("pixel has channels");
+                color.extend_from_slice(c);
+                alpha.push(*a);
+            }
+            (color, Some(alpha))
+        } else {
+            (samples.to_vec(), None)
+        };
+
+        Ok(Self {
+            width: info.width,
+            height: info.height,
+            color_space,
+            encoding: Encoding::Flate,
+            data: deflate(&color),
+            alpha: alpha.map(|a| deflate(&a)),
+        })
+    }
+}
+
+fn deflate(data: &[u8]) -> Vec<u8> {
+    miniz_oxide::deflate::compress_to_vec_zlib(data, 6)
+}
+
+/// Width, height and number of components from the first SOF segment.
+fn jpeg_frame(data: &[u8]) -> Result<(u16, u16, u8), ImageError> {
+    let truncated = || ImageError::Jpeg("no frame header".into());
+    let mut pos = 2;
+
+    loop {
+        let marker = *data.get(pos + 1).ok_or_else(truncated)?;
+        if data[pos] != 0xff {
+            return Err(ImageError::Jpeg(format!("expected marker at byte {}", pos)));
+        }
+        match marker {
+            // Fill bytes and markers without payload
+            0xff => {
+                pos += 1;
+                continue;
+            }
+            0x01 | 0xd0..=0xd7 => {
+                pos += 2;
+                continue;
+            }
+            // Scan data or end of image before any frame header
+            0xd9 | 0xda => return Err(truncated()),
+            _ => {}
+        }
+
+        let length = match data.get(pos + 2..pos + 4) {
+            Some(&[hi, lo]) => u16::from_be_bytes([hi, lo]) as usize,
+            _ => return Err(truncated()),
+        };
+
+        // SOF0-SOF15 except DHT (C4), JPG (C8) and DAC (CC)
+        if matches!(marker, 0xc0..=0xcf) && !matches!(marker, 0xc4 | 0xc8 | 0xcc) {
+            // Precision, height, width, number of components
+            let frame = data.get(pos + 4..pos + 10).ok_or_else(truncated)?;
+            let height = u16::from_be_bytes([frame[1], frame[2]]);
+            let width = u16::from_be_bytes([frame[3], frame[4]]);
+            return Ok((width, height, frame[5]));
+        }
+        pos += 2 + length;
+    }
+}
@@ -0,0 +1,479 @@
+//! Minimal A4 document writer for printable reports
+//!
+//! Lays out text, table rows, rules and images top to bottom and starts a new
+//! page when the current one is full. Text is set in the standard Helvetica
+//! fonts with WinAnsi encoding, so no font files are embedded and German
+//! umlauts print correctly.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use crate::models::pdf::{Column, Font, PdfDocument};
+//!
+//! let mut doc = PdfDocument::new("Leistungsnachweis");
+//! doc.paragraph("Leistungsnachweis 01/2024", Font::Bold, 16.0);
+//! doc.row(&[Column::left(100.0), Column::right(60.0)], &["Ziffer", "Menge"], Font::Regular, 9.0);
+//! let bytes = doc.finish();
+//! ```
+
+mod font;
+mod image;
+
+use chrono::{Datelike, Timelike, Utc};
+use pdf_writer::{Content, Date, Filter, Finish, Name, Pdf, Rect, Ref, Str, TextStr};
+
+pub use font::{Font, encode_win_ansi};
+pub use image::{ColorSpace, Encoding, Image};
+
+const PAGE_WIDTH: f32 = 595.28;
+const PAGE_HEIGHT: f32 = 841.89;
+const MARGIN: f32 = 50.0;
+/// Space at the bottom of each page reserved for the footer
+const FOOTER_HEIGHT: f32 = 25.0;
+const LINE_SPACING: f32 = 1.35;
+
+/// Usable width between the margins, in points
+pub const CONTENT_WIDTH: f32 = PAGE_WIDTH - 2.0 * MARGIN;
+
+/// Horizontal alignment of a table cell
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Align {
+    Left,
+    Right,
+}
+
+/// Table column
+#[derive(Debug, Clone, Copy)]
+pub struct Column {
+    pub width: f32,
+    pub align: Align,
+}
+
+impl Column {
+    pub const fn left(width: f32) -> Self {
+        Self {
+            width,
+            align: Align::Left,
+        }
+    }
+
+    pub const fn right(width: f32) -> Self {
+        Self {
+            width,
+            align: Align::Right,
+        }
+    }
+}
+
+/// A document being laid out
+pub struct PdfDocument {
+    title: String,
+    footer: String,
+    pages: Vec<Content>,
+    page: Content,
+    /// Distance of the cursor from the top edge of the page
+    y: f32,
+    images: Vec<Image>,
+}
+
+impl PdfDocument {
+    pub fn new(title: &str) -> Self {
+        Self {
+            title: title.to_string(),
+            footer: String::new(),
+            pages: Vec::new(),
+            page: Content::new(),
+            y: MARGIN,
+            images: Vec::new(),
+        }
+    }
+
+    /// Text printed at the bottom left of every page, next to the page number.
+    pub fn set_footer(&mut self, footer: &str) {
+        self.footer = footer.to_string();
+    }
+
+    /// Whether `height` points still fit on the current page.
+    pub fn fits(&self, height: f32) -> bool {
+        self.y + height <= PAGE_HEIGHT - MARGIN - FOOTER_HEIGHT
+    }
+
+    /// Height of one line of text at `size`.
+    pub fn line_height(size: f32) -> f32 {
+        size * LINE_SPACING
+    }
+
+    pub fn new_page(&mut self) {
+        let page = std::mem::replace(&mut self.page, Content::new());
+        self.pages.push(page);
+        self.y = MARGIN;
+    }
+
+    /// Vertical space, dropped at a page break.
+    pub fn space(&mut self, height: f32) {
+        if self.fits(height) {
+            self.y += height;
+        } else {
+            self.new_page();
+        }
+    }
+
+    /// Text wrapped at the content width.
+    pub fn paragraph(&mut self, text: &str, font: Font, size: f32) {
+        for line in wrap(text, font, size, CONTENT_WIDTH) {
+            self.line(&[(MARGIN, line.as_str())], font, size);
+        }
+    }
+
+    /// One line of table cells. Cells too long for their column are cut off
+    /// with an ellipsis.
+    pub fn row(&mut self, columns: &[Column], cells: &[&str], font: Font, size: f32) {
+        let mut x = MARGIN;
+        let mut placed = Vec::with_capacity(columns.len());
+        for (column, cell) in columns.iter().zip(cells) {
+            // Keep a small gap between columns
+            let text = truncate(cell, font, size, column.width - 4.0);
+            let offset = match column.align {
+                Align::Left => 0.0,
+                Align::Right => column.width - 4.0 - font.width(&text, size),
+            };
+            placed.push((x + offset, text));
+            x += column.width;
+        }
+
+        let placed: Vec<(f32, &str)> = placed.iter().map(|(x, t)| (*x, t.as_str())).collect();
+        self.line(&placed, font, size);
+    }
+
+    /// Horizontal line across the content width.
+    pub fn rule(&mut self) {
+        if !self.fits(4.0) {
+            self.new_page();
+        }
+        let y = PAGE_HEIGHT - self.y - 2.0;
+        self.page
+            .set_line_width(0.5)
+            .move_to(MARGIN, y)
+            .line_to(PAGE_WIDTH - MARGIN, y)
+            .stroke();
+        self.y += 4.0;
+    }
+
+    /// Image scaled to fit `max_width` × `max_height`, keeping its aspect ratio.
+    pub fn image(&mut self, image: Image, max_width: f32, max_height: f32) {
+        let scale = (max_width / image.width as f32).min(max_height / image.height as f32);
+        let (width, height) = (image.width as f32 * scale, image.height as f32 * scale);
+        if !self.fits(height) {
+            self.new_page();
+        }
+
+        let name = image_name(self.images.len());
+        self.images.push(image);
+        self.page
+            .save_state()
+            .transform([
+                width,
+                0.0,
+                0.0,
+                height,
+                MARGIN,
+                PAGE_HEIGHT - self.y - height,
+            ])
+            .x_object(Name(name.as_bytes()))
+            .restore_state();
+        self.y += height;
+    }
+
+    /// Writes the document with page numbers and returns the PDF bytes.
+    pub fn finish(mut self) -> Vec<u8> {
+        // Skip the current page if a page break left it empty
+        if self.y > MARGIN || self.pages.is_empty() {
+            self.new_page();
+        }
+        let page_count = self.pages.len();
+
+        let mut pdf = Pdf::new();
+        let mut next = Ref::new(1);
+        let catalog_id = next.bump();
+        let tree_id = next.bump();
+        let info_id = next.bump();
+        let font_ids = [next.bump(), next.bump()];
+        let image_ids: Vec<(Ref, Ref)> = self
+            .images
+            .iter()
+            .map(|_| (next.bump(), next.bump()))
+            .collect();
+        let page_ids: Vec<(Ref, Ref)> = (0..page_count)
+            .map(|_| (next.bump(), next.bump()))
+            .collect();
+
+        pdf.catalog(catalog_id).pages(tree_id);
+        pdf.pages(tree_id)
+            .kids(page_ids.iter().map(|(page, _)| *page))
+            .count(page_count as i32);
+
+        let now = Utc::now();
+        pdf.document_info(info_id)
+            .title(TextStr(&self.title))
+            .producer(TextStr("doc-proxy"))
+            .creation_date(
+                Date::new(now.year() as u16)
+                    .month(now.month() as u8)
+                    .day(now.day() as u8)
+                    .hour(now.hour() as u8)
+                    .minute(now.minute() as u8)
+                    .second(now.second() as u8),
+            );
+
+        for (font, id) in [Font::Regular, Font::Bold].into_iter().zip(font_ids) {
+            pdf.type1_font(id)
+                .base_font(Name(font.base_font()))
+                .encoding_predefined(Name(b"WinAnsiEncoding"));
+        }
+
+        for (image, (id, mask_id)) in self.images.iter().zip(&image_ids) {
+            let mut xobject = pdf.image_xobject(*id, &image.data);
+            xobject.filter(match image.encoding {
+                Encoding::Dct => Filter::DctDecode,
+                Encoding::Flate => Filter::FlateDecode,
+            });
+            xobject.width(image.width as i32);
+            xobject.height(image.height as i32);
+            match image.color_space {
+                ColorSpace::Gray => xobject.color_space().device_gray(),
+                ColorSpace::Rgb => xobject.color_space().device_rgb(),
+                ColorSpace::Cmyk => xobject.color_space().device_cmyk(),
+            }
+            xobject.bits_per_component(8);
+            if image.alpha.is_some() {
+                xobject.s_mask(*mask_id);
+            }
+            xobject.finish();
+
+            if let Some(alpha) = &image.alpha {
+                let mut mask = pdf.image_xobject(*mask_id, alpha);
+                mask.filter(Filter::FlateDecode);
+                mask.width(image.width as i32);
+                mask.height(image.height as i32);
+                mask.color_space().device_gray();
+                mask.bits_per_component(8);
+            }
+        }
+
+        let pages = std::mem::take(&mut self.pages);
+        for (index, (mut content, (page_id, content_id))) in
+            pages.into_iter().zip(page_ids).enumerate()
+        {
+            let number = format!("Seite {} von {}", index + 1, page_count);
+            write_footer(&mut content, &self.footer, &number);
+            let stream = miniz_oxide::deflate::compress_to_vec_zlib(&content.finish(), 6);
+            pdf.stream(content_id, &stream).filter(Filter::FlateDecode);
+
+            let mut page = pdf.page(page_id);
+            page.media_box(Rect::new(0.0, 0.0, PAGE_WIDTH, PAGE_HEIGHT))
+                .parent(tree_id)
+                .contents(content_id);
+            let mut resources = page.resources();
+            resources
+                .fonts()
+                .pair(Name(Font::Regular.resource_name()), font_ids[0])
+                .pair(Name(Font::Bold.resource_name()), font_ids[1]);
+            let mut xobjects = resources.x_objects();
+            for (i, (id, _)) in image_ids.iter().enumerate() {
+                xobjects.pair(Name(image_name(i).as_bytes()), *id);
+            }
+        }
+
+        pdf.finish()
+    }
+
+    /// Text runs on one baseline, starting a new page if the line does not fit.
+    fn line(&mut self, runs: &[(f32, &str)], font: Font, size: f32) {
+        let height = Self::line_height(size);
+        if !self.fits(height) {
+            self.new_page();
+        }
+        // Baseline sits one font size below the top of the line
+        let baseline = PAGE_HEIGHT - self.y - size;
+        for (x, text) in runs.iter().filter(|(_, t)| !t.is_empty()) {
+            show_text(&mut self.page, font, size, *x, baseline, text);
+        }
+        self.y += height;
+    }
+}
+
+fn show_text(content: &mut Content, font: Font, size: f32, x: f32, y: f32, text: &str) {
+    content
+        .begin_text()
+        .set_font(Name(font.resource_name()), size)
+        .next_line(x, y)
+        .show(Str(&encode_win_ansi(text)))
+        .end_text();
+}
+
+fn write_footer(content: &mut Content, footer: &str, number: &str) {
+    let size = 8.0;
+    let y = MARGIN / 2.0;
+    content.set_fill_gray(0.4);
+    if !footer.is_empty() {
+        show_text(content, Font::Regular, size, MARGIN, y, footer);
+    }
+    let x = PAGE_WIDTH - MARGIN - Font::Regular.width(number, size);
+    show_text(content, Font::Regular, size, x, y, number);
+}
+
+fn image_name(index: usize) -> String {
+    format!("Im{}", index)
+}
+
+/// Cuts `text` to `width`, marking the cut with an ellipsis.
+fn truncate(text: &str, font: Font, size: f32, width: f32) -> String {
+    if font.width(text, size) <= width {
+        return text.to_string();
+    }
+    let mut cut: String = text.to_string();
+    while !cut.is_empty() && font.width(&cut, size) + font.width("…", size) > width {
+        cut.pop();
+    }
+    format!("{}…", cut.trim_end())
+}
+
+/// Breaks `text` into lines of at most `width`, at spaces where possible.
+fn wrap(text: &str, font: Font, size: f32, width: f32) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            let candidate = if line.is_empty() {
+                word.to_string()
+            } else {
+                format!("{} {}", line, word)
+            };
+            if font.width(&candidate, size) <= width || line.is_empty() {
+                line = candidate;
+            } else {
+                lines.push(std::mem::replace(&mut line, word.to_string()));
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::image::ImageError;
+    use super::*;
+
+    fn page_count(pdf: &[u8]) -> usize {
+        pdf.windows(b"/Type /Page\n".len())
+            .filter(|w| *w == b"/Type /Page\n")
+            .count()
+    }
+
+    fn png(width: u32, height: u32, color: png::ColorType, pixels: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        let mut encoder = png::Encoder::new(&mut out, width, height);
+        encoder.set_color(color);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder
+            .write_header()
+            .unwrap()
+            .write_image_data(pixels)
+            .unwrap();
+        out
+    }
+
+    #[test]
+    fn test_encode_win_ansi() {
+        assert_eq!(encode_win_ansi("Größe"), b"Gr\xf6\xdfe");
+        assert_eq!(encode_win_ansi("5 €"), b"5 \x80");
+        assert_eq!(encode_win_ansi("…–"), b"\x85\x96");
+        assert_eq!(encode_win_ansi("✓"), b"?");
+    }
+
+    #[test]
+    fn test_text_width() {
+        // H 722 + a 556 + l 222 + l 222 + o 556 = 2278
+        assert!((Font::Regular.width("Hallo", 10.0) - 22.78).abs() < 0.001);
+        assert!(Font::Bold.width("Hallo", 10.0) > Font::Regular.width("Hallo", 10.0));
+        assert_eq!(
+            Font::Regular.width("ä", 10.0),
+            Font::Regular.width("a", 10.0)
+        );
+    }
+
+    #[test]
+    fn test_truncate_and_wrap() {
+        let cut = truncate("Körperpflege und Ankleiden", Font::Regular, 9.0, 60.0);
+        assert!(cut.ends_with('…'));
+        assert!(Font::Regular.width(&cut, 9.0) <= 60.0);
+        assert_eq!(truncate("Baden", Font::Regular, 9.0, 60.0), "Baden");
+
+        let lines = wrap("eins zwei drei vier fünf", Font::Regular, 10.0, 50.0);
+        assert!(lines.len() > 1);
+        assert_eq!(lines.join(" "), "eins zwei drei vier fünf");
+    }
+
+    #[test]
+    fn test_document_breaks_pages() {
+        let mut doc = PdfDocument::new("Test");
+        doc.set_footer("Leistungsnachweis");
+        let columns = [Column::left(200.0), Column::right(100.0)];
+        for i in 0..100 {
+            doc.row(
+                &columns,
+                &[&format!("Zeile {}", i), "1"],
+                Font::Regular,
+                9.0,
+            );
+        }
+        let pdf = doc.finish();
+
+        assert!(pdf.starts_with(b"%PDF-"));
+        assert_eq!(page_count(&pdf), 2);
+    }
+
+    #[test]
+    fn test_png_alpha_becomes_soft_mask() {
+        let rgba = png(
+            2,
+            1,
+            png::ColorType::Rgba,
+            &[0, 0, 0, 255, 255, 255, 255, 0],
+        );
+        let image = Image::from_bytes(&rgba).unwrap();
+        assert_eq!((image.width, image.height), (2, 1));
+        assert_eq!(image.color_space, ColorSpace::Rgb);
+        assert!(image.alpha.is_some());
+
+        let gray = png(1, 1, png::ColorType::Grayscale, &[0]);
+        let image = Image::from_bytes(&gray).unwrap();
+        assert_eq!(image.color_space, ColorSpace::Gray);
+        assert!(image.alpha.is_none());
+
+        let mut doc = PdfDocument::new("Bild");
+        doc.image(Image::from_bytes(&rgba).unwrap(), 200.0, 80.0);
+        let pdf = doc.finish();
+        assert!(pdf.windows(6).any(|w| w == b"/SMask"));
+    }
+
+    #[test]
+    fn test_jpeg_frame_header() {
+        // SOI, APP0 with 2 bytes payload, SOF0 with 40x30 RGB
+        let jpeg = [
+            0xff, 0xd8, 0xff, 0xe0, 0x00, 0x04, 0x00, 0x00, 0xff, 0xc0, 0x00, 0x11, 0x08, 0x00,
+            0x1e, 0x00, 0x28, 0x03,
+        ];
+        let image = Image::from_bytes(&jpeg).unwrap();
+        assert_eq!((image.width, image.height), (40, 30));
+        assert_eq!(image.color_space, ColorSpace::Rgb);
+        assert_eq!(image.encoding, Encoding::Dct);
+
+        assert!(Image::from_bytes(&[0xff, 0xd8, 0xff, 0xd9]).is_err());
+        assert!(matches!(
+            Image::from_bytes(b"GIF89a"),
+            Err(ImageError::Unsupported)
+        ));
+    }
+}
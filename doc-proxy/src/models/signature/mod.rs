@@ -0,0 +1,429 @@
+//! Signature images as embedded in the billing XML.
+//!
+//! The declared format is checked against the file signature of the decoded
+//! data. Raster images are decoded and checked for ink, so an empty canvas is
+//! refused; images over the byte limit are re-encoded and, if needed,
+//! downscaled. SVG signatures are rasterized to PNG, since the schemas have
+//! no vector format. PDF cannot be decoded here and must fit the limit as it
+//! is.
+//!
+//! Decoding and rendering take CPU time in proportion to the image; callers
+//! on the async runtime run [`normalize`] on a blocking thread.
+
+mod svg;
+
+use std::io::Cursor;
+
+use image::{
+    DynamicImage, ExtendedColorType, ImageEncoder, ImageReader, Limits, RgbaImage,
+    codecs::{
+        jpeg::JpegEncoder,
+        png::{CompressionType, FilterType, PngEncoder},
+    },
+    imageops,
+};
+use thiserror::Error;
+
+/// Longest side of a decoded image, in pixels
+const MAX_SIDE: u32 = 4096;
+
+/// Largest allocation while decoding, in bytes
+const MAX_DECODE_ALLOC: u64 = 256 * 1024 * 1024;
+
+/// Smallest side an image is downscaled to before giving up
+const MIN_SIDE: u32 = 64;
+
+/// Quality of re-encoded JPEGs
+const JPEG_QUALITY: u8 = 80;
+
+/// Pixels darker than this over white count as ink (0-255)
+const INK_LUMINANCE: u32 = 160;
+
+/// Fewest ink pixels of a signature, absolute and per thousand pixels
+const MIN_INK_PIXELS: usize = 50;
+const MIN_INK_PER_MILLE: usize = 1;
+
+#[derive(Error, Debug)]
+pub enum SignatureImageError {
+    #[error("Signature data is not valid base64")]
+    InvalidBase64,
+
+    #[error("Signature data is not a supported image")]
+    UnknownFormat,
+
+    #[error("Signature declared as {declared} but the data is {actual}")]
+    FormatMismatch {
+        declared: &'static str,
+        actual: &'static str,
+    },
+
+    #[error("Invalid signature image: {0}")]
+    Invalid(String),
+
+    #[error("Signature SVG is too complex: {0}")]
+    TooComplex(&'static str),
+
+    #[error("Signature image is blank")]
+    Blank,
+
+    #[error("Signature image has {size} bytes, at most {max} are allowed")]
+    TooLarge { size: usize, max: usize },
+}
+
+/// File formats recognized by their signature
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileKind {
+    Png,
+    Jpeg,
+    Gif,
+    Tiff,
+    Pdf,
+    Svg,
+}
+
+impl FileKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            FileKind::Png => "PNG",
+            FileKind::Jpeg => "JPEG",
+            FileKind::Gif => "GIF",
+            FileKind::Tiff => "TIFF",
+            FileKind::Pdf => "PDF",
+            FileKind::Svg => "SVG",
+        }
+    }
+
+    /// Detects the format from the first bytes of a file
+    pub fn sniff(data: &[u8]) -> Option<Self> {
+        if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Some(FileKind::Png)
+        } else if data.starts_with(&[0xff, 0xd8, 0xff]) {
+            Some(FileKind::Jpeg)
+        } else if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+            Some(FileKind::Gif)
+        } else if data.starts_with(b"II*\0") || data.starts_with(b"MM\0*") {
+            Some(FileKind::Tiff)
+        } else if data.starts_with(b"%PDF-") {
+            Some(FileKind::Pdf)
+        } else if is_svg(data) {
+            Some(FileKind::Svg)
+        } else {
+            None
+        }
+    }
+
+    fn raster_format(self) -> Option<image::ImageFormat> {
+        match self {
+            FileKind::Png => Some(image::ImageFormat::Png),
+            FileKind::Jpeg => Some(image::ImageFormat::Jpeg),
+            FileKind::Gif => Some(image::ImageFormat::Gif),
+            FileKind::Tiff => Some(image::ImageFormat::Tiff),
+            FileKind::Pdf | FileKind::Svg => None,
+        }
+    }
+}
+
+/// SVG is text: an `<svg` root, possibly after a BOM, XML declaration,
+/// comments or a doctype
+fn is_svg(data: &[u8]) -> bool {
+    let head = &data[..data.len().min(1024)];
+    let Ok(text) = std::str::from_utf8(head).or_else(|e| {
+        // The head may end within a multi-byte character
+        std::str::from_utf8(&head[..e.valid_up_to()])
+    }) else {
+        return false;
+    };
+    let text = text.trim_start_matches('\u{feff}').trim_start();
+    text.starts_with('<') && text.contains("<svg")
+}
+
+/// A signature image ready for embedding
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignatureImage {
+    pub kind: FileKind,
+    pub data: Vec<u8>,
+}
+
+/// Checks decoded signature data against the declared format and
+/// normalizes it to at most `max_bytes`.
+///
+/// Images that fit are kept as uploaded. Larger JPEGs are re-encoded as
+/// JPEG, all other raster formats and SVG as PNG.
+pub fn normalize(
+    data: &[u8],
+    declared: FileKind,
+    max_bytes: usize,
+) -> Result<SignatureImage, SignatureImageError> {
+    let actual = FileKind::sniff(data).ok_or(SignatureImageError::UnknownFormat)?;
+    if actual != declared {
+        return Err(SignatureImageError::FormatMismatch {
+            declared: declared.name(),
+            actual: actual.name(),
+        });
+    }
+
+    let kept = || SignatureImage {
+        kind: actual,
+        data: data.to_vec(),
+    };
+    match actual {
+        FileKind::Svg => {
+            let image = svg::rasterize(data)?;
+            ensure_ink(&image)?;
+            encode_within(image, FileKind::Png, max_bytes)
+        }
+        FileKind::Pdf if data.len() <= max_bytes => Ok(kept()),
+        FileKind::Pdf => Err(SignatureImageError::TooLarge {
+            size: data.len(),
+            max: max_bytes,
+        }),
+        kind => {
+            let image = decode(data, kind)?;
+            ensure_ink(&image)?;
+            if data.len() <= max_bytes {
+                return Ok(kept());
+            }
+            let target = if kind == FileKind::Jpeg {
+                FileKind::Jpeg
+            } else {
+                FileKind::Png
+            };
+            encode_within(image, target, max_bytes)
+        }
+    }
+}
+
+fn decode(data: &[u8], kind: FileKind) -> Result<RgbaImage, SignatureImageError> {
+    let format = kind
+        .raster_format()
+        .ok_or(SignatureImageError::UnknownFormat)?;
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_SIDE);
+    limits.max_image_height = Some(MAX_SIDE);
+    limits.max_alloc = Some(MAX_DECODE_ALLOC);
+
+    let mut reader = ImageReader::with_format(Cursor::new(data), format);
+    reader.limits(limits);
+    let image = reader
+        .decode()
+        .map_err(|e| SignatureImageError::Invalid(e.to_string()))?;
+    Ok(image.into_rgba8())
+}
+
+/// Number of pixels that are dark when shown over white
+fn ink_pixels(image: &RgbaImage) -> usize {
+    image
+        .pixels()
+        .filter(|p| {
+            let [r, g, b, a] = p.0.map(u32::from);
+            let luminance = (299 * r + 587 * g + 114 * b) / 1000;
+            let over_white = (luminance * a + 255 * (255 - a)) / 255;
+            over_white < INK_LUMINANCE
+        })
+        .count()
+}
+
+fn ensure_ink(image: &RgbaImage) -> Result<(), SignatureImageError> {
+    let pixels = image.width() as usize * image.height() as usize;
+    let min = MIN_INK_PIXELS.max(pixels * MIN_INK_PER_MILLE / 1000);
+    if ink_pixels(image) < min {
+        return Err(SignatureImageError::Blank);
+    }
+    Ok(())
+}
+
+/// PNG with the fewest channels that keep the image, at best compression
+fn encode_png(image: &RgbaImage) -> Result<Vec<u8>, SignatureImageError> {
+    let gray = image.pixels().all(|p| p[0] == p[1] && p[1] == p[2]);
+    let opaque = image.pixels().all(|p| p[3] == 255);
+    let image = DynamicImage::ImageRgba8(image.clone());
+    let (samples, color) = match (gray, opaque) {
+        (true, true) => (image.to_luma8().into_raw(), ExtendedColorType::L8),
+        (true, false) => (image.to_luma_alpha8().into_raw(), ExtendedColorType::La8),
+        (false, true) => (image.to_rgb8().into_raw(), ExtendedColorType::Rgb8),
+        (false, false) => (image.to_rgba8().into_raw(), ExtendedColorType::Rgba8),
+    };
+
+    let mut out = Vec::new();
+    PngEncoder::new_with_quality(&mut out, CompressionType::Best, FilterType::Adaptive)
+        .write_image(&samples, image.width(), image.height(), color)
+        .map_err(|e| SignatureImageError::Invalid(e.to_string()))?;
+    Ok(out)
+}
+
+/// JPEG of the image shown over white, as JPEG has no transparency
+fn encode_jpeg(image: &RgbaImage) -> Result<Vec<u8>, SignatureImageError> {
+    let mut rgb = Vec::with_capacity(image.width() as usize * image.height() as usize * 3);
+    for p in image.pixels() {
+        let a = u32::from(p[3]);
+        rgb.extend([p[0], p[1], p[2]].map(|c| ((u32::from(c) * a + 255 * (255 - a)) / 255) as u8));
+    }
+
+    let mut out = Vec::new();
+    JpegEncoder::new_with_quality(&mut out, JPEG_QUALITY)
+        .write_image(&rgb, image.width(), image.height(), ExtendedColorType::Rgb8)
+        .map_err(|e| SignatureImageError::Invalid(e.to_string()))?;
+    Ok(out)
+}
+
+/// `target` image of at most `max_bytes`, halving the size until it fits
+fn encode_within(
+    mut image: RgbaImage,
+    target: FileKind,
+    max_bytes: usize,
+) -> Result<SignatureImage, SignatureImageError> {
+    loop {
+        let data = match target {
+            FileKind::Jpeg => encode_jpeg(&image)?,
+            _ => encode_png(&image)?,
+        };
+        if data.len() <= max_bytes {
+            return Ok(SignatureImage { kind: target, data });
+        }
+        if image.width().min(image.height()) / 2 < MIN_SIDE {
+            return Err(SignatureImageError::TooLarge {
+                size: data.len(),
+                max: max_bytes,
+            });
+        }
+        image = imageops::resize(
+            &image,
+            image.width() / 2,
+            image.height() / 2,
+            imageops::FilterType::Triangle,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::codecs::tiff::TiffEncoder;
+
+    use super::*;
+
+    /// White canvas with a dark diagonal band, or blank
+    fn canvas(width: u32, height: u32, ink: bool) -> RgbaImage {
+        RgbaImage::from_fn(width, height, |x, y| {
+            let stroke = ink && (x as i64 - y as i64 * width as i64 / height as i64).abs() < 4;
+            image::Rgba(if stroke { [20, 20, 40, 255] } else { [255; 4] })
+        })
+    }
+
+    /// Canvas with noise, which compresses badly so only downscaling helps
+    fn noisy_canvas(width: u32, height: u32) -> RgbaImage {
+        let mut image = canvas(width, height, true);
+        let mut state = 0x2545_f491_u32;
+        for (i, v) in image.iter_mut().enumerate() {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            if i % 4 != 3 && *v == 255 {
+                *v = 200 + (state % 56) as u8;
+            }
+        }
+        image
+    }
+
+    fn encode_tiff(image: &RgbaImage) -> Vec<u8> {
+        let mut out = Cursor::new(Vec::new());
+        TiffEncoder::new(&mut out)
+            .write_image(
+                image.as_raw(),
+                image.width(),
+                image.height(),
+                ExtendedColorType::Rgba8,
+            )
+            .unwrap();
+        out.into_inner()
+    }
+
+    #[test]
+    fn test_sniffs_file_signatures() {
+        assert_eq!(
+            FileKind::sniff(b"\x89PNG\r\n\x1a\n...."),
+            Some(FileKind::Png)
+        );
+        assert_eq!(
+            FileKind::sniff(&[0xff, 0xd8, 0xff, 0xe0]),
+            Some(FileKind::Jpeg)
+        );
+        assert_eq!(FileKind::sniff(b"%PDF-1.7"), Some(FileKind::Pdf));
+        assert_eq!(
+            FileKind::sniff(b"<?xml version=\"1.0\"?>\n<svg xmlns=\"...\"/>"),
+            Some(FileKind::Svg)
+        );
+        assert_eq!(FileKind::sniff(b"hello"), None);
+    }
+
+    #[test]
+    fn test_declared_format_must_match() {
+        let png = encode_png(&canvas(200, 80, true)).unwrap();
+        let err = normalize(&png, FileKind::Jpeg, 1 << 20).unwrap_err();
+        assert!(matches!(
+            err,
+            SignatureImageError::FormatMismatch {
+                declared: "JPEG",
+                actual: "PNG"
+            }
+        ));
+    }
+
+    #[test]
+    fn test_blank_canvas_is_refused() {
+        let blank = canvas(200, 80, false);
+        for (data, kind) in [
+            (encode_png(&blank).unwrap(), FileKind::Png),
+            (encode_jpeg(&blank).unwrap(), FileKind::Jpeg),
+            (encode_tiff(&blank), FileKind::Tiff),
+        ] {
+            assert!(matches!(
+                normalize(&data, kind, 1 << 20),
+                Err(SignatureImageError::Blank)
+            ));
+        }
+
+        let signed = encode_png(&canvas(200, 80, true)).unwrap();
+        let image = normalize(&signed, FileKind::Png, 1 << 20).unwrap();
+        assert_eq!(image.data, signed);
+    }
+
+    #[test]
+    fn test_oversized_png_is_shrunk_to_fit() {
+        let png = encode_png(&noisy_canvas(512, 256)).unwrap();
+        let max = png.len() / 3;
+
+        let image = normalize(&png, FileKind::Png, max).unwrap();
+        assert_eq!(image.kind, FileKind::Png);
+        assert!(image.data.len() <= max);
+        let shrunk = decode(&image.data, FileKind::Png).unwrap();
+        assert!(shrunk.width() < 512);
+
+        assert!(matches!(
+            normalize(&png, FileKind::Png, 100),
+            Err(SignatureImageError::TooLarge { max: 100, .. })
+        ));
+    }
+
+    #[test]
+    fn test_oversized_jpeg_and_tiff_are_reencoded() {
+        let jpeg = encode_jpeg(&noisy_canvas(512, 256)).unwrap();
+        let image = normalize(&jpeg, FileKind::Jpeg, jpeg.len() / 2).unwrap();
+        assert_eq!(image.kind, FileKind::Jpeg);
+        assert!(image.data.len() <= jpeg.len() / 2);
+
+        let tiff = encode_tiff(&canvas(400, 160, true));
+        let image = normalize(&tiff, FileKind::Tiff, tiff.len() / 4).unwrap();
+        assert_eq!(image.kind, FileKind::Png);
+        assert!(decode(&image.data, FileKind::Png).is_ok());
+    }
+
+    #[test]
+    fn test_pdf_passes_within_limit() {
+        let pdf = b"%PDF-1.7\n%%EOF\n";
+        assert_eq!(normalize(pdf, FileKind::Pdf, 100).unwrap().data, pdf);
+        assert!(matches!(
+            normalize(pdf, FileKind::Pdf, 4),
+            Err(SignatureImageError::TooLarge { size: 15, max: 4 })
+        ));
+    }
+}
@@ -0,0 +1,224 @@
+//! Rasterizing signature SVGs with resvg.
+//!
+//! Signature pads export strokes as paths, so the drawing is limited to that:
+//! elements and path points are counted, strokes may not be wider than a
+//! tenth of the drawing, and `<use>`, text, images, filters, masks and
+//! patterns are refused. External files are never loaded.
+
+use resvg::{
+    tiny_skia::{Pixmap, Transform},
+    usvg::{self, Group, ImageHrefResolver, Node, Paint},
+};
+use roxmltree::{Document, ParsingOptions};
+
+use super::SignatureImageError;
+
+/// Longest side of the raster; larger drawings are scaled down
+const MAX_SIDE: f32 = 2048.0;
+
+/// Elements in the document
+const MAX_ELEMENTS: usize = 10_000;
+
+/// Points of all paths, after shapes were converted to paths
+const MAX_POINTS: usize = 50_000;
+
+/// Widest stroke relative to the longer side of the drawing
+const MAX_STROKE_RATIO: f32 = 0.1;
+
+fn invalid(message: impl std::fmt::Display) -> SignatureImageError {
+    SignatureImageError::Invalid(format!("SVG {}", message))
+}
+
+/// Draws an SVG on a transparent raster
+pub(super) fn rasterize(data: &[u8]) -> Result<image::RgbaImage, SignatureImageError> {
+    let text = std::str::from_utf8(data).map_err(|_| invalid("is not UTF-8"))?;
+    let options = ParsingOptions {
+        allow_dtd: true,
+        ..ParsingOptions::default()
+    };
+    let document = Document::parse_with_options(text, options).map_err(invalid)?;
+    check_elements(&document)?;
+
+    let options = usvg::Options {
+        image_href_resolver: ImageHrefResolver {
+            resolve_data: Box::new(|_, _, _| None),
+            resolve_string: Box::new(|_, _| None),
+        },
+        ..usvg::Options::default()
+    };
+    let tree = usvg::Tree::from_xmltree(&document, &options).map_err(invalid)?;
+
+    let size = tree.size();
+    let longer = size.width().max(size.height());
+    let mut points = 0;
+    check_group(tree.root(), longer, &mut points)?;
+
+    let scale = (MAX_SIDE / longer).min(1.0);
+    let width = (size.width() * scale).ceil() as u32;
+    let height = (size.height() * scale).ceil() as u32;
+    let mut pixmap =
+        Pixmap::new(width.max(1), height.max(1)).ok_or_else(|| invalid("has an invalid size"))?;
+    resvg::render(
+        &tree,
+        Transform::from_scale(scale, scale),
+        &mut pixmap.as_mut(),
+    );
+
+    let rgba = pixmap
+        .pixels()
+        .iter()
+        .flat_map(|p| {
+            let c = p.demultiply();
+            [c.red(), c.green(), c.blue(), c.alpha()]
+        })
+        .collect();
+    image::RgbaImage::from_raw(pixmap.width(), pixmap.height(), rgba)
+        .ok_or_else(|| invalid("could not be drawn"))
+}
+
+/// Refuses large documents and elements that are no strokes: `<use>` can
+/// multiply the drawing, and usvg drops images it does not load
+fn check_elements(document: &Document) -> Result<(), SignatureImageError> {
+    let mut elements = 0;
+    for node in document.descendants().filter(|n| n.is_element()) {
+        elements += 1;
+        if elements > MAX_ELEMENTS {
+            return Err(SignatureImageError::TooComplex("too many elements"));
+        }
+        match node.tag_name().name() {
+            "use" => return Err(SignatureImageError::TooComplex("<use> is not supported")),
+            "image" | "feImage" => {
+                return Err(SignatureImageError::TooComplex(
+                    "embedded images are not supported",
+                ));
+            }
+            "text" => return Err(SignatureImageError::TooComplex("text is not supported")),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn check_group(group: &Group, longer: f32, points: &mut usize) -> Result<(), SignatureImageError> {
+    if !group.filters().is_empty() || group.mask().is_some() {
+        return Err(SignatureImageError::TooComplex(
+            "filters and masks are not supported",
+        ));
+    }
+    if let Some(clip) = group.clip_path() {
+        check_group(clip.root(), longer, points)?;
+    }
+
+    for node in group.children() {
+        match node {
+            Node::Group(group) => check_group(group, longer, points)?,
+            Node::Path(path) => {
+                *points += path.data().points().len();
+                if *points > MAX_POINTS {
+                    return Err(SignatureImageError::TooComplex("too many path points"));
+                }
+                let paints = path
+                    .fill()
+                    .map(|f| f.paint())
+                    .into_iter()
+                    .chain(path.stroke().map(|s| s.paint()));
+                for paint in paints {
+                    if matches!(paint, Paint::Pattern(_)) {
+                        return Err(SignatureImageError::TooComplex(
+                            "patterns are not supported",
+                        ));
+                    }
+                }
+                if let Some(stroke) = path.stroke() {
+                    let (sx, sy) = path.abs_transform().get_scale();
+                    if stroke.width().get() * sx.max(sy) > longer * MAX_STROKE_RATIO {
+                        return Err(SignatureImageError::TooComplex("stroke is too wide"));
+                    }
+                }
+            }
+            Node::Image(_) => {
+                return Err(SignatureImageError::TooComplex(
+                    "embedded images are not supported",
+                ));
+            }
+            Node::Text(_) => {
+                return Err(SignatureImageError::TooComplex("text is not supported"));
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ink(image: &image::RgbaImage) -> usize {
+        image.pixels().filter(|p| p[3] > 128).count()
+    }
+
+    #[test]
+    fn test_draws_paths_shapes_and_transforms() {
+        let svg = br##"<?xml version="1.0"?>
+            <svg xmlns="http://www.w3.org/2000/svg" width="400" height="100" viewBox="0 0 200 50">
+              <g transform="translate(10 5) rotate(5)" stroke="#123" fill="none" stroke-width="2">
+                <path d="M0 20 C 20 0, 40 40, 60 20 A 10 10 0 0 1 80 20"/>
+                <polyline points="90,10 100,30 110,10"/>
+                <circle cx="150" cy="20" r="8"/>
+              </g>
+            </svg>"##;
+        let image = rasterize(svg).unwrap();
+        assert_eq!((image.width(), image.height()), (400, 100));
+        assert!(ink(&image) > 500);
+    }
+
+    #[test]
+    fn test_large_drawing_is_scaled_down() {
+        let svg = br#"<svg xmlns="http://www.w3.org/2000/svg" width="8000" height="2000">
+            <path d="M0 0 L8000 2000" stroke="black" stroke-width="20"/></svg>"#;
+        let image = rasterize(svg).unwrap();
+        assert_eq!((image.width(), image.height()), (2048, 512));
+    }
+
+    #[test]
+    fn test_empty_drawing_has_no_ink() {
+        let svg = br#"<svg xmlns="http://www.w3.org/2000/svg" width="200" height="80"/>"#;
+        assert_eq!(ink(&rasterize(svg).unwrap()), 0);
+    }
+
+    #[test]
+    fn test_refuses_costly_drawings() {
+        let points = (0..60_000)
+            .map(|i| format!("{} {}", i % 200, i % 80))
+            .collect::<Vec<_>>()
+            .join(" L");
+        let many_points = format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="200" height="80">
+            <path d="M{}" stroke="black"/></svg>"#,
+            points
+        );
+        let wide_stroke = r#"<svg xmlns="http://www.w3.org/2000/svg" width="200" height="80">
+            <path d="M0 0 L200 80" stroke="black" stroke-width="1" transform="scale(40)"/></svg>"#;
+        let uses = r##"<svg xmlns="http://www.w3.org/2000/svg"
+            xmlns:xlink="http://www.w3.org/1999/xlink" width="200" height="80">
+            <path id="a" d="M0 0 L10 10" stroke="black"/><use xlink:href="#a"/></svg>"##;
+        let image = r#"<svg xmlns="http://www.w3.org/2000/svg"
+            xmlns:xlink="http://www.w3.org/1999/xlink" width="200" height="80">
+            <image width="10" height="10" xlink:href="/etc/passwd"/></svg>"#;
+
+        for svg in [many_points.as_str(), wide_stroke, uses, image] {
+            assert!(matches!(
+                rasterize(svg.as_bytes()),
+                Err(SignatureImageError::TooComplex(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_refuses_malformed_svg() {
+        assert!(matches!(
+            rasterize(b"<svg xmlns=\"http://www.w3.org/2000/svg\"><path"),
+            Err(SignatureImageError::Invalid(_))
+        ));
+    }
+}
@@ -1,21 +1,78 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use crate::config::auth::UserRole;
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct UserResponse {
+    pub id: Uuid,
+    pub username: String,
+    pub name: String,
+    pub email: Option<String>,
+    pub role: UserRole,
+    pub is_active: bool,
+    pub locked_until: Option<DateTime<Utc>>,
+    pub last_login: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    /// Versicherter a client account belongs to
+    pub versichertennummer: Option<String>,
+    /// Versichertennummern of the clients assigned to a staff account
+    pub assigned_clients: Vec<String>,
+    /// Devices bound to the user via `devices.user_id`
+    pub devices: Vec<UserDeviceResponse>,
+}
+
+/// Device bound to a user
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UserDeviceResponse {
     pub id: Uuid,
     pub name: String,
-    pub email: String,
+    pub mac_address: String,
+    pub is_active: bool,
+    pub last_seen: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct CreateUserRequest {
+    pub username: String,
     pub name: String,
-    pub email: String,
+    pub email: Option<String>,
+    pub password: String,
+    pub role: Option<UserRole>,
+    /// Required for client accounts
+    pub versichertennummer: Option<String>,
+    /// Clients a staff account may access
+    #[serde(default)]
+    pub assigned_clients: Vec<String>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct UpdateUserRequest {
     pub name: Option<String>,
     pub email: Option<String>,
+    pub role: Option<UserRole>,
+    pub is_active: Option<bool>,
+    /// New password; also lifts a login lockout
+    pub password: Option<String>,
+    pub versichertennummer: Option<String>,
+    /// Replaces the clients assigned to a staff account
+    pub assigned_clients: Option<Vec<String>>,
+}
+
+/// Query parameters for listing users
+#[derive(Debug, Deserialize)]
+pub struct ListUsersQuery {
+    /// Page number (0-indexed), defaults to 0
+    #[serde(default)]
+    pub page: u64,
+    /// Page size, defaults to 20
+    #[serde(default = "default_page_size")]
+    pub size: u64,
+    /// Case-insensitive search in name and e-mail
+    pub search: Option<String>,
+}
+
+fn default_page_size() -> u64 {
+    20
 }
@@ -0,0 +1,37 @@
+//! Error types for loading XSD schemas.
+
+use thiserror::Error;
+
+/// Errors that can occur while loading a set of XSD schemas.
+#[derive(Error, Debug)]
+pub enum SchemaError {
+    /// The schema document is not well-formed XML
+    #[error("Schema {location} is not well-formed: {source}")]
+    Xml {
+        location: String,
+        source: roxmltree::Error,
+    },
+
+    /// An `xs:import` points to a schema that is not part of the set
+    #[error("Schema {location} imports missing schema {import}")]
+    MissingImport { location: String, import: String },
+
+    /// A type reference could not be resolved against the loaded schemas
+    #[error("Unresolved type reference: {0}")]
+    UnresolvedType(String),
+
+    /// A QName prefix is not bound to a namespace
+    #[error("Unbound namespace prefix in {0}")]
+    UnboundPrefix(String),
+
+    /// An `xs:pattern` facet could not be compiled
+    #[error("Invalid pattern `{pattern}`: {source}")]
+    InvalidPattern {
+        pattern: String,
+        source: regex::Error,
+    },
+
+    /// The schema uses an XSD construct the validator does not support
+    #[error("Unsupported schema construct: {0}")]
+    Unsupported(String),
+}
@@ -0,0 +1,220 @@
+//! XSD validation for the GKV data exchange schemas.
+//!
+//! Implements the subset of XML Schema used by the HKP/SLP schemas shipped
+//! in `examples/`: global and local element declarations, named and anonymous
+//! complex types with `xs:sequence`/`xs:choice` content and min/max occurs,
+//! and simple types derived by restriction (length, pattern, enumeration,
+//! whiteSpace, min/max inclusive, fraction/total digits) across imported
+//! namespaces.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use crate::models::xsd;
+//!
+//! let violations = xsd::schemas().validate(xml);
+//! for v in &violations {
+//!     println!("{}: {}", v.xpath, v.message);
+//! }
+//! ```
+
+pub mod error;
+mod schema;
+mod simple;
+mod validate;
+
+use std::sync::LazyLock;
+
+pub use schema::{QName, SchemaSet, XS_NS};
+pub use validate::Violation;
+
+/// Namespace of the shared SLP base types (`SLP_BAS_1.0.0.xsd`).
+pub const NS_SLP_BAS: &str = "http://www.gkv-datenaustausch.de/XMLSchema/SLP_BAS/1.0";
+/// Namespace of the HKP Leistungsnachweis (`HKP_LNW_1.0.0.xsd`).
+pub const NS_SLE_LWN: &str = "http://www.gkv-datenaustausch.de/XMLSchema/SLE_LWN/1.0";
+/// Namespace of the Abrechnungsfall (`HKP_ARF_1.0.0.xsd`).
+pub const NS_HKP_ARF: &str = "http://www.gkv-datenaustausch.de/XMLSchema/HKP_ARF/1.0";
+/// Namespace of the Abrechnungsnachricht (`HKP_ARN_1.0.0.xsd`).
+pub const NS_HKP_ARN: &str = "http://www.gkv-datenaustausch.de/XMLSchema/HKP_ARN/1.0";
+/// Namespace of the technical error message (`HKP_FEH_TECH_1.0.0.xsd`).
+pub const NS_HKP_FEH_TECH: &str = "http://www.gkv-datenaustausch.de/XMLSchema/HKP_FEH_TECH/1.0";
+/// Namespace of the transport envelope (`HKP_DAT_1.0.0.xsd`).
+pub const NS_SLP: &str = "http://www.gkv-datenaustausch.de/XMLSchema/SLP";
+
+/// Schema documents shipped in `examples/`, keyed by their `schemaLocation`.
+const SHIPPED_SCHEMAS: &[(&str, &str)] = &[
+    (
+        "SLP_BAS_1.0.0.xsd",
+        include_str!("../../../examples/SLP_BAS_1.0.0.xsd"),
+    ),
+    (
+        "HKP_BAS_1.0.0.xsd",
+        include_str!("../../../examples/HKP_BAS_1.0.0.xsd"),
+    ),
+    (
+        "HKP_LNW_1.0.0.xsd",
+        include_str!("../../../examples/HKP_LNW_1.0.0.xsd"),
+    ),
+    (
+        "HKP_ARF_1.0.0.xsd",
+        include_str!("../../../examples/HKP_ARF_1.0.0.xsd"),
+    ),
+    (
+        "HKP_ARN_1.0.0.xsd",
+        include_str!("../../../examples/HKP_ARN_1.0.0.xsd"),
+    ),
+    (
+        "HKP_FEH_TECH_1.0.0.xsd",
+        include_str!("../../../examples/HKP_FEH_TECH_1.0.0.xsd"),
+    ),
+    (
+        "HKP_DAT_1.0.0.xsd",
+        include_str!("../../../examples/HKP_DAT_1.0.0.xsd"),
+    ),
+];
+
+static SCHEMAS: LazyLock<SchemaSet> =
+    LazyLock::new(|| SchemaSet::load(SHIPPED_SCHEMAS).expect("Shipped XSD schemas must load"));
+
+/// Returns the schema set compiled from the schemas shipped in `examples/`.
+pub fn schemas() -> &'static SchemaSet {
+    &SCHEMAS
+}
+
+/// Checks a value against a simple type from the SLP base schema.
+pub fn check_slp_bas(type_name: &str, value: &str) -> Result<(), String> {
+    schemas().check_simple_type(&QName::new(NS_SLP_BAS, type_name), value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VALID_LNW: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Leistungsnachweis xmlns="http://www.gkv-datenaustausch.de/XMLSchema/SLE_LWN/1.0">
+    <Leistungserbringer_IK>123456789</Leistungserbringer_IK>
+    <Krankenversichertennummer>A123456789</Krankenversichertennummer>
+    <Verantwortliche_Fachkraft>111222333</Verantwortliche_Fachkraft>
+    <Einsatz>
+        <Beginn_Datum_Uhrzeit>2024-11-15T08:00:00</Beginn_Datum_Uhrzeit>
+        <Ende_Uhrzeit>08:30:00</Ende_Uhrzeit>
+        <Leistungsnachweis_Position>
+            <Positions_Nr>010101</Positions_Nr>
+            <Menge>1.00</Menge>
+            <Beschäftigtennummer>987654321</Beschäftigtennummer>
+        </Leistungsnachweis_Position>
+    </Einsatz>
+    <Unterschrift_Versicherter>
+        <Art_der_Unterschrift>1</Art_der_Unterschrift>
+        <Datum_Uhrzeit_der_Unterschrift>2024-11-15T08:30:00</Datum_Uhrzeit_der_Unterschrift>
+        <Unterschrift>
+            <Datei>iVBORw0KGgo=</Datei>
+            <Dateityp>3</Dateityp>
+        </Unterschrift>
+    </Unterschrift_Versicherter>
+    <Leistungsnachweis-ID>550e8400-e29b-41d4-a716-446655440000</Leistungsnachweis-ID>
+</Leistungsnachweis>"#;
+
+    #[test]
+    fn test_shipped_schemas_load() {
+        let set = schemas();
+        assert!(
+            set.element(&QName::new(NS_SLE_LWN, "Leistungsnachweis"))
+                .is_some()
+        );
+        assert!(set.element(&QName::new(NS_SLP, "Nutzdaten")).is_some());
+        assert!(
+            set.complex_type(&QName::new(NS_HKP_ARN, "Abrechnungsnachricht_Ctp"))
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn test_valid_hkp_lnw() {
+        let violations = schemas().validate(VALID_LNW);
+        assert!(
+            violations.is_empty(),
+            "unexpected violations: {:?}",
+            violations
+        );
+    }
+
+    #[test]
+    fn test_simple_type_violations_are_located() {
+        let xml = VALID_LNW
+            .replace(
+                "<Leistungserbringer_IK>123456789<",
+                "<Leistungserbringer_IK>12345<",
+            )
+            .replace("<Menge>1.00<", "<Menge>12.345<")
+            .replace("<Dateityp>3<", "<Dateityp>7<");
+        let violations = schemas().validate(&xml);
+
+        let xpaths: Vec<&str> = violations.iter().map(|v| v.xpath.as_str()).collect();
+        assert_eq!(
+            xpaths,
+            vec![
+                "/Leistungsnachweis/Leistungserbringer_IK",
+                "/Leistungsnachweis/Einsatz[1]/Leistungsnachweis_Position[1]/Menge",
+                "/Leistungsnachweis/Unterschrift_Versicherter/Unterschrift/Dateityp",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_structure_violations() {
+        let xml = VALID_LNW
+            .replace(
+                "<Verantwortliche_Fachkraft>111222333</Verantwortliche_Fachkraft>",
+                "",
+            )
+            .replace(
+                "<Beschäftigtennummer>987654321</Beschäftigtennummer>",
+                "<Beschäftigtennummer>987654321</Beschäftigtennummer>\
+                 <Beschäftigtennummer>987654322</Beschäftigtennummer>\
+                 <Beschäftigtennummer>987654323</Beschäftigtennummer>",
+            );
+        let violations = schemas().validate(&xml);
+
+        assert!(
+            violations.iter().any(|v| v.xpath == "/Leistungsnachweis"
+                && v.message.contains("Verantwortliche_Fachkraft"))
+        );
+        assert!(violations.iter().any(|v| v.xpath
+            == "/Leistungsnachweis/Einsatz[1]/Leistungsnachweis_Position[1]/Beschäftigtennummer[3]"
+            && v.message.contains("Unexpected")));
+    }
+
+    #[test]
+    fn test_choice_requires_one_alternative() {
+        let xml = VALID_LNW.replace(
+            "<Unterschrift>\n            <Datei>iVBORw0KGgo=</Datei>\n            <Dateityp>3</Dateityp>\n        </Unterschrift>",
+            "",
+        );
+        let violations = schemas().validate(&xml);
+
+        assert_eq!(violations.len(), 1);
+        assert_eq!(
+            violations[0].xpath,
+            "/Leistungsnachweis/Unterschrift_Versicherter"
+        );
+        assert!(violations[0].message.contains("fehlende_Unterschrift"));
+    }
+
+    #[test]
+    fn test_unknown_root() {
+        let violations = schemas().validate("<Foo/>");
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].xpath, "/Foo");
+    }
+
+    #[test]
+    fn test_check_slp_bas() {
+        assert!(check_slp_bas("Institutionskennzeichen_Stp", "123456789").is_ok());
+        assert!(check_slp_bas("Institutionskennzeichen_Stp", "12345678X").is_err());
+        assert!(check_slp_bas("Abrechnungsmonat_Stp", "202413").is_err());
+        assert!(check_slp_bas("Anzahl_Dezimal_Stp", "9999.99").is_ok());
+        assert!(check_slp_bas("Anzahl_Dezimal_Stp", "10000").is_err());
+        assert!(check_slp_bas("Name_Stp", "  Müller  ").is_ok());
+    }
+}
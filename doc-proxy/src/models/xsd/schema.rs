@@ -0,0 +1,521 @@
+//! In-memory model of the XSD subset used by the GKV data exchange schemas,
+//! and the loader that builds it from schema documents.
+
+use std::collections::HashMap;
+
+use regex::Regex;
+use roxmltree::{Document, Node};
+
+use super::error::SchemaError;
+
+/// Namespace of the XML Schema language itself.
+pub const XS_NS: &str = "http://www.w3.org/2001/XMLSchema";
+
+/// Namespace-qualified name of an element or type.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct QName {
+    pub ns: String,
+    pub local: String,
+}
+
+impl QName {
+    pub fn new(ns: impl Into<String>, local: impl Into<String>) -> Self {
+        Self {
+            ns: ns.into(),
+            local: local.into(),
+        }
+    }
+
+    /// Returns true if this name refers to a built-in XSD datatype.
+    pub fn is_builtin(&self) -> bool {
+        self.ns == XS_NS
+    }
+}
+
+impl std::fmt::Display for QName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{{{}}}{}", self.ns, self.local)
+    }
+}
+
+/// Upper bound of `maxOccurs`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MaxOccurs {
+    Bounded(u32),
+    Unbounded,
+}
+
+impl MaxOccurs {
+    pub fn allows(&self, count: u32) -> bool {
+        match self {
+            MaxOccurs::Bounded(max) => count < *max,
+            MaxOccurs::Unbounded => true,
+        }
+    }
+
+    pub fn is_repeatable(&self) -> bool {
+        *self != MaxOccurs::Bounded(1)
+    }
+}
+
+/// Type of an element declaration.
+#[derive(Debug)]
+pub enum TypeRef {
+    /// Reference to a named simple or complex type (or a built-in datatype)
+    Named(QName),
+    /// Anonymous simple type declared inline
+    Simple(Box<SimpleType>),
+    /// Anonymous complex type declared inline
+    Complex(Box<ComplexType>),
+}
+
+/// Element declaration (global or local).
+#[derive(Debug)]
+pub struct ElementDecl {
+    pub name: QName,
+    pub ty: TypeRef,
+}
+
+/// Content model particle with its occurrence constraints.
+#[derive(Debug)]
+pub struct Particle {
+    pub kind: ParticleKind,
+    pub min_occurs: u32,
+    pub max_occurs: MaxOccurs,
+}
+
+#[derive(Debug)]
+pub enum ParticleKind {
+    Element(ElementDecl),
+    Sequence(Vec<Particle>),
+    Choice(Vec<Particle>),
+}
+
+/// Complex type with element-only content.
+#[derive(Debug)]
+pub struct ComplexType {
+    pub content: Option<Particle>,
+}
+
+/// `xs:whiteSpace` facet values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WhiteSpace {
+    Preserve,
+    Replace,
+    Collapse,
+}
+
+/// Constraining facets of a simple type restriction.
+#[derive(Debug, Default)]
+pub struct Facets {
+    pub length: Option<usize>,
+    pub min_length: Option<usize>,
+    pub max_length: Option<usize>,
+    /// Compiled patterns with their source; a value must match at least one
+    pub patterns: Vec<(String, Regex)>,
+    pub enumeration: Vec<String>,
+    pub white_space: Option<WhiteSpace>,
+    pub min_inclusive: Option<String>,
+    pub max_inclusive: Option<String>,
+    pub fraction_digits: Option<u32>,
+    pub total_digits: Option<u32>,
+}
+
+/// Simple type derived by restriction.
+#[derive(Debug)]
+pub struct SimpleType {
+    pub name: Option<QName>,
+    pub base: QName,
+    pub facets: Facets,
+}
+
+/// A set of schema documents with their global declarations merged
+/// across namespaces, so that `xs:import`s resolve within the set.
+#[derive(Debug, Default)]
+pub struct SchemaSet {
+    pub(super) elements: HashMap<QName, ElementDecl>,
+    pub(super) simple_types: HashMap<QName, SimpleType>,
+    pub(super) complex_types: HashMap<QName, ComplexType>,
+}
+
+/// Built-in datatypes the validator knows how to check.
+pub const SUPPORTED_BUILTINS: &[&str] = &[
+    "string",
+    "normalizedString",
+    "token",
+    "decimal",
+    "integer",
+    "date",
+    "dateTime",
+    "time",
+    "base64Binary",
+    "boolean",
+];
+
+impl SchemaSet {
+    /// Loads schema documents given as `(schemaLocation, content)` pairs.
+    ///
+    /// Every `xs:import` must refer to a location contained in `sources`,
+    /// and every type reference must resolve once all documents are loaded.
+    pub fn load(sources: &[(&str, &str)]) -> Result<Self, SchemaError> {
+        let mut set = SchemaSet::default();
+
+        for (location, content) in sources {
+            let doc = Document::parse(content).map_err(|source| SchemaError::Xml {
+                location: location.to_string(),
+                source,
+            })?;
+            set.load_document(location, &doc, sources)?;
+        }
+
+        set.check_references()?;
+        Ok(set)
+    }
+
+    /// Looks up a global element declaration.
+    pub fn element(&self, name: &QName) -> Option<&ElementDecl> {
+        self.elements.get(name)
+    }
+
+    /// Looks up a named simple type.
+    pub fn simple_type(&self, name: &QName) -> Option<&SimpleType> {
+        self.simple_types.get(name)
+    }
+
+    /// Looks up a named complex type.
+    pub fn complex_type(&self, name: &QName) -> Option<&ComplexType> {
+        self.complex_types.get(name)
+    }
+
+    fn load_document(
+        &mut self,
+        location: &str,
+        doc: &Document,
+        sources: &[(&str, &str)],
+    ) -> Result<(), SchemaError> {
+        let root = doc.root_element();
+        let target_ns = root.attribute("targetNamespace").unwrap_or("").to_string();
+        let ctx = Context {
+            target_ns: &target_ns,
+            qualified: root.attribute("elementFormDefault") == Some("qualified"),
+        };
+
+        for child in xs_children(root) {
+            match child.tag_name().name() {
+                "import" | "annotation" => {
+                    if let Some(import) = child.attribute("schemaLocation")
+                        && !sources.iter().any(|(loc, _)| *loc == import)
+                    {
+                        return Err(SchemaError::MissingImport {
+                            location: location.to_string(),
+                            import: import.to_string(),
+                        });
+                    }
+                }
+                "element" => {
+                    let decl = parse_element_decl(child, &ctx, true)?;
+                    self.elements.insert(decl.name.clone(), decl);
+                }
+                "simpleType" => {
+                    let st = parse_simple_type(child, &ctx)?;
+                    if let Some(name) = st.name.clone() {
+                        self.simple_types.insert(name, st);
+                    }
+                }
+                "complexType" => {
+                    let name = required_name(child, &ctx)?;
+                    let ct = parse_complex_type(child, &ctx)?;
+                    self.complex_types.insert(name, ct);
+                }
+                other => {
+                    return Err(SchemaError::Unsupported(format!(
+                        "top-level xs:{} in {}",
+                        other, location
+                    )));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Verifies that all type references resolve within the set.
+    fn check_references(&self) -> Result<(), SchemaError> {
+        for st in self.simple_types.values() {
+            self.check_simple_base(st)?;
+        }
+        for ct in self.complex_types.values() {
+            self.check_complex(ct)?;
+        }
+        for decl in self.elements.values() {
+            self.check_type_ref(&decl.ty)?;
+        }
+        Ok(())
+    }
+
+    fn check_simple_base(&self, st: &SimpleType) -> Result<(), SchemaError> {
+        if st.base.is_builtin() {
+            if SUPPORTED_BUILTINS.contains(&st.base.local.as_str()) {
+                return Ok(());
+            }
+            return Err(SchemaError::Unsupported(format!(
+                "built-in type xs:{}",
+                st.base.local
+            )));
+        }
+        if self.simple_types.contains_key(&st.base) {
+            Ok(())
+        } else {
+            Err(SchemaError::UnresolvedType(st.base.to_string()))
+        }
+    }
+
+    fn check_complex(&self, ct: &ComplexType) -> Result<(), SchemaError> {
+        match &ct.content {
+            Some(particle) => self.check_particle(particle),
+            None => Ok(()),
+        }
+    }
+
+    fn check_particle(&self, particle: &Particle) -> Result<(), SchemaError> {
+        match &particle.kind {
+            ParticleKind::Element(decl) => self.check_type_ref(&decl.ty),
+            ParticleKind::Sequence(items) | ParticleKind::Choice(items) => {
+                items.iter().try_for_each(|p| self.check_particle(p))
+            }
+        }
+    }
+
+    fn check_type_ref(&self, ty: &TypeRef) -> Result<(), SchemaError> {
+        match ty {
+            TypeRef::Named(name) if name.is_builtin() => {
+                if SUPPORTED_BUILTINS.contains(&name.local.as_str()) {
+                    Ok(())
+                } else {
+                    Err(SchemaError::Unsupported(format!(
+                        "built-in type xs:{}",
+                        name.local
+                    )))
+                }
+            }
+            TypeRef::Named(name) => {
+                if self.simple_types.contains_key(name) || self.complex_types.contains_key(name) {
+                    Ok(())
+                } else {
+                    Err(SchemaError::UnresolvedType(name.to_string()))
+                }
+            }
+            TypeRef::Simple(st) => self.check_simple_base(st),
+            TypeRef::Complex(ct) => self.check_complex(ct),
+        }
+    }
+}
+
+// ============================================================================
+// Schema document parsing
+// ============================================================================
+
+struct Context<'a> {
+    target_ns: &'a str,
+    qualified: bool,
+}
+
+/// Iterates over the `xs:*` element children of a schema node.
+fn xs_children<'a, 'input>(node: Node<'a, 'input>) -> impl Iterator<Item = Node<'a, 'input>> {
+    node.children()
+        .filter(|n| n.is_element() && n.tag_name().namespace() == Some(XS_NS))
+}
+
+fn required_name(node: Node, ctx: &Context) -> Result<QName, SchemaError> {
+    node.attribute("name")
+        .map(|name| QName::new(ctx.target_ns, name))
+        .ok_or_else(|| {
+            SchemaError::Unsupported(format!("anonymous top-level xs:{}", node.tag_name().name()))
+        })
+}
+
+/// Resolves a `prefix:local` QName using the namespace bindings in scope.
+fn resolve_qname(node: Node, value: &str) -> Result<QName, SchemaError> {
+    let (prefix, local) = match value.split_once(':') {
+        Some((prefix, local)) => (Some(prefix), local),
+        None => (None, value),
+    };
+    let ns = node
+        .lookup_namespace_uri(prefix)
+        .ok_or_else(|| SchemaError::UnboundPrefix(value.to_string()))?;
+    Ok(QName::new(ns, local))
+}
+
+fn parse_occurs(node: Node) -> Result<(u32, MaxOccurs), SchemaError> {
+    let min = match node.attribute("minOccurs") {
+        Some(v) => v
+            .parse()
+            .map_err(|_| SchemaError::Unsupported(format!("minOccurs=\"{}\"", v)))?,
+        None => 1,
+    };
+    let max = match node.attribute("maxOccurs") {
+        Some("unbounded") => MaxOccurs::Unbounded,
+        Some(v) => MaxOccurs::Bounded(
+            v.parse()
+                .map_err(|_| SchemaError::Unsupported(format!("maxOccurs=\"{}\"", v)))?,
+        ),
+        None => MaxOccurs::Bounded(1),
+    };
+    Ok((min, max))
+}
+
+fn parse_element_decl(node: Node, ctx: &Context, global: bool) -> Result<ElementDecl, SchemaError> {
+    if node.has_attribute("ref") {
+        return Err(SchemaError::Unsupported("xs:element ref".into()));
+    }
+
+    let local = node
+        .attribute("name")
+        .ok_or_else(|| SchemaError::Unsupported("xs:element without name".into()))?;
+    let ns = if global || ctx.qualified {
+        ctx.target_ns
+    } else {
+        ""
+    };
+
+    let ty = if let Some(type_name) = node.attribute("type") {
+        TypeRef::Named(resolve_qname(node, type_name)?)
+    } else if let Some(ct) = xs_children(node).find(|n| n.has_tag_name((XS_NS, "complexType"))) {
+        TypeRef::Complex(Box::new(parse_complex_type(ct, ctx)?))
+    } else if let Some(st) = xs_children(node).find(|n| n.has_tag_name((XS_NS, "simpleType"))) {
+        TypeRef::Simple(Box::new(parse_simple_type(st, ctx)?))
+    } else {
+        // No type given: xs:anyType, which we treat as an unrestricted string
+        TypeRef::Named(QName::new(XS_NS, "string"))
+    };
+
+    Ok(ElementDecl {
+        name: QName::new(ns, local),
+        ty,
+    })
+}
+
+fn parse_complex_type(node: Node, ctx: &Context) -> Result<ComplexType, SchemaError> {
+    let mut content = None;
+
+    for child in xs_children(node) {
+        match child.tag_name().name() {
+            "annotation" => {}
+            "sequence" | "choice" => content = Some(parse_particle(child, ctx)?),
+            other => {
+                return Err(SchemaError::Unsupported(format!(
+                    "xs:{} in complexType",
+                    other
+                )));
+            }
+        }
+    }
+
+    Ok(ComplexType { content })
+}
+
+fn parse_particle(node: Node, ctx: &Context) -> Result<Particle, SchemaError> {
+    let (min_occurs, max_occurs) = parse_occurs(node)?;
+
+    let kind = match node.tag_name().name() {
+        "element" => ParticleKind::Element(parse_element_decl(node, ctx, false)?),
+        group @ ("sequence" | "choice") => {
+            let items = xs_children(node)
+                .filter(|n| !n.has_tag_name((XS_NS, "annotation")))
+                .map(|n| parse_particle(n, ctx))
+                .collect::<Result<Vec<_>, _>>()?;
+            if group == "sequence" {
+                ParticleKind::Sequence(items)
+            } else {
+                ParticleKind::Choice(items)
+            }
+        }
+        other => return Err(SchemaError::Unsupported(format!("xs:{} particle", other))),
+    };
+
+    Ok(Particle {
+        kind,
+        min_occurs,
+        max_occurs,
+    })
+}
+
+fn parse_simple_type(node: Node, ctx: &Context) -> Result<SimpleType, SchemaError> {
+    let name = node
+        .attribute("name")
+        .map(|name| QName::new(ctx.target_ns, name));
+
+    let restriction = xs_children(node)
+        .find(|n| n.has_tag_name((XS_NS, "restriction")))
+        .ok_or_else(|| {
+            SchemaError::Unsupported("simpleType without xs:restriction (list/union)".into())
+        })?;
+
+    let base = restriction
+        .attribute("base")
+        .ok_or_else(|| SchemaError::Unsupported("xs:restriction without base".into()))?;
+    let base = resolve_qname(restriction, base)?;
+
+    Ok(SimpleType {
+        name,
+        base,
+        facets: parse_facets(restriction)?,
+    })
+}
+
+fn parse_facets(restriction: Node) -> Result<Facets, SchemaError> {
+    let mut facets = Facets::default();
+
+    for facet in xs_children(restriction) {
+        let facet_name = facet.tag_name().name();
+        if facet_name == "annotation" {
+            continue;
+        }
+
+        let value = facet
+            .attribute("value")
+            .ok_or_else(|| SchemaError::Unsupported(format!("xs:{} without value", facet_name)))?;
+        let as_number = || {
+            value
+                .parse::<u32>()
+                .map_err(|_| SchemaError::Unsupported(format!("xs:{}=\"{}\"", facet_name, value)))
+        };
+
+        match facet_name {
+            "length" => facets.length = Some(as_number()? as usize),
+            "minLength" => facets.min_length = Some(as_number()? as usize),
+            "maxLength" => facets.max_length = Some(as_number()? as usize),
+            "fractionDigits" => facets.fraction_digits = Some(as_number()?),
+            "totalDigits" => facets.total_digits = Some(as_number()?),
+            "minInclusive" => facets.min_inclusive = Some(value.to_string()),
+            "maxInclusive" => facets.max_inclusive = Some(value.to_string()),
+            "enumeration" => facets.enumeration.push(value.to_string()),
+            "pattern" => {
+                // XSD patterns are implicitly anchored at both ends
+                let regex = Regex::new(&format!("^(?:{})$", value)).map_err(|source| {
+                    SchemaError::InvalidPattern {
+                        pattern: value.to_string(),
+                        source,
+                    }
+                })?;
+                facets.patterns.push((value.to_string(), regex));
+            }
+            "whiteSpace" => {
+                facets.white_space = Some(match value {
+                    "preserve" => WhiteSpace::Preserve,
+                    "replace" => WhiteSpace::Replace,
+                    "collapse" => WhiteSpace::Collapse,
+                    other => {
+                        return Err(SchemaError::Unsupported(format!(
+                            "xs:whiteSpace=\"{}\"",
+                            other
+                        )));
+                    }
+                })
+            }
+            other => return Err(SchemaError::Unsupported(format!("facet xs:{}", other))),
+        }
+    }
+
+    Ok(facets)
+}
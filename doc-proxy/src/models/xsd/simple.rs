@@ -0,0 +1,246 @@
+//! Checking of text values against simple types and their facets.
+
+use base64::Engine;
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+
+use super::schema::{Facets, QName, SchemaSet, SimpleType, WhiteSpace};
+
+impl SchemaSet {
+    /// Checks `value` against the named simple type (or built-in datatype).
+    ///
+    /// Returns a human-readable reason if the value is not valid.
+    pub fn check_simple_type(&self, ty: &QName, value: &str) -> Result<(), String> {
+        if ty.is_builtin() {
+            let normalized = normalize(value, builtin_white_space(&ty.local));
+            return check_builtin(&ty.local, &normalized);
+        }
+
+        let st = self
+            .simple_type(ty)
+            .ok_or_else(|| format!("unknown simple type {}", ty))?;
+        self.check_simple(st, value)
+    }
+
+    /// Checks `value` against a (possibly anonymous) simple type.
+    pub(super) fn check_simple(&self, st: &SimpleType, value: &str) -> Result<(), String> {
+        // Collect the derivation chain, most derived type first
+        let mut chain = vec![st];
+        let mut base = &st.base;
+        while !base.is_builtin() {
+            let parent = self
+                .simple_type(base)
+                .ok_or_else(|| format!("unknown simple type {}", base))?;
+            chain.push(parent);
+            base = &parent.base;
+        }
+        let builtin = base.local.as_str();
+
+        let white_space = chain
+            .iter()
+            .find_map(|t| t.facets.white_space)
+            .unwrap_or_else(|| builtin_white_space(builtin));
+        let normalized = normalize(value, white_space);
+
+        check_builtin(builtin, &normalized)?;
+
+        for t in &chain {
+            let type_name = t
+                .name
+                .as_ref()
+                .map(|n| n.local.as_str())
+                .unwrap_or("anonymous type");
+            check_facets(&t.facets, builtin, &normalized)
+                .map_err(|reason| format!("{} ({})", reason, type_name))?;
+        }
+
+        Ok(())
+    }
+}
+
+fn builtin_white_space(builtin: &str) -> WhiteSpace {
+    match builtin {
+        "string" => WhiteSpace::Preserve,
+        "normalizedString" => WhiteSpace::Replace,
+        _ => WhiteSpace::Collapse,
+    }
+}
+
+fn normalize(value: &str, white_space: WhiteSpace) -> String {
+    match white_space {
+        WhiteSpace::Preserve => value.to_string(),
+        WhiteSpace::Replace => value
+            .chars()
+            .map(|c| {
+                if matches!(c, '\t' | '\n' | '\r') {
+                    ' '
+                } else {
+                    c
+                }
+            })
+            .collect(),
+        WhiteSpace::Collapse => value.split_whitespace().collect::<Vec<_>>().join(" "),
+    }
+}
+
+/// Checks the lexical space of a built-in datatype.
+fn check_builtin(builtin: &str, value: &str) -> Result<(), String> {
+    let valid = match builtin {
+        "string" | "normalizedString" | "token" => true,
+        "decimal" => parse_decimal(value).is_some(),
+        "integer" => {
+            let digits = value.strip_prefix(['+', '-']).unwrap_or(value);
+            !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit())
+        }
+        "boolean" => matches!(value, "true" | "false" | "1" | "0"),
+        "date" => NaiveDate::parse_from_str(strip_timezone(value), "%Y-%m-%d").is_ok(),
+        "dateTime" => {
+            NaiveDateTime::parse_from_str(strip_timezone(value), "%Y-%m-%dT%H:%M:%S%.f").is_ok()
+        }
+        "time" => NaiveTime::parse_from_str(strip_timezone(value), "%H:%M:%S%.f").is_ok(),
+        "base64Binary" => {
+            let compact: String = value.chars().filter(|c| !c.is_whitespace()).collect();
+            base64::engine::general_purpose::STANDARD
+                .decode(compact)
+                .is_ok()
+        }
+        other => return Err(format!("unsupported built-in type xs:{}", other)),
+    };
+
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("`{}` is not a valid xs:{}", value, builtin))
+    }
+}
+
+/// Strips an optional `Z` or `±hh:mm` timezone suffix from a date/time value.
+fn strip_timezone(value: &str) -> &str {
+    if let Some(stripped) = value.strip_suffix('Z') {
+        return stripped;
+    }
+    let bytes = value.as_bytes();
+    if bytes.len() > 6 {
+        let tz = &value[value.len() - 6..];
+        let tz_bytes = tz.as_bytes();
+        if matches!(tz_bytes[0], b'+' | b'-')
+            && tz_bytes[3] == b':'
+            && tz[1..3].chars().all(|c| c.is_ascii_digit())
+            && tz[4..6].chars().all(|c| c.is_ascii_digit())
+        {
+            return &value[..value.len() - 6];
+        }
+    }
+    value
+}
+
+/// Lexical decimal: returns (integer digits, fraction digits) without sign.
+fn parse_decimal(value: &str) -> Option<(&str, &str)> {
+    let unsigned = value.strip_prefix(['+', '-']).unwrap_or(value);
+    let (int_part, frac_part) = unsigned.split_once('.').unwrap_or((unsigned, ""));
+    let all_digits = |s: &str| s.chars().all(|c| c.is_ascii_digit());
+
+    if (int_part.is_empty() && frac_part.is_empty())
+        || !all_digits(int_part)
+        || !all_digits(frac_part)
+    {
+        return None;
+    }
+    Some((int_part, frac_part))
+}
+
+fn check_facets(facets: &Facets, builtin: &str, value: &str) -> Result<(), String> {
+    let len = value.chars().count();
+
+    if let Some(length) = facets.length
+        && len != length
+    {
+        return Err(format!(
+            "`{}` must be exactly {} characters, got {}",
+            value, length, len
+        ));
+    }
+    if let Some(min) = facets.min_length
+        && len < min
+    {
+        return Err(format!(
+            "`{}` must be at least {} characters, got {}",
+            value, min, len
+        ));
+    }
+    if let Some(max) = facets.max_length
+        && len > max
+    {
+        return Err(format!(
+            "value must be at most {} characters, got {}",
+            max, len
+        ));
+    }
+
+    if !facets.patterns.is_empty() && !facets.patterns.iter().any(|(_, re)| re.is_match(value)) {
+        let patterns: Vec<&str> = facets
+            .patterns
+            .iter()
+            .map(|(src, _)| src.as_str())
+            .collect();
+        return Err(format!(
+            "`{}` does not match pattern `{}`",
+            value,
+            patterns.join("|")
+        ));
+    }
+
+    if !facets.enumeration.is_empty() && !facets.enumeration.iter().any(|e| e == value) {
+        return Err(format!(
+            "`{}` is not one of [{}]",
+            value,
+            facets.enumeration.join(", ")
+        ));
+    }
+
+    if matches!(builtin, "decimal" | "integer") {
+        check_numeric_facets(facets, value)?;
+    }
+
+    Ok(())
+}
+
+fn check_numeric_facets(facets: &Facets, value: &str) -> Result<(), String> {
+    let Some((int_part, frac_part)) = parse_decimal(value) else {
+        return Err(format!("`{}` is not a valid number", value));
+    };
+    let int_digits = int_part.trim_start_matches('0').len();
+    let frac_digits = frac_part.trim_end_matches('0').len();
+
+    if let Some(max) = facets.fraction_digits
+        && frac_digits > max as usize
+    {
+        return Err(format!("`{}` has more than {} fraction digits", value, max));
+    }
+    if let Some(max) = facets.total_digits
+        && int_digits + frac_digits > max as usize
+    {
+        return Err(format!("`{}` has more than {} total digits", value, max));
+    }
+
+    let number: f64 = value
+        .parse()
+        .map_err(|_| format!("`{}` is not a valid number", value))?;
+    if let Some(min) = facets
+        .min_inclusive
+        .as_deref()
+        .and_then(|m| m.parse::<f64>().ok())
+        && number < min
+    {
+        return Err(format!("`{}` is less than the minimum {}", value, min));
+    }
+    if let Some(max) = facets
+        .max_inclusive
+        .as_deref()
+        .and_then(|m| m.parse::<f64>().ok())
+        && number > max
+    {
+        return Err(format!("`{}` is greater than the maximum {}", value, max));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,353 @@
+//! Validation of XML instance documents against a [`SchemaSet`].
+
+use roxmltree::{Document, Node};
+use serde::Serialize;
+
+use super::schema::{ComplexType, ElementDecl, Particle, ParticleKind, QName, SchemaSet, TypeRef};
+
+/// A single schema violation, located by an XPath expression.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Violation {
+    /// Location of the offending node, e.g. `/Leistungsnachweis/Einsatz[2]/Ende_Uhrzeit`
+    pub xpath: String,
+    /// Human-readable description of the violation
+    pub message: String,
+}
+
+impl Violation {
+    pub fn new(xpath: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            xpath: xpath.into(),
+            message: message.into(),
+        }
+    }
+
+    /// Joins violations into a single `; `-separated message.
+    pub fn join(violations: &[Violation]) -> String {
+        violations
+            .iter()
+            .map(Violation::to_string)
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+}
+
+impl std::fmt::Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.xpath, self.message)
+    }
+}
+
+/// Resolved type of an element during validation.
+enum Resolved<'a> {
+    Simple(SimpleRef<'a>),
+    Complex(&'a ComplexType),
+}
+
+enum SimpleRef<'a> {
+    Named(&'a QName),
+    Inline(&'a super::schema::SimpleType),
+}
+
+impl SchemaSet {
+    /// Validates an XML document against the global element declaration
+    /// matching its root element.
+    ///
+    /// Returns every violation found; an empty list means the document is valid.
+    pub fn validate(&self, xml: &str) -> Vec<Violation> {
+        let doc = match Document::parse(xml) {
+            Ok(doc) => doc,
+            Err(e) => return vec![Violation::new("/", format!("Malformed XML: {}", e))],
+        };
+
+        let root = doc.root_element();
+        let root_name = node_name(root);
+        let xpath = format!("/{}", root_name.local);
+
+        let Some(decl) = self.element(&root_name) else {
+            return vec![Violation::new(
+                xpath,
+                format!("No schema declares root element {}", root_name),
+            )];
+        };
+
+        let mut violations = Vec::new();
+        self.validate_element(root, decl, &xpath, &mut violations);
+        violations
+    }
+
+    /// Validates an XML document whose root element is not declared globally,
+    /// checking the root name against `root` and its content against the
+    /// named complex type `ty` (e.g. `Abrechnungsnachricht_Ctp`).
+    pub fn validate_as(&self, xml: &str, root: &QName, ty: &QName) -> Vec<Violation> {
+        let doc = match Document::parse(xml) {
+            Ok(doc) => doc,
+            Err(e) => return vec![Violation::new("/", format!("Malformed XML: {}", e))],
+        };
+
+        let node = doc.root_element();
+        let root_name = node_name(node);
+        let xpath = format!("/{}", root_name.local);
+
+        if &root_name != root {
+            return vec![Violation::new(
+                xpath,
+                format!("Expected root element {}, found {}", root, root_name),
+            )];
+        }
+
+        let decl = ElementDecl {
+            name: root.clone(),
+            ty: TypeRef::Named(ty.clone()),
+        };
+        let mut violations = Vec::new();
+        self.validate_element(node, &decl, &xpath, &mut violations);
+        violations
+    }
+
+    fn resolve<'a>(&'a self, ty: &'a TypeRef) -> Option<Resolved<'a>> {
+        match ty {
+            TypeRef::Named(name) if name.is_builtin() => {
+                Some(Resolved::Simple(SimpleRef::Named(name)))
+            }
+            TypeRef::Named(name) => {
+                if self.simple_type(name).is_some() {
+                    Some(Resolved::Simple(SimpleRef::Named(name)))
+                } else {
+                    self.complex_type(name).map(Resolved::Complex)
+                }
+            }
+            TypeRef::Simple(st) => Some(Resolved::Simple(SimpleRef::Inline(st))),
+            TypeRef::Complex(ct) => Some(Resolved::Complex(ct)),
+        }
+    }
+
+    fn validate_element(
+        &self,
+        node: Node,
+        decl: &ElementDecl,
+        xpath: &str,
+        out: &mut Vec<Violation>,
+    ) {
+        for attr in node.attributes() {
+            // xsi:* attributes (schemaLocation, type, nil) are allowed anywhere
+            if attr.namespace() != Some("http://www.w3.org/2001/XMLSchema-instance") {
+                out.push(Violation::new(
+                    format!("{}/@{}", xpath, attr.name()),
+                    format!("Attribute `{}` is not allowed", attr.name()),
+                ));
+            }
+        }
+
+        let Some(resolved) = self.resolve(&decl.ty) else {
+            out.push(Violation::new(xpath, "Element has an unresolvable type"));
+            return;
+        };
+
+        match resolved {
+            Resolved::Simple(simple) => {
+                if node.children().any(|c| c.is_element()) {
+                    out.push(Violation::new(
+                        xpath,
+                        "Element must contain text only, found child elements",
+                    ));
+                    return;
+                }
+                let text: String = node
+                    .children()
+                    .filter(|c| c.is_text())
+                    .filter_map(|c| c.text())
+                    .collect();
+                let result = match simple {
+                    SimpleRef::Named(name) => self.check_simple_type(name, &text),
+                    SimpleRef::Inline(st) => self.check_simple(st, &text),
+                };
+                if let Err(reason) = result {
+                    out.push(Violation::new(xpath, reason));
+                }
+            }
+            Resolved::Complex(ct) => self.validate_complex(node, ct, xpath, out),
+        }
+    }
+
+    fn validate_complex(
+        &self,
+        node: Node,
+        ct: &ComplexType,
+        xpath: &str,
+        out: &mut Vec<Violation>,
+    ) {
+        if node
+            .children()
+            .any(|c| c.is_text() && !c.text().unwrap_or("").trim().is_empty())
+        {
+            out.push(Violation::new(xpath, "Text content is not allowed here"));
+        }
+
+        let children: Vec<Node> = node.children().filter(|c| c.is_element()).collect();
+        let mut cursor = Cursor {
+            children: &children,
+            pos: 0,
+            parent: xpath,
+        };
+
+        if let Some(particle) = &ct.content {
+            self.match_particle(particle, &mut cursor, out);
+        }
+
+        for child in &children[cursor.pos..] {
+            out.push(Violation::new(
+                cursor.child_xpath(*child, true),
+                format!("Unexpected element `{}`", child.tag_name().name()),
+            ));
+        }
+    }
+
+    /// Matches a particle greedily against the children at the cursor.
+    fn match_particle(&self, particle: &Particle, cursor: &mut Cursor, out: &mut Vec<Violation>) {
+        let mut count = 0;
+
+        while particle.max_occurs.allows(count) {
+            let Some(next) = cursor.peek() else { break };
+            if count >= particle.min_occurs && !first_accepts(particle, &node_name(next)) {
+                break;
+            }
+
+            let start = cursor.pos;
+            match &particle.kind {
+                ParticleKind::Element(decl) => {
+                    if node_name(next) != decl.name {
+                        break;
+                    }
+                    let xpath = cursor.child_xpath(next, particle.max_occurs.is_repeatable());
+                    cursor.pos += 1;
+                    self.validate_element(next, decl, &xpath, out);
+                }
+                ParticleKind::Sequence(items) => {
+                    for item in items {
+                        self.match_particle(item, cursor, out);
+                    }
+                }
+                ParticleKind::Choice(items) => {
+                    let name = node_name(next);
+                    match items.iter().find(|p| first_accepts(p, &name)) {
+                        Some(alternative) => self.match_particle(alternative, cursor, out),
+                        None => break,
+                    }
+                }
+            }
+            count += 1;
+
+            // Guard against looping on groups that matched nothing
+            if cursor.pos == start && count >= particle.min_occurs {
+                break;
+            }
+        }
+
+        if count < particle.min_occurs && !nullable(particle) {
+            let expected = expected_names(particle).join("`, `");
+            let found = match cursor.peek() {
+                Some(n) => format!("found `{}`", n.tag_name().name()),
+                None => "found end of element".to_string(),
+            };
+            let message = match &particle.kind {
+                ParticleKind::Element(_) if particle.min_occurs > 1 => format!(
+                    "Element `{}` must occur at least {} times, found {}",
+                    expected, particle.min_occurs, count
+                ),
+                ParticleKind::Element(_) => {
+                    format!("Missing required element `{}`, {}", expected, found)
+                }
+                _ => format!("Expected one of `{}`, {}", expected, found),
+            };
+            out.push(Violation::new(cursor.parent, message));
+        }
+    }
+}
+
+/// Position within the element children of a complex element.
+struct Cursor<'a, 'doc, 'input> {
+    children: &'a [Node<'doc, 'input>],
+    pos: usize,
+    parent: &'a str,
+}
+
+impl<'doc, 'input> Cursor<'_, 'doc, 'input> {
+    fn peek(&self) -> Option<Node<'doc, 'input>> {
+        self.children.get(self.pos).copied()
+    }
+
+    /// XPath of a child, with a 1-based position predicate if requested.
+    fn child_xpath(&self, child: Node, indexed: bool) -> String {
+        let local = child.tag_name().name();
+        if !indexed {
+            return format!("{}/{}", self.parent, local);
+        }
+        let name = node_name(child);
+        let index = self
+            .children
+            .iter()
+            .take_while(|c| c.id() != child.id())
+            .filter(|c| node_name(**c) == name)
+            .count()
+            + 1;
+        format!("{}/{}[{}]", self.parent, local, index)
+    }
+}
+
+fn node_name(node: Node) -> QName {
+    QName::new(
+        node.tag_name().namespace().unwrap_or(""),
+        node.tag_name().name(),
+    )
+}
+
+/// Returns true if the particle can start with an element named `name`.
+fn first_accepts(particle: &Particle, name: &QName) -> bool {
+    match &particle.kind {
+        ParticleKind::Element(decl) => decl.name == *name,
+        ParticleKind::Choice(items) => items.iter().any(|p| first_accepts(p, name)),
+        ParticleKind::Sequence(items) => {
+            for item in items {
+                if first_accepts(item, name) {
+                    return true;
+                }
+                if !nullable(item) {
+                    return false;
+                }
+            }
+            false
+        }
+    }
+}
+
+/// Returns true if the particle may match no elements at all.
+fn nullable(particle: &Particle) -> bool {
+    if particle.min_occurs == 0 {
+        return true;
+    }
+    match &particle.kind {
+        ParticleKind::Element(_) => false,
+        ParticleKind::Sequence(items) => items.iter().all(nullable),
+        ParticleKind::Choice(items) => items.iter().any(nullable),
+    }
+}
+
+/// Local names of the elements a particle can start with, for error messages.
+fn expected_names(particle: &Particle) -> Vec<String> {
+    match &particle.kind {
+        ParticleKind::Element(decl) => vec![decl.name.local.clone()],
+        ParticleKind::Choice(items) => items.iter().flat_map(expected_names).collect(),
+        ParticleKind::Sequence(items) => {
+            let mut names = Vec::new();
+            for item in items {
+                names.extend(expected_names(item));
+                if !nullable(item) {
+                    break;
+                }
+            }
+            names
+        }
+    }
+}
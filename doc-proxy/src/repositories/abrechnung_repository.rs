@@ -0,0 +1,125 @@
+use chrono::Utc;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection, DbBackend, DbErr,
+    EntityTrait, QueryFilter, QueryOrder, Set, Statement,
+};
+use uuid::Uuid;
+
+use super::entity::{
+    abrechnung::{self, Entity as Abrechnung},
+    abrechnung_leistungsnachweis::{self, Entity as AbrechnungLeistungsnachweis},
+};
+
+/// Invoice to persist after its XML has been generated
+#[derive(Debug, Clone)]
+pub struct NewAbrechnung {
+    pub id: Uuid,
+    pub rechnungsnummer: String,
+    pub rechnungssteller_ik: String,
+    pub kostentraeger_ik: String,
+    pub rechnungsempfaenger_ik: String,
+    pub abrechnungsmonat: String,
+    pub rechnungsdatum: chrono::NaiveDate,
+    pub gesamtbetrag: rust_decimal::Decimal,
+    pub anzahl_faelle: i32,
+    pub xml: String,
+}
+
+/// Reserve the next running invoice number for a Rechnungssteller and year.
+///
+/// Runs as a single upsert so concurrent requests never receive the same
+/// number. Call inside the transaction that stores the invoice, so a failed
+/// request does not consume a number.
+pub async fn next_rechnungsnummer<C: ConnectionTrait>(
+    db: &C,
+    rechnungssteller_ik: &str,
+    jahr: i32,
+) -> Result<i64, DbErr> {
+    let row = db
+        .query_one(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            r#"INSERT INTO rechnungsnummern (rechnungssteller_ik, jahr, letzte_nummer)
+               VALUES ($1, $2, 1)
+               ON CONFLICT (rechnungssteller_ik, jahr)
+               DO UPDATE SET letzte_nummer = rechnungsnummern.letzte_nummer + 1
+               RETURNING letzte_nummer"#,
+            [rechnungssteller_ik.into(), jahr.into()],
+        ))
+        .await?
+        .ok_or_else(|| DbErr::RecordNotInserted)?;
+
+    row.try_get("", "letzte_nummer")
+}
+
+/// Store a generated invoice
+pub async fn create<C: ConnectionTrait>(
+    db: &C,
+    new: NewAbrechnung,
+) -> Result<abrechnung::Model, DbErr> {
+    let abrechnung = abrechnung::ActiveModel {
+        id: Set(new.id),
+        rechnungsnummer: Set(new.rechnungsnummer),
+        rechnungssteller_ik: Set(new.rechnungssteller_ik),
+        kostentraeger_ik: Set(new.kostentraeger_ik),
+        rechnungsempfaenger_ik: Set(new.rechnungsempfaenger_ik),
+        abrechnungsmonat: Set(new.abrechnungsmonat),
+        rechnungsdatum: Set(new.rechnungsdatum),
+        gesamtbetrag: Set(new.gesamtbetrag),
+        anzahl_faelle: Set(new.anzahl_faelle),
+        xml: Set(new.xml),
+        created_at: Set(Utc::now()),
+    };
+
+    abrechnung.insert(db).await
+}
+
+/// Record the Leistungsnachweise an invoice bills.
+///
+/// Fails with a unique constraint violation if one of them is already
+/// billed, also when a concurrent request billed it first.
+pub async fn link_leistungsnachweise<C: ConnectionTrait>(
+    db: &C,
+    abrechnung_id: Uuid,
+    leistungsnachweis_ids: &[String],
+) -> Result<(), DbErr> {
+    let now = Utc::now();
+    AbrechnungLeistungsnachweis::insert_many(leistungsnachweis_ids.iter().map(|id| {
+        abrechnung_leistungsnachweis::ActiveModel {
+            leistungsnachweis_id: Set(id.clone()),
+            abrechnung_id: Set(abrechnung_id),
+            created_at: Set(now),
+        }
+    }))
+    .exec_without_returning(db)
+    .await?;
+    Ok(())
+}
+
+/// Find the billed Leistungsnachweise among the given IDs
+pub async fn find_billed<C: ConnectionTrait>(
+    db: &C,
+    leistungsnachweis_ids: &[String],
+) -> Result<Vec<abrechnung_leistungsnachweis::Model>, DbErr> {
+    AbrechnungLeistungsnachweis::find()
+        .filter(
+            abrechnung_leistungsnachweis::Column::LeistungsnachweisId.is_in(leistungsnachweis_ids),
+        )
+        .all(db)
+        .await
+}
+
+/// Find all invoices, newest first
+pub async fn find_all(db: &DatabaseConnection) -> Result<Vec<abrechnung::Model>, DbErr> {
+    Abrechnung::find()
+        .order_by_desc(abrechnung::Column::CreatedAt)
+        .all(db)
+        .await
+}
+
+/// Find invoice by Abrechnung_ID
+pub async fn find_by_id(
+    db: &DatabaseConnection,
+    id: Uuid,
+) -> Result<Option<abrechnung::Model>, DbErr> {
+    Abrechnung::find_by_id(id).one(db).await
+}
@@ -0,0 +1,302 @@
+//! Append-only audit log of signatures and security-relevant actions.
+//!
+//! Every entry stores the hash of its predecessor and a hash over its own
+//! columns, so deleting, reordering or editing entries breaks the chain.
+//! The database refuses updates and deletes; the chain also catches changes
+//! made around those triggers, e.g. directly in a restored dump.
+
+use chrono::{SecondsFormat, SubsecRound, Utc};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection, DbErr, EntityTrait,
+    IntoActiveModel, PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, Select,
+    TransactionTrait,
+};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use super::entity::audit_log::{self, Entity as AuditLog};
+use crate::config::audit::AuditContext;
+use crate::models::audit::{AuditAction, AuditEntryResponse, AuditFilter, ChainVerification};
+use crate::models::pagination::{PageResult, Pageable};
+
+/// `prev_hash` of the first entry
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Advisory lock serializing appends, so the chain never forks
+const APPEND_LOCK_KEY: i64 = 0x6175_6469_745f_6c6f; // "audit_lo"
+
+/// Entries loaded at once while verifying the chain
+const VERIFY_BATCH_SIZE: u64 = 1000;
+
+impl From<audit_log::Model> for AuditEntryResponse {
+    fn from(entry: audit_log::Model) -> Self {
+        Self {
+            seq: entry.seq,
+            occurred_at: entry.occurred_at,
+            actor: entry.actor,
+            device_id: entry.device_id,
+            client_ip: entry.client_ip,
+            user_agent: entry.user_agent,
+            action: entry.action,
+            target_id: entry.target_id,
+            payload_digest: entry.payload_digest,
+            prev_hash: entry.prev_hash,
+            hash: entry.hash,
+        }
+    }
+}
+
+/// SHA-256 of a request payload as stored in `payload_digest`
+pub fn payload_digest<T: Serialize>(payload: &T) -> String {
+    let bytes = serde_json::to_vec(payload).unwrap_or_default();
+    hex::encode(Sha256::digest(bytes))
+}
+
+/// Hash over all columns of an entry except `hash` itself.
+///
+/// Fields are length-prefixed, so moving text between adjacent columns
+/// changes the hash; missing values get a length no string can have.
+fn entry_hash(entry: &audit_log::Model) -> String {
+    let device_id = entry.device_id.map(|id| id.to_string());
+    let occurred_at = entry
+        .occurred_at
+        .to_rfc3339_opts(SecondsFormat::Micros, true);
+    let fields: [Option<&str>; 9] = [
+        Some(&entry.prev_hash),
+        Some(&occurred_at),
+        entry.actor.as_deref(),
+        device_id.as_deref(),
+        entry.client_ip.as_deref(),
+        entry.user_agent.as_deref(),
+        Some(&entry.action),
+        entry.target_id.as_deref(),
+        entry.payload_digest.as_deref(),
+    ];
+
+    let mut hasher = Sha256::new();
+    hasher.update(entry.seq.to_be_bytes());
+    for field in fields {
+        match field {
+            Some(value) => {
+                hasher.update((value.len() as u64).to_be_bytes());
+                hasher.update(value.as_bytes());
+            }
+            None => hasher.update(u64::MAX.to_be_bytes()),
+        }
+    }
+    hex::encode(hasher.finalize())
+}
+
+/// Append an entry to the end of the chain
+pub async fn append(
+    db: &DatabaseConnection,
+    context: &AuditContext,
+    action: AuditAction,
+    target_id: Option<&str>,
+    payload_digest: Option<String>,
+) -> Result<audit_log::Model, DbErr> {
+    let txn = db.begin().await?;
+    txn.execute_unprepared(&format!("SELECT pg_advisory_xact_lock({APPEND_LOCK_KEY})"))
+        .await?;
+
+    let last = AuditLog::find()
+        .order_by_desc(audit_log::Column::Seq)
+        .one(&txn)
+        .await?;
+    let (seq, prev_hash) = match last {
+        Some(last) => (last.seq + 1, last.hash),
+        None => (1, GENESIS_HASH.to_string()),
+    };
+
+    let mut entry = audit_log::Model {
+        seq,
+        // Postgres keeps microseconds; the hash must survive the round trip
+        occurred_at: Utc::now().trunc_subsecs(6),
+        actor: context.actor.clone(),
+        device_id: context.device_id,
+        client_ip: context.client_ip.clone(),
+        user_agent: context.user_agent.clone(),
+        action: action.as_str().to_string(),
+        target_id: target_id.map(str::to_string),
+        payload_digest,
+        prev_hash,
+        hash: String::new(),
+    };
+    entry.hash = entry_hash(&entry);
+
+    let entry = entry.into_active_model().reset_all().insert(&txn).await?;
+    txn.commit().await?;
+
+    Ok(entry)
+}
+
+fn filtered(filter: &AuditFilter) -> Select<AuditLog> {
+    let mut query = AuditLog::find();
+    if let Some(actor) = &filter.actor {
+        query = query.filter(audit_log::Column::Actor.eq(actor));
+    }
+    if let Some(action) = filter.action {
+        query = query.filter(audit_log::Column::Action.eq(action.as_str()));
+    }
+    if let Some(target_id) = &filter.target_id {
+        query = query.filter(audit_log::Column::TargetId.eq(target_id));
+    }
+    if let Some(from) = filter.from {
+        query = query.filter(audit_log::Column::OccurredAt.gte(from));
+    }
+    if let Some(to) = filter.to {
+        query = query.filter(audit_log::Column::OccurredAt.lt(to));
+    }
+    query
+}
+
+/// Find a page of entries matching the filter, newest first
+pub async fn find_page(
+    db: &DatabaseConnection,
+    filter: &AuditFilter,
+    pageable: &Pageable,
+) -> Result<PageResult<AuditEntryResponse>, DbErr> {
+    let paginator = filtered(filter)
+        .order_by_desc(audit_log::Column::Seq)
+        .paginate(db, pageable.size);
+    let total = paginator.num_items().await?;
+    let entries = paginator.fetch_page(pageable.page).await?;
+
+    Ok(PageResult::new(
+        entries.into_iter().map(Into::into).collect(),
+        pageable.page,
+        pageable.size,
+        total,
+    ))
+}
+
+/// Find up to `limit` entries matching the filter, oldest first
+pub async fn find_all(
+    db: &DatabaseConnection,
+    filter: &AuditFilter,
+    limit: u64,
+) -> Result<Vec<AuditEntryResponse>, DbErr> {
+    let entries = filtered(filter)
+        .order_by_asc(audit_log::Column::Seq)
+        .limit(limit)
+        .all(db)
+        .await?;
+
+    Ok(entries.into_iter().map(Into::into).collect())
+}
+
+/// Number of entries matching the filter
+pub async fn count(db: &DatabaseConnection, filter: &AuditFilter) -> Result<u64, DbErr> {
+    filtered(filter).count(db).await
+}
+
+/// First entry in `entries` that does not continue the chain after
+/// `prev_seq`/`prev_hash`, or whose hash does not match its columns
+fn first_broken(prev_seq: i64, prev_hash: &str, entries: &[audit_log::Model]) -> Option<i64> {
+    let mut prev = (prev_seq, prev_hash);
+    for entry in entries {
+        if entry.seq != prev.0 + 1 || entry.prev_hash != prev.1 || entry.hash != entry_hash(entry) {
+            return Some(entry.seq);
+        }
+        prev = (entry.seq, &entry.hash);
+    }
+    None
+}
+
+/// Check the whole chain from the first entry
+pub async fn verify_chain(db: &DatabaseConnection) -> Result<ChainVerification, DbErr> {
+    let mut prev_seq = 0;
+    let mut prev_hash = GENESIS_HASH.to_string();
+    let mut entries = 0;
+
+    loop {
+        let batch = AuditLog::find()
+            .filter(audit_log::Column::Seq.gt(prev_seq))
+            .order_by_asc(audit_log::Column::Seq)
+            .limit(VERIFY_BATCH_SIZE)
+            .all(db)
+            .await?;
+        let Some(last) = batch.last() else {
+            break;
+        };
+
+        if let Some(seq) = first_broken(prev_seq, &prev_hash, &batch) {
+            return Ok(ChainVerification {
+                valid: false,
+                entries: entries + batch.iter().take_while(|e| e.seq < seq).count() as u64,
+                first_invalid_seq: Some(seq),
+            });
+        }
+
+        entries += batch.len() as u64;
+        prev_seq = last.seq;
+        prev_hash = last.hash.clone();
+    }
+
+    Ok(ChainVerification {
+        valid: true,
+        entries,
+        first_invalid_seq: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chain(len: i64) -> Vec<audit_log::Model> {
+        let mut prev_hash = GENESIS_HASH.to_string();
+        (1..=len)
+            .map(|seq| {
+                let mut entry = audit_log::Model {
+                    seq,
+                    occurred_at: Utc::now().trunc_subsecs(6),
+                    actor: Some("admin".to_string()),
+                    device_id: None,
+                    client_ip: Some("10.0.0.1".to_string()),
+                    user_agent: None,
+                    action: AuditAction::DocumentSigned.as_str().to_string(),
+                    target_id: Some(format!("doc-{seq}")),
+                    payload_digest: Some(payload_digest(&seq)),
+                    prev_hash: prev_hash.clone(),
+                    hash: String::new(),
+                };
+                entry.hash = entry_hash(&entry);
+                prev_hash = entry.hash.clone();
+                entry
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_intact_chain_verifies() {
+        assert_eq!(first_broken(0, GENESIS_HASH, &chain(5)), None);
+        assert_eq!(first_broken(0, GENESIS_HASH, &[]), None);
+    }
+
+    #[test]
+    fn test_edited_entry_breaks_chain() {
+        let mut entries = chain(5);
+        entries[2].target_id = Some("doc-other".to_string());
+        assert_eq!(first_broken(0, GENESIS_HASH, &entries), Some(3));
+
+        // Recomputing the hash of the edited entry breaks the link to the next
+        entries[2].hash = entry_hash(&entries[2]);
+        assert_eq!(first_broken(0, GENESIS_HASH, &entries), Some(4));
+    }
+
+    #[test]
+    fn test_removed_entry_breaks_chain() {
+        let mut entries = chain(5);
+        entries.remove(1);
+        assert_eq!(first_broken(0, GENESIS_HASH, &entries), Some(3));
+    }
+
+    #[test]
+    fn test_missing_values_differ_from_empty_ones() {
+        let mut entry = chain(1).remove(0);
+        let with_none = entry_hash(&entry);
+        entry.user_agent = Some(String::new());
+        assert_ne!(entry_hash(&entry), with_none);
+    }
+}
@@ -1,12 +1,36 @@
-use argon2::{
-    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
-    Argon2,
-};
+use std::sync::LazyLock;
+use std::time::Duration;
+
 use chrono::Utc;
-use sea_orm::{ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, Set};
+use rand::Rng;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection, EntityTrait, Order,
+    QueryFilter, QueryOrder, QuerySelect, Set, sea_query::NullOrdering,
+};
+use tracing::{info, warn};
 use uuid::Uuid;
 
 use super::entity::device::{self, Entity as Device};
+use crate::config::auth::UserRole;
+use crate::utils::hashing::{hash_secret, verify_secret};
+use crate::utils::rate_limit::RateLimiter;
+
+/// Logins with keys from before key IDs allowed per minute, for all clients
+/// together. Each one is checked against the hash of every device still
+/// without a key ID, so this bounds the Argon2 work such keys can cause.
+/// Successful logins give their slot back.
+static LEGACY_KEY_CHECKS: LazyLock<RateLimiter> = LazyLock::new(|| {
+    let max = std::env::var("DEVICE_LEGACY_KEY_CHECKS_PER_MINUTE")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|&n| n > 0)
+        .unwrap_or(10);
+    RateLimiter::new(max, Duration::from_secs(60))
+});
+
+/// Devices without a key ID a legacy key is checked against at most,
+/// recently seen ones first
+const LEGACY_KEY_MAX_DEVICES: u64 = 100;
 
 /// Device response for API
 #[derive(Debug, Clone, serde::Serialize)]
@@ -37,7 +61,7 @@ pub struct DeviceCreatedResponse {
 pub struct CreateDeviceRequest {
     pub mac_address: String,
     pub name: String,
-    pub role: Option<String>,
+    pub role: Option<UserRole>,
     pub user_id: Option<Uuid>,
 }
 
@@ -56,38 +80,57 @@ impl From<device::Model> for DeviceResponse {
     }
 }
 
-/// Generate a new API key in format: dk_{short_uuid}_{random}
-fn generate_api_key() -> String {
-    let uuid_part = Uuid::new_v4().to_string()[..8].to_string();
-    let random_part: String = (0..8)
+/// Length of the secret part of an API key
+const API_KEY_SECRET_LEN: usize = 32;
+
+/// Length of the secret part of keys issued before key IDs were stored
+const LEGACY_API_KEY_SECRET_LEN: usize = 8;
+
+/// Generate a new API key in format: dk_{key_id}_{secret}
+/// Returns the key ID and the key. The key ID is not secret; it is stored
+/// in plain text to find the device without trying every hash.
+fn generate_api_key() -> (String, String) {
+    let key_id = Uuid::new_v4().simple().to_string()[..8].to_string();
+    let mut rng = rand::thread_rng();
+    let secret: String = (0..API_KEY_SECRET_LEN)
         .map(|_| {
-            let idx = rand::random::<usize>() % 36;
+            let idx = rng.gen_range(0..36u8);
             if idx < 10 {
-                (b'0' + idx as u8) as char
+                (b'0' + idx) as char
             } else {
-                (b'a' + (idx - 10) as u8) as char
+                (b'a' + (idx - 10)) as char
             }
         })
         .collect();
-    format!("dk_{}_{}", uuid_part, random_part)
+    let api_key = format!("dk_{}_{}", key_id, secret);
+    (key_id, api_key)
 }
 
-/// Hash an API key using Argon2
-fn hash_api_key(api_key: &str) -> Result<String, argon2::password_hash::Error> {
-    let salt = SaltString::generate(&mut OsRng);
-    let argon2 = Argon2::default();
-    let hash = argon2.hash_password(api_key.as_bytes(), &salt)?;
-    Ok(hash.to_string())
+/// Generate a new API key whose key ID no other device uses
+async fn generate_unique_api_key<C: ConnectionTrait>(
+    db: &C,
+) -> Result<(String, String), sea_orm::DbErr> {
+    loop {
+        let (key_id, api_key) = generate_api_key();
+        let taken = Device::find()
+            .filter(device::Column::KeyId.eq(&key_id))
+            .one(db)
+            .await?
+            .is_some();
+        if !taken {
+            return Ok((key_id, api_key));
+        }
+    }
 }
 
-/// Verify an API key against a hash
-fn verify_api_key(api_key: &str, hash: &str) -> bool {
-    let Ok(parsed_hash) = PasswordHash::new(hash) else {
-        return false;
-    };
-    Argon2::default()
-        .verify_password(api_key.as_bytes(), &parsed_hash)
-        .is_ok()
+/// Key ID of an API key, `None` if the key is malformed
+fn key_id_of(api_key: &str) -> Option<&str> {
+    let (key_id, secret) = api_key.strip_prefix("dk_")?.split_once('_')?;
+    let valid = !key_id.is_empty()
+        && key_id.len() <= 32
+        && key_id.bytes().all(|b| b.is_ascii_alphanumeric())
+        && !secret.is_empty();
+    valid.then_some(key_id)
 }
 
 /// Find all devices
@@ -120,41 +163,96 @@ pub async fn find_by_mac(
     Ok(device.map(DeviceResponse::from))
 }
 
-/// Validate API key and return device if valid
+/// Validate API key and return device if valid.
+///
+/// The key ID in the key selects the device, so a login costs a single
+/// Argon2 verification. Keys issued before key IDs were stored are checked
+/// against the devices without a key ID within the `LEGACY_KEY_CHECKS`
+/// budget; the first successful login stores the key's ID for the device.
 pub async fn find_by_api_key(
     db: &DatabaseConnection,
     api_key: &str,
 ) -> Result<Option<DeviceResponse>, sea_orm::DbErr> {
-    // We need to check all active devices since we can't query by hash directly
+    let Some(key_id) = key_id_of(api_key) else {
+        return Ok(None);
+    };
+
+    let device = Device::find()
+        .filter(device::Column::KeyId.eq(key_id))
+        .one(db)
+        .await?;
+    let Some(device) = device else {
+        if is_legacy_api_key(api_key) {
+            return find_by_legacy_api_key(db, key_id, api_key).await;
+        }
+        return Ok(None);
+    };
+
+    let valid = device.is_active && verify_secret(api_key, &device.api_key_hash);
+    Ok(valid.then(|| DeviceResponse::from(device)))
+}
+
+/// Find the device of a key issued before key IDs were stored, and store
+/// the key's ID so its next login is looked up directly
+async fn find_by_legacy_api_key(
+    db: &DatabaseConnection,
+    key_id: &str,
+    api_key: &str,
+) -> Result<Option<DeviceResponse>, sea_orm::DbErr> {
+    let Ok(check) = LEGACY_KEY_CHECKS.reserve("legacy") else {
+        warn!(key_id = %key_id, "API key from before key IDs refused, too many checks");
+        return Ok(None);
+    };
+
     let devices = Device::find()
+        .filter(device::Column::KeyId.is_null())
         .filter(device::Column::IsActive.eq(true))
+        .order_by_with_nulls(device::Column::LastSeen, Order::Desc, NullOrdering::Last)
+        .limit(LEGACY_KEY_MAX_DEVICES)
         .all(db)
         .await?;
+    let Some(device) = devices
+        .into_iter()
+        .find(|d| verify_secret(api_key, &d.api_key_hash))
+    else {
+        return Ok(None);
+    };
+    check.refund();
 
-    for device in devices {
-        if verify_api_key(api_key, &device.api_key_hash) {
-            return Ok(Some(DeviceResponse::from(device)));
-        }
+    let mut active_model: device::ActiveModel = device.clone().into();
+    active_model.key_id = Set(Some(key_id.to_string()));
+    match active_model.update(db).await {
+        Ok(_) => info!(device_id = %device.id, "Stored key ID of API key from before key IDs"),
+        Err(e) => warn!(device_id = %device.id, error = %e, "Failed to store key ID"),
     }
+    Ok(Some(DeviceResponse::from(device)))
+}
 
-    Ok(None)
+/// Whether a key has the shape of keys issued before key IDs were stored,
+/// which had a secret of 8 characters
+fn is_legacy_api_key(api_key: &str) -> bool {
+    api_key
+        .rsplit_once('_')
+        .is_some_and(|(_, secret)| secret.len() == LEGACY_API_KEY_SECRET_LEN)
 }
 
 /// Create a new device - returns the plaintext API key (only shown once!)
-pub async fn create(
-    db: &DatabaseConnection,
+pub async fn create<C: ConnectionTrait>(
+    db: &C,
     payload: CreateDeviceRequest,
 ) -> Result<DeviceCreatedResponse, DeviceError> {
-    let api_key = generate_api_key();
-    let api_key_hash = hash_api_key(&api_key).map_err(|_| DeviceError::HashingFailed)?;
+    let (key_id, api_key) = generate_unique_api_key(db).await?;
+    let api_key_hash = hash_secret(&api_key).map_err(|_| DeviceError::HashingFailed)?;
 
+    let role = payload.role.unwrap_or(UserRole::Client);
     let new_device = device::ActiveModel {
         id: Set(Uuid::new_v4()),
         mac_address: Set(payload.mac_address.to_uppercase()),
         api_key_hash: Set(api_key_hash),
+        key_id: Set(Some(key_id)),
         name: Set(payload.name),
         user_id: Set(payload.user_id),
-        role: Set(payload.role.unwrap_or_else(|| "client".to_string())),
+        role: Set(role.as_str().to_string()),
         is_active: Set(true),
         last_seen: Set(None),
         created_at: Set(Utc::now()),
@@ -251,11 +349,12 @@ pub async fn regenerate_api_key(
         return Ok(None);
     };
 
-    let api_key = generate_api_key();
-    let api_key_hash = hash_api_key(&api_key).map_err(|_| DeviceError::HashingFailed)?;
+    let (key_id, api_key) = generate_unique_api_key(db).await?;
+    let api_key_hash = hash_secret(&api_key).map_err(|_| DeviceError::HashingFailed)?;
 
     let mut active_model: device::ActiveModel = device.into();
     active_model.api_key_hash = Set(api_key_hash);
+    active_model.key_id = Set(Some(key_id));
     let updated = active_model
         .update(db)
         .await
@@ -284,3 +383,93 @@ pub enum DeviceError {
     #[error("Failed to hash API key")]
     HashingFailed,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::test_db;
+
+    #[test]
+    fn test_generated_key_carries_its_key_id() {
+        let (key_id, api_key) = generate_api_key();
+        assert_eq!(key_id.len(), 8);
+        assert_eq!(key_id_of(&api_key), Some(key_id.as_str()));
+        assert_eq!(api_key.len(), "dk_".len() + 8 + 1 + API_KEY_SECRET_LEN);
+        assert_ne!(generate_api_key().1, api_key);
+    }
+
+    #[test]
+    fn test_malformed_keys_have_no_key_id() {
+        assert_eq!(key_id_of("dk_3f2a9c1b_k3j4h5g6"), Some("3f2a9c1b"));
+        assert_eq!(key_id_of("dk_3f2a9c1b_"), None);
+        assert_eq!(key_id_of("dk__k3j4h5g6"), None);
+        assert_eq!(key_id_of("dk_3f2a9c1b"), None);
+        assert_eq!(key_id_of("xx_3f2a9c1b_k3j4h5g6"), None);
+        assert_eq!(key_id_of("dk_3f2a%c1b_k3j4h5g6"), None);
+    }
+
+    #[test]
+    fn test_legacy_key_shape() {
+        assert!(is_legacy_api_key("dk_3f2a9c1b_k3j4h5g6"));
+        assert!(!is_legacy_api_key(&generate_api_key().1));
+    }
+
+    #[tokio::test]
+    async fn test_api_key_login_by_key_id() {
+        let Some(db) = test_db::connect().await else {
+            return;
+        };
+        let created = create(
+            &db,
+            CreateDeviceRequest {
+                mac_address: "aa:bb:cc:dd:ee:01".into(),
+                name: "Tablet 1".into(),
+                role: None,
+                user_id: None,
+            },
+        )
+        .await
+        .unwrap();
+        let found = find_by_api_key(&db, &created.api_key).await.unwrap();
+        assert_eq!(found.map(|d| d.id), Some(created.id));
+
+        let mut wrong_secret = created.api_key.clone();
+        let last = if wrong_secret.ends_with('a') {
+            "b"
+        } else {
+            "a"
+        };
+        wrong_secret.replace_range(wrong_secret.len() - 1.., last);
+        assert!(find_by_api_key(&db, &wrong_secret).await.unwrap().is_none());
+
+        // A key from before key IDs is found by its hash and gets its key ID
+        let legacy_key = "dk_3f2a9c1b_k3j4h5g6";
+        let legacy = device::ActiveModel {
+            id: Set(Uuid::new_v4()),
+            mac_address: Set("AA:BB:CC:DD:EE:02".into()),
+            api_key_hash: Set(hash_secret(legacy_key).unwrap()),
+            key_id: Set(None),
+            name: Set("Tablet 2".into()),
+            user_id: Set(None),
+            role: Set("client".into()),
+            is_active: Set(true),
+            last_seen: Set(None),
+            created_at: Set(Utc::now()),
+        }
+        .insert(&db)
+        .await
+        .unwrap();
+        let wrong_secret = find_by_api_key(&db, "dk_3f2a9c1b_00000000").await;
+        assert!(wrong_secret.unwrap().is_none());
+        let found = find_by_api_key(&db, legacy_key).await.unwrap();
+        assert_eq!(found.map(|d| d.id), Some(legacy.id));
+        let stored = Device::find_by_id(legacy.id).one(&db).await.unwrap();
+        assert_eq!(stored.unwrap().key_id.as_deref(), Some("3f2a9c1b"));
+        let found = find_by_api_key(&db, legacy_key).await.unwrap();
+        assert_eq!(found.map(|d| d.id), Some(legacy.id));
+
+        deactivate(&db, created.id).await.unwrap();
+        let found = find_by_api_key(&db, &created.api_key).await.unwrap();
+        assert!(found.is_none());
+    }
+}
@@ -0,0 +1,26 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "abrechnungen")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: Uuid,
+    pub rechnungsnummer: String,
+    pub rechnungssteller_ik: String,
+    pub kostentraeger_ik: String,
+    pub rechnungsempfaenger_ik: String,
+    pub abrechnungsmonat: String,
+    pub rechnungsdatum: NaiveDate,
+    #[sea_orm(column_type = "Decimal(Some((12, 2)))")]
+    pub gesamtbetrag: Decimal,
+    pub anzahl_faelle: i32,
+    #[sea_orm(column_type = "Text")]
+    pub xml: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
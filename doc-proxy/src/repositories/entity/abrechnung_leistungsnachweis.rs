@@ -0,0 +1,16 @@
+use chrono::{DateTime, Utc};
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "abrechnung_leistungsnachweise")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub leistungsnachweis_id: String,
+    pub abrechnung_id: Uuid,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
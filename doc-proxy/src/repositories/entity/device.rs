@@ -9,6 +9,8 @@ pub struct Model {
     #[sea_orm(unique)]
     pub mac_address: String,
     pub api_key_hash: String,
+    #[sea_orm(unique)]
+    pub key_id: Option<String>,
     pub name: String,
     pub user_id: Option<Uuid>,
     pub role: String,
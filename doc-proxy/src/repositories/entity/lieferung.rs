@@ -0,0 +1,23 @@
+use chrono::{DateTime, Utc};
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "lieferungen")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub datei_id: Uuid,
+    pub abrechnung_id: Option<Uuid>,
+    pub nachrichtentyp: String,
+    pub verfahrenskennung: String,
+    pub absender_ik: String,
+    pub empfaenger_ik: String,
+    pub erstellzeitpunkt: DateTime<Utc>,
+    #[sea_orm(column_type = "Text")]
+    pub xml: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -1,2 +1,19 @@
+pub mod abrechnung;
+pub mod abrechnung_leistungsnachweis;
+pub mod audit_log;
 pub mod device;
+pub mod device_pairing_code;
+pub mod fehlernachricht;
+pub mod idempotency_key;
+pub mod leistungsnachweis;
+pub mod lieferung;
+pub mod refresh_token;
+pub mod revoked_token;
+pub mod signatur_ereignis;
+pub mod staff_client;
+pub mod status_uebergang;
+pub mod sync_document;
+pub mod sync_upload;
+pub mod technischer_fehler;
 pub mod user;
+pub mod xml_artefakt;
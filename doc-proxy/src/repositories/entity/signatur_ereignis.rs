@@ -0,0 +1,22 @@
+use chrono::{DateTime, Utc};
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "signatur_ereignisse")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: Uuid,
+    pub leistungsnachweis_id: String,
+    pub art: String,
+    pub bildformat: Option<String>,
+    pub fehlgrund: Option<String>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub erlaeuterung: Option<String>,
+    pub signiert_von: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
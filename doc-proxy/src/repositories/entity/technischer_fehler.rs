@@ -0,0 +1,25 @@
+use chrono::{DateTime, Utc};
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "technische_fehler")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: Uuid,
+    pub fehlernachricht_id: Uuid,
+    pub leistungsnachweis_id: Option<String>,
+    pub fehlercode: String,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub fehlertext: Option<String>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub xpath: Option<String>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub feld: Option<String>,
+    pub resolved_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,146 @@
+use chrono::Utc;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection, DbErr, EntityTrait,
+    QueryFilter, QueryOrder, Set, sea_query::Expr,
+};
+use uuid::Uuid;
+
+use super::entity::{
+    fehlernachricht::{self, Entity as Fehlernachricht},
+    technischer_fehler::{self, Entity as TechnischerFehler},
+};
+
+/// Received Fehlernachricht_technisch to persist
+#[derive(Debug, Clone)]
+pub struct NewFehlernachricht {
+    pub datei_id: String,
+    pub bezug_datei_id: Option<Uuid>,
+    pub message_id: String,
+    pub absender_ik: String,
+    pub empfaenger_ik: String,
+    pub xml: String,
+}
+
+/// Error entry, already assigned to the Leistungsnachweis it concerns
+#[derive(Debug, Clone)]
+pub struct NewTechnischerFehler {
+    pub leistungsnachweis_id: Option<String>,
+    pub fehlercode: String,
+    pub fehlertext: Option<String>,
+    pub xpath: Option<String>,
+    pub feld: Option<String>,
+}
+
+/// Store a received error message together with its error entries.
+///
+/// The Datei_ID of the received file is unique, so ingesting the same file
+/// twice is rejected by Postgres.
+pub async fn create<C: ConnectionTrait>(
+    db: &C,
+    new: NewFehlernachricht,
+    fehler: Vec<NewTechnischerFehler>,
+) -> Result<(fehlernachricht::Model, Vec<technischer_fehler::Model>), DbErr> {
+    let now = Utc::now();
+    let nachricht = fehlernachricht::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        datei_id: Set(new.datei_id),
+        bezug_datei_id: Set(new.bezug_datei_id),
+        message_id: Set(new.message_id),
+        absender_ik: Set(new.absender_ik),
+        empfaenger_ik: Set(new.empfaenger_ik),
+        xml: Set(new.xml),
+        created_at: Set(now),
+    }
+    .insert(db)
+    .await?;
+
+    let mut stored = Vec::with_capacity(fehler.len());
+    for f in fehler {
+        let entry = technischer_fehler::ActiveModel {
+            id: Set(Uuid::new_v4()),
+            fehlernachricht_id: Set(nachricht.id),
+            leistungsnachweis_id: Set(f.leistungsnachweis_id),
+            fehlercode: Set(f.fehlercode),
+            fehlertext: Set(f.fehlertext),
+            xpath: Set(f.xpath),
+            feld: Set(f.feld),
+            resolved_at: Set(None),
+            created_at: Set(now),
+        };
+        stored.push(entry.insert(db).await?);
+    }
+
+    Ok((nachricht, stored))
+}
+
+/// Find error message by ID
+pub async fn find_by_id(
+    db: &DatabaseConnection,
+    id: Uuid,
+) -> Result<Option<fehlernachricht::Model>, DbErr> {
+    Fehlernachricht::find_by_id(id).one(db).await
+}
+
+/// Find error message by the Datei_ID of the received file
+pub async fn find_by_datei_id(
+    db: &DatabaseConnection,
+    datei_id: &str,
+) -> Result<Option<fehlernachricht::Model>, DbErr> {
+    Fehlernachricht::find()
+        .filter(fehlernachricht::Column::DateiId.eq(datei_id))
+        .one(db)
+        .await
+}
+
+/// Find the error entries of an error message
+pub async fn find_fehler(
+    db: &DatabaseConnection,
+    fehlernachricht_id: Uuid,
+) -> Result<Vec<technischer_fehler::Model>, DbErr> {
+    TechnischerFehler::find()
+        .filter(technischer_fehler::Column::FehlernachrichtId.eq(fehlernachricht_id))
+        .order_by_asc(technischer_fehler::Column::CreatedAt)
+        .all(db)
+        .await
+}
+
+/// Find unresolved error entries of the given Leistungsnachweise, oldest first
+pub async fn find_open_by_leistungsnachweise(
+    db: &DatabaseConnection,
+    leistungsnachweis_ids: &[String],
+) -> Result<Vec<technischer_fehler::Model>, DbErr> {
+    if leistungsnachweis_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    TechnischerFehler::find()
+        .filter(technischer_fehler::Column::LeistungsnachweisId.is_in(leistungsnachweis_ids))
+        .filter(technischer_fehler::Column::ResolvedAt.is_null())
+        .order_by_asc(technischer_fehler::Column::CreatedAt)
+        .all(db)
+        .await
+}
+
+/// Mark the open error entries of the given Leistungsnachweise as resolved.
+///
+/// Called when the documents are delivered again.
+pub async fn resolve_for_leistungsnachweise<C: ConnectionTrait>(
+    db: &C,
+    leistungsnachweis_ids: &[String],
+) -> Result<u64, DbErr> {
+    if leistungsnachweis_ids.is_empty() {
+        return Ok(0);
+    }
+
+    let result = TechnischerFehler::update_many()
+        .col_expr(
+            technischer_fehler::Column::ResolvedAt,
+            Expr::value(Some(Utc::now())),
+        )
+        .filter(technischer_fehler::Column::LeistungsnachweisId.is_in(leistungsnachweis_ids))
+        .filter(technischer_fehler::Column::ResolvedAt.is_null())
+        .exec(db)
+        .await?;
+
+    Ok(result.rows_affected)
+}
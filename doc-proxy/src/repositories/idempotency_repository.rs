@@ -0,0 +1,250 @@
+//! Stored responses of requests sent with an `Idempotency-Key` header.
+//!
+//! A key is claimed before the request runs, so a retry arriving while the
+//! first request is still running finds the claim instead of running again.
+//! A claim is held until `locked_until`; a key whose request never finished
+//! can be claimed again after that.
+
+use std::time::Duration as StdDuration;
+
+use chrono::{DateTime, Utc};
+use sea_orm::{
+    ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, Set,
+    sea_query::{Expr, OnConflict},
+};
+use tokio::task::JoinHandle;
+use tracing::{error, info};
+
+use super::entity::idempotency_key::{self, Entity as IdempotencyKey};
+
+/// A request about to run under an idempotency key
+#[derive(Debug, Clone)]
+pub struct NewIdempotencyKey {
+    pub actor: String,
+    pub idempotency_key: String,
+    pub request_fingerprint: String,
+    pub method: String,
+    pub path: String,
+    pub locked_until: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Outcome of claiming a key
+#[derive(Debug)]
+pub enum Claim {
+    /// The key is new; run the request and complete or release the key
+    Claimed,
+    /// The key was used before; its response, if it has one yet
+    Existing(Box<idempotency_key::Model>),
+}
+
+/// Claim a key for a request.
+///
+/// Keys past their retention window are claimed anew, as are keys of the
+/// same request still without a response past their lock.
+pub async fn claim(db: &DatabaseConnection, new: NewIdempotencyKey) -> Result<Claim, DbErr> {
+    IdempotencyKey::delete_many()
+        .filter(idempotency_key::Column::Actor.eq(&new.actor))
+        .filter(idempotency_key::Column::IdempotencyKey.eq(&new.idempotency_key))
+        .filter(idempotency_key::Column::ExpiresAt.lt(Utc::now()))
+        .exec(db)
+        .await?;
+
+    let id = (new.actor.clone(), new.idempotency_key.clone());
+    let fingerprint = new.request_fingerprint.clone();
+    let entry = idempotency_key::ActiveModel {
+        actor: Set(new.actor),
+        idempotency_key: Set(new.idempotency_key),
+        request_fingerprint: Set(new.request_fingerprint),
+        method: Set(new.method),
+        path: Set(new.path),
+        status_code: Set(None),
+        content_type: Set(None),
+        response_body: Set(None),
+        locked_until: Set(new.locked_until),
+        created_at: Set(Utc::now()),
+        expires_at: Set(new.expires_at),
+    };
+    let inserted = IdempotencyKey::insert(entry)
+        .on_conflict(
+            OnConflict::columns([
+                idempotency_key::Column::Actor,
+                idempotency_key::Column::IdempotencyKey,
+            ])
+            .do_nothing()
+            .to_owned(),
+        )
+        .exec_without_returning(db)
+        .await?;
+    if inserted > 0 {
+        return Ok(Claim::Claimed);
+    }
+
+    let taken_over = IdempotencyKey::update_many()
+        .col_expr(
+            idempotency_key::Column::LockedUntil,
+            Expr::value(new.locked_until),
+        )
+        .filter(idempotency_key::Column::Actor.eq(&id.0))
+        .filter(idempotency_key::Column::IdempotencyKey.eq(&id.1))
+        .filter(idempotency_key::Column::RequestFingerprint.eq(fingerprint))
+        .filter(idempotency_key::Column::StatusCode.is_null())
+        .filter(idempotency_key::Column::LockedUntil.lt(Utc::now()))
+        .exec(db)
+        .await?;
+    if taken_over.rows_affected > 0 {
+        return Ok(Claim::Claimed);
+    }
+
+    IdempotencyKey::find_by_id(id)
+        .one(db)
+        .await?
+        .map(|entry| Claim::Existing(Box::new(entry)))
+        // Released by the first request between the insert and the lookup
+        .ok_or(DbErr::RecordNotInserted)
+}
+
+/// Store the response of a claimed key
+pub async fn complete(
+    db: &DatabaseConnection,
+    actor: &str,
+    key: &str,
+    status_code: i16,
+    content_type: Option<String>,
+    body: Vec<u8>,
+) -> Result<(), DbErr> {
+    IdempotencyKey::update_many()
+        .col_expr(
+            idempotency_key::Column::StatusCode,
+            Expr::value(status_code),
+        )
+        .col_expr(
+            idempotency_key::Column::ContentType,
+            Expr::value(content_type),
+        )
+        .col_expr(idempotency_key::Column::ResponseBody, Expr::value(body))
+        .filter(idempotency_key::Column::Actor.eq(actor))
+        .filter(idempotency_key::Column::IdempotencyKey.eq(key))
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+/// Give up a claimed key without a response, so the request can be retried
+pub async fn release(db: &DatabaseConnection, actor: &str, key: &str) -> Result<(), DbErr> {
+    IdempotencyKey::delete_many()
+        .filter(idempotency_key::Column::Actor.eq(actor))
+        .filter(idempotency_key::Column::IdempotencyKey.eq(key))
+        .filter(idempotency_key::Column::StatusCode.is_null())
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+/// Delete keys past their retention window. Returns how many were removed.
+pub async fn purge_expired(db: &DatabaseConnection) -> Result<u64, DbErr> {
+    let purged = IdempotencyKey::delete_many()
+        .filter(idempotency_key::Column::ExpiresAt.lt(Utc::now()))
+        .exec(db)
+        .await?;
+    Ok(purged.rows_affected)
+}
+
+/// Periodically purge expired keys in the background.
+pub fn spawn_purge_task(db: DatabaseConnection, interval: StdDuration) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            match purge_expired(&db).await {
+                Ok(0) => {}
+                Ok(purged) => info!(purged, "Purged expired idempotency keys"),
+                Err(e) => error!(error = %e, "Failed to purge expired idempotency keys"),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+    use crate::repositories::test_db;
+
+    fn new_key(fingerprint: &str, locked_for: Duration) -> NewIdempotencyKey {
+        NewIdempotencyKey {
+            actor: "user-1".to_string(),
+            idempotency_key: "key-1".to_string(),
+            request_fingerprint: fingerprint.to_string(),
+            method: "POST".to_string(),
+            path: "/leistungsnachweise/LN-1/sign".to_string(),
+            locked_until: Utc::now() + locked_for,
+            expires_at: Utc::now() + Duration::hours(24),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_abandoned_claim_is_taken_over_after_its_lock() {
+        let Some(db) = test_db::connect().await else {
+            return;
+        };
+
+        let locked = new_key("a", Duration::minutes(2));
+        assert!(matches!(
+            claim(&db, locked.clone()).await,
+            Ok(Claim::Claimed)
+        ));
+        assert!(matches!(
+            claim(&db, locked).await,
+            Ok(Claim::Existing(entry)) if entry.status_code.is_none()
+        ));
+
+        // The first request died without completing or releasing the key
+        let expired = NewIdempotencyKey {
+            idempotency_key: "key-2".to_string(),
+            ..new_key("a", Duration::seconds(-1))
+        };
+        assert!(matches!(
+            claim(&db, expired.clone()).await,
+            Ok(Claim::Claimed)
+        ));
+        let other_request = NewIdempotencyKey {
+            request_fingerprint: "b".to_string(),
+            ..expired.clone()
+        };
+        assert!(matches!(
+            claim(&db, other_request).await,
+            Ok(Claim::Existing(_))
+        ));
+        let retry = NewIdempotencyKey {
+            locked_until: Utc::now() + Duration::minutes(2),
+            ..expired
+        };
+        assert!(matches!(
+            claim(&db, retry.clone()).await,
+            Ok(Claim::Claimed)
+        ));
+        assert!(matches!(claim(&db, retry).await, Ok(Claim::Existing(_))));
+    }
+
+    #[tokio::test]
+    async fn test_completed_key_is_not_released() {
+        let Some(db) = test_db::connect().await else {
+            return;
+        };
+
+        let key = new_key("a", Duration::seconds(-1));
+        claim(&db, key.clone()).await.unwrap();
+        complete(&db, "user-1", "key-1", 200, None, b"{}".to_vec())
+            .await
+            .unwrap();
+        release(&db, "user-1", "key-1").await.unwrap();
+
+        // Completed keys are replayed even past their lock
+        assert!(matches!(
+            claim(&db, key).await,
+            Ok(Claim::Existing(entry)) if entry.status_code == Some(200)
+        ));
+    }
+}
@@ -0,0 +1,218 @@
+use chrono::Utc;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection, DbErr, EntityTrait,
+    QueryFilter, QueryOrder, Set,
+    sea_query::{Expr, OnConflict},
+};
+use uuid::Uuid;
+
+use super::entity::{
+    leistungsnachweis::{self, Entity as Leistungsnachweis},
+    signatur_ereignis,
+    status_uebergang::{self, Entity as StatusUebergang},
+    xml_artefakt::{self, Entity as XmlArtefakt},
+};
+
+/// Snapshot of a document as fetched from core
+///
+/// `status` is only used when the document is not yet known locally; from
+/// then on the status changes through [`transition`] only.
+#[derive(Debug, Clone)]
+pub struct NewLeistungsnachweis {
+    pub id: String,
+    pub versichertennummer: String,
+    pub name: String,
+    pub vorname: String,
+    pub ik_pflegedienst: String,
+    pub abrechnungsmonat: String,
+    pub schema: String,
+    pub status: String,
+}
+
+/// Captured signature to persist
+#[derive(Debug, Clone)]
+pub struct NewSignaturEreignis {
+    pub leistungsnachweis_id: String,
+    pub art: String,
+    pub bildformat: Option<String>,
+    pub fehlgrund: Option<String>,
+    pub erlaeuterung: Option<String>,
+    pub signiert_von: String,
+}
+
+/// Generated XML to persist
+#[derive(Debug, Clone)]
+pub struct NewXmlArtefakt {
+    pub leistungsnachweis_id: String,
+    pub signatur_ereignis_id: Uuid,
+    pub schema: String,
+    pub xml: String,
+}
+
+/// Insert a document or refresh its snapshot if it is already known.
+///
+/// Returns the stored row, whose status is the current local status.
+pub async fn upsert<C: ConnectionTrait>(
+    db: &C,
+    new: NewLeistungsnachweis,
+) -> Result<leistungsnachweis::Model, DbErr> {
+    let now = Utc::now();
+    let document = leistungsnachweis::ActiveModel {
+        id: Set(new.id),
+        versichertennummer: Set(new.versichertennummer),
+        name: Set(new.name),
+        vorname: Set(new.vorname),
+        ik_pflegedienst: Set(new.ik_pflegedienst),
+        abrechnungsmonat: Set(new.abrechnungsmonat),
+        schema: Set(new.schema),
+        status: Set(new.status),
+        created_at: Set(now),
+        updated_at: Set(now),
+    };
+
+    Leistungsnachweis::insert(document)
+        .on_conflict(
+            OnConflict::column(leistungsnachweis::Column::Id)
+                .update_columns([
+                    leistungsnachweis::Column::Versichertennummer,
+                    leistungsnachweis::Column::Name,
+                    leistungsnachweis::Column::Vorname,
+                    leistungsnachweis::Column::IkPflegedienst,
+                    leistungsnachweis::Column::Abrechnungsmonat,
+                    leistungsnachweis::Column::Schema,
+                    leistungsnachweis::Column::UpdatedAt,
+                ])
+                .to_owned(),
+        )
+        .exec_with_returning(db)
+        .await
+}
+
+/// Move a document from `von` to `nach` and record the change.
+///
+/// The update only applies while the document is still in `von`, so two
+/// concurrent changes cannot both succeed. Returns `None` if the status had
+/// already changed.
+pub async fn transition<C: ConnectionTrait>(
+    db: &C,
+    leistungsnachweis_id: &str,
+    von: &str,
+    nach: &str,
+    grund: Option<String>,
+    geaendert_von: &str,
+) -> Result<Option<status_uebergang::Model>, DbErr> {
+    let now = Utc::now();
+    let result = Leistungsnachweis::update_many()
+        .col_expr(leistungsnachweis::Column::Status, Expr::value(nach))
+        .col_expr(leistungsnachweis::Column::UpdatedAt, Expr::value(now))
+        .filter(leistungsnachweis::Column::Id.eq(leistungsnachweis_id))
+        .filter(leistungsnachweis::Column::Status.eq(von))
+        .exec(db)
+        .await?;
+    if result.rows_affected == 0 {
+        return Ok(None);
+    }
+
+    let uebergang = status_uebergang::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        leistungsnachweis_id: Set(leistungsnachweis_id.to_string()),
+        von: Set(von.to_string()),
+        nach: Set(nach.to_string()),
+        grund: Set(grund),
+        geaendert_von: Set(geaendert_von.to_string()),
+        created_at: Set(now),
+    };
+
+    uebergang.insert(db).await.map(Some)
+}
+
+/// Store a captured signature
+pub async fn create_signatur_ereignis<C: ConnectionTrait>(
+    db: &C,
+    new: NewSignaturEreignis,
+) -> Result<signatur_ereignis::Model, DbErr> {
+    let ereignis = signatur_ereignis::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        leistungsnachweis_id: Set(new.leistungsnachweis_id),
+        art: Set(new.art),
+        bildformat: Set(new.bildformat),
+        fehlgrund: Set(new.fehlgrund),
+        erlaeuterung: Set(new.erlaeuterung),
+        signiert_von: Set(new.signiert_von),
+        created_at: Set(Utc::now()),
+    };
+
+    ereignis.insert(db).await
+}
+
+/// Store a generated XML document
+pub async fn create_xml_artefakt<C: ConnectionTrait>(
+    db: &C,
+    new: NewXmlArtefakt,
+) -> Result<xml_artefakt::Model, DbErr> {
+    let artefakt = xml_artefakt::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        leistungsnachweis_id: Set(new.leistungsnachweis_id),
+        signatur_ereignis_id: Set(new.signatur_ereignis_id),
+        schema: Set(new.schema),
+        xml: Set(new.xml),
+        created_at: Set(Utc::now()),
+    };
+
+    artefakt.insert(db).await
+}
+
+/// Find the most recently generated XML of a document
+pub async fn find_latest_xml<C: ConnectionTrait>(
+    db: &C,
+    leistungsnachweis_id: &str,
+) -> Result<Option<xml_artefakt::Model>, DbErr> {
+    XmlArtefakt::find()
+        .filter(xml_artefakt::Column::LeistungsnachweisId.eq(leistungsnachweis_id))
+        .order_by_desc(xml_artefakt::Column::CreatedAt)
+        .one(db)
+        .await
+}
+
+/// Find a captured signature by ID
+pub async fn find_signatur_ereignis<C: ConnectionTrait>(
+    db: &C,
+    id: Uuid,
+) -> Result<Option<signatur_ereignis::Model>, DbErr> {
+    signatur_ereignis::Entity::find_by_id(id).one(db).await
+}
+
+/// Find document by core ID
+pub async fn find_by_id<C: ConnectionTrait>(
+    db: &C,
+    id: &str,
+) -> Result<Option<leistungsnachweis::Model>, DbErr> {
+    Leistungsnachweis::find_by_id(id).one(db).await
+}
+
+/// Find all locally known documents among the given IDs
+pub async fn find_by_ids(
+    db: &DatabaseConnection,
+    ids: &[String],
+) -> Result<Vec<leistungsnachweis::Model>, DbErr> {
+    if ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    Leistungsnachweis::find()
+        .filter(leistungsnachweis::Column::Id.is_in(ids))
+        .all(db)
+        .await
+}
+
+/// Find the status history of a document, oldest first
+pub async fn find_uebergaenge(
+    db: &DatabaseConnection,
+    leistungsnachweis_id: &str,
+) -> Result<Vec<status_uebergang::Model>, DbErr> {
+    StatusUebergang::find()
+        .filter(status_uebergang::Column::LeistungsnachweisId.eq(leistungsnachweis_id))
+        .order_by_asc(status_uebergang::Column::CreatedAt)
+        .all(db)
+        .await
+}
@@ -0,0 +1,64 @@
+use chrono::{DateTime, Utc};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection, DbErr, EntityTrait,
+    QueryFilter, QueryOrder, Set,
+};
+use uuid::Uuid;
+
+use super::entity::lieferung::{self, Entity as Lieferung};
+
+/// Packaged file to persist
+#[derive(Debug, Clone)]
+pub struct NewLieferung {
+    pub datei_id: Uuid,
+    pub abrechnung_id: Option<Uuid>,
+    pub nachrichtentyp: String,
+    pub verfahrenskennung: String,
+    pub absender_ik: String,
+    pub empfaenger_ik: String,
+    pub erstellzeitpunkt: DateTime<Utc>,
+    pub xml: String,
+}
+
+/// Store a packaged file.
+///
+/// The Datei_ID is the primary key, so a duplicate is rejected by Postgres
+/// instead of being delivered twice.
+pub async fn create<C: ConnectionTrait>(
+    db: &C,
+    new: NewLieferung,
+) -> Result<lieferung::Model, DbErr> {
+    let lieferung = lieferung::ActiveModel {
+        datei_id: Set(new.datei_id),
+        abrechnung_id: Set(new.abrechnung_id),
+        nachrichtentyp: Set(new.nachrichtentyp),
+        verfahrenskennung: Set(new.verfahrenskennung),
+        absender_ik: Set(new.absender_ik),
+        empfaenger_ik: Set(new.empfaenger_ik),
+        erstellzeitpunkt: Set(new.erstellzeitpunkt),
+        xml: Set(new.xml),
+        created_at: Set(Utc::now()),
+    };
+
+    lieferung.insert(db).await
+}
+
+/// Find all files packaged for an invoice, newest first
+pub async fn find_by_abrechnung(
+    db: &DatabaseConnection,
+    abrechnung_id: Uuid,
+) -> Result<Vec<lieferung::Model>, DbErr> {
+    Lieferung::find()
+        .filter(lieferung::Column::AbrechnungId.eq(abrechnung_id))
+        .order_by_desc(lieferung::Column::CreatedAt)
+        .all(db)
+        .await
+}
+
+/// Find file by Datei_ID
+pub async fn find_by_datei_id(
+    db: &DatabaseConnection,
+    datei_id: Uuid,
+) -> Result<Option<lieferung::Model>, DbErr> {
+    Lieferung::find_by_id(datei_id).one(db).await
+}
@@ -1,4 +1,15 @@
-mod entity;
+pub mod entity;
 
+pub mod abrechnung_repository;
+pub mod audit_repository;
 pub mod device_repository;
+pub mod fehlernachricht_repository;
+pub mod idempotency_repository;
+pub mod leistungsnachweis_repository;
+pub mod lieferung_repository;
+pub mod pairing_repository;
+pub mod sync_repository;
+#[cfg(test)]
+pub mod test_db;
+pub mod token_repository;
 pub mod user_repository;
@@ -0,0 +1,293 @@
+//! One-time pairing codes for enrolling devices.
+//!
+//! An admin creates a code bound to the future device's name, role and user;
+//! the device redeems it once for its API key. Codes are 8 digits, so they
+//! can be typed on a tablet or shown as a QR code. A wrong code could have
+//! been a guess at any open code, so it counts against all of them, and a
+//! code is deleted after `DEVICE_PAIRING_CODE_MAX_FAILURES` wrong codes. With
+//! the short lifetime this bounds the chance of a guess regardless of where
+//! the guesses come from; the stored SHA-256 only keeps codes out of plain
+//! sight.
+
+use std::sync::LazyLock;
+
+use chrono::{DateTime, Duration, Utc};
+use rand::Rng;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, DatabaseTransaction, DbErr, EntityTrait,
+    QueryFilter, Set, SqlErr, TransactionTrait, sea_query::Expr,
+};
+use sha2::{Digest, Sha256};
+use tracing::warn;
+use uuid::Uuid;
+
+use super::device_repository::{self, CreateDeviceRequest, DeviceCreatedResponse, DeviceError};
+use super::entity::device::{self, Entity as Device};
+use super::entity::device_pairing_code::{self, Entity as PairingCode};
+use crate::config::auth::UserRole;
+
+/// Default lifetime of a pairing code in minutes
+static DEVICE_PAIRING_TTL_MINUTES: LazyLock<i64> = LazyLock::new(|| {
+    std::env::var("DEVICE_PAIRING_TTL_MINUTES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|&m| m > 0)
+        .unwrap_or(15)
+});
+
+/// Wrong codes tried while a code is open before it is deleted
+static DEVICE_PAIRING_CODE_MAX_FAILURES: LazyLock<i32> = LazyLock::new(|| {
+    std::env::var("DEVICE_PAIRING_CODE_MAX_FAILURES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|&n| n > 0)
+        .unwrap_or(50)
+});
+
+/// Longest lifetime an admin may ask for
+const MAX_TTL_MINUTES: i64 = 24 * 60;
+
+/// Number of digits of a pairing code
+const CODE_DIGITS: usize = 8;
+
+#[derive(Debug, thiserror::Error)]
+pub enum PairingError {
+    #[error("Database error: {0}")]
+    Database(#[from] DbErr),
+    #[error("Failed to create device: {0}")]
+    Device(#[from] DeviceError),
+    #[error("Invalid or expired pairing code")]
+    InvalidCode,
+    #[error("Device with this MAC address already exists")]
+    MacTaken,
+    #[error("User not found")]
+    UnknownUser,
+}
+
+/// Request to create a pairing code
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct CreatePairingCodeRequest {
+    pub name: String,
+    pub role: Option<UserRole>,
+    pub user_id: Option<Uuid>,
+    /// Lifetime in minutes (default: `DEVICE_PAIRING_TTL_MINUTES`, at most a day)
+    pub ttl_minutes: Option<i64>,
+}
+
+/// A new pairing code (includes the plaintext code)
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct PairingCodeResponse {
+    pub id: Uuid,
+    pub code: String, // Plaintext - only shown once!
+    pub name: String,
+    pub role: String,
+    pub user_id: Option<Uuid>,
+    pub expires_at: DateTime<Utc>,
+}
+
+fn hash_code(code: &str) -> String {
+    hex::encode(Sha256::digest(code.as_bytes()))
+}
+
+fn generate_code() -> String {
+    let mut rng = rand::thread_rng();
+    (0..CODE_DIGITS)
+        .map(|_| char::from(b'0' + rng.gen_range(0..10u8)))
+        .collect()
+}
+
+/// Codes as typed by users: separators and spaces are ignored
+fn normalize_code(code: &str) -> String {
+    code.chars().filter(char::is_ascii_digit).collect()
+}
+
+/// Create a pairing code - returns the plaintext code (only shown once!)
+pub async fn create(
+    db: &DatabaseConnection,
+    payload: CreatePairingCodeRequest,
+    created_by: &str,
+) -> Result<PairingCodeResponse, PairingError> {
+    purge_expired(db).await?;
+
+    let ttl = payload
+        .ttl_minutes
+        .unwrap_or(*DEVICE_PAIRING_TTL_MINUTES)
+        .clamp(1, MAX_TTL_MINUTES);
+    let now = Utc::now();
+    let role = payload.role.unwrap_or(UserRole::Client).as_str();
+
+    loop {
+        let code = generate_code();
+        let pairing = device_pairing_code::ActiveModel {
+            id: Set(Uuid::new_v4()),
+            code_hash: Set(hash_code(&code)),
+            name: Set(payload.name.clone()),
+            role: Set(role.to_string()),
+            user_id: Set(payload.user_id),
+            created_by: Set(created_by.to_string()),
+            expires_at: Set(now + Duration::minutes(ttl)),
+            failed_attempts: Set(0),
+            created_at: Set(now),
+        };
+
+        match pairing.insert(db).await {
+            Ok(pairing) => {
+                return Ok(PairingCodeResponse {
+                    id: pairing.id,
+                    code,
+                    name: pairing.name,
+                    role: pairing.role,
+                    user_id: pairing.user_id,
+                    expires_at: pairing.expires_at,
+                });
+            }
+            // Another open code has the same digits; draw again
+            Err(e) if matches!(e.sql_err(), Some(SqlErr::UniqueConstraintViolation(_))) => {}
+            Err(e) if matches!(e.sql_err(), Some(SqlErr::ForeignKeyConstraintViolation(_))) => {
+                return Err(PairingError::UnknownUser);
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+/// Redeem a pairing code: registers the device and returns its API key.
+///
+/// The code is deleted in the same transaction, so it works exactly once
+/// even for concurrent requests. A code is kept if the device cannot be
+/// registered, e.g. because its MAC address is already known. A wrong code
+/// counts as a failed attempt on every open code.
+pub async fn redeem(
+    db: &DatabaseConnection,
+    code: &str,
+    mac_address: &str,
+) -> Result<DeviceCreatedResponse, PairingError> {
+    let txn = db.begin().await?;
+
+    let pairing = PairingCode::delete_many()
+        .filter(device_pairing_code::Column::CodeHash.eq(hash_code(&normalize_code(code))))
+        .filter(device_pairing_code::Column::ExpiresAt.gt(Utc::now()))
+        .exec_with_returning(&txn)
+        .await?
+        .into_iter()
+        .next();
+    let Some(pairing) = pairing else {
+        count_failure(&txn).await?;
+        txn.commit().await?;
+        return Err(PairingError::InvalidCode);
+    };
+
+    let mac_address = mac_address.to_uppercase();
+    if Device::find()
+        .filter(device::Column::MacAddress.eq(&mac_address))
+        .one(&txn)
+        .await?
+        .is_some()
+    {
+        return Err(PairingError::MacTaken);
+    }
+
+    let device = device_repository::create(
+        &txn,
+        CreateDeviceRequest {
+            mac_address,
+            name: pairing.name,
+            role: Some(UserRole::from_db(&pairing.role)),
+            user_id: pairing.user_id,
+        },
+    )
+    .await?;
+    txn.commit().await?;
+
+    Ok(device)
+}
+
+/// Count a wrong code against all open codes and delete the codes that
+/// used up their attempts
+async fn count_failure(txn: &DatabaseTransaction) -> Result<(), DbErr> {
+    PairingCode::update_many()
+        .col_expr(
+            device_pairing_code::Column::FailedAttempts,
+            Expr::col(device_pairing_code::Column::FailedAttempts).add(1),
+        )
+        .filter(device_pairing_code::Column::ExpiresAt.gt(Utc::now()))
+        .exec(txn)
+        .await?;
+    let exhausted = PairingCode::delete_many()
+        .filter(device_pairing_code::Column::FailedAttempts.gte(*DEVICE_PAIRING_CODE_MAX_FAILURES))
+        .exec(txn)
+        .await?;
+    if exhausted.rows_affected > 0 {
+        warn!(
+            codes = exhausted.rows_affected,
+            "Deleted pairing codes after too many wrong codes"
+        );
+    }
+    Ok(())
+}
+
+/// Delete codes that expired without being redeemed
+async fn purge_expired(db: &DatabaseConnection) -> Result<(), DbErr> {
+    PairingCode::delete_many()
+        .filter(device_pairing_code::Column::ExpiresAt.lte(Utc::now()))
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::test_db;
+
+    #[test]
+    fn test_codes_are_numeric() {
+        let code = generate_code();
+        assert_eq!(code.len(), CODE_DIGITS);
+        assert!(code.chars().all(|c| c.is_ascii_digit()));
+    }
+
+    #[test]
+    fn test_typed_codes_match_generated_ones() {
+        assert_eq!(normalize_code("1234-5678"), "12345678");
+        assert_eq!(normalize_code(" 1234 5678 "), "12345678");
+        assert_eq!(
+            hash_code(&normalize_code("1234 5678")),
+            hash_code("12345678")
+        );
+    }
+
+    fn pairing(name: &str) -> CreatePairingCodeRequest {
+        CreatePairingCodeRequest {
+            name: name.into(),
+            role: None,
+            user_id: None,
+            ttl_minutes: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_wrong_codes_use_up_open_codes() {
+        let Some(db) = test_db::connect().await else {
+            return;
+        };
+        let first = create(&db, pairing("Tablet 1"), "admin").await.unwrap();
+        // Has no digits, so it matches no code
+        let wrong = "wrong";
+        for _ in 0..*DEVICE_PAIRING_CODE_MAX_FAILURES - 1 {
+            let result = redeem(&db, wrong, "AA:BB:CC:DD:EE:01").await;
+            assert!(matches!(result, Err(PairingError::InvalidCode)));
+        }
+        // Codes created later have their own budget
+        let second = create(&db, pairing("Tablet 2"), "admin").await.unwrap();
+        let result = redeem(&db, wrong, "AA:BB:CC:DD:EE:01").await;
+        assert!(matches!(result, Err(PairingError::InvalidCode)));
+
+        let result = redeem(&db, &first.code, "AA:BB:CC:DD:EE:01").await;
+        assert!(matches!(result, Err(PairingError::InvalidCode)));
+        let device = redeem(&db, &second.code, "AA:BB:CC:DD:EE:02")
+            .await
+            .unwrap();
+        assert_eq!(device.name, "Tablet 2");
+    }
+}
@@ -0,0 +1,139 @@
+//! Versions and uploads for offline sync.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection, DbBackend, DbErr,
+    EntityTrait, QueryFilter, Set, SqlErr, Statement, sea_query::OnConflict,
+};
+
+use super::entity::sync_document::{self, Entity as SyncDocument};
+use super::entity::sync_upload::{self, Entity as SyncUpload};
+
+/// Content of a document as delivered to tablets
+#[derive(Debug, Clone)]
+pub struct DocumentFingerprint {
+    pub leistungsnachweis_id: String,
+    pub versichertennummer: String,
+    pub fingerprint: String,
+}
+
+/// Result of an upload to keep for retries
+#[derive(Debug, Clone)]
+pub struct NewSyncUpload {
+    pub idempotency_key: String,
+    pub leistungsnachweis_id: String,
+    pub uploaded_by: String,
+    pub captured_at: DateTime<Utc>,
+    pub outcome: String,
+    pub result: serde_json::Value,
+}
+
+async fn next_version<C: ConnectionTrait>(db: &C) -> Result<i64, DbErr> {
+    let row = db
+        .query_one(Statement::from_string(
+            DbBackend::Postgres,
+            "SELECT nextval('sync_version_seq') AS version",
+        ))
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("sync_version_seq".to_string()))?;
+
+    row.try_get("", "version")
+}
+
+/// Record the current content of documents and return their versions.
+///
+/// Documents seen for the first time or whose fingerprint changed get a new
+/// version from `sync_version_seq`; all others keep theirs.
+pub async fn record_fingerprints(
+    db: &DatabaseConnection,
+    documents: Vec<DocumentFingerprint>,
+) -> Result<HashMap<String, i64>, DbErr> {
+    let ids: Vec<&str> = documents
+        .iter()
+        .map(|d| d.leistungsnachweis_id.as_str())
+        .collect();
+    let known: HashMap<String, sync_document::Model> = SyncDocument::find()
+        .filter(sync_document::Column::LeistungsnachweisId.is_in(ids))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|d| (d.leistungsnachweis_id.clone(), d))
+        .collect();
+
+    let mut versions = HashMap::with_capacity(documents.len());
+    for document in documents {
+        let version = match known.get(&document.leistungsnachweis_id) {
+            Some(known) if known.fingerprint == document.fingerprint => known.version,
+            _ => {
+                let version = next_version(db).await?;
+                let row = sync_document::ActiveModel {
+                    leistungsnachweis_id: Set(document.leistungsnachweis_id.clone()),
+                    versichertennummer: Set(document.versichertennummer),
+                    fingerprint: Set(document.fingerprint),
+                    version: Set(version),
+                    updated_at: Set(Utc::now()),
+                };
+                SyncDocument::insert(row)
+                    .on_conflict(
+                        OnConflict::column(sync_document::Column::LeistungsnachweisId)
+                            .update_columns([
+                                sync_document::Column::Versichertennummer,
+                                sync_document::Column::Fingerprint,
+                                sync_document::Column::Version,
+                                sync_document::Column::UpdatedAt,
+                            ])
+                            .to_owned(),
+                    )
+                    .exec(db)
+                    .await?;
+                version
+            }
+        };
+        versions.insert(document.leistungsnachweis_id, version);
+    }
+
+    Ok(versions)
+}
+
+/// Find an earlier upload of the same user with the same idempotency key
+pub async fn find_upload(
+    db: &DatabaseConnection,
+    uploaded_by: &str,
+    idempotency_key: &str,
+) -> Result<Option<sync_upload::Model>, DbErr> {
+    SyncUpload::find_by_id((uploaded_by.to_string(), idempotency_key.to_string()))
+        .one(db)
+        .await
+}
+
+/// Store the result of an upload.
+///
+/// If a concurrent upload with the same key was stored first, its row is
+/// returned instead, so both report the same result.
+pub async fn store_upload(
+    db: &DatabaseConnection,
+    new: NewSyncUpload,
+) -> Result<sync_upload::Model, DbErr> {
+    let (uploaded_by, key) = (new.uploaded_by.clone(), new.idempotency_key.clone());
+    let upload = sync_upload::ActiveModel {
+        idempotency_key: Set(new.idempotency_key),
+        leistungsnachweis_id: Set(new.leistungsnachweis_id),
+        uploaded_by: Set(new.uploaded_by),
+        captured_at: Set(new.captured_at),
+        outcome: Set(new.outcome),
+        result: Set(new.result),
+        created_at: Set(Utc::now()),
+    };
+
+    match upload.insert(db).await {
+        Ok(upload) => Ok(upload),
+        Err(e) if matches!(e.sql_err(), Some(SqlErr::UniqueConstraintViolation(_))) => {
+            find_upload(db, &uploaded_by, &key)
+                .await?
+                .ok_or_else(|| DbErr::RecordNotFound(key))
+        }
+        Err(e) => Err(e),
+    }
+}
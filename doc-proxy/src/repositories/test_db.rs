@@ -0,0 +1,62 @@
+//! Throwaway databases for repository tests.
+//!
+//! Tests that need Postgres connect to `TEST_DATABASE_URL` and are skipped
+//! when it is not set. Every test gets a schema of its own with all
+//! migrations applied, so tests can run in parallel.
+
+use sea_orm::{ConnectOptions, ConnectionTrait, Database, DatabaseConnection};
+use uuid::Uuid;
+
+const MIGRATIONS: &[&str] = &[
+    include_str!("../../migrations/001_create_devices.sql"),
+    include_str!("../../migrations/002_create_abrechnungen.sql"),
+    include_str!("../../migrations/003_create_lieferungen.sql"),
+    include_str!("../../migrations/004_create_fehlernachrichten.sql"),
+    include_str!("../../migrations/005_create_leistungsnachweise.sql"),
+    include_str!("../../migrations/006_create_status_uebergaenge.sql"),
+    include_str!("../../migrations/007_create_users.sql"),
+    include_str!("../../migrations/008_soft_delete_users.sql"),
+    include_str!("../../migrations/009_create_auth_tokens.sql"),
+    include_str!("../../migrations/010_client_assignments.sql"),
+    include_str!("../../migrations/011_oidc_users.sql"),
+    include_str!("../../migrations/012_device_key_ids.sql"),
+    include_str!("../../migrations/013_device_pairing_codes.sql"),
+    include_str!("../../migrations/014_create_audit_log.sql"),
+    include_str!("../../migrations/015_create_sync.sql"),
+    include_str!("../../migrations/016_create_idempotency_keys.sql"),
+    include_str!("../../migrations/017_abrechnung_leistungsnachweise.sql"),
+    include_str!("../../migrations/018_refresh_token_family_age.sql"),
+];
+
+/// A fresh database, or `None` if `TEST_DATABASE_URL` is not set
+pub async fn connect() -> Option<DatabaseConnection> {
+    let Ok(url) = std::env::var("TEST_DATABASE_URL") else {
+        eprintln!("TEST_DATABASE_URL not set, skipping database test");
+        return None;
+    };
+
+    let schema = format!("test_{}", Uuid::new_v4().simple());
+    let admin = Database::connect(&url)
+        .await
+        .expect("test database unreachable");
+    admin
+        .execute_unprepared(&format!("CREATE SCHEMA {schema}"))
+        .await
+        .expect("failed to create test schema");
+    admin.close().await.ok();
+
+    let mut options = ConnectOptions::new(url);
+    options
+        .set_schema_search_path(format!("{schema},public"))
+        .max_connections(8)
+        .sqlx_logging(false);
+    let db = Database::connect(options)
+        .await
+        .expect("test database unreachable");
+    for migration in MIGRATIONS {
+        db.execute_unprepared(migration)
+            .await
+            .expect("failed to apply migration");
+    }
+    Some(db)
+}
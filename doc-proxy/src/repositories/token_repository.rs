@@ -0,0 +1,449 @@
+//! Refresh tokens and the access token deny list.
+//!
+//! Refresh tokens are 256-bit random values, so a plain SHA-256 is enough to
+//! keep them unusable if the table leaks, and unlike a salted hash it can be
+//! looked up directly.
+
+use std::{sync::LazyLock, time::Duration as StdDuration};
+
+use chrono::{DateTime, Duration, Utc};
+use rand::RngCore;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection, DbErr, EntityTrait,
+    QueryFilter, Set, TransactionTrait,
+    sea_query::{Expr, OnConflict},
+};
+use sha2::{Digest, Sha256};
+use tokio::task::JoinHandle;
+use tracing::{error, info};
+use uuid::Uuid;
+
+use super::entity::{
+    refresh_token::{self, Entity as RefreshToken},
+    revoked_token::{self, Entity as RevokedToken},
+};
+
+/// Lifetime of a refresh token in days
+pub static REFRESH_TOKEN_TTL_DAYS: LazyLock<i64> = LazyLock::new(|| {
+    std::env::var("REFRESH_TOKEN_TTL_DAYS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|&d| d > 0)
+        .unwrap_or(30)
+});
+
+/// Days after a login until its refresh token family ends, however often
+/// its tokens were rotated
+pub static REFRESH_TOKEN_MAX_AGE_DAYS: LazyLock<i64> = LazyLock::new(|| {
+    std::env::var("REFRESH_TOKEN_MAX_AGE_DAYS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|&d| d > 0)
+        .unwrap_or(90)
+});
+
+#[derive(Debug, thiserror::Error)]
+pub enum TokenError {
+    #[error("Database error: {0}")]
+    Database(#[from] DbErr),
+    #[error("Unknown refresh token")]
+    Invalid,
+    #[error("Refresh token expired")]
+    Expired,
+    /// An already used or revoked token was presented; its family is revoked.
+    #[error("Refresh token reused, family {family_id} revoked")]
+    Reused { family_id: Uuid, user_id: Uuid },
+}
+
+/// A newly issued refresh token
+#[derive(Debug, Clone)]
+pub struct IssuedRefreshToken {
+    /// The token as handed to the client; only its hash is stored
+    pub token: String,
+    pub user_id: Uuid,
+    pub expires_at: DateTime<Utc>,
+}
+
+fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+fn generate_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+/// End of a family started at `family_created_at`
+fn family_expiry(family_created_at: DateTime<Utc>) -> DateTime<Utc> {
+    family_created_at + Duration::days(*REFRESH_TOKEN_MAX_AGE_DAYS)
+}
+
+async fn insert<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+    family_id: Uuid,
+    family_created_at: DateTime<Utc>,
+) -> Result<IssuedRefreshToken, DbErr> {
+    let token = generate_token();
+    let now = Utc::now();
+    let expires_at =
+        (now + Duration::days(*REFRESH_TOKEN_TTL_DAYS)).min(family_expiry(family_created_at));
+
+    refresh_token::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        family_id: Set(family_id),
+        user_id: Set(user_id),
+        token_hash: Set(hash_token(&token)),
+        expires_at: Set(expires_at),
+        used_at: Set(None),
+        revoked_at: Set(None),
+        created_at: Set(now),
+        family_created_at: Set(family_created_at),
+    }
+    .insert(db)
+    .await?;
+
+    Ok(IssuedRefreshToken {
+        token,
+        user_id,
+        expires_at,
+    })
+}
+
+/// Issue the first refresh token of a new family (on login).
+pub async fn issue(db: &DatabaseConnection, user_id: Uuid) -> Result<IssuedRefreshToken, DbErr> {
+    insert(db, user_id, Uuid::new_v4(), Utc::now()).await
+}
+
+/// Exchange a refresh token for a new one of the same family.
+///
+/// The presented token is marked as used. Presenting a used or revoked token
+/// again means it was copied, so the whole family is revoked and both the
+/// legitimate client and the thief have to log in again. No token outlives
+/// `REFRESH_TOKEN_MAX_AGE_DAYS` after the login that started its family.
+pub async fn rotate(
+    db: &DatabaseConnection,
+    token: &str,
+) -> Result<IssuedRefreshToken, TokenError> {
+    let txn = db.begin().await?;
+
+    let current = RefreshToken::find()
+        .filter(refresh_token::Column::TokenHash.eq(hash_token(token)))
+        .one(&txn)
+        .await?
+        .ok_or(TokenError::Invalid)?;
+
+    // Marking as used only succeeds once, even for concurrent requests
+    let now = Utc::now();
+    let claimed = RefreshToken::update_many()
+        .col_expr(refresh_token::Column::UsedAt, Expr::value(now))
+        .filter(refresh_token::Column::Id.eq(current.id))
+        .filter(refresh_token::Column::UsedAt.is_null())
+        .filter(refresh_token::Column::RevokedAt.is_null())
+        .exec(&txn)
+        .await?;
+
+    if claimed.rows_affected == 0 {
+        revoke_family_in(&txn, current.family_id).await?;
+        txn.commit().await?;
+        return Err(TokenError::Reused {
+            family_id: current.family_id,
+            user_id: current.user_id,
+        });
+    }
+    if current.expires_at <= now || family_expiry(current.family_created_at) <= now {
+        txn.commit().await?;
+        return Err(TokenError::Expired);
+    }
+
+    let issued = insert(
+        &txn,
+        current.user_id,
+        current.family_id,
+        current.family_created_at,
+    )
+    .await?;
+    txn.commit().await?;
+    Ok(issued)
+}
+
+/// Revoke the family of a refresh token (on logout). Unknown tokens are ignored.
+pub async fn revoke(db: &DatabaseConnection, token: &str) -> Result<(), DbErr> {
+    let current = RefreshToken::find()
+        .filter(refresh_token::Column::TokenHash.eq(hash_token(token)))
+        .one(db)
+        .await?;
+
+    if let Some(current) = current {
+        revoke_family_in(db, current.family_id).await?;
+    }
+    Ok(())
+}
+
+/// Revoke every refresh token of a user, e.g. after deactivation.
+pub async fn revoke_all_for_user(db: &DatabaseConnection, user_id: Uuid) -> Result<(), DbErr> {
+    RefreshToken::update_many()
+        .col_expr(refresh_token::Column::RevokedAt, Expr::value(Utc::now()))
+        .filter(refresh_token::Column::UserId.eq(user_id))
+        .filter(refresh_token::Column::RevokedAt.is_null())
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+async fn revoke_family_in<C: ConnectionTrait>(db: &C, family_id: Uuid) -> Result<(), DbErr> {
+    RefreshToken::update_many()
+        .col_expr(refresh_token::Column::RevokedAt, Expr::value(Utc::now()))
+        .filter(refresh_token::Column::FamilyId.eq(family_id))
+        .filter(refresh_token::Column::RevokedAt.is_null())
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+/// Put an access token on the deny list until it expires.
+pub async fn revoke_access_token(
+    db: &DatabaseConnection,
+    jti: &str,
+    expires_at: DateTime<Utc>,
+) -> Result<(), DbErr> {
+    let entry = revoked_token::ActiveModel {
+        jti: Set(jti.to_string()),
+        expires_at: Set(expires_at),
+        revoked_at: Set(Utc::now()),
+    };
+
+    RevokedToken::insert(entry)
+        .on_conflict(
+            OnConflict::column(revoked_token::Column::Jti)
+                .do_nothing()
+                .to_owned(),
+        )
+        .exec_without_returning(db)
+        .await?;
+    Ok(())
+}
+
+/// Whether an access token has been revoked.
+pub async fn is_access_token_revoked(db: &DatabaseConnection, jti: &str) -> Result<bool, DbErr> {
+    Ok(RevokedToken::find_by_id(jti.to_string())
+        .one(db)
+        .await?
+        .is_some())
+}
+
+/// Delete expired refresh tokens and deny list entries of expired access
+/// tokens. Returns how many rows were removed.
+pub async fn purge_expired(db: &DatabaseConnection) -> Result<u64, DbErr> {
+    let now = Utc::now();
+    let refresh = RefreshToken::delete_many()
+        .filter(refresh_token::Column::ExpiresAt.lt(now))
+        .exec(db)
+        .await?;
+    let revoked = RevokedToken::delete_many()
+        .filter(revoked_token::Column::ExpiresAt.lt(now))
+        .exec(db)
+        .await?;
+    Ok(refresh.rows_affected + revoked.rows_affected)
+}
+
+/// Periodically purge expired tokens in the background.
+pub fn spawn_purge_task(db: DatabaseConnection, interval: StdDuration) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            match purge_expired(&db).await {
+                Ok(0) => {}
+                Ok(purged) => info!(purged, "Purged expired tokens"),
+                Err(e) => error!(error = %e, "Failed to purge expired tokens"),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::SubsecRound;
+    use sea_orm::PaginatorTrait;
+
+    use super::*;
+    use crate::{
+        models::user::CreateUserRequest,
+        repositories::{test_db, user_repository},
+    };
+
+    async fn user(db: &DatabaseConnection) -> Uuid {
+        let request = CreateUserRequest {
+            username: "anna".to_string(),
+            name: "Anna".to_string(),
+            email: None,
+            password: "password1".to_string(),
+            role: None,
+            versichertennummer: None,
+            assigned_clients: Vec::new(),
+        };
+        user_repository::create(db, request).await.unwrap().id
+    }
+
+    async fn stored(db: &DatabaseConnection, token: &str) -> refresh_token::Model {
+        RefreshToken::find()
+            .filter(refresh_token::Column::TokenHash.eq(hash_token(token)))
+            .one(db)
+            .await
+            .unwrap()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_rotation_issues_a_new_token_of_the_family() {
+        let Some(db) = test_db::connect().await else {
+            return;
+        };
+        let user_id = user(&db).await;
+
+        let first = issue(&db, user_id).await.unwrap();
+        let second = rotate(&db, &first.token).await.unwrap();
+        assert_ne!(second.token, first.token);
+        assert_eq!(second.user_id, user_id);
+
+        let (first, second) = (
+            stored(&db, &first.token).await,
+            stored(&db, &second.token).await,
+        );
+        assert!(first.used_at.is_some());
+        assert_eq!(second.family_id, first.family_id);
+        assert!(second.used_at.is_none() && second.revoked_at.is_none());
+
+        assert!(matches!(
+            rotate(&db, "not-a-token").await,
+            Err(TokenError::Invalid)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_reuse_revokes_the_whole_family() {
+        let Some(db) = test_db::connect().await else {
+            return;
+        };
+        let user_id = user(&db).await;
+        let first = issue(&db, user_id).await.unwrap();
+        let second = rotate(&db, &first.token).await.unwrap();
+        let other_session = issue(&db, user_id).await.unwrap();
+
+        // The used token shows up again, e.g. from a copy
+        let family_id = stored(&db, &first.token).await.family_id;
+        assert!(matches!(
+            rotate(&db, &first.token).await,
+            Err(TokenError::Reused { family_id: f, user_id: u }) if f == family_id && u == user_id
+        ));
+        assert!(stored(&db, &second.token).await.revoked_at.is_some());
+        assert!(matches!(
+            rotate(&db, &second.token).await,
+            Err(TokenError::Reused { .. })
+        ));
+
+        // Other logins of the user are not affected
+        assert!(rotate(&db, &other_session.token).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_expired_token_is_not_rotated() {
+        let Some(db) = test_db::connect().await else {
+            return;
+        };
+        let issued = issue(&db, user(&db).await).await.unwrap();
+        RefreshToken::update_many()
+            .col_expr(
+                refresh_token::Column::ExpiresAt,
+                Expr::value(Utc::now() - Duration::minutes(1)),
+            )
+            .exec(&db)
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            rotate(&db, &issued.token).await,
+            Err(TokenError::Expired)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_family_ends_at_its_maximum_age() {
+        let Some(db) = test_db::connect().await else {
+            return;
+        };
+        let issued = issue(&db, user(&db).await).await.unwrap();
+        // Postgres keeps microseconds
+        let started =
+            (Utc::now() - Duration::days(*REFRESH_TOKEN_MAX_AGE_DAYS - 1)).trunc_subsecs(6);
+        RefreshToken::update_many()
+            .col_expr(refresh_token::Column::FamilyCreatedAt, Expr::value(started))
+            .exec(&db)
+            .await
+            .unwrap();
+
+        // Rotation does not extend the family past its maximum age
+        let rotated = rotate(&db, &issued.token).await.unwrap();
+        assert_eq!(rotated.expires_at, family_expiry(started));
+        assert_eq!(stored(&db, &rotated.token).await.family_created_at, started);
+
+        let started = Utc::now() - Duration::days(*REFRESH_TOKEN_MAX_AGE_DAYS);
+        RefreshToken::update_many()
+            .col_expr(refresh_token::Column::FamilyCreatedAt, Expr::value(started))
+            .exec(&db)
+            .await
+            .unwrap();
+        assert!(matches!(
+            rotate(&db, &rotated.token).await,
+            Err(TokenError::Expired)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_revoked_access_tokens_are_denied() {
+        let Some(db) = test_db::connect().await else {
+            return;
+        };
+        let expires_at = Utc::now() + Duration::minutes(15);
+        assert!(!is_access_token_revoked(&db, "jti-1").await.unwrap());
+
+        revoke_access_token(&db, "jti-1", expires_at).await.unwrap();
+        // Logging out twice is fine
+        revoke_access_token(&db, "jti-1", expires_at).await.unwrap();
+        assert!(is_access_token_revoked(&db, "jti-1").await.unwrap());
+        assert!(!is_access_token_revoked(&db, "jti-2").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_purge_removes_only_expired_entries() {
+        let Some(db) = test_db::connect().await else {
+            return;
+        };
+        let user_id = user(&db).await;
+        let expired = issue(&db, user_id).await.unwrap();
+        let current = issue(&db, user_id).await.unwrap();
+        RefreshToken::update_many()
+            .col_expr(
+                refresh_token::Column::ExpiresAt,
+                Expr::value(Utc::now() - Duration::minutes(1)),
+            )
+            .filter(refresh_token::Column::TokenHash.eq(hash_token(&expired.token)))
+            .exec(&db)
+            .await
+            .unwrap();
+        revoke_access_token(&db, "old", Utc::now() - Duration::minutes(1))
+            .await
+            .unwrap();
+        revoke_access_token(&db, "new", Utc::now() + Duration::minutes(1))
+            .await
+            .unwrap();
+
+        assert_eq!(purge_expired(&db).await.unwrap(), 2);
+        assert_eq!(RefreshToken::find().count(&db).await.unwrap(), 1);
+        assert!(rotate(&db, &current.token).await.is_ok());
+        assert!(!is_access_token_revoked(&db, "old").await.unwrap());
+        assert!(is_access_token_revoked(&db, "new").await.unwrap());
+    }
+}
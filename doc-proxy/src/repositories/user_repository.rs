@@ -1,65 +1,371 @@
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+use chrono::{DateTime, Utc};
 use sea_orm::{
-    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, Set,
+    sea_query::{Expr, Func, LikeExpr},
+    ActiveModelTrait, ColumnTrait, Condition, ConnectionTrait, DatabaseConnection, EntityTrait,
+    PaginatorTrait, QueryFilter, QueryOrder, Select, Set, SqlErr, TransactionTrait,
 };
 use uuid::Uuid;
 
+use super::entity::device::{self, Entity as Device};
+use super::entity::staff_client::{self, Entity as StaffClient};
 use super::entity::user::{self, Entity as User};
-use crate::models::user::{CreateUserRequest, UpdateUserRequest, UserResponse};
+use crate::config::auth::UserRole;
+use crate::models::pagination::{PageResult, Pageable};
+use crate::models::user::{CreateUserRequest, UpdateUserRequest, UserDeviceResponse, UserResponse};
+use crate::services::OidcIdentity;
+use crate::utils::hashing::{hash_secret, verify_secret};
 
-pub async fn find_all(db: &DatabaseConnection) -> Result<Vec<UserResponse>, sea_orm::DbErr> {
-    let users = User::find().all(db).await?;
+/// Failed logins before an account is locked
+static LOGIN_MAX_ATTEMPTS: LazyLock<i32> = LazyLock::new(|| {
+    std::env::var("LOGIN_MAX_ATTEMPTS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|&n| n > 0)
+        .unwrap_or(5)
+});
 
-    Ok(users
-        .into_iter()
-        .map(|u| UserResponse {
+/// How long a locked account refuses logins
+static LOGIN_LOCKOUT_MINUTES: LazyLock<i64> = LazyLock::new(|| {
+    std::env::var("LOGIN_LOCKOUT_MINUTES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|&m| m > 0)
+        .unwrap_or(15)
+});
+
+/// Password hash of accounts that log in via OpenID Connect; never verifies
+const NO_PASSWORD: &str = "!";
+
+/// `ADMIN_PASSWORD` of earlier `.env.example` files; refused like an empty one
+const EXAMPLE_ADMIN_PASSWORD: &str = "change-me-on-first-login";
+
+/// Hash verified for unknown usernames, so they take as long as wrong passwords
+static DUMMY_HASH: LazyLock<String> =
+    LazyLock::new(|| hash_secret("dummy-password").expect("Argon2 hashes a fixed password"));
+
+impl UserResponse {
+    fn new(
+        u: user::Model,
+        devices: Vec<UserDeviceResponse>,
+        assigned_clients: Vec<String>,
+    ) -> Self {
+        Self {
             id: u.id,
+            username: u.username,
             name: u.name,
             email: u.email,
+            role: UserRole::from_db(&u.role),
+            is_active: u.is_active,
+            locked_until: u.locked_until,
+            last_login: u.last_login,
+            created_at: u.created_at,
+            versichertennummer: u.versichertennummer,
+            assigned_clients,
+            devices,
+        }
+    }
+}
+
+impl From<device::Model> for UserDeviceResponse {
+    fn from(d: device::Model) -> Self {
+        Self {
+            id: d.id,
+            name: d.name,
+            mac_address: d.mac_address,
+            is_active: d.is_active,
+            last_seen: d.last_seen,
+        }
+    }
+}
+
+/// Usernames are matched case-insensitively
+fn normalize_username(username: &str) -> String {
+    username.trim().to_lowercase()
+}
+
+/// Users that have not been deleted
+fn not_deleted() -> Select<User> {
+    User::find().filter(user::Column::DeletedAt.is_null())
+}
+
+/// Find a page of users, optionally filtered by name or e-mail
+pub async fn find_page(
+    db: &DatabaseConnection,
+    pageable: &Pageable,
+    search: Option<&str>,
+) -> Result<PageResult<UserResponse>, sea_orm::DbErr> {
+    let mut query = not_deleted().order_by_asc(user::Column::Username);
+    if let Some(search) = search.map(str::trim).filter(|s| !s.is_empty()) {
+        let pattern = format!("%{}%", escape_like(&search.to_lowercase()));
+        let matches = |column: user::Column| {
+            Expr::expr(Func::lower(Expr::col(column))).like(LikeExpr::new(&pattern).escape('\\'))
+        };
+        query = query.filter(
+            Condition::any()
+                .add(matches(user::Column::Name))
+                .add(matches(user::Column::Email)),
+        );
+    }
+
+    let paginator = query.paginate(db, pageable.size);
+    let total = paginator.num_items().await?;
+    let users = paginator.fetch_page(pageable.page).await?;
+
+    let ids: Vec<Uuid> = users.iter().map(|u| u.id).collect();
+    let mut devices = find_devices(db, &ids).await?;
+    let mut clients = find_assigned_clients(db, &ids).await?;
+    let content = users
+        .into_iter()
+        .map(|u| {
+            let bound = devices.remove(&u.id).unwrap_or_default();
+            let assigned = clients.remove(&u.id).unwrap_or_default();
+            UserResponse::new(u, bound, assigned)
         })
-        .collect())
+        .collect();
+
+    Ok(PageResult::new(
+        content,
+        pageable.page,
+        pageable.size,
+        total,
+    ))
 }
 
 pub async fn find_by_id(
     db: &DatabaseConnection,
     id: Uuid,
 ) -> Result<Option<UserResponse>, sea_orm::DbErr> {
-    let user = User::find()
+    let user = not_deleted()
         .filter(user::Column::Id.eq(id))
         .one(db)
         .await?;
 
-    Ok(user.map(|u| UserResponse {
-        id: u.id,
-        name: u.name,
-        email: u.email,
+    match user {
+        Some(user) => Ok(Some(with_relations(db, user).await?)),
+        None => Ok(None),
+    }
+}
+
+/// Build the response for a single user, with its devices and clients
+async fn with_relations(
+    db: &DatabaseConnection,
+    user: user::Model,
+) -> Result<UserResponse, sea_orm::DbErr> {
+    let id = user.id;
+    let devices = find_devices(db, &[id])
+        .await?
+        .remove(&id)
+        .unwrap_or_default();
+    let clients = find_assigned_clients(db, &[id])
+        .await?
+        .remove(&id)
+        .unwrap_or_default();
+
+    Ok(UserResponse::new(user, devices, clients))
+}
+
+/// Find user by login name
+pub async fn find_by_username(
+    db: &DatabaseConnection,
+    username: &str,
+) -> Result<Option<user::Model>, sea_orm::DbErr> {
+    not_deleted()
+        .filter(user::Column::Username.eq(normalize_username(username)))
+        .one(db)
+        .await
+}
+
+/// Devices bound to the given users, by user ID
+async fn find_devices(
+    db: &DatabaseConnection,
+    user_ids: &[Uuid],
+) -> Result<HashMap<Uuid, Vec<UserDeviceResponse>>, sea_orm::DbErr> {
+    if user_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let devices = Device::find()
+        .filter(device::Column::UserId.is_in(user_ids.iter().copied()))
+        .order_by_asc(device::Column::Name)
+        .all(db)
+        .await?;
+
+    let mut by_user: HashMap<Uuid, Vec<UserDeviceResponse>> = HashMap::new();
+    for device in devices {
+        if let Some(user_id) = device.user_id {
+            by_user.entry(user_id).or_default().push(device.into());
+        }
+    }
+    Ok(by_user)
+}
+
+/// Versichertennummern assigned to the given users, by user ID
+async fn find_assigned_clients(
+    db: &DatabaseConnection,
+    user_ids: &[Uuid],
+) -> Result<HashMap<Uuid, Vec<String>>, sea_orm::DbErr> {
+    if user_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let assignments = StaffClient::find()
+        .filter(staff_client::Column::UserId.is_in(user_ids.iter().copied()))
+        .order_by_asc(staff_client::Column::Versichertennummer)
+        .all(db)
+        .await?;
+
+    let mut by_user: HashMap<Uuid, Vec<String>> = HashMap::new();
+    for assignment in assignments {
+        by_user
+            .entry(assignment.user_id)
+            .or_default()
+            .push(assignment.versichertennummer);
+    }
+    Ok(by_user)
+}
+
+/// Replace the clients assigned to a user
+async fn set_assigned_clients<C: ConnectionTrait>(
+    db: &C,
+    user_id: Uuid,
+    clients: &[String],
+) -> Result<(), sea_orm::DbErr> {
+    StaffClient::delete_many()
+        .filter(staff_client::Column::UserId.eq(user_id))
+        .exec(db)
+        .await?;
+
+    let mut clients: Vec<&str> = clients
+        .iter()
+        .map(|c| c.trim())
+        .filter(|c| !c.is_empty())
+        .collect();
+    clients.sort_unstable();
+    clients.dedup();
+    if clients.is_empty() {
+        return Ok(());
+    }
+
+    let now = Utc::now();
+    StaffClient::insert_many(clients.into_iter().map(|c| staff_client::ActiveModel {
+        user_id: Set(user_id),
+        versichertennummer: Set(c.to_string()),
+        created_at: Set(now),
     }))
+    .exec(db)
+    .await?;
+    Ok(())
 }
 
+/// Versichertennummern an active user may access with the given role:
+/// the own one for clients, the assigned ones for staff.
+pub async fn find_accessible_clients(
+    db: &DatabaseConnection,
+    user_id: Uuid,
+    role: &UserRole,
+) -> Result<Vec<String>, sea_orm::DbErr> {
+    let user = not_deleted()
+        .filter(user::Column::Id.eq(user_id))
+        .filter(user::Column::IsActive.eq(true))
+        .one(db)
+        .await?;
+    let Some(user) = user else {
+        return Ok(Vec::new());
+    };
+
+    match role {
+        UserRole::Client => Ok(user.versichertennummer.into_iter().collect()),
+        _ => Ok(find_assigned_clients(db, &[user_id])
+            .await?
+            .remove(&user_id)
+            .unwrap_or_default()),
+    }
+}
+
+/// Fails with `Conflict` if another user has this e-mail address.
+async fn ensure_email_available(
+    db: &DatabaseConnection,
+    email: &str,
+    except: Option<Uuid>,
+) -> Result<(), UserError> {
+    let mut query = not_deleted().filter(
+        Expr::expr(Func::lower(Expr::col(user::Column::Email))).eq(email.trim().to_lowercase()),
+    );
+    if let Some(id) = except {
+        query = query.filter(user::Column::Id.ne(id));
+    }
+
+    if query.one(db).await?.is_some() {
+        return Err(UserError::Conflict(format!(
+            "E-mail address {} is already in use",
+            email
+        )));
+    }
+    Ok(())
+}
+
+/// Create a user with an Argon2-hashed password
 pub async fn create(
     db: &DatabaseConnection,
     payload: CreateUserRequest,
-) -> Result<UserResponse, sea_orm::DbErr> {
+) -> Result<UserResponse, UserError> {
+    let username = normalize_username(&payload.username);
+    if User::find()
+        .filter(user::Column::Username.eq(&username))
+        .one(db)
+        .await?
+        .is_some()
+    {
+        return Err(UserError::Conflict(format!(
+            "Username {} is already taken",
+            username
+        )));
+    }
+    if let Some(email) = &payload.email {
+        ensure_email_available(db, email, None).await?;
+    }
+
+    let password_hash = hash_secret(&payload.password).map_err(|_| UserError::HashingFailed)?;
+    let now = Utc::now();
+
+    let txn = db.begin().await?;
     let new_user = user::ActiveModel {
         id: Set(Uuid::new_v4()),
+        username: Set(username),
         name: Set(payload.name),
-        email: Set(payload.email),
+        email: Set(payload.email.map(|e| e.trim().to_string())),
+        password_hash: Set(password_hash),
+        role: Set(payload.role.unwrap_or(UserRole::Staff).as_str().to_string()),
+        is_active: Set(true),
+        failed_login_attempts: Set(0),
+        locked_until: Set(None),
+        last_login: Set(None),
+        created_at: Set(now),
+        updated_at: Set(now),
+        deleted_at: Set(None),
+        versichertennummer: Set(trimmed(payload.versichertennummer)),
+        oidc_subject: Set(None),
     };
 
-    let user = new_user.insert(db).await?;
+    let user = new_user
+        .insert(&txn)
+        .await
+        .map_err(UserError::from_insert)?;
+    set_assigned_clients(&txn, user.id, &payload.assigned_clients).await?;
+    txn.commit().await?;
 
-    Ok(UserResponse {
-        id: user.id,
-        name: user.name,
-        email: user.email,
-    })
+    Ok(with_relations(db, user).await?)
 }
 
 pub async fn update(
     db: &DatabaseConnection,
     id: Uuid,
     payload: UpdateUserRequest,
-) -> Result<Option<UserResponse>, sea_orm::DbErr> {
-    let user = User::find()
+) -> Result<Option<UserResponse>, UserError> {
+    let user = not_deleted()
         .filter(user::Column::Id.eq(id))
         .one(db)
         .await?;
@@ -74,19 +380,301 @@ pub async fn update(
         active_model.name = Set(name);
     }
     if let Some(email) = payload.email {
-        active_model.email = Set(email);
+        ensure_email_available(db, &email, Some(id)).await?;
+        active_model.email = Set(Some(email.trim().to_string()));
     }
+    if let Some(role) = payload.role {
+        active_model.role = Set(role.as_str().to_string());
+    }
+    if let Some(is_active) = payload.is_active {
+        active_model.is_active = Set(is_active);
+    }
+    if let Some(password) = payload.password {
+        let password_hash = hash_secret(&password).map_err(|_| UserError::HashingFailed)?;
+        active_model.password_hash = Set(password_hash);
+        active_model.failed_login_attempts = Set(0);
+        active_model.locked_until = Set(None);
+    }
+    if let Some(versichertennummer) = payload.versichertennummer {
+        active_model.versichertennummer = Set(trimmed(Some(versichertennummer)));
+    }
+    active_model.updated_at = Set(Utc::now());
 
-    let updated = active_model.update(db).await?;
+    let txn = db.begin().await?;
+    let updated = active_model
+        .update(&txn)
+        .await
+        .map_err(UserError::from_insert)?;
+    if let Some(clients) = payload.assigned_clients {
+        set_assigned_clients(&txn, id, &clients).await?;
+    }
+    txn.commit().await?;
 
-    Ok(Some(UserResponse {
-        id: updated.id,
-        name: updated.name,
-        email: updated.email,
-    }))
+    Ok(Some(with_relations(db, updated).await?))
 }
 
-pub async fn delete(db: &DatabaseConnection, id: Uuid) -> Result<(), sea_orm::DbErr> {
-    User::delete_by_id(id).exec(db).await?;
+/// Soft delete a user: the row is kept, but the user can no longer log in
+/// and is hidden from all queries. The user's devices are deactivated in the
+/// same transaction. Returns false if there was no such user.
+pub async fn soft_delete(db: &DatabaseConnection, id: Uuid) -> Result<bool, sea_orm::DbErr> {
+    let now = Utc::now();
+    let txn = db.begin().await?;
+    let result = User::update_many()
+        .col_expr(user::Column::DeletedAt, Expr::value(now))
+        .col_expr(user::Column::IsActive, Expr::value(false))
+        .col_expr(user::Column::UpdatedAt, Expr::value(now))
+        .filter(user::Column::Id.eq(id))
+        .filter(user::Column::DeletedAt.is_null())
+        .exec(&txn)
+        .await?;
+    if result.rows_affected == 0 {
+        return Ok(false);
+    }
+
+    // Devices of the user would otherwise keep authenticating
+    Device::update_many()
+        .col_expr(device::Column::IsActive, Expr::value(false))
+        .filter(device::Column::UserId.eq(id))
+        .exec(&txn)
+        .await?;
+    txn.commit().await?;
+    Ok(true)
+}
+
+/// Trimmed value, `None` if blank
+fn trimmed(value: Option<String>) -> Option<String> {
+    value
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+/// Escapes LIKE wildcards in user input
+fn escape_like(input: &str) -> String {
+    input
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+/// Check a login against the stored password hash.
+///
+/// Unknown, inactive and wrong-password logins all fail with
+/// `InvalidCredentials`. After `LOGIN_MAX_ATTEMPTS` failures in a row the
+/// account is locked for `LOGIN_LOCKOUT_MINUTES`; a successful login resets
+/// the counter.
+pub async fn authenticate(
+    db: &DatabaseConnection,
+    username: &str,
+    password: &str,
+) -> Result<user::Model, UserError> {
+    let Some(user) = find_by_username(db, username).await? else {
+        verify_secret(password, &DUMMY_HASH);
+        return Err(UserError::InvalidCredentials);
+    };
+
+    let now = Utc::now();
+    if let Some(locked_until) = user.locked_until.filter(|&t| t > now) {
+        return Err(UserError::Locked(locked_until));
+    }
+
+    if !verify_secret(password, &user.password_hash) {
+        record_failed_login(db, user.id).await?;
+        return Err(UserError::InvalidCredentials);
+    }
+    if !user.is_active {
+        return Err(UserError::InvalidCredentials);
+    }
+
+    let mut active_model: user::ActiveModel = user.into();
+    active_model.failed_login_attempts = Set(0);
+    active_model.locked_until = Set(None);
+    active_model.last_login = Set(Some(now));
+    Ok(active_model.update(db).await?)
+}
+
+/// Count a failed login and lock the account once the limit is reached.
+async fn record_failed_login(db: &DatabaseConnection, id: Uuid) -> Result<(), sea_orm::DbErr> {
+    // Incremented in the database so concurrent attempts are all counted
+    let updated = User::update_many()
+        .col_expr(
+            user::Column::FailedLoginAttempts,
+            Expr::col(user::Column::FailedLoginAttempts).add(1),
+        )
+        .filter(user::Column::Id.eq(id))
+        .exec_with_returning(db)
+        .await?;
+
+    for user in updated {
+        if user.failed_login_attempts >= *LOGIN_MAX_ATTEMPTS {
+            let mut active_model: user::ActiveModel = user.into();
+            active_model.failed_login_attempts = Set(0);
+            active_model.locked_until = Set(Some(
+                Utc::now() + chrono::Duration::minutes(*LOGIN_LOCKOUT_MINUTES),
+            ));
+            active_model.update(db).await?;
+        }
+    }
+
     Ok(())
 }
+
+/// Find or create the account of a user who logged in via OpenID Connect.
+///
+/// Accounts are matched by the provider's subject, never by username or
+/// e-mail, so a provider account cannot take over a local one. Name and role
+/// follow the provider on every login. Deactivated and deleted accounts fail
+/// with `InvalidCredentials`.
+pub async fn upsert_oidc_user(
+    db: &DatabaseConnection,
+    identity: &OidcIdentity,
+    role: &UserRole,
+) -> Result<user::Model, UserError> {
+    let now = Utc::now();
+    let existing = User::find()
+        .filter(user::Column::OidcSubject.eq(&identity.subject))
+        .one(db)
+        .await?;
+
+    if let Some(user) = existing {
+        if !user.is_active || user.deleted_at.is_some() {
+            return Err(UserError::InvalidCredentials);
+        }
+        let mut active_model: user::ActiveModel = user.into();
+        active_model.name = Set(identity.name.clone());
+        active_model.role = Set(role.as_str().to_string());
+        active_model.last_login = Set(Some(now));
+        active_model.updated_at = Set(now);
+        return Ok(active_model.update(db).await?);
+    }
+
+    // Taken usernames are left to their accounts
+    let mut username = normalize_username(&identity.username);
+    let taken = User::find()
+        .filter(user::Column::Username.eq(&username))
+        .one(db)
+        .await?
+        .is_some();
+    if username.is_empty() || taken {
+        username = normalize_username(&format!("oidc-{}", identity.subject));
+    }
+    let username: String = username.chars().take(64).collect();
+
+    let email = match &identity.email {
+        Some(email) if ensure_email_available(db, email, None).await.is_ok() => {
+            Some(email.trim().to_string())
+        }
+        _ => None,
+    };
+
+    let new_user = user::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        username: Set(username),
+        name: Set(identity.name.clone()),
+        email: Set(email),
+        password_hash: Set(NO_PASSWORD.to_string()),
+        role: Set(role.as_str().to_string()),
+        is_active: Set(true),
+        failed_login_attempts: Set(0),
+        locked_until: Set(None),
+        last_login: Set(Some(now)),
+        created_at: Set(now),
+        updated_at: Set(now),
+        deleted_at: Set(None),
+        versichertennummer: Set(None),
+        oidc_subject: Set(Some(identity.subject.clone())),
+    };
+
+    new_user.insert(db).await.map_err(UserError::from_insert)
+}
+
+/// Create the first admin account if there are no users yet.
+/// Returns whether an account was created. An empty or example password is
+/// refused even if there are users, so it cannot linger in the configuration.
+pub async fn bootstrap_admin(
+    db: &DatabaseConnection,
+    username: &str,
+    password: &str,
+) -> Result<bool, UserError> {
+    if [EXAMPLE_ADMIN_PASSWORD, ""].contains(&password.trim()) {
+        return Err(UserError::InsecureAdminPassword);
+    }
+    if User::find().count(db).await? > 0 {
+        return Ok(false);
+    }
+
+    create(
+        db,
+        CreateUserRequest {
+            username: username.to_string(),
+            name: "Administrator".to_string(),
+            email: None,
+            password: password.to_string(),
+            role: Some(UserRole::Admin),
+            versichertennummer: None,
+            assigned_clients: Vec::new(),
+        },
+    )
+    .await?;
+
+    Ok(true)
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum UserError {
+    #[error("Database error: {0}")]
+    Database(#[from] sea_orm::DbErr),
+    #[error("Failed to hash password")]
+    HashingFailed,
+    #[error("Invalid credentials")]
+    InvalidCredentials,
+    #[error("Account locked until {0}")]
+    Locked(DateTime<Utc>),
+    #[error("{0}")]
+    Conflict(String),
+    #[error(
+        "ADMIN_PASSWORD is empty or the example value; choose a password of your own \
+         or unset ADMIN_PASSWORD"
+    )]
+    InsecureAdminPassword,
+}
+
+impl UserError {
+    /// Maps unique constraint violations that slipped past the checks to `Conflict`
+    fn from_insert(err: sea_orm::DbErr) -> Self {
+        match err.sql_err() {
+            Some(SqlErr::UniqueConstraintViolation(_)) => {
+                Self::Conflict("Username or e-mail address is already in use".to_string())
+            }
+            _ => Self::Database(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::test_db;
+
+    #[tokio::test]
+    async fn test_bootstrap_admin_refuses_example_password() {
+        let Some(db) = test_db::connect().await else {
+            return;
+        };
+
+        for password in [EXAMPLE_ADMIN_PASSWORD, "", "  "] {
+            assert!(matches!(
+                bootstrap_admin(&db, "admin", password).await,
+                Err(UserError::InsecureAdminPassword)
+            ));
+        }
+        assert_eq!(User::find().count(&db).await.unwrap(), 0);
+
+        let password = "a-password-of-our-own";
+        assert!(bootstrap_admin(&db, "admin", password).await.unwrap());
+        assert!(!bootstrap_admin(&db, "admin", password).await.unwrap());
+        assert!(matches!(
+            bootstrap_admin(&db, "admin", EXAMPLE_ADMIN_PASSWORD).await,
+            Err(UserError::InsecureAdminPassword)
+        ));
+    }
+}
@@ -0,0 +1,252 @@
+//! In-process cache of core server responses.
+//!
+//! Response bodies are kept as returned by the core server, together with
+//! their `ETag`. Fresh entries are served without a request; stale ones are
+//! revalidated with `If-None-Match`, so an unchanged document costs the core
+//! server a 304 instead of a full response.
+
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+
+/// What a cached response was requested for
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum CacheKey {
+    /// A single Leistungsnachweis
+    Detail(String),
+    /// A page of a client's Leistungsnachweise
+    List {
+        client_id: String,
+        page: u64,
+        size: u64,
+    },
+}
+
+/// Result of looking up a key
+#[derive(Debug)]
+pub enum Lookup {
+    /// Within the TTL, use as is
+    Fresh(Arc<[u8]>),
+    /// Past the TTL, revalidate with this `ETag`
+    Stale(String),
+    /// Not cached, or stale without an `ETag`
+    Missing,
+}
+
+#[derive(Debug)]
+struct Entry {
+    body: Arc<[u8]>,
+    etag: Option<String>,
+    fetched_at: Instant,
+    /// Documents contained in the response, for invalidation
+    documents: Vec<String>,
+}
+
+/// Cache counters as exposed on `/metrics`
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheMetrics {
+    /// Served from the cache without asking the core server
+    pub hits: u64,
+    /// Requested from the core server, including revalidations
+    pub misses: u64,
+    /// Misses the core server answered with 304 Not Modified
+    pub revalidations: u64,
+    /// Entries currently cached
+    pub entries: usize,
+}
+
+/// Responses cached for `ttl`, at most `max_entries` of them.
+#[derive(Debug)]
+pub struct CoreCache {
+    ttl: Duration,
+    max_entries: usize,
+    entries: Mutex<HashMap<CacheKey, Entry>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    revalidations: AtomicU64,
+}
+
+impl CoreCache {
+    pub fn new(ttl: Duration, max_entries: usize) -> Self {
+        Self {
+            ttl,
+            max_entries,
+            entries: Mutex::new(HashMap::new()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            revalidations: AtomicU64::new(0),
+        }
+    }
+
+    fn entries(&self) -> std::sync::MutexGuard<'_, HashMap<CacheKey, Entry>> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Looks up a response; fresh entries count as hits, all others as
+    /// misses.
+    pub fn lookup(&self, key: &CacheKey) -> Lookup {
+        let lookup = match self.entries().get(key) {
+            Some(entry) if entry.fetched_at.elapsed() < self.ttl => {
+                Lookup::Fresh(entry.body.clone())
+            }
+            Some(Entry {
+                etag: Some(etag), ..
+            }) => Lookup::Stale(etag.clone()),
+            _ => Lookup::Missing,
+        };
+        match lookup {
+            Lookup::Fresh(_) => self.hits.fetch_add(1, Ordering::Relaxed),
+            _ => self.misses.fetch_add(1, Ordering::Relaxed),
+        };
+        lookup
+    }
+
+    /// The core server confirmed a stale entry; it is fresh again
+    pub fn revalidated(&self, key: &CacheKey) -> Option<Arc<[u8]>> {
+        let mut entries = self.entries();
+        let entry = entries.get_mut(key)?;
+        entry.fetched_at = Instant::now();
+        self.revalidations.fetch_add(1, Ordering::Relaxed);
+        Some(entry.body.clone())
+    }
+
+    /// Caches a response containing `documents`
+    pub fn store(
+        &self,
+        key: CacheKey,
+        body: Arc<[u8]>,
+        etag: Option<String>,
+        documents: Vec<String>,
+    ) {
+        if self.max_entries == 0 {
+            return;
+        }
+        let mut entries = self.entries();
+        if entries.len() >= self.max_entries && !entries.contains_key(&key) {
+            // Full: drop expired entries, or else the oldest one
+            entries.retain(|_, entry| entry.fetched_at.elapsed() < self.ttl);
+            if entries.len() >= self.max_entries {
+                let oldest = entries
+                    .iter()
+                    .min_by_key(|(_, entry)| entry.fetched_at)
+                    .map(|(key, _)| key.clone());
+                if let Some(oldest) = oldest {
+                    entries.remove(&oldest);
+                }
+            }
+        }
+        entries.insert(
+            key,
+            Entry {
+                body,
+                etag,
+                fetched_at: Instant::now(),
+                documents,
+            },
+        );
+    }
+
+    /// Drops a document and every list page containing it
+    pub fn invalidate(&self, id: &str) {
+        self.entries()
+            .retain(|_, entry| !entry.documents.iter().any(|d| d == id));
+    }
+
+    pub fn metrics(&self) -> CacheMetrics {
+        CacheMetrics {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            revalidations: self.revalidations.load(Ordering::Relaxed),
+            entries: self.entries().len(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detail(id: &str) -> CacheKey {
+        CacheKey::Detail(id.to_string())
+    }
+
+    fn body(text: &str) -> Arc<[u8]> {
+        Arc::from(text.as_bytes())
+    }
+
+    #[test]
+    fn fresh_entries_are_hits() {
+        let cache = CoreCache::new(Duration::from_secs(60), 10);
+        assert!(matches!(cache.lookup(&detail("a")), Lookup::Missing));
+
+        cache.store(detail("a"), body("{}"), None, vec!["a".to_string()]);
+        assert!(matches!(cache.lookup(&detail("a")), Lookup::Fresh(b) if &*b == b"{}"));
+
+        let metrics = cache.metrics();
+        assert_eq!((metrics.hits, metrics.misses, metrics.entries), (1, 1, 1));
+    }
+
+    #[test]
+    fn stale_entries_are_revalidated_by_etag() {
+        let cache = CoreCache::new(Duration::ZERO, 10);
+        cache.store(detail("a"), body("{}"), None, vec!["a".to_string()]);
+        assert!(matches!(cache.lookup(&detail("a")), Lookup::Missing));
+
+        cache.store(
+            detail("a"),
+            body("{}"),
+            Some("\"v1\"".to_string()),
+            vec!["a".to_string()],
+        );
+        assert!(matches!(cache.lookup(&detail("a")), Lookup::Stale(etag) if etag == "\"v1\""));
+        assert!(cache.revalidated(&detail("a")).is_some());
+
+        let metrics = cache.metrics();
+        assert_eq!(
+            (metrics.hits, metrics.misses, metrics.revalidations),
+            (0, 2, 1)
+        );
+    }
+
+    #[test]
+    fn invalidation_drops_lists_containing_the_document() {
+        let cache = CoreCache::new(Duration::from_secs(60), 10);
+        let list = |page| CacheKey::List {
+            client_id: "c1".to_string(),
+            page,
+            size: 2,
+        };
+        cache.store(detail("a"), body("{}"), None, vec!["a".to_string()]);
+        cache.store(
+            list(0),
+            body("[]"),
+            None,
+            vec!["a".to_string(), "b".to_string()],
+        );
+        cache.store(list(1), body("[]"), None, vec!["c".to_string()]);
+
+        cache.invalidate("a");
+        assert!(matches!(cache.lookup(&detail("a")), Lookup::Missing));
+        assert!(matches!(cache.lookup(&list(0)), Lookup::Missing));
+        assert!(matches!(cache.lookup(&list(1)), Lookup::Fresh(_)));
+    }
+
+    #[test]
+    fn oldest_entry_is_evicted_when_full() {
+        let cache = CoreCache::new(Duration::from_secs(60), 2);
+        for id in ["a", "b", "c"] {
+            cache.store(detail(id), body("{}"), None, vec![id.to_string()]);
+        }
+
+        assert_eq!(cache.metrics().entries, 2);
+        assert!(matches!(cache.lookup(&detail("a")), Lookup::Missing));
+        assert!(matches!(cache.lookup(&detail("c")), Lookup::Fresh(_)));
+    }
+}
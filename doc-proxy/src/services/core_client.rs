@@ -1,21 +1,30 @@
 //! HTTP client for the core server API.
 
-use reqwest::{Client, StatusCode};
-use serde::{de::DeserializeOwned, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+
+use rand::Rng;
+use reqwest::{Client, RequestBuilder, StatusCode, header};
+use serde::{Serialize, de::DeserializeOwned};
 use thiserror::Error;
-use tracing::info;
+use tracing::{info, warn};
 
+use super::core_cache::{CacheKey, CacheMetrics, CoreCache, Lookup};
+use super::mock_data;
 use crate::handlers::leistungsnachweis::{
     request::SignLeistungsnachweisRequest,
     response::{LeistungsnachweisDetail, LeistungsnachweisListItem, SignedDocumentResponse},
 };
 use crate::models::pagination::PageResult;
-use super::mock_data;
+use crate::utils::circuit_breaker::{CircuitBreaker, CircuitState};
+
+/// Longest wait between two attempts
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(5);
 
 #[derive(Error, Debug)]
 pub enum CoreClientError {
     #[error("Request failed: {0}")]
-    Request(#[from] reqwest::Error),
+    Request(reqwest::Error),
 
     #[error("Resource not found")]
     NotFound,
@@ -25,16 +34,157 @@ pub enum CoreClientError {
 
     #[error("Core server error: {status}")]
     ServerError { status: StatusCode },
+
+    #[error("Core server did not respond in time")]
+    Timeout,
+
+    #[error("Core server unavailable, retry in {}s", .retry_after.as_secs().max(1))]
+    CircuitOpen { retry_after: Duration },
+
+    #[error("Invalid response from core server: {0}")]
+    Decode(#[from] serde_json::Error),
+}
+
+impl CoreClientError {
+    /// Whether the core server failed rather than refused the request;
+    /// these errors are retried and count towards the circuit breaker.
+    fn is_transient(&self) -> bool {
+        match self {
+            CoreClientError::Request(e) => e.is_connect() || e.is_request(),
+            CoreClientError::ServerError { status } => status.is_server_error(),
+            CoreClientError::Timeout => true,
+            _ => false,
+        }
+    }
+}
+
+impl From<reqwest::Error> for CoreClientError {
+    fn from(err: reqwest::Error) -> Self {
+        if err.is_timeout() {
+            CoreClientError::Timeout
+        } else {
+            CoreClientError::Request(err)
+        }
+    }
+}
+
+/// Timeouts, retries and circuit breaker settings for the core server.
+#[derive(Debug, Clone)]
+pub struct CoreClientConfig {
+    /// Time to establish a connection
+    pub connect_timeout: Duration,
+    /// Time for a whole request, including the response body
+    pub request_timeout: Duration,
+    /// Retries of failed GET requests; POSTs are never retried
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled for each further one
+    pub retry_base_delay: Duration,
+    /// Consecutive failures that open the circuit
+    pub breaker_threshold: u32,
+    /// Time the circuit stays open before a trial request
+    pub breaker_cooldown: Duration,
+    /// Time cached responses are served without asking the core server;
+    /// afterwards they are revalidated by `ETag`
+    pub cache_ttl: Duration,
+    /// Responses kept in the cache; 0 disables caching
+    pub cache_max_entries: usize,
+}
+
+impl Default for CoreClientConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout: Duration::from_secs(5),
+            request_timeout: Duration::from_secs(30),
+            max_retries: 2,
+            retry_base_delay: Duration::from_millis(200),
+            breaker_threshold: 5,
+            breaker_cooldown: Duration::from_secs(30),
+            cache_ttl: Duration::from_secs(30),
+            cache_max_entries: 1000,
+        }
+    }
+}
+
+impl CoreClientConfig {
+    /// Reads the settings from the environment, falling back to the defaults.
+    ///
+    /// Env vars:
+    /// - `CORE_CONNECT_TIMEOUT_SECS` (default: 5)
+    /// - `CORE_REQUEST_TIMEOUT_SECS` (default: 30)
+    /// - `CORE_MAX_RETRIES` (default: 2)
+    /// - `CORE_RETRY_BASE_DELAY_MS` (default: 200)
+    /// - `CORE_BREAKER_THRESHOLD` (default: 5)
+    /// - `CORE_BREAKER_COOLDOWN_SECS` (default: 30)
+    /// - `CORE_CACHE_TTL_SECS` (default: 30)
+    /// - `CORE_CACHE_MAX_ENTRIES` (default: 1000)
+    pub fn from_env() -> Self {
+        fn var<T: std::str::FromStr>(name: &str) -> Option<T> {
+            std::env::var(name).ok().and_then(|v| v.parse().ok())
+        }
+
+        let defaults = Self::default();
+        Self {
+            connect_timeout: var("CORE_CONNECT_TIMEOUT_SECS")
+                .filter(|&s| s > 0)
+                .map(Duration::from_secs)
+                .unwrap_or(defaults.connect_timeout),
+            request_timeout: var("CORE_REQUEST_TIMEOUT_SECS")
+                .filter(|&s| s > 0)
+                .map(Duration::from_secs)
+                .unwrap_or(defaults.request_timeout),
+            max_retries: var("CORE_MAX_RETRIES").unwrap_or(defaults.max_retries),
+            retry_base_delay: var("CORE_RETRY_BASE_DELAY_MS")
+                .map(Duration::from_millis)
+                .unwrap_or(defaults.retry_base_delay),
+            breaker_threshold: var("CORE_BREAKER_THRESHOLD")
+                .filter(|&n| n > 0)
+                .unwrap_or(defaults.breaker_threshold),
+            breaker_cooldown: var("CORE_BREAKER_COOLDOWN_SECS")
+                .filter(|&s| s > 0)
+                .map(Duration::from_secs)
+                .unwrap_or(defaults.breaker_cooldown),
+            cache_ttl: var("CORE_CACHE_TTL_SECS")
+                .map(Duration::from_secs)
+                .unwrap_or(defaults.cache_ttl),
+            cache_max_entries: var("CORE_CACHE_MAX_ENTRIES").unwrap_or(defaults.cache_max_entries),
+        }
+    }
+}
+
+/// Circuit breaker state as reported on `/health`
+#[derive(Debug, Clone, Serialize)]
+pub struct CoreStatus {
+    pub circuit: CircuitState,
+    pub consecutive_failures: u32,
+}
+
+/// Successful response from the core server
+enum Fetched {
+    Body {
+        body: Arc<[u8]>,
+        etag: Option<String>,
+    },
+    /// The cached response is still current
+    NotModified,
 }
 
 /// Client for communicating with the core server.
 /// Supports mock mode for development without a real backend.
+///
+/// Requests time out, failed GETs are retried with jittered exponential
+/// backoff, and a circuit breaker shared by all clones fails fast while the
+/// core server is down. Detail and list responses are cached, see
+/// [`CoreCache`].
 #[derive(Clone)]
 pub struct CoreClient {
     client: Client,
     base_url: String,
     api_token: String,
     mock_mode: bool,
+    max_retries: u32,
+    retry_base_delay: Duration,
+    breaker: Arc<CircuitBreaker>,
+    cache: Arc<CoreCache>,
 }
 
 impl CoreClient {
@@ -44,6 +194,8 @@ impl CoreClient {
     /// - `MOCK_MODE`: Set to "true" to enable mock data (default: true for development)
     /// - `CORE_API_URL`: Base URL of the core server (e.g., "http://localhost:8081")
     /// - `CORE_API_TOKEN`: Service token for authentication
+    ///
+    /// Timeouts and retries are read by [`CoreClientConfig::from_env`].
     pub fn from_env() -> Self {
         let mock_mode = std::env::var("MOCK_MODE")
             .map(|v| v.to_lowercase() == "true" || v == "1")
@@ -53,22 +205,38 @@ impl CoreClient {
             info!("CoreClient running in MOCK MODE - using sample data");
         }
 
-        let base_url = std::env::var("CORE_API_URL")
-            .unwrap_or_else(|_| "http://localhost:8081".to_string());
+        let base_url =
+            std::env::var("CORE_API_URL").unwrap_or_else(|_| "http://localhost:8081".to_string());
         let api_token =
             std::env::var("CORE_API_TOKEN").unwrap_or_else(|_| "dev-service-token".to_string());
 
-        Self::new(base_url, api_token, mock_mode)
+        Self::new(base_url, api_token, mock_mode, CoreClientConfig::from_env())
     }
 
     /// Creates a new CoreClient with explicit configuration.
-    pub fn new(base_url: String, api_token: String, mock_mode: bool) -> Self {
-        let client = Client::new();
+    pub fn new(
+        base_url: String,
+        api_token: String,
+        mock_mode: bool,
+        config: CoreClientConfig,
+    ) -> Self {
+        let client = Client::builder()
+            .connect_timeout(config.connect_timeout)
+            .timeout(config.request_timeout)
+            .build()
+            .expect("Failed to build HTTP client");
         Self {
             client,
             base_url: base_url.trim_end_matches('/').to_string(),
             api_token,
             mock_mode,
+            max_retries: config.max_retries,
+            retry_base_delay: config.retry_base_delay,
+            breaker: Arc::new(CircuitBreaker::new(
+                config.breaker_threshold,
+                config.breaker_cooldown,
+            )),
+            cache: Arc::new(CoreCache::new(config.cache_ttl, config.cache_max_entries)),
         }
     }
 
@@ -77,6 +245,25 @@ impl CoreClient {
         self.mock_mode
     }
 
+    /// Returns the circuit breaker state.
+    pub fn status(&self) -> CoreStatus {
+        let (circuit, consecutive_failures) = self.breaker.status();
+        CoreStatus {
+            circuit,
+            consecutive_failures,
+        }
+    }
+
+    /// Returns the response cache counters.
+    pub fn cache_metrics(&self) -> CacheMetrics {
+        self.cache.metrics()
+    }
+
+    /// Drops cached responses containing a document, e.g. after signing it.
+    pub fn invalidate(&self, id: &str) {
+        self.cache.invalidate(id);
+    }
+
     /// Fetches a paginated list of Leistungsnachweise for a client.
     pub async fn list_leistungsnachweise(
         &self,
@@ -92,8 +279,16 @@ impl CoreClient {
             "{}/api/leistungsnachweise?clientId={}&page={}&size={}",
             self.base_url, client_id, page, size
         );
+        let key = CacheKey::List {
+            client_id: client_id.to_string(),
+            page,
+            size,
+        };
 
-        self.get(&url).await
+        self.get_cached(key, &url, |page: &PageResult<LeistungsnachweisListItem>| {
+            page.content.iter().map(|item| item.id.clone()).collect()
+        })
+        .await
     }
 
     /// Fetches a single Leistungsnachweis by ID.
@@ -106,8 +301,12 @@ impl CoreClient {
         }
 
         let url = format!("{}/api/leistungsnachweise/{}", self.base_url, id);
+        let key = CacheKey::Detail(id.to_string());
 
-        self.get(&url).await
+        self.get_cached(key, &url, |_: &LeistungsnachweisDetail| {
+            vec![id.to_string()]
+        })
+        .await
     }
 
     /// Submits a signature to the core server for a Leistungsnachweis.
@@ -124,20 +323,68 @@ impl CoreClient {
 
         let url = format!("{}/api/leistungsnachweise/{}/sign", self.base_url, id);
 
-        self.post(&url, _request).await
+        let signed = self.post(&url, _request).await;
+        self.invalidate(id);
+        signed
     }
 
-    /// Generic GET request with authentication.
-    async fn get<T: DeserializeOwned>(&self, url: &str) -> Result<T, CoreClientError> {
-        let response = self
-            .client
-            .get(url)
-            .header("Authorization", format!("Bearer {}", self.api_token))
-            .header("Accept", "application/json")
-            .send()
-            .await?;
+    /// GET through the cache: fresh responses are served from it, stale ones
+    /// are revalidated with `If-None-Match`. `documents` lists the documents
+    /// contained in a response, so signing one of them drops it.
+    async fn get_cached<T: DeserializeOwned>(
+        &self,
+        key: CacheKey,
+        url: &str,
+        documents: impl FnOnce(&T) -> Vec<String>,
+    ) -> Result<T, CoreClientError> {
+        let etag = match self.cache.lookup(&key) {
+            Lookup::Fresh(body) => return Ok(serde_json::from_slice(&body)?),
+            Lookup::Stale(etag) => Some(etag),
+            Lookup::Missing => None,
+        };
 
-        self.handle_response(response).await
+        match self.get(url, etag.as_deref()).await? {
+            Fetched::Body { body, etag } => {
+                let value = serde_json::from_slice(&body)?;
+                self.cache.store(key, body, etag, documents(&value));
+                Ok(value)
+            }
+            Fetched::NotModified => match self.cache.revalidated(&key) {
+                Some(body) => Ok(serde_json::from_slice(&body)?),
+                // Invalidated while revalidating
+                None => match self.get(url, None).await? {
+                    Fetched::Body { body, .. } => Ok(serde_json::from_slice(&body)?),
+                    Fetched::NotModified => Err(CoreClientError::ServerError {
+                        status: StatusCode::NOT_MODIFIED,
+                    }),
+                },
+            },
+        }
+    }
+
+    /// Generic GET request with authentication, retried on transient errors.
+    async fn get(&self, url: &str, etag: Option<&str>) -> Result<Fetched, CoreClientError> {
+        let mut attempt = 0;
+        loop {
+            let mut request = self
+                .client
+                .get(url)
+                .header("Authorization", format!("Bearer {}", self.api_token))
+                .header("Accept", "application/json");
+            if let Some(etag) = etag {
+                request = request.header(header::IF_NONE_MATCH, etag);
+            }
+
+            match self.send(request).await {
+                Err(e) if e.is_transient() && attempt < self.max_retries => {
+                    let delay = self.retry_delay(attempt);
+                    warn!(error = %e, url = %url, attempt, delay = ?delay, "Retrying core request");
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
     }
 
     /// Generic POST request with authentication.
@@ -146,28 +393,302 @@ impl CoreClient {
         url: &str,
         body: &B,
     ) -> Result<T, CoreClientError> {
-        let response = self
+        let request = self
             .client
             .post(url)
             .header("Authorization", format!("Bearer {}", self.api_token))
             .header("Accept", "application/json")
-            .json(body)
-            .send()
-            .await?;
+            .json(body);
+
+        match self.send(request).await? {
+            Fetched::Body { body, .. } => Ok(serde_json::from_slice(&body)?),
+            Fetched::NotModified => Err(CoreClientError::ServerError {
+                status: StatusCode::NOT_MODIFIED,
+            }),
+        }
+    }
+
+    /// Sends a request unless the circuit is open, and reports the outcome
+    /// to the circuit breaker.
+    async fn send(&self, request: RequestBuilder) -> Result<Fetched, CoreClientError> {
+        self.breaker
+            .check()
+            .map_err(|retry_after| CoreClientError::CircuitOpen { retry_after })?;
+
+        let result = match request.send().await {
+            Ok(response) => self.handle_response(response).await,
+            Err(e) => Err(e.into()),
+        };
+        match &result {
+            Err(e) if e.is_transient() => self.breaker.record_failure(),
+            _ => self.breaker.record_success(),
+        }
+        result
+    }
 
-        self.handle_response(response).await
+    /// Exponential backoff with jitter, so clients do not retry in lockstep.
+    fn retry_delay(&self, attempt: u32) -> Duration {
+        let delay = self
+            .retry_base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(MAX_RETRY_DELAY);
+        delay.mul_f64(rand::thread_rng().gen_range(0.5..=1.0))
     }
 
     /// Handle response status codes.
-    async fn handle_response<T: DeserializeOwned>(
+    async fn handle_response(
         &self,
         response: reqwest::Response,
-    ) -> Result<T, CoreClientError> {
+    ) -> Result<Fetched, CoreClientError> {
         match response.status() {
-            StatusCode::OK | StatusCode::CREATED => Ok(response.json().await?),
+            StatusCode::OK | StatusCode::CREATED => {
+                let etag = response
+                    .headers()
+                    .get(header::ETAG)
+                    .and_then(|v| v.to_str().ok())
+                    .map(str::to_string);
+                let body = Arc::from(response.bytes().await?.as_ref());
+                Ok(Fetched::Body { body, etag })
+            }
+            StatusCode::NOT_MODIFIED => Ok(Fetched::NotModified),
             StatusCode::NOT_FOUND => Err(CoreClientError::NotFound),
             StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Err(CoreClientError::Unauthorized),
             status => Err(CoreClientError::ServerError { status }),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use axum::{
+        Json, Router,
+        extract::State,
+        http::{HeaderMap, StatusCode},
+        response::{IntoResponse, Response},
+        routing::get,
+        routing::post,
+    };
+
+    use super::*;
+    use crate::handlers::leistungsnachweis::request::SignatureType;
+
+    const DOCUMENT_ID: &str = "7c1e4b2a-9d3f-4e8a-b6c5-1a2b3c4d5e01";
+
+    const ETAG: &str = "\"v1\"";
+
+    /// Requests seen by the stub, and how many of them fail first
+    struct Stub {
+        hits: AtomicUsize,
+        failures: usize,
+        delay: Duration,
+    }
+
+    /// Starts a local core server whose first `failures` requests get a 503
+    async fn stub_core(failures: usize, delay: Duration) -> (String, Arc<Stub>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let stub = Arc::new(Stub {
+            hits: AtomicUsize::new(0),
+            failures,
+            delay,
+        });
+
+        let app = Router::new()
+            .route("/api/leistungsnachweise/{id}", get(detail))
+            .route("/api/leistungsnachweise/{id}/sign", post(sign))
+            .with_state(stub.clone());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (base_url, stub)
+    }
+
+    async fn detail(State(stub): State<Arc<Stub>>, headers: HeaderMap) -> Response {
+        let hit = stub.hits.fetch_add(1, Ordering::SeqCst);
+        tokio::time::sleep(stub.delay).await;
+        if hit < stub.failures {
+            return StatusCode::SERVICE_UNAVAILABLE.into_response();
+        }
+        if headers
+            .get(header::IF_NONE_MATCH)
+            .is_some_and(|v| v == ETAG)
+        {
+            return StatusCode::NOT_MODIFIED.into_response();
+        }
+        (
+            [(header::ETAG, ETAG)],
+            Json(mock_data::mock_detail(DOCUMENT_ID).unwrap()),
+        )
+            .into_response()
+    }
+
+    async fn sign(State(stub): State<Arc<Stub>>) -> StatusCode {
+        stub.hits.fetch_add(1, Ordering::SeqCst);
+        StatusCode::SERVICE_UNAVAILABLE
+    }
+
+    fn client(base_url: String, config: CoreClientConfig) -> CoreClient {
+        CoreClient::new(base_url, "token".to_string(), false, config)
+    }
+
+    fn config() -> CoreClientConfig {
+        CoreClientConfig {
+            connect_timeout: Duration::from_secs(1),
+            request_timeout: Duration::from_secs(1),
+            max_retries: 2,
+            retry_base_delay: Duration::from_millis(1),
+            breaker_threshold: 10,
+            breaker_cooldown: Duration::from_secs(60),
+            cache_ttl: Duration::from_secs(60),
+            cache_max_entries: 10,
+        }
+    }
+
+    fn sign_request() -> SignLeistungsnachweisRequest {
+        SignLeistungsnachweisRequest {
+            signature_type: SignatureType::Missing,
+            signature: None,
+            missing_reason: None,
+            missing_explanation: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_is_retried_on_server_errors() {
+        let (base_url, stub) = stub_core(2, Duration::ZERO).await;
+        let core = client(base_url, config());
+
+        let detail = core.get_leistungsnachweis(DOCUMENT_ID).await.unwrap();
+        assert_eq!(detail.id, DOCUMENT_ID);
+        assert_eq!(stub.hits.load(Ordering::SeqCst), 3);
+        assert_eq!(core.status().consecutive_failures, 0);
+    }
+
+    #[tokio::test]
+    async fn test_retries_are_limited() {
+        let (base_url, stub) = stub_core(usize::MAX, Duration::ZERO).await;
+        let core = client(base_url, config());
+
+        let err = core.get_leistungsnachweis(DOCUMENT_ID).await.unwrap_err();
+        assert!(matches!(
+            err,
+            CoreClientError::ServerError {
+                status: StatusCode::SERVICE_UNAVAILABLE
+            }
+        ));
+        assert_eq!(stub.hits.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_post_is_not_retried() {
+        let (base_url, stub) = stub_core(0, Duration::ZERO).await;
+        let core = client(base_url, config());
+
+        let err = core
+            .sign_leistungsnachweis(DOCUMENT_ID, &sign_request())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, CoreClientError::ServerError { .. }));
+        assert_eq!(stub.hits.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_slow_responses_time_out() {
+        let (base_url, _stub) = stub_core(0, Duration::from_millis(500)).await;
+        let core = client(
+            base_url,
+            CoreClientConfig {
+                request_timeout: Duration::from_millis(50),
+                max_retries: 0,
+                ..config()
+            },
+        );
+
+        let err = core.get_leistungsnachweis(DOCUMENT_ID).await.unwrap_err();
+        assert!(matches!(err, CoreClientError::Timeout));
+    }
+
+    #[tokio::test]
+    async fn test_open_circuit_fails_fast() {
+        let (base_url, stub) = stub_core(usize::MAX, Duration::ZERO).await;
+        let core = client(
+            base_url,
+            CoreClientConfig {
+                max_retries: 0,
+                breaker_threshold: 2,
+                ..config()
+            },
+        );
+
+        for _ in 0..2 {
+            assert!(core.get_leistungsnachweis(DOCUMENT_ID).await.is_err());
+        }
+        assert_eq!(core.status().circuit, CircuitState::Open);
+
+        // Clones share the breaker
+        let err = core
+            .clone()
+            .get_leistungsnachweis(DOCUMENT_ID)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, CoreClientError::CircuitOpen { .. }));
+        assert_eq!(stub.hits.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_not_found_does_not_trip_breaker() {
+        let (base_url, _stub) = stub_core(0, Duration::ZERO).await;
+        let core = client(
+            base_url,
+            CoreClientConfig {
+                breaker_threshold: 1,
+                ..config()
+            },
+        );
+
+        let err = core
+            .list_leistungsnachweise("client-1", 0, 20)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, CoreClientError::NotFound));
+        assert_eq!(core.status().circuit, CircuitState::Closed);
+    }
+
+    #[tokio::test]
+    async fn test_details_are_cached_until_signed() {
+        let (base_url, stub) = stub_core(0, Duration::ZERO).await;
+        let core = client(base_url, config());
+
+        core.get_leistungsnachweis(DOCUMENT_ID).await.unwrap();
+        core.get_leistungsnachweis(DOCUMENT_ID).await.unwrap();
+        assert_eq!(stub.hits.load(Ordering::SeqCst), 1);
+
+        // Signing drops the cached detail, even if the core server failed
+        let _ = core
+            .sign_leistungsnachweis(DOCUMENT_ID, &sign_request())
+            .await;
+        core.get_leistungsnachweis(DOCUMENT_ID).await.unwrap();
+        assert_eq!(stub.hits.load(Ordering::SeqCst), 3);
+
+        let metrics = core.cache_metrics();
+        assert_eq!((metrics.hits, metrics.misses), (1, 2));
+    }
+
+    #[tokio::test]
+    async fn test_stale_details_are_revalidated() {
+        let (base_url, stub) = stub_core(0, Duration::ZERO).await;
+        let core = client(
+            base_url,
+            CoreClientConfig {
+                cache_ttl: Duration::ZERO,
+                ..config()
+            },
+        );
+
+        core.get_leistungsnachweis(DOCUMENT_ID).await.unwrap();
+        let detail = core.get_leistungsnachweis(DOCUMENT_ID).await.unwrap();
+        assert_eq!(detail.id, DOCUMENT_ID);
+        assert_eq!(stub.hits.load(Ordering::SeqCst), 2);
+        assert_eq!(core.cache_metrics().revalidations, 1);
+    }
+}
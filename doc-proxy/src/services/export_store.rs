@@ -0,0 +1,349 @@
+//! Temporary storage for export archives behind signed download links.
+//!
+//! Archives are written to a local directory as `{id}-{expires}.zip`. The
+//! download link carries the expiry and an HMAC over both, so it can be
+//! handed to the browser without an Authorization header and cannot be
+//! extended or pointed at another archive.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use chrono::{DateTime, SubsecRound, Utc};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha2::Sha256;
+use thiserror::Error;
+use tokio::task::JoinHandle;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Placeholder of earlier `.env.example` files; refused like a missing key
+const EXAMPLE_SIGNING_KEY: &str = "your-export-signing-key-change-in-production";
+
+#[derive(Error, Debug)]
+pub enum ExportStoreError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Invalid download signature")]
+    InvalidSignature,
+
+    #[error("Download link expired")]
+    Expired,
+
+    #[error("Export not found")]
+    NotFound,
+
+    #[error(
+        "EXPORT_SIGNING_KEY is missing or the example value; configure a random key, \
+         or set ALLOW_DEV_EXPORT_KEY=true for development"
+    )]
+    InsecureKey,
+}
+
+/// An archive written to the store
+#[derive(Debug, Clone)]
+pub struct StoredExport {
+    pub id: Uuid,
+    pub expires_at: DateTime<Utc>,
+    /// Signed, time-limited link to the archive
+    pub download_url: String,
+}
+
+/// Local directory of export archives.
+#[derive(Clone)]
+pub struct ExportStore {
+    dir: PathBuf,
+    key: Arc<[u8]>,
+    ttl: chrono::Duration,
+    base_url: String,
+}
+
+impl ExportStore {
+    /// Creates a new ExportStore from environment variables.
+    ///
+    /// Env vars:
+    /// - `EXPORT_DIR`: Directory for archives (default: `doc-proxy-exports` in the temp dir)
+    /// - `EXPORT_SIGNING_KEY`: Key for download links (required)
+    /// - `ALLOW_DEV_EXPORT_KEY`: Use a random key if none is set; links die on
+    ///   restart (development only)
+    /// - `EXPORT_TTL_MINUTES`: Lifetime of an archive and its link (default: 15)
+    /// - `EXPORT_BASE_URL`: Prefix of download links as seen by the browser (default: none)
+    pub fn from_env() -> Result<Self, ExportStoreError> {
+        let dir = std::env::var("EXPORT_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| std::env::temp_dir().join("doc-proxy-exports"));
+
+        let allow_dev_key = std::env::var("ALLOW_DEV_EXPORT_KEY")
+            .map(|v| v.to_lowercase() == "true" || v == "1")
+            .unwrap_or(false);
+        let key = signing_key(
+            std::env::var("EXPORT_SIGNING_KEY").ok().as_deref(),
+            allow_dev_key,
+        )?;
+
+        let ttl_minutes = std::env::var("EXPORT_TTL_MINUTES")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|&m| m > 0)
+            .unwrap_or(15);
+        let base_url = std::env::var("EXPORT_BASE_URL").unwrap_or_default();
+
+        Ok(Self::new(
+            dir,
+            key,
+            chrono::Duration::minutes(ttl_minutes),
+            base_url,
+        ))
+    }
+
+    /// Creates a new ExportStore with explicit configuration.
+    pub fn new(dir: PathBuf, key: Vec<u8>, ttl: chrono::Duration, base_url: String) -> Self {
+        Self {
+            dir,
+            key: key.into(),
+            ttl,
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Writes an archive and returns its signed download link.
+    pub async fn store(
+        &self,
+        id: Uuid,
+        archive: Vec<u8>,
+    ) -> Result<StoredExport, ExportStoreError> {
+        let expires_at = Utc::now().trunc_subsecs(0) + self.ttl;
+        tokio::fs::create_dir_all(&self.dir).await?;
+        tokio::fs::write(self.path(id, expires_at.timestamp()), archive).await?;
+
+        let expires = expires_at.timestamp();
+        Ok(StoredExport {
+            id,
+            expires_at,
+            download_url: format!(
+                "{}/export/downloads/{}?expires={}&signature={}",
+                self.base_url,
+                id,
+                expires,
+                self.sign(id, expires)
+            ),
+        })
+    }
+
+    /// Reads an archive after checking its download link.
+    pub async fn open(
+        &self,
+        id: Uuid,
+        expires: i64,
+        signature: &str,
+    ) -> Result<Vec<u8>, ExportStoreError> {
+        let signature = hex::decode(signature).map_err(|_| ExportStoreError::InvalidSignature)?;
+        self.mac(id, expires)
+            .verify_slice(&signature)
+            .map_err(|_| ExportStoreError::InvalidSignature)?;
+        if expires <= Utc::now().timestamp() {
+            return Err(ExportStoreError::Expired);
+        }
+
+        match tokio::fs::read(self.path(id, expires)).await {
+            Ok(archive) => Ok(archive),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Err(ExportStoreError::NotFound),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Deletes all archives whose link has expired. Returns how many were removed.
+    pub async fn purge_expired(&self) -> Result<usize, ExportStoreError> {
+        let mut entries = match tokio::fs::read_dir(&self.dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e.into()),
+        };
+
+        let now = Utc::now().timestamp();
+        let mut purged = 0;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if expiry_of(&path).is_some_and(|expires| expires <= now) {
+                tokio::fs::remove_file(&path).await?;
+                purged += 1;
+            }
+        }
+        Ok(purged)
+    }
+
+    /// Starts a background task that purges expired archives every `interval`.
+    pub fn spawn_purge_task(&self, interval: Duration) -> JoinHandle<()> {
+        let store = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                match store.purge_expired().await {
+                    Ok(0) => {}
+                    Ok(purged) => info!(purged, "Purged expired exports"),
+                    Err(e) => error!(error = %e, "Failed to purge expired exports"),
+                }
+            }
+        })
+    }
+
+    fn path(&self, id: Uuid, expires: i64) -> PathBuf {
+        self.dir.join(format!("{}-{}.zip", id, expires))
+    }
+
+    fn sign(&self, id: Uuid, expires: i64) -> String {
+        hex::encode(self.mac(id, expires).finalize().into_bytes())
+    }
+
+    fn mac(&self, id: Uuid, expires: i64) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(&self.key).expect("HMAC accepts any key length");
+        mac.update(format!("{}:{}", id, expires).as_bytes());
+        mac
+    }
+}
+
+/// The configured signing key; without one, a random key if `allow_dev_key`.
+fn signing_key(key: Option<&str>, allow_dev_key: bool) -> Result<Vec<u8>, ExportStoreError> {
+    match key.filter(|k| !k.is_empty()) {
+        Some(key) if key != EXAMPLE_SIGNING_KEY => Ok(key.as_bytes().to_vec()),
+        _ if allow_dev_key => {
+            warn!("EXPORT_SIGNING_KEY not set - export links are invalid after a restart");
+            let mut key = vec![0u8; 32];
+            rand::thread_rng().fill_bytes(&mut key);
+            Ok(key)
+        }
+        _ => Err(ExportStoreError::InsecureKey),
+    }
+}
+
+/// Expiry encoded in an archive's file name, `None` for foreign files.
+fn expiry_of(path: &Path) -> Option<i64> {
+    let stem = path.file_name()?.to_str()?.strip_suffix(".zip")?;
+    let (id, expires) = stem.rsplit_once('-')?;
+    Uuid::parse_str(id).ok()?;
+    expires.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store(ttl: chrono::Duration) -> ExportStore {
+        let dir = std::env::temp_dir().join(format!("doc-proxy-export-tests-{}", Uuid::new_v4()));
+        ExportStore::new(dir, b"test-key".to_vec(), ttl, "/api/".to_string())
+    }
+
+    /// Expiry and signature from a download link
+    fn link_parts(export: &StoredExport) -> (i64, String) {
+        let query = export.download_url.split_once('?').unwrap().1;
+        let (expires, signature) = query.split_once('&').unwrap();
+        let expires = expires.strip_prefix("expires=").unwrap().parse().unwrap();
+        let signature = signature.strip_prefix("signature=").unwrap().to_string();
+        (expires, signature)
+    }
+
+    #[tokio::test]
+    async fn test_signed_link_opens_the_archive() {
+        let store = store(chrono::Duration::minutes(15));
+        let id = Uuid::new_v4();
+        let export = store.store(id, b"zip".to_vec()).await.unwrap();
+
+        let prefix = format!("/api/export/downloads/{}?expires=", id);
+        assert!(export.download_url.starts_with(&prefix));
+        let (expires, signature) = link_parts(&export);
+        assert_eq!(expires, export.expires_at.timestamp());
+        assert_eq!(store.open(id, expires, &signature).await.unwrap(), b"zip");
+    }
+
+    #[tokio::test]
+    async fn test_tampered_links_are_refused() {
+        let store = store(chrono::Duration::minutes(15));
+        let id = Uuid::new_v4();
+        let export = store.store(id, b"zip".to_vec()).await.unwrap();
+        let (expires, signature) = link_parts(&export);
+
+        let mut flipped = signature.clone().into_bytes();
+        flipped[0] = if flipped[0] == b'0' { b'1' } else { b'0' };
+        let flipped = String::from_utf8(flipped).unwrap();
+        let other_key = ExportStore::new(
+            store.dir.clone(),
+            b"other-key".to_vec(),
+            store.ttl,
+            String::new(),
+        );
+
+        for result in [
+            store.open(id, expires, &flipped).await,
+            store.open(id, expires, "not-hex").await,
+            store.open(id, expires + 3600, &signature).await,
+            store.open(Uuid::new_v4(), expires, &signature).await,
+            other_key.open(id, expires, &signature).await,
+        ] {
+            assert!(matches!(result, Err(ExportStoreError::InvalidSignature)));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_expired_and_missing_archives() {
+        let expired = store(chrono::Duration::minutes(-1));
+        let id = Uuid::new_v4();
+        let export = expired.store(id, b"zip".to_vec()).await.unwrap();
+        let (expires, signature) = link_parts(&export);
+        assert!(matches!(
+            expired.open(id, expires, &signature).await,
+            Err(ExportStoreError::Expired)
+        ));
+
+        let store = store(chrono::Duration::minutes(15));
+        let export = store.store(id, b"zip".to_vec()).await.unwrap();
+        let (expires, signature) = link_parts(&export);
+        tokio::fs::remove_file(store.path(id, expires))
+            .await
+            .unwrap();
+        assert!(matches!(
+            store.open(id, expires, &signature).await,
+            Err(ExportStoreError::NotFound)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_purge_removes_only_expired_archives() {
+        let store = store(chrono::Duration::minutes(15));
+        assert_eq!(store.purge_expired().await.unwrap(), 0);
+
+        let current = store.store(Uuid::new_v4(), b"zip".to_vec()).await.unwrap();
+        let old = Uuid::new_v4();
+        let old_path = store.path(old, Utc::now().timestamp() - 60);
+        tokio::fs::write(&old_path, b"zip").await.unwrap();
+        let foreign = store.dir.join("notes-1.zip");
+        tokio::fs::write(&foreign, b"keep").await.unwrap();
+
+        assert_eq!(store.purge_expired().await.unwrap(), 1);
+        assert!(!old_path.exists());
+        assert!(foreign.exists());
+        let (expires, signature) = link_parts(&current);
+        assert!(store.open(current.id, expires, &signature).await.is_ok());
+    }
+
+    #[test]
+    fn test_signing_key_is_required_outside_development() {
+        for key in [None, Some(""), Some(EXAMPLE_SIGNING_KEY)] {
+            assert!(matches!(
+                signing_key(key, false),
+                Err(ExportStoreError::InsecureKey)
+            ));
+            assert_eq!(signing_key(key, true).unwrap().len(), 32);
+        }
+        assert_eq!(
+            signing_key(Some("a-real-key"), false).unwrap(),
+            b"a-real-key"
+        );
+    }
+}
@@ -1,7 +1,7 @@
 //! Mock data for development mode.
 
 use crate::handlers::leistungsnachweis::response::{
-    ClientInfo, DeploymentResponse, DocumentStatus, LeistungsnachweisDetail,
+    ClientInfo, DeploymentResponse, DocumentSchema, DocumentStatus, LeistungsnachweisDetail,
     LeistungsnachweisListItem, ProviderInfo, ServiceDayResponse, ServiceResponse,
     SignedDocumentResponse,
 };
@@ -11,7 +11,7 @@ use crate::models::pagination::PageResult;
 pub fn mock_list(client_id: &str, page: u64, size: u64) -> PageResult<LeistungsnachweisListItem> {
     let all_items = vec![
         LeistungsnachweisListItem {
-            id: "lnw-2024-001".to_string(),
+            id: "7c1e4b2a-9d3f-4e8a-b6c5-1a2b3c4d5e01".to_string(),
             client_id: client_id.to_string(),
             client_name: "Müller, Hans".to_string(),
             billing_month: "202412".to_string(),
@@ -21,7 +21,7 @@ pub fn mock_list(client_id: &str, page: u64, size: u64) -> PageResult<Leistungsn
             status: DocumentStatus::PendingSignature,
         },
         LeistungsnachweisListItem {
-            id: "lnw-2024-002".to_string(),
+            id: "7c1e4b2a-9d3f-4e8a-b6c5-1a2b3c4d5e02".to_string(),
             client_id: client_id.to_string(),
             client_name: "Müller, Hans".to_string(),
             billing_month: "202411".to_string(),
@@ -31,7 +31,7 @@ pub fn mock_list(client_id: &str, page: u64, size: u64) -> PageResult<Leistungsn
             status: DocumentStatus::PendingSignature,
         },
         LeistungsnachweisListItem {
-            id: "lnw-2024-003".to_string(),
+            id: "7c1e4b2a-9d3f-4e8a-b6c5-1a2b3c4d5e03".to_string(),
             client_id: client_id.to_string(),
             client_name: "Müller, Hans".to_string(),
             billing_month: "202410".to_string(),
@@ -55,13 +55,28 @@ pub fn mock_list(client_id: &str, page: u64, size: u64) -> PageResult<Leistungsn
 
 /// Generate mock detail for a single Leistungsnachweis.
 pub fn mock_detail(id: &str) -> Option<LeistungsnachweisDetail> {
-    let (billing_month, status) = match id {
-        "lnw-2024-001" => ("202412", DocumentStatus::PendingSignature),
-        "lnw-2024-002" => ("202411", DocumentStatus::PendingSignature),
-        "lnw-2024-003" => ("202410", DocumentStatus::Signed),
+    let (billing_month, schema, status) = match id {
+        "7c1e4b2a-9d3f-4e8a-b6c5-1a2b3c4d5e01" => (
+            "202412",
+            DocumentSchema::PflLnw,
+            DocumentStatus::PendingSignature,
+        ),
+        "7c1e4b2a-9d3f-4e8a-b6c5-1a2b3c4d5e02" => (
+            "202411",
+            DocumentSchema::HkpLnw,
+            DocumentStatus::PendingSignature,
+        ),
+        "7c1e4b2a-9d3f-4e8a-b6c5-1a2b3c4d5e03" => {
+            ("202410", DocumentSchema::PflLnw, DocumentStatus::Signed)
+        }
         _ => return None,
     };
 
+    let service_days = match schema {
+        DocumentSchema::PflLnw => mock_service_days(billing_month),
+        DocumentSchema::HkpLnw => mock_hkp_service_days(billing_month),
+    };
+
     Some(LeistungsnachweisDetail {
         id: id.to_string(),
         client: ClientInfo {
@@ -71,12 +86,14 @@ pub fn mock_detail(id: &str) -> Option<LeistungsnachweisDetail> {
         },
         provider: ProviderInfo {
             ik: "123456789".to_string(),
-            responsible_staff_id: "LBNR-001".to_string(),
+            responsible_staff_id: "100000001".to_string(),
         },
         billing_month: billing_month.to_string(),
-        service_days: mock_service_days(billing_month),
+        schema,
+        service_days,
         signature: None,
         status,
+        technical_errors: Vec::new(),
     })
 }
 
@@ -99,14 +116,14 @@ fn mock_service_days(billing_month: &str) -> Vec<ServiceDayResponse> {
                             description: "Grundpflege".to_string(),
                             quantity: Some("1".to_string()),
                             duration_minutes: Some(30),
-                            staff_ids: vec!["LBNR-001".to_string()],
+                            staff_ids: vec!["100000001".to_string()],
                         },
                         ServiceResponse {
                             code: "02".to_string(),
                             description: "Behandlungspflege".to_string(),
                             quantity: Some("1".to_string()),
                             duration_minutes: Some(15),
-                            staff_ids: vec!["LBNR-001".to_string()],
+                            staff_ids: vec!["100000001".to_string()],
                         },
                     ],
                 },
@@ -119,7 +136,7 @@ fn mock_service_days(billing_month: &str) -> Vec<ServiceDayResponse> {
                         description: "Grundpflege".to_string(),
                         quantity: Some("1".to_string()),
                         duration_minutes: Some(30),
-                        staff_ids: vec!["LBNR-002".to_string()],
+                        staff_ids: vec!["100000002".to_string()],
                     }],
                 },
             ],
@@ -137,7 +154,7 @@ fn mock_service_days(billing_month: &str) -> Vec<ServiceDayResponse> {
                         description: "Hauswirtschaftliche Versorgung".to_string(),
                         quantity: Some("1".to_string()),
                         duration_minutes: Some(60),
-                        staff_ids: vec!["LBNR-003".to_string()],
+                        staff_ids: vec!["100000003".to_string()],
                     },
                 ],
             }],
@@ -156,7 +173,7 @@ fn mock_service_days(billing_month: &str) -> Vec<ServiceDayResponse> {
                             description: "Grundpflege".to_string(),
                             quantity: Some("1".to_string()),
                             duration_minutes: Some(45),
-                            staff_ids: vec!["LBNR-001".to_string()],
+                            staff_ids: vec!["100000001".to_string()],
                         },
                     ],
                 },
@@ -165,6 +182,56 @@ fn mock_service_days(billing_month: &str) -> Vec<ServiceDayResponse> {
     ]
 }
 
+/// Home nursing (SGB V) services with 6-digit HKP position numbers.
+fn mock_hkp_service_days(billing_month: &str) -> Vec<ServiceDayResponse> {
+    let year = &billing_month[0..4];
+    let month = &billing_month[4..6];
+
+    vec![
+        ServiceDayResponse {
+            date: format!("{}{}01", year, month),
+            display_date: format!("{}-{}-01", year, month),
+            deployments: vec![DeploymentResponse {
+                sequence_number: 1,
+                start_time: "0700".to_string(),
+                display_start_time: "07:00".to_string(),
+                services: vec![
+                    ServiceResponse {
+                        code: "030101".to_string(),
+                        description: "Medikamentengabe".to_string(),
+                        quantity: Some("1".to_string()),
+                        duration_minutes: Some(10),
+                        staff_ids: vec!["100000001".to_string()],
+                    },
+                    ServiceResponse {
+                        code: "030102".to_string(),
+                        description: "Blutzuckermessung".to_string(),
+                        quantity: Some("1".to_string()),
+                        duration_minutes: Some(5),
+                        staff_ids: vec!["100000001".to_string()],
+                    },
+                ],
+            }],
+        },
+        ServiceDayResponse {
+            date: format!("{}{}02", year, month),
+            display_date: format!("{}-{}-02", year, month),
+            deployments: vec![DeploymentResponse {
+                sequence_number: 1,
+                start_time: "0730".to_string(),
+                display_start_time: "07:30".to_string(),
+                services: vec![ServiceResponse {
+                    code: "030201".to_string(),
+                    description: "Verbandwechsel".to_string(),
+                    quantity: Some("1".to_string()),
+                    duration_minutes: Some(20),
+                    staff_ids: vec!["100000002".to_string(), "100000003".to_string()],
+                }],
+            }],
+        },
+    ]
+}
+
 /// Generate mock response for signing.
 pub fn mock_sign_response(id: &str) -> SignedDocumentResponse {
     SignedDocumentResponse {
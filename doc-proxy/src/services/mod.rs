@@ -1,6 +1,11 @@
 //! External service clients.
 
+pub mod core_cache;
 pub mod core_client;
+pub mod export_store;
 pub mod mock_data;
+pub mod oidc;
 
 pub use core_client::{CoreClient, CoreClientError};
+pub use export_store::{ExportStore, ExportStoreError};
+pub use oidc::{OidcClient, OidcError, OidcIdentity};
@@ -0,0 +1,739 @@
+//! OpenID Connect login against an external identity provider.
+//!
+//! Authorization code flow with PKCE: `authorization_url` hands state, nonce
+//! and code verifier of a login to the browser in a signed value, so started
+//! logins take no server memory. `complete` checks that value, exchanges the
+//! code at the provider and validates the ID token against the provider's JWKS.
+//! Discovery metadata is fetched on first use. Keys are fetched again when an
+//! ID token names an unknown `kid`, so the provider can rotate its keys.
+
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use jsonwebtoken::{
+    Algorithm, DecodingKey, Validation, decode, decode_header,
+    jwk::{Jwk, JwkSet},
+};
+use rand::RngCore;
+use reqwest::{Client, Url};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use tokio::sync::{Mutex, OnceCell, RwLock};
+use tracing::{info, warn};
+
+use crate::config::auth::UserRole;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// How long a started login may take to reach the callback
+pub const LOGIN_TIMEOUT: Duration = Duration::from_secs(600);
+
+#[derive(Error, Debug)]
+pub enum OidcError {
+    #[error("Request to identity provider failed: {0}")]
+    Request(#[from] reqwest::Error),
+
+    #[error("Identity provider misbehaved: {0}")]
+    Provider(String),
+
+    #[error("Identity provider rejected the authorization code: {0}")]
+    Rejected(String),
+
+    #[error("Unknown or expired login state")]
+    UnknownState,
+
+    #[error("Invalid ID token: {0}")]
+    InvalidIdToken(String),
+}
+
+/// Provider configuration.
+#[derive(Debug, Clone)]
+pub struct OidcConfig {
+    /// Issuer URL; discovery metadata is read from
+    /// `{issuer}/.well-known/openid-configuration`
+    pub issuer: String,
+    pub client_id: String,
+    /// Secret of confidential clients; public clients rely on PKCE alone
+    pub client_secret: Option<String>,
+    /// Callback URL registered at the provider
+    pub redirect_uri: String,
+    pub scopes: String,
+    /// ID token claim with the user's groups, dots separate nested claims
+    pub groups_claim: String,
+    /// Provider group and the role it grants
+    pub group_roles: Vec<(String, UserRole)>,
+}
+
+/// A user authenticated by the provider.
+#[derive(Debug, Clone)]
+pub struct OidcIdentity {
+    pub subject: String,
+    pub username: String,
+    pub name: String,
+    pub email: Option<String>,
+    pub groups: Vec<String>,
+}
+
+/// The endpoints of the provider's discovery metadata that the login uses
+#[derive(Debug, Deserialize)]
+struct ProviderMetadata {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    jwks_uri: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct IdTokenClaims {
+    sub: String,
+    nonce: Option<String>,
+    name: Option<String>,
+    preferred_username: Option<String>,
+    email: Option<String>,
+    #[serde(flatten)]
+    other: HashMap<String, serde_json::Value>,
+}
+
+/// A login between the redirect to the provider and the callback, kept by
+/// the browser
+#[derive(Debug, Serialize, Deserialize)]
+struct PendingLogin {
+    state: String,
+    nonce: String,
+    code_verifier: String,
+    /// Unix timestamp after which the callback is refused
+    expires: i64,
+}
+
+/// Client for the OpenID Connect provider.
+#[derive(Clone)]
+pub struct OidcClient {
+    client: Client,
+    config: Arc<OidcConfig>,
+    metadata: Arc<OnceCell<ProviderMetadata>>,
+    jwks: Arc<RwLock<JwkSet>>,
+    /// Signs pending logins; logins started before a restart must start again
+    key: Arc<[u8]>,
+    /// States of completed logins until they expire, so each completes once
+    completed: Arc<Mutex<HashMap<String, i64>>>,
+}
+
+impl OidcClient {
+    /// Creates a new OidcClient from environment variables, `None` while
+    /// OIDC login is not configured.
+    ///
+    /// Env vars:
+    /// - `OIDC_ISSUER`: Issuer URL of the provider (OIDC login is disabled without it)
+    /// - `OIDC_CLIENT_ID`: Client ID registered at the provider
+    /// - `OIDC_CLIENT_SECRET`: Client secret (optional for public clients)
+    /// - `OIDC_REDIRECT_URI`: Callback URL registered at the provider
+    /// - `OIDC_SCOPES`: Requested scopes (default: "openid profile email groups")
+    /// - `OIDC_GROUPS_CLAIM`: ID token claim with the groups (default: "groups")
+    /// - `OIDC_GROUP_ROLES`: Comma-separated `group=role` pairs, e.g. "pdl=admin,pflege=staff"
+    pub fn from_env() -> Option<Self> {
+        let issuer = std::env::var("OIDC_ISSUER")
+            .ok()
+            .filter(|v| !v.is_empty())?;
+        let (Ok(client_id), Ok(redirect_uri)) = (
+            std::env::var("OIDC_CLIENT_ID"),
+            std::env::var("OIDC_REDIRECT_URI"),
+        ) else {
+            warn!("OIDC_CLIENT_ID or OIDC_REDIRECT_URI missing - OIDC login disabled");
+            return None;
+        };
+
+        let group_roles = parse_group_roles(&std::env::var("OIDC_GROUP_ROLES").unwrap_or_default());
+        if group_roles.is_empty() {
+            warn!("OIDC_GROUP_ROLES is empty - nobody can log in via OIDC");
+        }
+        info!(issuer = %issuer, "OIDC login enabled");
+
+        Some(Self::new(OidcConfig {
+            issuer,
+            client_id,
+            client_secret: std::env::var("OIDC_CLIENT_SECRET")
+                .ok()
+                .filter(|v| !v.is_empty()),
+            redirect_uri,
+            scopes: std::env::var("OIDC_SCOPES")
+                .unwrap_or_else(|_| "openid profile email groups".to_string()),
+            groups_claim: std::env::var("OIDC_GROUPS_CLAIM")
+                .unwrap_or_else(|_| "groups".to_string()),
+            group_roles,
+        }))
+    }
+
+    /// Creates a new OidcClient with explicit configuration.
+    pub fn new(mut config: OidcConfig) -> Self {
+        config.issuer = config.issuer.trim_end_matches('/').to_string();
+        Self {
+            client: Client::new(),
+            config: Arc::new(config),
+            metadata: Arc::new(OnceCell::new()),
+            jwks: Arc::new(RwLock::new(JwkSet { keys: Vec::new() })),
+            key: random_token().into_bytes().into(),
+            completed: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Starts a login. Returns the provider URL to send the browser to and
+    /// the signed login the browser has to present at the callback.
+    pub async fn authorization_url(&self) -> Result<(String, String), OidcError> {
+        let metadata = self.metadata().await?;
+        let state = random_token();
+        let nonce = random_token();
+        let code_verifier = random_token();
+
+        let mut url = Url::parse(&metadata.authorization_endpoint)
+            .map_err(|e| OidcError::Provider(format!("Invalid authorization endpoint: {}", e)))?;
+        url.query_pairs_mut()
+            .append_pair("response_type", "code")
+            .append_pair("client_id", &self.config.client_id)
+            .append_pair("redirect_uri", &self.config.redirect_uri)
+            .append_pair("scope", &self.config.scopes)
+            .append_pair("state", &state)
+            .append_pair("nonce", &nonce)
+            .append_pair("code_challenge", &code_challenge(&code_verifier))
+            .append_pair("code_challenge_method", "S256");
+
+        let login = self.seal(&PendingLogin {
+            state,
+            nonce,
+            code_verifier,
+            expires: Utc::now().timestamp() + LOGIN_TIMEOUT.as_secs() as i64,
+        });
+
+        Ok((url.into(), login))
+    }
+
+    /// Completes a login: checks the signed `login` from `authorization_url`
+    /// against the callback's `state`, exchanges the authorization code and
+    /// validates the ID token. Each login can be completed once.
+    pub async fn complete(
+        &self,
+        code: &str,
+        state: &str,
+        login: &str,
+    ) -> Result<OidcIdentity, OidcError> {
+        let login = self
+            .open(login)
+            .filter(|login| login.state == state && login.expires > Utc::now().timestamp())
+            .ok_or(OidcError::UnknownState)?;
+        if self.completed.lock().await.contains_key(state) {
+            return Err(OidcError::UnknownState);
+        }
+        let metadata = self.metadata().await?;
+
+        let mut form = vec![
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", &self.config.redirect_uri),
+            ("client_id", &self.config.client_id),
+            ("code_verifier", &login.code_verifier),
+        ];
+        if let Some(secret) = &self.config.client_secret {
+            form.push(("client_secret", secret));
+        }
+
+        let response = self
+            .client
+            .post(&metadata.token_endpoint)
+            .form(&form)
+            .send()
+            .await?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            let message = format!("{}: {}", status, body);
+            return Err(if status.is_client_error() {
+                OidcError::Rejected(message)
+            } else {
+                OidcError::Provider(message)
+            });
+        }
+
+        let tokens: TokenResponse = response.json().await?;
+        let identity = self
+            .validate_id_token(&tokens.id_token, &login.nonce)
+            .await?;
+
+        let mut completed = self.completed.lock().await;
+        let now = Utc::now().timestamp();
+        completed.retain(|_, expires| *expires > now);
+        if completed.insert(login.state, login.expires).is_some() {
+            return Err(OidcError::UnknownState);
+        }
+        Ok(identity)
+    }
+
+    /// Role granted by the user's groups, the highest one if several match
+    pub fn role_for(&self, groups: &[String]) -> Option<UserRole> {
+        self.config
+            .group_roles
+            .iter()
+            .filter(|(group, _)| groups.contains(group))
+            .map(|(_, role)| role)
+            .max_by_key(|role| privilege(role))
+            .cloned()
+    }
+
+    /// A pending login as `{payload}.{signature}`, both base64url
+    fn seal(&self, login: &PendingLogin) -> String {
+        let payload = URL_SAFE_NO_PAD
+            .encode(serde_json::to_vec(login).expect("pending logins serialize to JSON"));
+        let signature = URL_SAFE_NO_PAD.encode(self.mac(&payload).finalize().into_bytes());
+        format!("{}.{}", payload, signature)
+    }
+
+    /// The pending login of a value from `seal`, `None` if it was altered
+    fn open(&self, sealed: &str) -> Option<PendingLogin> {
+        let (payload, signature) = sealed.split_once('.')?;
+        let signature = URL_SAFE_NO_PAD.decode(signature).ok()?;
+        self.mac(payload).verify_slice(&signature).ok()?;
+        serde_json::from_slice(&URL_SAFE_NO_PAD.decode(payload).ok()?).ok()
+    }
+
+    fn mac(&self, payload: &str) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(&self.key).expect("HMAC accepts any key length");
+        mac.update(payload.as_bytes());
+        mac
+    }
+
+    async fn validate_id_token(
+        &self,
+        id_token: &str,
+        nonce: &str,
+    ) -> Result<OidcIdentity, OidcError> {
+        let header = decode_header(id_token).map_err(invalid_token)?;
+        if matches!(
+            header.alg,
+            Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512
+        ) {
+            return Err(OidcError::InvalidIdToken(
+                "symmetric signatures are not accepted".to_string(),
+            ));
+        }
+        let key = self.decoding_key(header.kid.as_deref()).await?;
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_issuer(&[&self.config.issuer]);
+        validation.set_audience(&[&self.config.client_id]);
+        validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+        let claims = decode::<IdTokenClaims>(id_token, &key, &validation)
+            .map_err(invalid_token)?
+            .claims;
+
+        if claims.nonce.as_deref() != Some(nonce) {
+            return Err(OidcError::InvalidIdToken("nonce mismatch".to_string()));
+        }
+
+        let groups = groups_of(&claims.other, &self.config.groups_claim);
+        let username = claims
+            .preferred_username
+            .or_else(|| claims.email.clone())
+            .unwrap_or_else(|| claims.sub.clone());
+        Ok(OidcIdentity {
+            name: claims.name.unwrap_or_else(|| username.clone()),
+            subject: claims.sub,
+            username,
+            email: claims.email,
+            groups,
+        })
+    }
+
+    /// Key for an ID token, fetching the provider's keys again if unknown
+    async fn decoding_key(&self, kid: Option<&str>) -> Result<DecodingKey, OidcError> {
+        if let Some(jwk) = find_key(&*self.jwks.read().await, kid) {
+            return DecodingKey::from_jwk(&jwk).map_err(invalid_token);
+        }
+
+        let metadata = self.metadata().await?;
+        let jwks: JwkSet = self
+            .client
+            .get(&metadata.jwks_uri)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let jwk = find_key(&jwks, kid);
+        *self.jwks.write().await = jwks;
+
+        let jwk =
+            jwk.ok_or_else(|| OidcError::InvalidIdToken("unknown signing key".to_string()))?;
+        DecodingKey::from_jwk(&jwk).map_err(invalid_token)
+    }
+
+    async fn metadata(&self) -> Result<&ProviderMetadata, OidcError> {
+        self.metadata
+            .get_or_try_init(|| async {
+                let url = format!("{}/.well-known/openid-configuration", self.config.issuer);
+                let metadata: ProviderMetadata = self
+                    .client
+                    .get(&url)
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json()
+                    .await?;
+                if metadata.issuer.trim_end_matches('/') != self.config.issuer {
+                    return Err(OidcError::Provider(format!(
+                        "Discovery metadata names issuer {}",
+                        metadata.issuer
+                    )));
+                }
+                Ok(metadata)
+            })
+            .await
+    }
+}
+
+/// Parse `group=role` pairs; unknown roles are skipped
+fn parse_group_roles(value: &str) -> Vec<(String, UserRole)> {
+    value
+        .split(',')
+        .filter_map(|entry| {
+            let (group, role) = entry.trim().split_once('=')?;
+            let role = match role.trim() {
+                "admin" => UserRole::Admin,
+                "staff" => UserRole::Staff,
+                "client" => UserRole::Client,
+                other => {
+                    warn!(group, role = other, "Ignoring OIDC group with unknown role");
+                    return None;
+                }
+            };
+            Some((group.trim().to_string(), role))
+        })
+        .collect()
+}
+
+fn privilege(role: &UserRole) -> u8 {
+    match role {
+        UserRole::Admin => 2,
+        UserRole::Staff => 1,
+        UserRole::Client => 0,
+    }
+}
+
+/// Groups in a claim given as a string or a list of strings
+fn groups_of(claims: &HashMap<String, serde_json::Value>, path: &str) -> Vec<String> {
+    let mut segments = path.split('.');
+    let Some(mut value) = segments.next().and_then(|first| claims.get(first)) else {
+        return Vec::new();
+    };
+    for segment in segments {
+        match value.get(segment) {
+            Some(nested) => value = nested,
+            None => return Vec::new(),
+        }
+    }
+
+    match value {
+        serde_json::Value::String(group) => vec![group.clone()],
+        serde_json::Value::Array(groups) => groups
+            .iter()
+            .filter_map(|g| g.as_str().map(str::to_string))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// The key named by `kid`, or the only key of a provider that omits it
+fn find_key(jwks: &JwkSet, kid: Option<&str>) -> Option<Jwk> {
+    match kid {
+        Some(kid) => jwks.find(kid).cloned(),
+        None if jwks.keys.len() == 1 => jwks.keys.first().cloned(),
+        None => None,
+    }
+}
+
+fn invalid_token(err: jsonwebtoken::errors::Error) -> OidcError {
+    OidcError::InvalidIdToken(err.to_string())
+}
+
+/// 256 random bits, URL-safe; used for state, nonce and code verifier
+fn random_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// PKCE S256 challenge of a code verifier
+fn code_challenge(code_verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{Form, Json, Router, extract::State, http::StatusCode, routing::get, routing::post};
+    use jsonwebtoken::{EncodingKey, Header, encode};
+    use ring::signature::{Ed25519KeyPair, KeyPair};
+    use serde_json::json;
+
+    use super::*;
+
+    /// What the mock provider learned from the authorization request
+    #[derive(Default)]
+    struct Authorization {
+        code_challenge: String,
+        nonce: String,
+    }
+
+    struct MockProvider {
+        issuer: String,
+        audience: String,
+        key: EncodingKey,
+        public_key: Vec<u8>,
+        authorization: Mutex<Authorization>,
+    }
+
+    /// Starts a local identity provider issuing Ed25519-signed ID tokens
+    async fn mock_provider(audience: &str) -> Arc<MockProvider> {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let issuer = format!("http://{}", listener.local_addr().unwrap());
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&ring::rand::SystemRandom::new()).unwrap();
+        let key_pair = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap();
+        let provider = Arc::new(MockProvider {
+            issuer,
+            audience: audience.to_string(),
+            key: EncodingKey::from_ed_der(pkcs8.as_ref()),
+            public_key: key_pair.public_key().as_ref().to_vec(),
+            authorization: Mutex::new(Authorization::default()),
+        });
+
+        let app = Router::new()
+            .route("/.well-known/openid-configuration", get(discovery))
+            .route("/jwks", get(jwks))
+            .route("/token", post(token))
+            .with_state(provider.clone());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        provider
+    }
+
+    async fn discovery(State(provider): State<Arc<MockProvider>>) -> Json<serde_json::Value> {
+        Json(json!({
+            "issuer": provider.issuer,
+            "authorization_endpoint": format!("{}/authorize", provider.issuer),
+            "token_endpoint": format!("{}/token", provider.issuer),
+            "jwks_uri": format!("{}/jwks", provider.issuer),
+        }))
+    }
+
+    async fn jwks(State(provider): State<Arc<MockProvider>>) -> Json<serde_json::Value> {
+        Json(json!({ "keys": [{
+            "kty": "OKP",
+            "crv": "Ed25519",
+            "x": URL_SAFE_NO_PAD.encode(&provider.public_key),
+            "kid": "mock-key",
+            "alg": "EdDSA",
+            "use": "sig",
+        }]}))
+    }
+
+    async fn token(
+        State(provider): State<Arc<MockProvider>>,
+        Form(form): Form<HashMap<String, String>>,
+    ) -> Result<Json<serde_json::Value>, StatusCode> {
+        let authorization = provider.authorization.lock().await;
+        let verifier = form.get("code_verifier").ok_or(StatusCode::BAD_REQUEST)?;
+        if form.get("code").map(String::as_str) != Some("mock-code")
+            || code_challenge(verifier) != authorization.code_challenge
+        {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+
+        let mut header = Header::new(Algorithm::EdDSA);
+        header.kid = Some("mock-key".to_string());
+        let now = chrono::Utc::now().timestamp();
+        let claims = json!({
+            "iss": provider.issuer,
+            "aud": provider.audience,
+            "sub": "f3a1c2d4",
+            "iat": now,
+            "exp": now + 300,
+            "nonce": authorization.nonce,
+            "name": "Erika Mustermann",
+            "preferred_username": "erika",
+            "email": "erika@example.org",
+            "groups": ["pflege", "pdl"],
+        });
+        let id_token = encode(&header, &claims, &provider.key).unwrap();
+        Ok(Json(
+            json!({ "id_token": id_token, "token_type": "Bearer" }),
+        ))
+    }
+
+    fn client(provider: &MockProvider) -> OidcClient {
+        OidcClient::new(OidcConfig {
+            issuer: provider.issuer.clone(),
+            client_id: "doc-proxy".to_string(),
+            client_secret: None,
+            redirect_uri: "http://localhost:31001/auth/callback".to_string(),
+            scopes: "openid profile email groups".to_string(),
+            groups_claim: "groups".to_string(),
+            group_roles: parse_group_roles("pflege=staff, pdl=admin"),
+        })
+    }
+
+    /// Starts a login and lets the user consent at the mock provider.
+    /// Returns the callback's state and the signed login.
+    async fn authorize(client: &OidcClient, provider: &MockProvider) -> (String, String) {
+        let (url, login) = client.authorization_url().await.unwrap();
+        let url = Url::parse(&url).unwrap();
+        let params: HashMap<_, _> = url.query_pairs().into_owned().collect();
+        assert_eq!(params["code_challenge_method"], "S256");
+        assert_eq!(params["client_id"], "doc-proxy");
+
+        *provider.authorization.lock().await = Authorization {
+            code_challenge: params["code_challenge"].clone(),
+            nonce: params["nonce"].clone(),
+        };
+        (params["state"].clone(), login)
+    }
+
+    #[tokio::test]
+    async fn test_login_against_mock_provider() {
+        let provider = mock_provider("doc-proxy").await;
+        let client = client(&provider);
+        let (state, login) = authorize(&client, &provider).await;
+
+        let identity = client.complete("mock-code", &state, &login).await.unwrap();
+        assert_eq!(identity.subject, "f3a1c2d4");
+        assert_eq!(identity.username, "erika");
+        assert_eq!(identity.name, "Erika Mustermann");
+        assert_eq!(identity.email.as_deref(), Some("erika@example.org"));
+        assert_eq!(client.role_for(&identity.groups), Some(UserRole::Admin));
+
+        // A login completes once
+        assert!(matches!(
+            client.complete("mock-code", &state, &login).await,
+            Err(OidcError::UnknownState)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_login_must_be_signed_for_the_state() {
+        let provider = mock_provider("doc-proxy").await;
+        let client = client(&provider);
+        let (state, login) = authorize(&client, &provider).await;
+        let (other_state, _) = authorize(&client, &provider).await;
+
+        let (payload, signature) = login.split_once('.').unwrap();
+        let mut forged: PendingLogin =
+            serde_json::from_slice(&URL_SAFE_NO_PAD.decode(payload).unwrap()).unwrap();
+        forged.state = other_state.clone();
+        let forged = format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(serde_json::to_vec(&forged).unwrap()),
+            signature
+        );
+        let expired = client.seal(&PendingLogin {
+            state: other_state.clone(),
+            nonce: String::new(),
+            code_verifier: String::new(),
+            expires: Utc::now().timestamp(),
+        });
+        // Another instance, e.g. after a restart, has another key
+        let restarted = self::client(&provider);
+
+        for (state, login) in [
+            (&other_state, &login),
+            (&other_state, &forged),
+            (&other_state, &expired),
+            (&state, &"garbage".to_string()),
+        ] {
+            assert!(matches!(
+                client.complete("mock-code", state, login).await,
+                Err(OidcError::UnknownState)
+            ));
+        }
+        assert!(matches!(
+            restarted.complete("mock-code", &state, &login).await,
+            Err(OidcError::UnknownState)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_code_verifier_is_checked_by_provider() {
+        let provider = mock_provider("doc-proxy").await;
+        let client = client(&provider);
+        let (state, login) = authorize(&client, &provider).await;
+        provider.authorization.lock().await.code_challenge = code_challenge("another verifier");
+
+        assert!(matches!(
+            client.complete("mock-code", &state, &login).await,
+            Err(OidcError::Rejected(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_id_token_for_other_client_is_rejected() {
+        let provider = mock_provider("other-client").await;
+        let client = client(&provider);
+        let (state, login) = authorize(&client, &provider).await;
+
+        assert!(matches!(
+            client.complete("mock-code", &state, &login).await,
+            Err(OidcError::InvalidIdToken(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_nonce_must_match_login() {
+        let provider = mock_provider("doc-proxy").await;
+        let client = client(&provider);
+        let (state, login) = authorize(&client, &provider).await;
+        provider.authorization.lock().await.nonce = random_token();
+
+        assert!(matches!(
+            client.complete("mock-code", &state, &login).await,
+            Err(OidcError::InvalidIdToken(_))
+        ));
+    }
+
+    #[test]
+    fn test_groups_map_to_highest_role() {
+        let roles = parse_group_roles("pflege=staff,pdl=admin,angehoerige=client,x=root");
+        assert_eq!(roles.len(), 3);
+
+        let client = OidcClient::new(OidcConfig {
+            issuer: "http://idp.example/".to_string(),
+            client_id: "doc-proxy".to_string(),
+            client_secret: None,
+            redirect_uri: "http://localhost/callback".to_string(),
+            scopes: "openid".to_string(),
+            groups_claim: "groups".to_string(),
+            group_roles: roles,
+        });
+        let groups = |names: &[&str]| names.iter().map(|g| g.to_string()).collect::<Vec<_>>();
+        assert_eq!(client.role_for(&groups(&["pflege"])), Some(UserRole::Staff));
+        assert_eq!(
+            client.role_for(&groups(&["angehoerige", "pdl"])),
+            Some(UserRole::Admin)
+        );
+        assert_eq!(client.role_for(&groups(&["x", "verwaltung"])), None);
+    }
+
+    #[test]
+    fn test_groups_claim_may_be_nested() {
+        let claims: HashMap<String, serde_json::Value> = serde_json::from_value(json!({
+            "groups": "pflege",
+            "realm_access": { "roles": ["pdl", 7] },
+        }))
+        .unwrap();
+
+        assert_eq!(groups_of(&claims, "groups"), vec!["pflege"]);
+        assert_eq!(groups_of(&claims, "realm_access.roles"), vec!["pdl"]);
+        assert!(groups_of(&claims, "realm_access.missing").is_empty());
+        assert!(groups_of(&claims, "missing").is_empty());
+    }
+}
@@ -0,0 +1,136 @@
+//! Circuit breaker for calls to an upstream service.
+
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+
+/// State of a circuit breaker
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    /// Calls go through
+    Closed,
+    /// Calls are refused until the cooldown has passed
+    Open,
+    /// A single trial call decides whether to close again
+    HalfOpen,
+}
+
+#[derive(Debug)]
+struct Inner {
+    state: CircuitState,
+    failures: u32,
+    /// When the circuit opened, or when the trial call started
+    since: Instant,
+}
+
+/// Stops calling an upstream after `threshold` failures in a row.
+///
+/// After `cooldown` one trial call is let through; its outcome closes the
+/// circuit or opens it for another cooldown. Kept per process.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    threshold: u32,
+    cooldown: Duration,
+    inner: Mutex<Inner>,
+}
+
+impl CircuitBreaker {
+    /// Opens after `threshold` consecutive failures for `cooldown`
+    pub fn new(threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            threshold: threshold.max(1),
+            cooldown,
+            inner: Mutex::new(Inner {
+                state: CircuitState::Closed,
+                failures: 0,
+                since: Instant::now(),
+            }),
+        }
+    }
+
+    /// `Ok` if a call may go through, otherwise the time until the next
+    /// trial call
+    pub fn check(&self) -> Result<(), Duration> {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let elapsed = inner.since.elapsed();
+        match inner.state {
+            CircuitState::Closed => Ok(()),
+            // A trial call that never reported back does not block forever
+            CircuitState::Open | CircuitState::HalfOpen if elapsed >= self.cooldown => {
+                inner.state = CircuitState::HalfOpen;
+                inner.since = Instant::now();
+                Ok(())
+            }
+            CircuitState::Open | CircuitState::HalfOpen => Err(self.cooldown - elapsed),
+        }
+    }
+
+    /// The upstream answered
+    pub fn record_success(&self) {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        inner.state = CircuitState::Closed;
+        inner.failures = 0;
+    }
+
+    /// The upstream failed or did not answer
+    pub fn record_failure(&self) {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        inner.failures = inner.failures.saturating_add(1);
+        if inner.state == CircuitState::HalfOpen || inner.failures >= self.threshold {
+            inner.state = CircuitState::Open;
+            inner.since = Instant::now();
+        }
+    }
+
+    /// Current state and consecutive failures
+    pub fn status(&self) -> (CircuitState, u32) {
+        let inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        (inner.state, inner.failures)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn opens_after_consecutive_failures() {
+        let breaker = CircuitBreaker::new(3, Duration::from_secs(60));
+        breaker.record_failure();
+        breaker.record_failure();
+        breaker.record_success();
+        breaker.record_failure();
+        breaker.record_failure();
+        assert!(breaker.check().is_ok());
+
+        breaker.record_failure();
+        assert_eq!(breaker.status(), (CircuitState::Open, 3));
+        let retry_after = breaker.check().unwrap_err();
+        assert!(retry_after > Duration::from_secs(59));
+    }
+
+    #[test]
+    fn trial_call_decides_after_cooldown() {
+        let breaker = CircuitBreaker::new(1, Duration::from_millis(20));
+        breaker.record_failure();
+        assert!(breaker.check().is_err());
+
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(breaker.check().is_ok());
+        assert_eq!(breaker.status().0, CircuitState::HalfOpen);
+        // Only one trial call at a time
+        assert!(breaker.check().is_err());
+
+        breaker.record_failure();
+        assert_eq!(breaker.status().0, CircuitState::Open);
+
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(breaker.check().is_ok());
+        breaker.record_success();
+        assert_eq!(breaker.status(), (CircuitState::Closed, 0));
+    }
+}
@@ -0,0 +1,37 @@
+//! Argon2 hashing for stored secrets (passwords, device API keys).
+
+use argon2::{
+    Argon2,
+    password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString, rand_core::OsRng},
+};
+
+/// Hash a secret using Argon2 with a random salt
+pub fn hash_secret(secret: &str) -> Result<String, argon2::password_hash::Error> {
+    let salt = SaltString::generate(&mut OsRng);
+    let hash = Argon2::default().hash_password(secret.as_bytes(), &salt)?;
+    Ok(hash.to_string())
+}
+
+/// Verify a secret against a hash in PHC string format
+pub fn verify_secret(secret: &str, hash: &str) -> bool {
+    let Ok(parsed_hash) = PasswordHash::new(hash) else {
+        return false;
+    };
+    Argon2::default()
+        .verify_password(secret.as_bytes(), &parsed_hash)
+        .is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verifies_only_the_hashed_secret() {
+        let hash = hash_secret("correct horse").unwrap();
+        assert!(hash.starts_with("$argon2"));
+        assert!(verify_secret("correct horse", &hash));
+        assert!(!verify_secret("wrong horse", &hash));
+        assert!(!verify_secret("correct horse", "not-a-hash"));
+    }
+}
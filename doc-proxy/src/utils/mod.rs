@@ -1 +1,5 @@
 //! Utility functions and helpers.
+
+pub mod circuit_breaker;
+pub mod hashing;
+pub mod rate_limit;
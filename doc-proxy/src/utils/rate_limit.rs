@@ -0,0 +1,159 @@
+//! In-memory rate limiting.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Keys kept before windows without events are swept
+const SWEEP_KEYS: usize = 1024;
+
+/// Sliding window limit on events per key, e.g. failed attempts per client.
+///
+/// Kept per process; with several instances each one has its own budget.
+#[derive(Debug)]
+pub struct RateLimiter {
+    max: usize,
+    window: Duration,
+    events: Mutex<HashMap<String, VecDeque<Instant>>>,
+}
+
+/// An event counted against the budget of a key until it is refunded.
+///
+/// Dropping the reservation keeps the event, so an attempt that is cancelled
+/// halfway still counts.
+#[derive(Debug)]
+pub struct Reservation<'a> {
+    limiter: &'a RateLimiter,
+    key: String,
+    at: Instant,
+}
+
+impl RateLimiter {
+    /// At most `max` events per key within `window`
+    pub fn new(max: usize, window: Duration) -> Self {
+        Self {
+            max,
+            window,
+            events: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Counts an event for `key` if its budget is not used up, otherwise
+    /// returns the time until the oldest event leaves the window.
+    ///
+    /// Checking and counting happen under one lock, so concurrent attempts
+    /// cannot all pass on the last free slot.
+    pub fn reserve(&self, key: &str) -> Result<Reservation<'_>, Duration> {
+        let mut events = self.events.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        if events.len() > SWEEP_KEYS {
+            events.retain(|_, window| {
+                window
+                    .back()
+                    .is_some_and(|&t| now.duration_since(t) < self.window)
+            });
+        }
+
+        let window = events.entry(key.to_string()).or_default();
+        while window
+            .front()
+            .is_some_and(|&t| now.duration_since(t) >= self.window)
+        {
+            window.pop_front();
+        }
+        if let Some(&oldest) = window.front()
+            && window.len() >= self.max
+        {
+            return Err(self.window - now.duration_since(oldest));
+        }
+
+        window.push_back(now);
+        Ok(Reservation {
+            limiter: self,
+            key: key.to_string(),
+            at: now,
+        })
+    }
+}
+
+impl Reservation<'_> {
+    /// Gives the slot back, e.g. after a successful attempt
+    pub fn refund(self) {
+        let mut events = self
+            .limiter
+            .events
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        if let Some(window) = events.get_mut(&self.key)
+            && let Some(pos) = window.iter().rposition(|&t| t == self.at)
+        {
+            window.remove(pos);
+            if window.is_empty() {
+                events.remove(&self.key);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refuses_once_budget_is_used_up() {
+        let limiter = RateLimiter::new(2, Duration::from_secs(60));
+        assert!(limiter.reserve("a").is_ok());
+        assert!(limiter.reserve("a").is_ok());
+
+        let retry_after = limiter.reserve("a").unwrap_err();
+        assert!(retry_after > Duration::from_secs(59));
+        assert!(retry_after <= Duration::from_secs(60));
+    }
+
+    #[test]
+    fn events_leave_the_window() {
+        let limiter = RateLimiter::new(1, Duration::from_millis(20));
+        limiter.reserve("a").unwrap();
+        assert!(limiter.reserve("a").is_err());
+
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(limiter.reserve("a").is_ok());
+    }
+
+    #[test]
+    fn keys_have_their_own_budget() {
+        let limiter = RateLimiter::new(1, Duration::from_secs(60));
+        limiter.reserve("a").unwrap();
+        assert!(limiter.reserve("a").is_err());
+        assert!(limiter.reserve("b").is_ok());
+    }
+
+    #[test]
+    fn refunded_slots_are_free_again() {
+        let limiter = RateLimiter::new(1, Duration::from_secs(60));
+        let held = limiter.reserve("a").unwrap();
+        // The slot is taken while the attempt is still running
+        assert!(limiter.reserve("a").is_err());
+
+        held.refund();
+        assert!(limiter.reserve("a").is_ok());
+    }
+
+    #[test]
+    fn concurrent_attempts_share_the_budget() {
+        let limiter = RateLimiter::new(5, Duration::from_secs(60));
+        let passed = std::thread::scope(|s| {
+            let attempts: Vec<_> = (0..20)
+                .map(|_| s.spawn(|| limiter.reserve("a").is_ok()))
+                .collect();
+            attempts
+                .into_iter()
+                .map(|a| a.join().unwrap())
+                .filter(|&passed| passed)
+                .count()
+        });
+        assert_eq!(passed, 5);
+    }
+}
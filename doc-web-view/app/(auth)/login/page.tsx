@@ -2,7 +2,7 @@
 
 import { useState } from "react";
 import { useRouter } from "next/navigation";
-import { login } from "@/lib/auth";
+import { login, startOidcLogin } from "@/lib/auth";
 import { Button } from "@/components/ui/button";
 
 export default function LoginPage() {
@@ -122,22 +122,17 @@ export default function LoginPage() {
                         </Button>
                     </form>
 
-                    {/* Demo Credentials */}
-                    <div className="mt-6 rounded-lg bg-muted/50 p-3">
-                        <p className="mb-2 text-xs font-medium text-muted-foreground">
-                            Demo-Zugangsdaten:
-                        </p>
-                        <div className="space-y-1 text-xs text-muted-foreground">
-                            <p>
-                                <span className="font-medium">Admin:</span> admin / admin123
-                            </p>
-                            <p>
-                                <span className="font-medium">Pflegekraft:</span> staff / staff123
-                            </p>
-                            <p>
-                                <span className="font-medium">Klient:</span> client / client123
-                            </p>
-                        </div>
+                    {/* Single sign-on */}
+                    <div className="mt-4 border-t pt-4">
+                        <Button
+                            type="button"
+                            variant="outline"
+                            className="w-full"
+                            onClick={startOidcLogin}
+                            disabled={isLoading}
+                        >
+                            Mit Single Sign-On anmelden
+                        </Button>
                     </div>
                 </div>
             </div>
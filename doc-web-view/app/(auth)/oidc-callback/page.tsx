@@ -0,0 +1,71 @@
+"use client";
+
+import { useEffect, useRef, useState } from "react";
+import { useRouter, useSearchParams } from "next/navigation";
+import { completeOidcLogin } from "@/lib/auth";
+
+export default function OidcCallbackPage() {
+	const router = useRouter();
+	const searchParams = useSearchParams();
+	const [error, setError] = useState<string | null>(null);
+	// The code can be redeemed once; guard against effects running twice
+	const started = useRef(false);
+
+	useEffect(() => {
+		if (started.current) return;
+		started.current = true;
+
+		const code = searchParams.get("code");
+		const state = searchParams.get("state");
+		const providerError = searchParams.get("error");
+
+		if (providerError || !code || !state) {
+			setError(searchParams.get("error_description") || "Anmeldung abgebrochen");
+			return;
+		}
+
+		completeOidcLogin(code, state)
+			.then(() => router.replace("/"))
+			.catch((err) => {
+				setError(err instanceof Error ? err.message : "Anmeldung fehlgeschlagen");
+			});
+	}, [searchParams, router]);
+
+	return (
+		<div className="min-h-screen flex items-center justify-center bg-background">
+			<div className="max-w-md w-full mx-4 p-6 rounded-xl border bg-card shadow-lg">
+				{!error && (
+					<div className="text-center">
+						<div className="animate-spin rounded-full h-12 w-12 border-b-2 border-primary mx-auto mb-4"></div>
+						<h2 className="text-lg font-semibold">Anmeldung wird abgeschlossen...</h2>
+						<p className="text-sm text-muted-foreground mt-2">
+							Bitte warten...
+						</p>
+					</div>
+				)}
+
+				{error && (
+					<div className="text-center">
+						<div className="w-12 h-12 rounded-full bg-red-100 dark:bg-red-900/30 flex items-center justify-center mx-auto mb-4">
+							<svg className="w-6 h-6 text-red-600 dark:text-red-400" fill="none" stroke="currentColor" viewBox="0 0 24 24">
+								<path strokeLinecap="round" strokeLinejoin="round" strokeWidth={2} d="M6 18L18 6M6 6l12 12" />
+							</svg>
+						</div>
+						<h2 className="text-lg font-semibold text-red-600 dark:text-red-400">
+							Anmeldung fehlgeschlagen
+						</h2>
+						<p className="text-sm text-muted-foreground mt-2">
+							{error}
+						</p>
+						<button
+							onClick={() => router.push("/login")}
+							className="mt-4 px-4 py-2 bg-primary text-primary-foreground rounded-lg text-sm font-medium hover:bg-primary/90"
+						>
+							Zur Anmeldung
+						</button>
+					</div>
+				)}
+			</div>
+		</div>
+	);
+}
@@ -4,6 +4,7 @@
 const API_BASE_URL = process.env.NEXT_PUBLIC_API_URL || "http://localhost:3001";
 const TOKEN_KEY = "auth_token";
 const USER_KEY = "auth_user";
+const REFRESH_TOKEN_KEY = "auth_refresh_token";
 
 export interface User {
 	id: string;
@@ -13,6 +14,7 @@ export interface User {
 
 export interface LoginResponse {
 	token: string;
+	refreshToken: string;
 	user: User;
 	expiresIn: number;
 }
@@ -84,28 +86,84 @@ export async function login(
 		throw new Error(error.error || "Login failed");
 	}
 
-	const data = await response.json();
+	return storeLoginResponse(await response.json());
+}
+
+/**
+ * Start a single sign-on login: the proxy redirects to the identity provider,
+ * which sends the browser back to /oidc-callback
+ */
+export function startOidcLogin(): void {
+	window.location.href = `${API_BASE_URL}/auth/oidc/login`;
+}
+
+/**
+ * Complete a single sign-on login with the code and state from the identity
+ * provider's redirect. The proxy checks the state against its login cookie.
+ */
+export async function completeOidcLogin(
+	code: string,
+	state: string
+): Promise<LoginResponse> {
+	const params = new URLSearchParams({ code, state });
+	const response = await fetch(`${API_BASE_URL}/auth/oidc/callback?${params}`, {
+		credentials: "include",
+	});
+
+	if (!response.ok) {
+		const error = await response.json().catch(() => ({}));
+		throw new Error(error.error || "Login failed");
+	}
+
+	return storeLoginResponse(await response.json());
+}
 
-	// Store in localStorage
+/**
+ * Store tokens and user from a login or refresh response in localStorage
+ */
+function storeLoginResponse(data: {
+	token: string;
+	refresh_token: string;
+	user: User;
+	expires_in: number;
+}): LoginResponse {
 	if (typeof window !== "undefined") {
 		localStorage.setItem(TOKEN_KEY, data.token);
+		localStorage.setItem(REFRESH_TOKEN_KEY, data.refresh_token);
 		localStorage.setItem(USER_KEY, JSON.stringify(data.user));
 	}
 
 	return {
 		token: data.token,
+		refreshToken: data.refresh_token,
 		user: data.user,
 		expiresIn: data.expires_in,
 	};
 }
 
 /**
- * Logout - clear stored credentials (both localStorage and cookies)
+ * Logout - revoke the session on the server and clear stored credentials
+ * (both localStorage and cookies)
  */
 export function logout(): void {
 	if (typeof window !== "undefined") {
+		const token = localStorage.getItem(TOKEN_KEY);
+		const refreshToken = localStorage.getItem(REFRESH_TOKEN_KEY);
+		if (token || refreshToken) {
+			// Fire and forget; local credentials are cleared either way
+			fetch(`${API_BASE_URL}/auth/logout`, {
+				method: "POST",
+				headers: {
+					"Content-Type": "application/json",
+					...(token ? { Authorization: `Bearer ${token}` } : {}),
+				},
+				body: JSON.stringify({ refresh_token: refreshToken }),
+			}).catch(() => {});
+		}
+
 		// Clear localStorage
 		localStorage.removeItem(TOKEN_KEY);
+		localStorage.removeItem(REFRESH_TOKEN_KEY);
 		localStorage.removeItem(USER_KEY);
 		// Clear cookies
 		deleteCookie(TOKEN_KEY);
@@ -178,18 +236,21 @@ export function getAuthState(): AuthState {
 }
 
 /**
- * Refresh the auth token
+ * Exchange the stored refresh token for a new access and refresh token.
+ * Refresh tokens are single use, the new one replaces the old one.
  */
 export async function refreshToken(): Promise<LoginResponse | null> {
-	const token = getToken();
-	if (!token) return null;
+	if (typeof window === "undefined") return null;
+	const refresh = localStorage.getItem(REFRESH_TOKEN_KEY);
+	if (!refresh) return null;
 
 	try {
 		const response = await fetch(`${API_BASE_URL}/auth/refresh`, {
 			method: "POST",
 			headers: {
-				Authorization: `Bearer ${token}`,
+				"Content-Type": "application/json",
 			},
+			body: JSON.stringify({ refresh_token: refresh }),
 		});
 
 		if (!response.ok) {
@@ -197,18 +258,7 @@ export async function refreshToken(): Promise<LoginResponse | null> {
 			return null;
 		}
 
-		const data = await response.json();
-
-		if (typeof window !== "undefined") {
-			localStorage.setItem(TOKEN_KEY, data.token);
-			localStorage.setItem(USER_KEY, JSON.stringify(data.user));
-		}
-
-		return {
-			token: data.token,
-			user: data.user,
-			expiresIn: data.expires_in,
-		};
+		return storeLoginResponse(await response.json());
 	} catch {
 		logout();
 		return null;
@@ -26,6 +26,22 @@ export interface CreateDeviceRequest {
 	user_id?: string;
 }
 
+export interface CreatePairingCodeRequest {
+	name: string;
+	role?: string;
+	user_id?: string;
+	ttl_minutes?: number;
+}
+
+export interface PairingCode {
+	id: string;
+	code: string; // Only returned on creation!
+	name: string;
+	role: string;
+	user_id: string | null;
+	expires_at: string;
+}
+
 class DeviceApiError extends Error {
 	constructor(
 		message: string,
@@ -87,6 +103,35 @@ export async function registerDevice(request: CreateDeviceRequest): Promise<Devi
 	return handleResponse(response);
 }
 
+/**
+ * Create a one-time pairing code for a new device (admin only)
+ * Returns the code - shown only once!
+ */
+export async function createPairingCode(
+	request: CreatePairingCodeRequest
+): Promise<PairingCode> {
+	const response = await fetch(`${API_BASE_URL}/devices/pairing-codes`, {
+		method: "POST",
+		headers: getHeaders(),
+		credentials: "include",
+		body: JSON.stringify(request),
+	});
+	return handleResponse(response);
+}
+
+/**
+ * Redeem a pairing code on the device itself
+ * Returns the device's API key - shown only once!
+ */
+export async function pairDevice(code: string, macAddress: string): Promise<DeviceCreated> {
+	const response = await fetch(`${API_BASE_URL}/auth/device/pair`, {
+		method: "POST",
+		headers: { "Content-Type": "application/json" },
+		body: JSON.stringify({ code, mac_address: macAddress }),
+	});
+	return handleResponse(response);
+}
+
 /**
  * Deactivate a device (admin only)
  */